@@ -0,0 +1,58 @@
+//! Benchmarks of the configuration search, run with `cargo bench`.
+//!
+//! The candidate paths are built for every search, so this measures the shortcut methods
+//! with many configuration names and folders, against empty and populated directories.
+
+use conrig::testing::Sandbox;
+use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+conrig!(const BENCH_CONFIG<()> = {
+    project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-bench"),
+    config_name: &["conrig-bench", "conrig", "config", "settings"],
+    config_option: ConfigOption::DEFAULT_CONFIG,
+    extra_files: &[],
+    extra_folders: &[
+        concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-bench/a"),
+        concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-bench/b"),
+        concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-bench/c"),
+    ],
+    default_format: FileFormat::Toml,
+});
+
+/// Run `f` repeatedly for about a second, and print the average time of an iteration.
+fn bench<R>(name: &str, mut f: impl FnMut() -> R) {
+    let start = Instant::now();
+    let mut iterations = 0u32;
+    while start.elapsed() < Duration::from_secs(1) {
+        black_box(f());
+        iterations += 1;
+    }
+    let average = start.elapsed() / iterations;
+    println!("{name:<32} {average:>12?}/iter ({iterations} iterations)");
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let _sandbox = Sandbox::new()?;
+    let dir = std::path::PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-bench");
+    for folder in ["a", "b", "c"] {
+        std::fs::create_dir_all(dir.join(folder))?;
+    }
+
+    bench("search_config_file (none)", || {
+        BENCH_CONFIG.search_config_file().unwrap().path
+    });
+    bench("search_all_config_files (none)", || {
+        BENCH_CONFIG.search_all_config_files().unwrap()
+    });
+
+    std::fs::write(dir.join("c").join(".settings.json"), "null")?;
+    bench("search_config_file (last)", || {
+        BENCH_CONFIG.search_config_file().unwrap().path
+    });
+    bench("read (last)", || BENCH_CONFIG.read().unwrap());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,331 @@
+//! Derive macros for [`conrig`](https://docs.rs/conrig).
+//!
+//! Enable the `derive` feature of `conrig` and use `conrig::Conrig` instead of depending on
+//! this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, parse_macro_input, Data, DeriveInput, Error, Expr, Fields, Ident};
+use syn::{LitStr, Token};
+
+/// Generate the `ConfigPathMetadata` of a configuration structure,
+/// along with its `load` and `save` methods.
+///
+/// ## Attributes
+///
+/// - `app`, `org` and `qualifier`: the [`ProjectPath`] of your application. Required.
+/// - `name`: the name of the configuration file. Default: the value of `app`.
+/// - `format`: the default format, one of `toml`, `json`, `yaml`, `ron` and `plist`.
+///   Default: `toml`.
+///
+/// ## Field attributes
+///
+/// - `min` and `max`: the bounds of a number, e.g. `#[conrig(min = 1, max = 65535)]`;
+/// - `regex`: a pattern a string must match, e.g. `#[conrig(regex = "^[a-z]+$")]`;
+/// - `one_of`: the values a string is restricted to, e.g. `#[conrig(one_of("a", "b"))]`;
+/// - `requires`: another field required if this one is set, e.g. `#[conrig(requires = "cert")]`;
+/// - `restart_required`: the field can't be changed without restarting the application;
+/// - `secret`: the field is kept in the secret store, e.g. the OS keyring, instead of the file.
+///
+/// Rules spanning nested fields are declared on the structure, e.g.
+/// `#[conrig(requires("tls.enabled" => "tls.cert"))]`, and so are the nested fields requiring
+/// a restart, e.g. `#[conrig(restart_required("server.port", "server.host"))]`, and the nested
+/// secrets, e.g. `#[conrig(secret("database.password"))]`.
+///
+/// ## Generated items
+///
+/// - `const CONFIG: ConfigPathMetadata<'static, Self>`, using the default `ConfigOption`;
+/// - `fn load() -> Result<Self, ConrigError>`, calling `Self::CONFIG.read_or_default()`,
+///   or reading the secrets as well, with `Self::CONFIG.read_secrets()`, if there's any;
+/// - `fn save(&self) -> Result<(), ConrigError>`, calling `Self::CONFIG.write(self)`,
+///   or `Self::CONFIG.write_secrets(self)` if there's any secret;
+/// - if any field is constrained, the `Constrained` and `Validate` implementations checking
+///   the constraints;
+/// - the `RestartRequired` implementation listing the fields requiring a restart;
+/// - the `Secrets` implementation listing the secret fields.
+///
+/// [`ProjectPath`]: https://docs.rs/conrig/latest/conrig/struct.ProjectPath.html
+#[proc_macro_derive(Conrig, attributes(conrig))]
+pub fn derive_conrig(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut app = None;
+    let mut org = None;
+    let mut qualifier = None;
+    let mut name = None;
+    let mut format = None;
+    let mut rules = Vec::new();
+    let mut restart_required = Vec::new();
+    let mut secrets = Vec::new();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("conrig"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("requires") {
+                let content;
+                parenthesized!(content in meta.input);
+                let field = content.parse::<LitStr>()?;
+                content.parse::<Token![=>]>()?;
+                let required = content.parse::<LitStr>()?;
+                rules.push(quote!((#field, ::conrig::validate::Constraint::Requires(#required))));
+                return Ok(());
+            }
+            if meta.path.is_ident("restart_required") {
+                let content;
+                parenthesized!(content in meta.input);
+                restart_required
+                    .extend(Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?);
+                return Ok(());
+            }
+            if meta.path.is_ident("secret") {
+                let content;
+                parenthesized!(content in meta.input);
+                secrets.extend(Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?);
+                return Ok(());
+            }
+            let slot = if meta.path.is_ident("app") {
+                &mut app
+            } else if meta.path.is_ident("org") {
+                &mut org
+            } else if meta.path.is_ident("qualifier") {
+                &mut qualifier
+            } else if meta.path.is_ident("name") {
+                &mut name
+            } else if meta.path.is_ident("format") {
+                &mut format
+            } else {
+                return Err(meta.error("unknown `conrig` attribute"));
+            };
+            *slot = Some(meta.value()?.parse::<LitStr>()?);
+            Ok(())
+        })?;
+    }
+
+    let missing = |key: &str| {
+        Error::new(
+            Span::call_site(),
+            format!("missing `#[conrig({key} = \"...\")]` attribute"),
+        )
+    };
+    let app = app.ok_or_else(|| missing("app"))?;
+    let org = org.ok_or_else(|| missing("org"))?;
+    let qualifier = qualifier.ok_or_else(|| missing("qualifier"))?;
+    let name = name.unwrap_or_else(|| app.clone());
+    let format = match format {
+        Some(format) => {
+            let variant = match format.value().as_str() {
+                "toml" => "Toml",
+                "json" => "Json",
+                "yaml" => "Yaml",
+                "ron" => "Ron",
+                "plist" => "Plist",
+                _ => {
+                    return Err(Error::new(
+                        format.span(),
+                        "expected one of `toml`, `json`, `yaml`, `ron` and `plist`",
+                    ))
+                }
+            };
+            Ident::new(variant, format.span())
+        }
+        None => Ident::new("Toml", Span::call_site()),
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let attributes = field_attributes(&input)?;
+    let mut constraints = attributes.constraints;
+    constraints.extend(rules);
+    let restart_required = attributes
+        .restart_required
+        .into_iter()
+        .chain(restart_required.iter().map(LitStr::value));
+    let secrets: Vec<String> = attributes
+        .secrets
+        .into_iter()
+        .chain(secrets.iter().map(LitStr::value))
+        .collect();
+    let (load, save) = if secrets.is_empty() {
+        (
+            quote!(Self::CONFIG.read_or_default()),
+            quote!(Self::CONFIG.write(self)),
+        )
+    } else {
+        (
+            quote! {
+                match Self::CONFIG.read_secrets() {
+                    ::std::result::Result::Err(err) if err.is_missing() => {
+                        let value = Self::CONFIG.default_value()?;
+                        Self::CONFIG.write_secrets(&value)?;
+                        ::std::result::Result::Ok(value)
+                    }
+                    value => value,
+                }
+            },
+            quote!(Self::CONFIG.write_secrets(self)),
+        )
+    };
+    let validate = (!constraints.is_empty()).then(|| {
+        quote! {
+            impl #impl_generics ::conrig::validate::Constrained for #ident #ty_generics
+            #where_clause
+            {
+                const CONSTRAINTS: &'static [(&'static str, ::conrig::validate::Constraint)] =
+                    &[#(#constraints),*];
+            }
+
+            impl #impl_generics ::conrig::validate::Validate for #ident #ty_generics
+            #where_clause
+            {
+                fn validate(
+                    &self,
+                ) -> ::std::result::Result<(), ::std::vec::Vec<::conrig::error::Violation>> {
+                    ::conrig::validate::check_constraints(self)
+                }
+            }
+        }
+    });
+    Ok(quote! {
+        #validate
+
+        impl #impl_generics ::conrig::restart::RestartRequired for #ident #ty_generics
+        #where_clause
+        {
+            const RESTART_REQUIRED: &'static [&'static str] = &[#(#restart_required),*];
+        }
+
+        impl #impl_generics ::conrig::secret::Secrets for #ident #ty_generics
+        #where_clause
+        {
+            const SECRETS: &'static [&'static str] = &[#(#secrets),*];
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// The configuration metadata, generated by `#[derive(Conrig)]`.
+            pub const CONFIG: ::conrig::ConfigPathMetadata<'static, Self> =
+                ::conrig::ConfigPathMetadata::new(
+                    ::conrig::ProjectPath::new(#qualifier, #org, #app),
+                    &[#name],
+                    ::conrig::FileFormat::#format,
+                    &[],
+                    &[],
+                    ::conrig::ConfigOption::DEFAULT_CONFIG,
+                );
+
+            /// Read the configuration, or create it with the default value.
+            pub fn load() -> ::std::result::Result<Self, ::conrig::ConrigError> {
+                #load
+            }
+
+            /// Write the configuration.
+            pub fn save(&self) -> ::std::result::Result<(), ::conrig::ConrigError> {
+                #save
+            }
+        }
+    })
+}
+
+/// The `#[conrig(...)]` attributes declared on the fields.
+#[derive(Default)]
+struct FieldAttributes {
+    /// The constraints, as `(field, Constraint)` expressions.
+    constraints: Vec<proc_macro2::TokenStream>,
+    /// The names of the fields requiring a restart.
+    restart_required: Vec<String>,
+    /// The names of the secret fields.
+    secrets: Vec<String>,
+}
+
+/// Collect the attributes declared on the fields.
+fn field_attributes(input: &DeriveInput) -> syn::Result<FieldAttributes> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Ok(FieldAttributes::default()),
+        },
+        _ => return Ok(FieldAttributes::default()),
+    };
+    let mut attributes = FieldAttributes::default();
+    for field in fields {
+        let name = serialized_name(field)?;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("conrig"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("restart_required") {
+                    attributes.restart_required.push(name.clone());
+                    return Ok(());
+                }
+                if meta.path.is_ident("secret") {
+                    attributes.secrets.push(name.clone());
+                    return Ok(());
+                }
+                let constraint = if meta.path.is_ident("min") {
+                    let bound = meta.value()?.parse::<Expr>()?;
+                    quote!(Min((#bound) as f64))
+                } else if meta.path.is_ident("max") {
+                    let bound = meta.value()?.parse::<Expr>()?;
+                    quote!(Max((#bound) as f64))
+                } else if meta.path.is_ident("regex") {
+                    let regex = meta.value()?.parse::<LitStr>()?;
+                    quote!(Regex(#regex))
+                } else if meta.path.is_ident("requires") {
+                    let required = meta.value()?.parse::<LitStr>()?;
+                    quote!(Requires(#required))
+                } else if meta.path.is_ident("one_of") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let values = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                    let values = values.iter();
+                    quote!(OneOf(&[#(#values),*]))
+                } else {
+                    return Err(meta.error("unknown `conrig` field attribute"));
+                };
+                attributes
+                    .constraints
+                    .push(quote!((#name, ::conrig::validate::Constraint::#constraint)));
+                Ok(())
+            })?;
+        }
+    }
+    Ok(attributes)
+}
+
+/// The name of a field once serialized, honoring `#[serde(rename = "...")]`.
+fn serialized_name(field: &syn::Field) -> syn::Result<String> {
+    let mut name = field
+        .ident
+        .as_ref()
+        .map(|ident| ident.to_string().trim_start_matches("r#").to_owned())
+        .unwrap_or_default();
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(Token![=]) {
+                name = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let content;
+                parenthesized!(content in meta.input);
+                content.parse::<proc_macro2::TokenStream>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(name)
+}
@@ -0,0 +1,184 @@
+//! Applying configuration changes to the subsystems of an application.
+//!
+//! Each subsystem registers an applier for the section of the configuration it depends on,
+//! e.g. the logger for `log`. When the configuration is reloaded, only the appliers whose
+//! section has changed are invoked, in the order they were registered.
+//!
+//! The changes [requiring a restart][crate::restart] are never hot-applied: an applier
+//! isn't invoked if those are the only changes of its section.
+//!
+//! Reloads are transactional: if an applier fails, the configuration is rolled back to its
+//! previous snapshot, and re-applied to the subsystems already updated.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::apply::Appliers;
+//! use conrig::restart::{diff, RestartRequired};
+//! # use serde_derive::Serialize;
+//!
+//! #[derive(Clone, Serialize)]
+//! struct Config {
+//!     log: String,
+//!     cache_size: usize,
+//! }
+//!
+//! impl RestartRequired for Config {}
+//!
+//! let appliers = Appliers::new()
+//!     .register("log", |config: &Config| {
+//!         println!("log level is now {}", config.log);
+//!         Ok(())
+//!     })
+//!     .register("cache_size", |config: &Config| {
+//!         println!("cache size is now {}", config.cache_size);
+//!         Ok(())
+//!     });
+//!
+//! let old = Config { log: "info".into(), cache_size: 64 };
+//! let mut new = old.clone();
+//! new.log = "debug".into();
+//! assert_eq!(appliers.apply(&diff(&old, &new)?, &new)?, ["log"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::parser::ConfigFile;
+use crate::restart::{ConfigDiff, RestartRequired};
+use crate::value::{from_value, to_value};
+use crate::ConrigError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// The error returned by an applier.
+pub type ApplierError = Box<dyn std::error::Error + Send + Sync>;
+
+type Applier<T> = Box<dyn Fn(&T) -> Result<(), ApplierError> + Send + Sync>;
+
+/// The appliers of the subsystems of an application, keyed by configuration section.
+///
+/// See the [module documentation][self] for more information.
+pub struct Appliers<T> {
+    appliers: Vec<(String, Applier<T>)>,
+    rollback_file: bool,
+}
+
+impl<T> Default for Appliers<T> {
+    fn default() -> Self {
+        Self {
+            appliers: Vec::new(),
+            rollback_file: false,
+        }
+    }
+}
+
+impl<T> Appliers<T> {
+    /// Create an empty set of appliers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an applier for the section at the dotted path `section`.
+    ///
+    /// The applier is invoked with the new configuration when a key of the section,
+    /// or one of its parents, changes. An empty `section` matches any change.
+    pub fn register(
+        mut self,
+        section: impl Into<String>,
+        applier: impl Fn(&T) -> Result<(), ApplierError> + Send + Sync + 'static,
+    ) -> Self {
+        self.appliers.push((section.into(), Box::new(applier)));
+        self
+    }
+
+    /// Also restore the configuration file when a reload is rolled back. Default: `false`.
+    ///
+    /// The previous configuration is written back into the file,
+    /// so the rejected one is kept as a backup if [`backup`] is enabled.
+    ///
+    /// [`backup`]: crate::ConfigOption#structfield.backup
+    pub fn with_rollback_file(mut self, rollback_file: bool) -> Self {
+        self.rollback_file = rollback_file;
+        self
+    }
+
+    /// Invoke the appliers whose section has hot-applicable changes, in order.
+    ///
+    /// Returns the sections applied. If an applier fails, the following ones are skipped,
+    /// and an [`Apply`] error will be returned.
+    ///
+    /// [`Apply`]: crate::ConrigError::Apply
+    pub fn apply(&self, diff: &ConfigDiff, config: &T) -> Result<Vec<&str>, ConrigError> {
+        self.invoke(config, |section| diff.is_hot_applicable(section))
+    }
+
+    /// Invoke every applier in order, e.g. at startup.
+    ///
+    /// See [`Appliers::apply`] for more information.
+    pub fn apply_all(&self, config: &T) -> Result<Vec<&str>, ConrigError> {
+        self.invoke(config, |_| true)
+    }
+
+    fn invoke(&self, config: &T, changed: impl Fn(&str) -> bool) -> Result<Vec<&str>, ConrigError> {
+        let mut applied = Vec::new();
+        self.invoke_into(config, changed, &mut applied)?;
+        Ok(applied)
+    }
+
+    /// Invoke the appliers of the changed sections, pushing the applied ones into `applied`.
+    fn invoke_into<'a>(
+        &'a self,
+        config: &T,
+        changed: impl Fn(&str) -> bool,
+        applied: &mut Vec<&'a str>,
+    ) -> Result<(), ConrigError> {
+        for (section, applier) in &self.appliers {
+            if !changed(section) {
+                continue;
+            }
+            applier(config).map_err(|source| ConrigError::Apply {
+                section: section.clone(),
+                source,
+            })?;
+            applied.push(section.as_str());
+        }
+        Ok(())
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + RestartRequired> Appliers<T> {
+    /// Reload the configuration into `value` if the file has been changed by someone else,
+    /// and apply the changes.
+    ///
+    /// Returns `None` if the file hasn't been changed, or the changes otherwise.
+    /// See [`ConfigFile::reload_diff`] and [`Appliers::apply`] for more information.
+    ///
+    /// If an applier fails, `value` is rolled back to its previous snapshot, which is applied
+    /// again by the appliers already invoked, and by the [file][Appliers::with_rollback_file]
+    /// if enabled. The rollback is best-effort: its own failures are ignored, and the
+    /// [`Apply`] error of the failed applier is returned.
+    ///
+    /// [`Apply`]: crate::ConrigError::Apply
+    pub fn reload(
+        &self,
+        file: &ConfigFile,
+        value: &mut T,
+    ) -> Result<Option<ConfigDiff>, ConrigError> {
+        let snapshot = to_value(&*value)?;
+        let Some(diff) = file.reload_diff(value)? else {
+            return Ok(None);
+        };
+        let mut applied = Vec::new();
+        let changed = |section: &str| diff.is_hot_applicable(section);
+        let Err(err) = self.invoke_into(value, changed, &mut applied) else {
+            return Ok(Some(diff));
+        };
+        *value = from_value(snapshot)?;
+        let _ = self.invoke(value, |section| applied.contains(&section));
+        if self.rollback_file {
+            let _ = file.write(value);
+        }
+        Err(err)
+    }
+}
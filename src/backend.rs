@@ -0,0 +1,136 @@
+//! Storage backends of configurations.
+//!
+//! Configurations are stored in files by default. With the `windows-registry` feature,
+//! they are stored in the Windows registry on Windows instead, under
+//! `HKCU\Software\<organization>\<application>`.
+//!
+//! A [`ConfigBackend`] reads and writes a configuration the same way, whatever the backend.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Default, Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//! }
+//!
+//! conrig!(const CONFIG<Config> = {
+//!     project_path: ProjectPath {
+//!         qualifier: "org",
+//!         organization: "foo",
+//!         application: "conrig-backend",
+//!     },
+//!     config_name: &["conrig"],
+//!     config_option: ConfigOption::DEFAULT_CONFIG,
+//!     extra_files: &[],
+//!     extra_folders: &[],
+//!     default_format: FileFormat::Toml,
+//! });
+//!
+//! let backend = CONFIG.backend()?;
+//! let config: Config = backend.read_or_default()?;
+//! backend.write(&config)?;
+//! # if let conrig::backend::ConfigBackend::File(file) = backend {
+//! #     std::fs::remove_file(file.path).unwrap();
+//! # }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::parser::ConfigFile;
+#[cfg(all(windows, feature = "windows-registry"))]
+use crate::registry::RegistryKey;
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Where a configuration is stored.
+#[derive(Debug, Clone)]
+pub enum ConfigBackend {
+    /// A configuration file.
+    File(ConfigFile),
+    /// A key of the Windows registry.
+    #[cfg(all(windows, feature = "windows-registry"))]
+    #[cfg_attr(docsrs, doc(cfg(all(windows, feature = "windows-registry"))))]
+    Registry(RegistryKey),
+}
+
+impl ConfigBackend {
+    /// Read and deserialize the configuration.
+    pub fn read<T: DeserializeOwned>(&self) -> Result<T, ConrigError> {
+        match self {
+            Self::File(file) => file.read(),
+            #[cfg(all(windows, feature = "windows-registry"))]
+            Self::Registry(key) => key.read(),
+        }
+    }
+
+    /// Serialize and write the configuration.
+    pub fn write<T: Serialize>(&self, value: &T) -> Result<(), ConrigError> {
+        match self {
+            Self::File(file) => file.write(value),
+            #[cfg(all(windows, feature = "windows-registry"))]
+            Self::Registry(key) => key.write(value),
+        }
+    }
+
+    /// Read and deserialize the configuration, or write `default` if it doesn't exist.
+    pub fn read_or_new<T: Serialize + DeserializeOwned>(
+        &self,
+        default: T,
+    ) -> Result<T, ConrigError> {
+        match self {
+            Self::File(file) => file.read_or_new(default),
+            #[cfg(all(windows, feature = "windows-registry"))]
+            Self::Registry(key) => key.read_or_new(default),
+        }
+    }
+
+    /// Read and deserialize the configuration, or write the default value if it doesn't exist.
+    pub fn read_or_default<T: Serialize + DeserializeOwned + Default>(
+        &self,
+    ) -> Result<T, ConrigError> {
+        self.read_or_new(T::default())
+    }
+}
+
+impl From<ConfigFile> for ConfigBackend {
+    fn from(file: ConfigFile) -> Self {
+        Self::File(file)
+    }
+}
+
+#[cfg(all(windows, feature = "windows-registry"))]
+impl From<RegistryKey> for ConfigBackend {
+    fn from(key: RegistryKey) -> Self {
+        Self::Registry(key)
+    }
+}
+
+impl<'p, T> ConfigPathMetadata<'p, T> {
+    /// Get the backend storing the configuration.
+    ///
+    /// On Windows with the `windows-registry` feature, this is the
+    /// [`registry_path`] key of the project.
+    /// Otherwise, this is `self.search_config_file()?.fallback_default()?`.
+    ///
+    /// [`registry_path`]: crate::ProjectPath::registry_path
+    pub fn backend(&self) -> Result<ConfigBackend, ConrigError> {
+        #[cfg(all(windows, feature = "windows-registry"))]
+        {
+            Ok(ConfigBackend::Registry(RegistryKey::new(
+                self.project_path.registry_path(),
+            )))
+        }
+        #[cfg(not(all(windows, feature = "windows-registry")))]
+        {
+            Ok(ConfigBackend::File(
+                self.search_config_file()?.fallback_default()?,
+            ))
+        }
+    }
+}
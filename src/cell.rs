@@ -0,0 +1,148 @@
+//! A configuration shared by a whole application.
+//!
+//! A [`ConrigCell`] holds the current configuration as an [`Arc`] snapshot. Readers get the
+//! snapshot without waiting on writers, and an [update][ConrigCell::update] mutates a copy
+//! of the configuration, persists it, and only then swaps the snapshot, so the file and the
+//! readers never see a half-applied change.
+//!
+//! With the `json` feature, [`ConrigCell::as_json`] exports the current configuration to the
+//! scripting engines embedded by an application, e.g. `rhai` or `mlua`, so the scripts read
+//! the same settings as the application itself.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Clone, Default, Serialize, Deserialize)]
+//! struct Config {
+//!     volume: u8,
+//! }
+//!
+//! conrig!(const CONFIG<Config> = {
+//!     project_path: ProjectPath {
+//!         qualifier: "org",
+//!         organization: "foo",
+//!         application: "conrig-cell",
+//!     },
+//!     config_name: &["conrig-cell"],
+//!     config_option: ConfigOption::DEFAULT_CONFIG,
+//!     extra_files: &[],
+//!     extra_folders: &[],
+//!     default_format: FileFormat::Toml,
+//! });
+//!
+//! let cell = CONFIG.cell()?;
+//! cell.update(|config| config.volume = 11)?;
+//! assert_eq!(cell.read().volume, 11);
+//! # std::fs::remove_file(&cell.file().path).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::parser::ConfigFile;
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+/// A thread-safe configuration, saved into its file on every update.
+///
+/// See the [module documentation][self] for more information.
+#[derive(Debug)]
+pub struct ConrigCell<T> {
+    file: ConfigFile,
+    snapshot: RwLock<Arc<T>>,
+    /// Serializes the updates, so none of them is lost.
+    updating: Mutex<()>,
+}
+
+impl<T> ConrigCell<T> {
+    /// Create a new `ConrigCell` holding `value`, without writing the file.
+    pub fn new(file: ConfigFile, value: T) -> Self {
+        Self {
+            file,
+            snapshot: RwLock::new(Arc::new(value)),
+            updating: Mutex::new(()),
+        }
+    }
+
+    /// The configuration file.
+    pub fn file(&self) -> &ConfigFile {
+        &self.file
+    }
+
+    /// Get a snapshot of the current configuration.
+    pub fn read(&self) -> Arc<T> {
+        self.snapshot
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn swap(&self, value: T) {
+        *self
+            .snapshot
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Arc::new(value);
+    }
+}
+
+impl<T: DeserializeOwned> ConrigCell<T> {
+    /// Read the configuration file into a new `ConrigCell`.
+    pub fn open(file: ConfigFile) -> Result<Self, ConrigError> {
+        let value = file.read()?;
+        Ok(Self::new(file, value))
+    }
+
+    /// Read the configuration file again, replacing the snapshot, e.g. after it has been
+    /// changed by someone else.
+    pub fn reload(&self) -> Result<(), ConrigError> {
+        let _updating = self.updating.lock().unwrap_or_else(PoisonError::into_inner);
+        self.swap(self.file.read()?);
+        Ok(())
+    }
+}
+
+impl<T: Clone + Serialize> ConrigCell<T> {
+    /// Mutate a copy of the configuration, write it into the file, then make it the
+    /// current snapshot.
+    ///
+    /// The file is written according to its [`atomic_write`] option. If the write fails,
+    /// the error is returned and the snapshot is left unchanged.
+    ///
+    /// [`atomic_write`]: crate::ConfigOption#structfield.atomic_write
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R, ConrigError> {
+        let _updating = self.updating.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut value = T::clone(&self.read());
+        let result = f(&mut value);
+        self.file.write(&value)?;
+        self.swap(value);
+        Ok(result)
+    }
+}
+
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+impl<T: Serialize> ConrigCell<T> {
+    /// Get a snapshot of the current configuration as a [`serde_json::Value`].
+    pub fn as_json(&self) -> Result<serde_json::Value, ConrigError> {
+        serde_json::to_value(&*self.read())
+            .map_err(|err| crate::LangError::JsonError(err).into())
+    }
+}
+
+impl<'p, T: Serialize + DeserializeOwned + Default> ConfigPathMetadata<'p, T> {
+    /// Get a [`ConrigCell`] of the configuration, holding its current value.
+    ///
+    /// The value is read as by [`read_or_default`].
+    ///
+    /// [`read_or_default`]: crate::ConfigPathMetadata::read_or_default
+    pub fn cell(&self) -> Result<ConrigCell<T>, ConrigError> {
+        let file = self.search_config_file()?.fallback_default()?;
+        let value = file.read_or_try_else(|| self.default_value())?;
+        Ok(ConrigCell::new(file, value))
+    }
+}
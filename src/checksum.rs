@@ -0,0 +1,293 @@
+//! Integrity checksums of configuration files.
+//!
+//! If [`ConfigOption.checksum`] is enabled, every write records the SHA-256 digest of the
+//! configuration file in a sidecar file, `<file>.sha256`, and every read verifies it, failing
+//! with an [`IntegrityError`] if the file has been modified behind the application's back,
+//! or corrupted.
+//!
+//! A configuration file without a sidecar is accepted as is, so existing and hand-written
+//! files keep working. To accept a file edited by hand, delete its sidecar.
+//!
+//! **Note**: The checksum detects corruptions and accidental edits. It's not a signature:
+//! anyone able to modify the configuration file can update the sidecar as well.
+//!
+//! ## Content hashes
+//!
+//! [`ConfigFile::content_hash`] digests the configuration itself rather than its file,
+//! so applications can skip rebuilding expensive derived data, e.g. an index or a compiled
+//! theme, when the configuration hasn't changed since their last run. The hash of the last
+//! processed configuration is kept in a sidecar file, `<file>.processed`, by task name.
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::parser::ConfigFile;
+//! use conrig::FileFormat;
+//!
+//! let file = ConfigFile::new(FileFormat::Toml, std::env::temp_dir().join("conrig-hash.toml"));
+//! file.write(&toml::toml! { theme = "dark" })?;
+//!
+//! if let Some(hash) = file.needs_processing("theme")? {
+//!     // rebuild the theme...
+//!     file.set_processed_hash("theme", &hash)?;
+//! }
+//! assert_eq!(file.needs_processing("theme")?, None);
+//! # std::fs::remove_file(conrig::checksum::processed_path(&file.path)).unwrap();
+//! # std::fs::remove_file(&file.path).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ConfigOption.checksum`]: crate::ConfigOption#structfield.checksum
+//! [`IntegrityError`]: crate::ConrigError::IntegrityError
+
+use crate::error::{ConrigResultExt, FileSystemError, Operation};
+use crate::parser::ConfigFile;
+use crate::value::Value;
+use crate::{filesystem, ConrigError};
+use std::path::{Path, PathBuf};
+
+/// The path of the checksum of a configuration file: `<file>.sha256`.
+pub fn checksum_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".sha256");
+    path.with_file_name(file_name)
+}
+
+/// The checksum of some contents: their SHA-256 digest, in lowercase hexadecimal.
+pub fn checksum(contents: &[u8]) -> String {
+    sha256(contents)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// The path of the hashes of the last processed configurations: `<file>.processed`.
+///
+/// Each line holds the name of a task and the [content hash] it processed last.
+///
+/// [content hash]: crate::parser::ConfigFile::content_hash
+pub fn processed_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".processed");
+    path.with_file_name(file_name)
+}
+
+impl ConfigFile {
+    /// The hash of the configuration: the SHA-256 digest of its document,
+    /// merged with the included files, the drop-in fragments and the profile file.
+    ///
+    /// The hash only depends on the values: neither the formatting, the comments,
+    /// nor the order of the keys of the tables change it.
+    pub fn content_hash(&self) -> Result<String, ConrigError> {
+        let document = self
+            .read_document()
+            .context_path(Operation::Read, &self.path)?;
+        let mut bytes = Vec::new();
+        digest_value(&document, &mut bytes);
+        Ok(checksum(&bytes))
+    }
+
+    /// The [content hash] last processed by a task, if any.
+    ///
+    /// See [`set_processed_hash`] for more information.
+    ///
+    /// [content hash]: crate::parser::ConfigFile::content_hash
+    /// [`set_processed_hash`]: crate::parser::ConfigFile::set_processed_hash
+    pub fn last_processed_hash(&self, task: &str) -> Result<Option<String>, ConrigError> {
+        Ok(self
+            .processed_hashes()?
+            .into_iter()
+            .find(|(name, _)| name == task)
+            .map(|(_, hash)| hash))
+    }
+
+    /// Record the [content hash] processed by a task, into the [sidecar] of the file.
+    ///
+    /// [content hash]: crate::parser::ConfigFile::content_hash
+    /// [sidecar]: crate::checksum::processed_path
+    pub fn set_processed_hash(&self, task: &str, hash: &str) -> Result<(), ConrigError> {
+        let mut hashes = self.processed_hashes()?;
+        match hashes.iter_mut().find(|(name, _)| name == task) {
+            Some((_, previous)) => *previous = hash.to_owned(),
+            None => hashes.push((task.to_owned(), hash.to_owned())),
+        }
+        let contents: String = hashes
+            .iter()
+            .map(|(name, hash)| format!("{name} {hash}\n"))
+            .collect();
+        let path = processed_path(&self.path);
+        filesystem::write(&path, contents.as_bytes()).context_path(Operation::Write, &path)
+    }
+
+    /// Get the current [content hash] if it differs from the one last processed by a task,
+    /// or `None` if the task is up to date.
+    ///
+    /// Once the task has processed the configuration, record the hash with
+    /// [`set_processed_hash`].
+    ///
+    /// [content hash]: crate::parser::ConfigFile::content_hash
+    /// [`set_processed_hash`]: crate::parser::ConfigFile::set_processed_hash
+    pub fn needs_processing(&self, task: &str) -> Result<Option<String>, ConrigError> {
+        let hash = self.content_hash()?;
+        let last = self.last_processed_hash(task)?;
+        Ok((last.as_deref() != Some(hash.as_str())).then_some(hash))
+    }
+
+    /// The task names and hashes recorded in the sidecar, in order.
+    fn processed_hashes(&self) -> Result<Vec<(String, String)>, ConrigError> {
+        let path = processed_path(&self.path);
+        if !filesystem::exists(&path) {
+            return Ok(Vec::new());
+        }
+        let contents = filesystem::read(&path).context_path(Operation::Read, &path)?;
+        Ok(String::from_utf8_lossy(&contents)
+            .lines()
+            .filter_map(|line| line.trim().rsplit_once(' '))
+            .map(|(name, hash)| (name.trim_end().to_owned(), hash.to_owned()))
+            .collect())
+    }
+}
+
+/// Encode a value unambiguously, with the keys of the tables sorted.
+fn digest_value(value: &Value, bytes: &mut Vec<u8>) {
+    let string = |tag: u8, string: &str, bytes: &mut Vec<u8>| {
+        bytes.push(tag);
+        bytes.extend_from_slice(&(string.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(string.as_bytes());
+    };
+    match value {
+        Value::Null => bytes.push(b'n'),
+        Value::Bool(value) => bytes.extend_from_slice(if *value { b"t" } else { b"f" }),
+        Value::Integer(value) => {
+            bytes.push(b'i');
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Value::Float(value) => {
+            bytes.push(b'd');
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Value::String(value) => string(b's', value, bytes),
+        Value::Array(array) => {
+            bytes.push(b'a');
+            bytes.extend_from_slice(&(array.len() as u64).to_le_bytes());
+            for value in array {
+                digest_value(value, bytes);
+            }
+        }
+        Value::Table(table) => {
+            bytes.push(b'm');
+            bytes.extend_from_slice(&(table.len() as u64).to_le_bytes());
+            let mut entries: Vec<_> = table.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            for (key, value) in entries {
+                string(b'k', key, bytes);
+                digest_value(value, bytes);
+            }
+        }
+    }
+}
+
+/// Record the checksum of a configuration file in its sidecar.
+pub(crate) fn seal(path: &Path) -> Result<(), ConrigError> {
+    let contents = filesystem::read(path)?;
+    filesystem::write(
+        &checksum_path(path),
+        format!("{}\n", checksum(&contents)).as_bytes(),
+    )?;
+    Ok(())
+}
+
+/// Check the contents of a configuration file against its sidecar, if there's one.
+pub(crate) fn verify(path: &Path, contents: &[u8]) -> Result<(), ConrigError> {
+    let sidecar = checksum_path(path);
+    if !filesystem::exists(&sidecar) {
+        return Ok(());
+    }
+    let expected = String::from_utf8(filesystem::read(&sidecar)?)
+        .map_err(|err| {
+            let err = std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+            FileSystemError::read_config(&sidecar)(err)
+        })?
+        .trim()
+        .to_ascii_lowercase();
+    let actual = checksum(contents);
+    if expected != actual {
+        return Err(ConrigError::IntegrityError {
+            path: path.to_path_buf(),
+            expected,
+            actual,
+        });
+    }
+    Ok(())
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The SHA-256 digest of some bytes, as specified by FIPS 180-4.
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((bytes.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
@@ -0,0 +1,138 @@
+//! Command-line integration, based on [`clap`].
+//!
+//! Flatten [`ConfigArgs`] into your own command-line parser,
+//! and read your configuration with [`ConfigPathMetadata::read_with_args`]:
+//!
+//! ```rust
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use clap::Parser;
+//! use conrig::cli::ConfigArgs;
+//! use conrig::value::Value;
+//!
+//! #[derive(Parser)]
+//! struct Cli {
+//!     #[command(flatten)]
+//!     config: ConfigArgs,
+//! }
+//!
+//! let cli = Cli::parse_from(["app", "--set", "server.port=8080"]);
+//! assert_eq!(cli.config.set, [("server.port".to_owned(), Value::Integer(8080))]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Flatten [`InitArgs`] into an `init` subcommand to create a configuration file,
+//! optionally from a [preset][crate::preset], with [`ConfigPathMetadata::init_with_args`].
+
+use crate::error::{ConrigResultExt, Operation, UnknownFormat};
+use crate::parser::ConfigFile;
+use crate::value::{from_value, to_value, Value};
+use crate::{ConfigPathMetadata, ConrigError, FileFormat};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Command-line arguments overriding the file-based configuration.
+#[derive(Debug, Clone, Default, PartialEq, clap::Args)]
+pub struct ConfigArgs {
+    /// Use the given configuration file instead of searching for one.
+    #[arg(long, value_name = "PATH")]
+    pub config_path: Option<PathBuf>,
+    /// The format of the configuration file.
+    #[arg(long, value_name = "FORMAT", value_parser = parse_format)]
+    pub config_format: Option<FileFormat>,
+    /// Override a configuration value, e.g. `--set server.port=8080`.
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_override)]
+    pub set: Vec<(String, Value)>,
+}
+
+impl<'p, T: DeserializeOwned> ConfigPathMetadata<'p, T> {
+    /// Read a configuration file, with the command-line arguments applied over it.
+    ///
+    /// If `--config-path` is specified, that file is read instead of searching for one,
+    /// and its format is detected from its extension unless `--config-format` is given.
+    /// Every `--set` override is then applied, in order, as the highest-priority layer.
+    pub fn read_with_args(&self, args: &ConfigArgs) -> Result<T, ConrigError> {
+        let mut file = match &args.config_path {
+            Some(path) => ConfigFile::new(
+                self.config_option
+                    .format_of(path)
+                    .unwrap_or(self.default_format),
+                path.clone(),
+            ),
+            None => self.search_config_file()?.fallback_default()?,
+        };
+        if let Some(file_format) = args.config_format {
+            file.file_format = file_format;
+        }
+
+        if args.set.is_empty() {
+            return file.read();
+        }
+        let mut value: Value = file.read()?;
+        for (key, item) in &args.set {
+            value
+                .set_path(key, item.clone())
+                .context_path(Operation::Read, &file.path)?;
+        }
+        from_value(value).context_path(Operation::Read, &file.path)
+    }
+}
+
+/// Command-line arguments creating a new configuration file, e.g. `init --preset recommended`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, clap::Args)]
+pub struct InitArgs {
+    /// Fill the configuration file with the given preset instead of the default value.
+    #[arg(long, value_name = "NAME")]
+    pub preset: Option<String>,
+    /// Overwrite the configuration file if it already exists.
+    #[arg(long)]
+    pub force: bool,
+}
+
+impl<'p, T: Serialize + Default> ConfigPathMetadata<'p, T> {
+    /// Create a new configuration file, as requested by the command-line arguments.
+    ///
+    /// The file is filled with the `--preset` if specified, or with the [default value].
+    /// Existing configuration files are only overwritten with `--force`.
+    /// See [`init_preset`] for more information.
+    ///
+    /// [default value]: crate::ConfigPathMetadata::default_value
+    /// [`init_preset`]: crate::ConfigPathMetadata::init_preset
+    pub fn init_with_args(&self, args: &InitArgs) -> Result<ConfigFile, ConrigError> {
+        let value = match &args.preset {
+            Some(name) => self.preset(name)?,
+            None => to_value(&self.default_value()?)?,
+        };
+        self.init_with(&value, args.force)
+    }
+}
+
+fn parse_format(input: &str) -> Result<FileFormat, String> {
+    input.parse().map_err(|err: UnknownFormat| err.to_string())
+}
+
+/// Parse a `key=value` pair.
+///
+/// Booleans and numbers are recognized, and anything else is kept as a string.
+/// Wrap the value in double quotes to force a string, e.g. `--set name="42"`.
+fn parse_override(input: &str) -> Result<(String, Value), String> {
+    let (key, value) = input
+        .split_once('=')
+        .ok_or_else(|| format!("expected `KEY=VALUE`, found `{input}`"))?;
+    let value = if let Some(value) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Value::String(value.to_owned())
+    } else if let Ok(value) = value.parse() {
+        Value::Bool(value)
+    } else if let Ok(value) = value.parse() {
+        Value::Integer(value)
+    } else if let Ok(value) = value.parse() {
+        Value::Float(value)
+    } else {
+        Value::String(value.to_owned())
+    };
+    Ok((key.trim().to_owned(), value))
+}
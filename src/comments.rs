@@ -0,0 +1,434 @@
+//! Retention of the users' comments.
+//!
+//! Serializing a configuration drops the comments of its file. If [`ConfigOption.keep_comments`]
+//! is enabled, [`ConfigFile::write`] gathers the comments of the file being replaced, by the
+//! dotted key path they describe, and puts them back above the same keys.
+//!
+//! Comments are gathered from, and written into, TOML and YAML files. The formats without
+//! comments, e.g. JSON, keep them in a sidecar file instead, `<file>.comments`, written in the
+//! same format. So [converting][ConfigFile::convert] a commented TOML file into JSON, and back
+//! later, loses nothing.
+//!
+//! Comments are attached to the key on the next line, or on the same line for trailing ones,
+//! which are moved above their key. Comments inside arrays, or after the last key, are dropped.
+//!
+//! [`ConfigOption.keep_comments`]: crate::ConfigOption#structfield.keep_comments
+
+use crate::error::{ConrigResultExt, FileSystemError, Operation};
+use crate::parser::ConfigFile;
+use crate::value::{to_value, Value};
+use crate::{filesystem, ConrigError, FileFormat};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// The comments of a configuration file, by the dotted key path they describe.
+///
+/// Multi-line comments are joined with `\n`, without their comment markers.
+pub type Comments = BTreeMap<String, String>;
+
+/// The path of the comments sidecar of a configuration file: `<file>.comments`.
+pub fn comments_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".comments");
+    path.with_file_name(file_name)
+}
+
+/// Whether comments can be gathered from, and written into, the files of a format.
+pub fn supports_comments(file_format: FileFormat) -> bool {
+    match file_format {
+        #[cfg(feature = "toml")]
+        FileFormat::Toml => true,
+        #[cfg(feature = "yaml")]
+        FileFormat::Yaml => true,
+        _ => false,
+    }
+}
+
+/// Gather the comments of the content of a configuration file.
+///
+/// Returns no comments for the formats which don't [support] them.
+///
+/// [support]: crate::comments::supports_comments
+pub fn extract(file_format: FileFormat, contents: &str) -> Comments {
+    let mut comments = Comments::new();
+    let Some(lines) = scan(file_format, contents) else {
+        return comments;
+    };
+    let mut pending = Vec::new();
+    for (raw, line) in contents.lines().zip(lines) {
+        if line.standalone {
+            pending.extend(line.comment);
+            continue;
+        }
+        if let Some(key) = line.key {
+            pending.extend(line.comment);
+            if !pending.is_empty() {
+                comments.entry(key).or_insert_with(|| pending.join("\n"));
+            }
+        } else if raw.trim().is_empty() {
+            continue;
+        }
+        // the comments of anything but a key, e.g. an array item, are dropped.
+        pending.clear();
+    }
+    comments
+}
+
+/// Put comments back above their keys in the content of a configuration file.
+///
+/// Each comment is written once, above the first occurrence of its key.
+/// The content is returned unchanged for the formats which don't [support] comments.
+///
+/// [support]: crate::comments::supports_comments
+pub fn inject(file_format: FileFormat, contents: &str, comments: &Comments) -> String {
+    let Some(lines) = scan(file_format, contents).filter(|_| !comments.is_empty()) else {
+        return contents.to_owned();
+    };
+    let mut injected = BTreeSet::new();
+    let mut output = String::with_capacity(contents.len());
+    for (raw, line) in contents.lines().zip(lines) {
+        if let Some((key, comment)) = line
+            .key
+            .and_then(|key| comments.get_key_value(key.as_str()))
+        {
+            if injected.insert(key) {
+                for comment in comment.lines() {
+                    output.push_str(line.indent);
+                    output.push('#');
+                    if !comment.is_empty() {
+                        output.push(' ');
+                        output.push_str(comment);
+                    }
+                    output.push('\n');
+                }
+            }
+        }
+        output.push_str(raw);
+        output.push('\n');
+    }
+    output
+}
+
+impl ConfigFile {
+    /// Gather the comments of the configuration file.
+    ///
+    /// They're read from the file itself for the formats which [support] comments,
+    /// and from its [sidecar] otherwise. Returns no comments if there's no such file.
+    ///
+    /// [support]: crate::comments::supports_comments
+    /// [sidecar]: crate::comments::comments_path
+    pub fn comments(&self) -> Result<Comments, ConrigError> {
+        let path = if supports_comments(self.file_format) {
+            self.path.clone()
+        } else {
+            comments_path(&self.path)
+        };
+        if !filesystem::exists(&path) {
+            return Ok(Comments::new());
+        }
+        let contents = filesystem::read(&path)
+            .map(|contents| String::from_utf8_lossy(&contents).into_owned())
+            .context_path(Operation::Read, &path)?;
+        if supports_comments(self.file_format) {
+            let contents = crate::upgrade::strip_header(self.file_format, &contents);
+            return Ok(extract(self.file_format, contents));
+        }
+        self.file_format
+            .parse_str(&contents, Some(&path))
+            .context_path(Operation::Read, &path)
+    }
+
+    /// Convert the configuration file into another one, typically of another format,
+    /// along with its comments.
+    ///
+    /// The document is converted as is, without merging the included files. The comments
+    /// are written into `target`, or into its [sidecar] if its format doesn't support them.
+    ///
+    /// [sidecar]: crate::comments::comments_path
+    pub fn convert(&self, target: &ConfigFile) -> Result<(), ConrigError> {
+        let document: Value = self
+            .read_contents()
+            .and_then(|contents| self.file_format.parse_str(&contents, Some(&self.path)))
+            .context_path(Operation::Read, &self.path)?;
+        let comments = self.comments()?;
+        target.write_commented(&document, &comments)
+    }
+
+    /// Serialize and write a value into the configuration file, along with `comments`.
+    ///
+    /// The comments of the keys missing from `value` are dropped.
+    pub(crate) fn write_commented<T: Serialize>(
+        &self,
+        value: &T,
+        comments: &Comments,
+    ) -> Result<(), ConrigError> {
+        if supports_comments(self.file_format) {
+            return self.write_with(value, |writer| {
+                let mut contents = Vec::new();
+                self.file_format.write(value, &mut contents)?;
+                let contents = inject(
+                    self.file_format,
+                    &String::from_utf8_lossy(&contents),
+                    comments,
+                );
+                writer
+                    .write_all(contents.as_bytes())
+                    .map_err(|err| FileSystemError::write_config(&self.path)(err).into())
+            });
+        }
+
+        self.write_with(value, |mut writer| {
+            self.file_format.write(value, &mut writer)
+        })?;
+        let sidecar = comments_path(&self.path);
+        let document = to_value(value).context_path(Operation::Write, &self.path)?;
+        let comments: Comments = comments
+            .iter()
+            .filter(|(key, _)| document.get_path(key).is_some())
+            .map(|(key, comment)| (key.clone(), comment.clone()))
+            .collect();
+        if comments.is_empty() && !filesystem::exists(&sidecar) {
+            return Ok(());
+        }
+        let mut contents = Vec::new();
+        self.file_format
+            .write(&comments, &mut contents)
+            .and_then(|_| Ok(filesystem::write(&sidecar, &contents)?))
+            .context_path(Operation::Write, &sidecar)
+    }
+}
+
+/// A line of a configuration file, as far as comments are concerned.
+struct Line<'a> {
+    /// The leading whitespaces of the line.
+    indent: &'a str,
+    /// The dotted key path defined on the line.
+    key: Option<String>,
+    /// The text of the comment on the line.
+    comment: Option<&'a str>,
+    /// Whether the line is nothing but a comment.
+    standalone: bool,
+}
+
+impl<'a> Line<'a> {
+    fn new(line: &'a str) -> (Self, &'a str) {
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        let mut line = Self {
+            indent,
+            key: None,
+            comment: None,
+            standalone: false,
+        };
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            line.comment = Some(comment.strip_prefix(' ').unwrap_or(comment).trim_end());
+            line.standalone = true;
+        }
+        (line, trimmed)
+    }
+}
+
+/// Scan the lines of a configuration file, or `None` if its format doesn't support comments.
+fn scan(file_format: FileFormat, contents: &str) -> Option<Vec<Line<'_>>> {
+    match file_format {
+        #[cfg(feature = "toml")]
+        FileFormat::Toml => Some(scan_toml(contents)),
+        #[cfg(feature = "yaml")]
+        FileFormat::Yaml => Some(scan_yaml(contents)),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "toml")]
+fn scan_toml(contents: &str) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut table = Vec::new();
+    // the delimiter of the multi-line string being scanned, if any.
+    let mut string = None;
+    // the nesting of the multi-line array or inline table being scanned.
+    let mut depth = 0;
+    for raw in contents.lines() {
+        let (mut line, trimmed) = Line::new(raw);
+        if let Some(delimiter) = string {
+            line.comment = None;
+            line.standalone = false;
+            if raw.matches(delimiter).count() % 2 == 1 {
+                string = None;
+            }
+        } else if depth > 0 {
+            line.comment = None;
+            line.standalone = false;
+            depth += nesting(trimmed);
+        } else if !line.standalone {
+            if let Some(header) = trimmed.strip_prefix('[') {
+                let header = header.strip_prefix('[').unwrap_or(header);
+                let end = find_unquoted(header, ']').unwrap_or(header.len());
+                table = split_key(&header[..end]);
+                line.key = Some(table.join("."));
+                line.comment = trailing_comment(&header[end..]);
+            } else if let Some(equal) = find_unquoted(trimmed, '=') {
+                let mut key = table.clone();
+                key.extend(split_key(&trimmed[..equal]));
+                line.key = Some(key.join("."));
+                let value = &trimmed[equal + 1..];
+                string = ["\"\"\"", "'''"]
+                    .into_iter()
+                    .find(|delimiter| value.matches(delimiter).count() % 2 == 1);
+                depth = nesting(value).max(0);
+                line.comment = trailing_comment(value);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The number of brackets and braces opened but not closed in a line, before its comment.
+#[cfg(feature = "toml")]
+fn nesting(text: &str) -> isize {
+    let mut depth = 0;
+    let mut rest = text;
+    loop {
+        let next = ['[', ']', '{', '}', '#']
+            .into_iter()
+            .filter_map(|target| Some((find_unquoted(rest, target)?, target)))
+            .min();
+        match next {
+            Some((_, '#')) | None => return depth,
+            Some((index, target)) => {
+                depth += if matches!(target, '[' | '{') { 1 } else { -1 };
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+}
+
+#[cfg(feature = "yaml")]
+fn scan_yaml(contents: &str) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    // the keys of the enclosing mappings, along with their indentation.
+    // sequences are `None`, as the keys inside them have no dotted path.
+    let mut stack: Vec<(usize, Option<String>)> = Vec::new();
+    // the indentation of the key owning the block scalar being scanned, if any.
+    let mut block = None;
+    for raw in contents.lines() {
+        let (mut line, trimmed) = Line::new(raw);
+        let mut indent = line.indent.len();
+        if let Some(owner) = block {
+            if trimmed.is_empty() || indent > owner {
+                line.comment = None;
+                line.standalone = false;
+                lines.push(line);
+                continue;
+            }
+            block = None;
+        }
+        if line.standalone || trimmed.is_empty() || trimmed == "---" || trimmed == "..." {
+            lines.push(line);
+            continue;
+        }
+
+        let mut content = trimmed;
+        while let Some(item) = content
+            .strip_prefix("- ")
+            .or_else(|| (content == "-").then_some(""))
+        {
+            while stack.last().is_some_and(|(owner, _)| *owner > indent) {
+                stack.pop();
+            }
+            if !stack
+                .last()
+                .is_some_and(|(owner, key)| *owner == indent && key.is_none())
+            {
+                stack.push((indent, None));
+            }
+            indent += content.len() - item.len();
+            content = item;
+        }
+
+        let colon = find_unquoted(content, ':').filter(|&colon| {
+            content[colon + 1..].is_empty() || content[colon + 1..].starts_with([' ', '\t'])
+        });
+        let Some(colon) = colon else {
+            line.comment = trailing_comment(content);
+            lines.push(line);
+            continue;
+        };
+        while stack.last().is_some_and(|(owner, _)| *owner >= indent) {
+            stack.pop();
+        }
+        let key = unquote(content[..colon].trim()).to_owned();
+        line.key = stack
+            .iter()
+            .map(|(_, key)| key.as_deref())
+            .chain([Some(key.as_str())])
+            .collect::<Option<Vec<_>>>()
+            .map(|path| path.join("."));
+        stack.push((indent, Some(key)));
+
+        let value = &content[colon + 1..];
+        if value.trim_start().starts_with(['|', '>']) {
+            block = Some(indent);
+        }
+        line.comment = trailing_comment(value);
+        lines.push(line);
+    }
+    lines
+}
+
+/// Find a character outside of the quoted strings.
+fn find_unquoted(text: &str, target: char) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, char) in text.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if char == '\\' => escaped = true,
+            Some(open) if char == open => quote = None,
+            Some(_) => {}
+            None if char == target => return Some(index),
+            None if char == '"' || char == '\'' => quote = Some(char),
+            None => {}
+        }
+    }
+    None
+}
+
+/// The trailing comment of a line, after its key.
+fn trailing_comment(text: &str) -> Option<&str> {
+    let mut rest = text;
+    let mut offset = 0;
+    while let Some(index) = find_unquoted(rest, '#') {
+        let start = offset + index;
+        // `#` only starts a comment after a whitespace, e.g. not in `color: #fff` of YAML.
+        if start == 0 || text[..start].ends_with([' ', '\t']) {
+            let comment = text[start + 1..].trim();
+            return (!comment.is_empty()).then_some(comment);
+        }
+        offset = start + 1;
+        rest = &text[offset..];
+    }
+    None
+}
+
+/// Split a dotted key, e.g. `server."host.name"`, into its unquoted segments.
+#[cfg(feature = "toml")]
+fn split_key(key: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut rest = key;
+    while let Some(dot) = find_unquoted(rest, '.') {
+        segments.push(unquote(rest[..dot].trim()).to_owned());
+        rest = &rest[dot + 1..];
+    }
+    segments.push(unquote(rest.trim()).to_owned());
+    segments
+}
+
+/// Strip the quotes around a key.
+fn unquote(key: &str) -> &str {
+    ["\"", "'"]
+        .into_iter()
+        .find_map(|quote| key.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(key)
+}
@@ -0,0 +1,107 @@
+//! Deprecation of configuration keys.
+//!
+//! Register [`Deprecation`]s in [`ConfigOption::deprecations`] to retire configuration keys:
+//!
+//! - Until your [application's version][version] passes [`removed_after`],
+//!   reading a configuration containing the key triggers a [`DeprecatedKey`] warning.
+//! - Afterward, the read fails with a [`RemovedKey`] error.
+//!
+//! [`ConfigOption::deprecations`]: crate::ConfigOption#structfield.deprecations
+//! [version]: crate::ConfigOption#structfield.app_version
+//! [`removed_after`]: crate::deprecation::Deprecation#structfield.removed_after
+//! [`DeprecatedKey`]: crate::error::ConrigWarning::DeprecatedKey
+//! [`RemovedKey`]: crate::ConrigError::RemovedKey
+
+use crate::error::ConrigWarning;
+use crate::value::Value;
+use crate::{ConfigOption, ConrigError};
+use std::cmp::Ordering;
+
+/// A deprecated configuration key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deprecation {
+    /// The dotted path of the key, e.g. `server.address`.
+    pub key: &'static str,
+    /// The last version of your application supporting the key.
+    pub removed_after: &'static str,
+    /// The dotted path of the key replacing the deprecated one, if any.
+    pub replacement: Option<&'static str>,
+}
+
+impl Deprecation {
+    /// Create a new `Deprecation` without replacement.
+    pub const fn new(key: &'static str, removed_after: &'static str) -> Self {
+        Self {
+            key,
+            removed_after,
+            replacement: None,
+        }
+    }
+
+    /// Modify the [`replacement`] field.
+    ///
+    /// [`replacement`]: crate::deprecation::Deprecation#structfield.replacement
+    pub const fn with_replacement(mut self, replacement: &'static str) -> Self {
+        self.replacement = Some(replacement);
+        self
+    }
+
+    /// A hint about the replacement, used by error messages.
+    pub(crate) fn hint(&self) -> String {
+        match self.replacement {
+            Some(replacement) => format!(" Use `{replacement}` instead."),
+            None => String::new(),
+        }
+    }
+}
+
+/// Check a document against the deprecations registered in the options.
+pub(crate) fn check_deprecations(
+    document: &Value,
+    config_option: &ConfigOption,
+) -> Result<(), ConrigError> {
+    for deprecation in config_option.deprecations {
+        if document.get_path(deprecation.key).is_none() {
+            continue;
+        }
+        match config_option.app_version {
+            Some(version) if compare_versions(version, deprecation.removed_after).is_gt() => {
+                return Err(ConrigError::RemovedKey(*deprecation));
+            }
+            _ => config_option.warn(ConrigWarning::DeprecatedKey(*deprecation)),
+        }
+    }
+    Ok(())
+}
+
+/// Compare two dotted versions, like `1.2.0` and `1.10`.
+///
+/// Missing components are considered as `0`, and pre-release or build metadata is ignored.
+pub(crate) fn compare_versions(lhs: &str, rhs: &str) -> Ordering {
+    fn components(version: &str) -> impl Iterator<Item = &str> {
+        version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+    }
+
+    let mut lhs = components(lhs);
+    let mut rhs = components(rhs);
+    loop {
+        let ordering = match (lhs.next(), rhs.next()) {
+            (None, None) => return Ordering::Equal,
+            (l, r) => {
+                let (l, r) = (l.unwrap_or("0"), r.unwrap_or("0"));
+                match (l.parse::<u64>(), r.parse::<u64>()) {
+                    (Ok(l), Ok(r)) => l.cmp(&r),
+                    _ => l.cmp(r),
+                }
+            }
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
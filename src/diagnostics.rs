@@ -0,0 +1,122 @@
+//! Rich diagnostics of configuration errors, for command line applications.
+//!
+//! A [`Diagnostic`] renders a [`ConrigError`] along with the code frame of the configuration
+//! file when it can't be parsed: the offending line, its neighbour, and a caret under the
+//! location reported by the backend.
+//!
+//! ```text
+//! error: Fail to read `~/.config/app/conrig.toml`: Parse error at line 2, column 8: ...
+//!   --> ~/.config/app/conrig.toml:2:8
+//!    |
+//!  1 | name = "conrig"
+//!  2 | port =
+//!    |        ^ invalid string
+//! ```
+//!
+//! ## Example
+//!
+//! ```rust
+//! use conrig::parser::ConfigFile;
+//! use conrig::FileFormat;
+//!
+//! let path = std::env::temp_dir().join("conrig-diagnostics.json");
+//! std::fs::write(&path, "{\n  \"name\": }").unwrap();
+//! let error = ConfigFile::new(FileFormat::Json, path.clone())
+//!     .read::<serde_json::Value>()
+//!     .unwrap_err();
+//! let diagnostic = error.diagnostic().to_string();
+//! assert!(diagnostic.contains(" 2 |   \"name\": }\n"));
+//! eprintln!("{diagnostic}");
+//! # std::fs::remove_file(path).unwrap();
+//! ```
+
+use crate::error::ParseError;
+use crate::filesystem;
+use crate::ConrigError;
+use std::fmt;
+
+/// A [`ConrigError`] rendered with the code frame of the configuration file.
+///
+/// See the [`diagnostics`](crate::diagnostics) module for more information.
+#[derive(Debug)]
+pub struct Diagnostic<'e> {
+    error: &'e ConrigError,
+    source: Option<String>,
+}
+
+impl ConrigError {
+    /// Create a rich [`Diagnostic`] of the error.
+    ///
+    /// If the configuration file can't be parsed, it's read again to show its code frame.
+    pub fn diagnostic(&self) -> Diagnostic<'_> {
+        let source = self
+            .parse_error()
+            .and_then(|error| error.path.as_deref())
+            .and_then(|path| filesystem::read(path).ok())
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+        Diagnostic {
+            error: self,
+            source,
+        }
+    }
+}
+
+impl<'e> Diagnostic<'e> {
+    /// Use another source code for the code frame, e.g. a document which isn't read from a file.
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// The error being diagnosed.
+    pub fn error(&self) -> &'e ConrigError {
+        self.error
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "error: {}", self.error)?;
+        let Some(error) = self.error.parse_error() else {
+            return Ok(());
+        };
+        let Some(line) = error.line else {
+            return Ok(());
+        };
+        let column = error.column.unwrap_or(1);
+        if let Some(path) = &error.path {
+            writeln!(f, "  --> {}:{line}:{column}", path.display())?;
+        }
+        let Some(source) = &self.source else {
+            return Ok(());
+        };
+        let lines: Vec<&str> = source.lines().collect();
+        let Some(text) = line.checked_sub(1).and_then(|index| lines.get(index)) else {
+            return Ok(());
+        };
+        code_frame(f, error, &lines, line, text)
+    }
+}
+
+/// Write the offending line and the previous one, with a caret under the column.
+fn code_frame(
+    f: &mut fmt::Formatter<'_>,
+    error: &ParseError,
+    lines: &[&str],
+    line: usize,
+    text: &str,
+) -> fmt::Result {
+    let width = line.to_string().len();
+    writeln!(f, " {:width$} |", "")?;
+    if line > 1 {
+        writeln!(f, " {:>width$} | {}", line - 1, lines[line - 2])?;
+    }
+    writeln!(f, " {line:>width$} | {text}")?;
+    // keep the tabs, so the caret is aligned whatever their width.
+    let indent: String = text
+        .chars()
+        .take(error.column.unwrap_or(1).saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    writeln!(f, " {:width$} | {indent}^ {}", "", error.message)
+}
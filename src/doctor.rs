@@ -0,0 +1,181 @@
+//! Diagnostics of the configuration environment, e.g. for bug reports.
+//!
+//! [`ConfigPathMetadata::doctor`] collects everything the resolution of a configuration
+//! depends on into a [`DoctorReport`]: the candidate files, the one in use, and the
+//! environment variables involved, along with whether each of them affected the resolution.
+//! Its [`Display`][fmt::Display] implementation is a plain-text dump for support.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+//!
+//! conrig!(const CONFIG<()> = {
+//!     project_path: ProjectPath::new("org", "foo", "conrig-doctor"),
+//!     config_name: &["conrig-doctor"],
+//!     config_option: ConfigOption::DEFAULT_CONFIG.with_path_env_var("CONRIG_DOCTOR_PATH"),
+//!     extra_files: &[],
+//!     extra_folders: &[],
+//!     default_format: FileFormat::Toml,
+//! });
+//!
+//! let report = CONFIG.doctor();
+//! assert!(report.selected.is_none());
+//! assert!(report.environment.iter().any(|var| var.name == "CONRIG_DOCTOR_PATH"));
+//! eprintln!("{report}");
+//! ```
+
+use crate::parser::ConfigFile;
+use crate::{ConfigPathMetadata, FileFormat};
+use std::fmt;
+use std::path::PathBuf;
+
+/// The environment variables locating the base directories, by platform.
+#[cfg(windows)]
+const DIRECTORY_VARS: &[&str] = &["USERPROFILE", "APPDATA", "LOCALAPPDATA", "PROGRAMDATA"];
+#[cfg(not(windows))]
+const DIRECTORY_VARS: &[&str] = &[
+    "HOME",
+    "XDG_CONFIG_HOME",
+    "XDG_DATA_HOME",
+    "XDG_STATE_HOME",
+    "XDG_CACHE_HOME",
+];
+
+/// An environment variable involved in the resolution of a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvVar {
+    /// The name of the variable, e.g. `XDG_CONFIG_HOME`.
+    pub name: String,
+    /// The value of the variable, or `None` if it's unset or not valid unicode.
+    pub value: Option<String>,
+    /// What the variable controls, e.g. `configuration file path`.
+    pub purpose: &'static str,
+    /// Whether the variable affected the resolution of the configuration.
+    pub affected: bool,
+}
+
+/// Everything the resolution of a configuration depends on.
+///
+/// See the [`doctor`](crate::doctor) module for more information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoctorReport {
+    /// The configuration file in use, if any.
+    pub selected: Option<PathBuf>,
+    /// Every existing configuration file, in the order of priority.
+    ///
+    /// See [`ConfigPathMetadata::search_all_config_files`] for more information.
+    pub candidates: Vec<(PathBuf, FileFormat)>,
+    /// The directories searched, besides the extra files and folders.
+    pub directories: Vec<PathBuf>,
+    /// The environment variables involved.
+    pub environment: Vec<EnvVar>,
+    /// The errors met while resolving the configuration.
+    pub problems: Vec<String>,
+}
+
+impl<T> ConfigPathMetadata<'_, T> {
+    /// Collect everything the resolution of the configuration depends on.
+    ///
+    /// Errors don't stop the diagnostics: they're collected into [`problems`].
+    ///
+    /// [`problems`]: crate::doctor::DoctorReport#structfield.problems
+    pub fn doctor(&self) -> DoctorReport {
+        let mut problems = Vec::new();
+        let selected = match self.search_config_file() {
+            Ok(source) => source
+                .path
+                .map(|path| ConfigFile::new(source.file_format, path)),
+            Err(err) => {
+                problems.push(err.to_string());
+                None
+            }
+        };
+        let candidates = self.search_all_config_files().unwrap_or_else(|err| {
+            problems.push(err.to_string());
+            Vec::new()
+        });
+
+        let option = &self.config_option;
+        let mut directories = Vec::new();
+        directories.extend(self.sys_dirs());
+        match self.local_dirs() {
+            Ok(local_dirs) => directories.extend(local_dirs),
+            Err(err) => problems.push(err.to_string()),
+        }
+        if option.system_wide {
+            directories.extend(self.machine_dir());
+        }
+
+        let mut environment = Vec::new();
+        let mut push = |name: &str, purpose, affected: &dyn Fn(&str) -> bool| {
+            let value = std::env::var(name).ok();
+            environment.push(EnvVar {
+                name: name.to_owned(),
+                affected: value
+                    .as_deref()
+                    .is_some_and(|value| !value.is_empty() && affected(value)),
+                value,
+                purpose,
+            });
+        };
+        if let Some(name) = option.path_env_var {
+            push(name, "configuration file path", &|_| true);
+        }
+        if let Some(name) = option.profile_env {
+            let profile_file = selected
+                .clone()
+                .and_then(|file| file.with_config_option(*option).profile_file());
+            push(name, "profile", &|_| profile_file.is_some());
+        }
+        if let Some(name) = option.variant_env {
+            let selectable = option.variant_key.is_some() && option.variant.is_none();
+            push(name, "variant", &|_| selectable);
+        }
+        for name in DIRECTORY_VARS {
+            push(name, "base directory", &|value| {
+                directories.iter().any(|dir| dir.starts_with(value))
+            });
+        }
+
+        DoctorReport {
+            selected: selected.map(|file| file.path),
+            candidates,
+            directories,
+            environment,
+            problems,
+        }
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.selected {
+            Some(path) => writeln!(f, "Configuration file: {}", path.display())?,
+            None => writeln!(f, "Configuration file: none")?,
+        }
+        writeln!(f, "Candidates:")?;
+        for (path, file_format) in &self.candidates {
+            writeln!(f, "  {} ({file_format:?})", path.display())?;
+        }
+        writeln!(f, "Directories:")?;
+        for dir in &self.directories {
+            writeln!(f, "  {}", dir.display())?;
+        }
+        writeln!(f, "Environment:")?;
+        for var in &self.environment {
+            let used = if var.affected { ", used" } else { "" };
+            match &var.value {
+                Some(value) => writeln!(f, "  {}={value} ({}{used})", var.name, var.purpose)?,
+                None => writeln!(f, "  {} unset ({})", var.name, var.purpose)?,
+            }
+        }
+        if !self.problems.is_empty() {
+            writeln!(f, "Problems:")?;
+            for problem in &self.problems {
+                writeln!(f, "  {problem}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! Configuration files holding several documents.
+//!
+//! Some configurations are lists, e.g. of rules or pipeline stages, more naturally stored as one
+//! document per item than as a single array. [`ConfigFile::read_all_docs`] and
+//! [`ConfigFile::write_all_docs`] read and write such files as a `Vec<T>`:
+//!
+//! - YAML files hold documents separated by `---` lines;
+//! - JSON files hold one document per line, i.e. [JSON Lines]. Any whitespace between the
+//!   documents is accepted when reading.
+//!
+//! Map the `jsonl` extension to JSON with [`ConfigOption.extensions`] to search for JSON Lines
+//! files. The other formats hold a single document, and fail with a [`SingleDocument`] error.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::parser::ConfigFile;
+//! use conrig::FileFormat;
+//!
+//! let path = std::env::temp_dir().join("conrig-rules.yaml");
+//! let file = ConfigFile::new(FileFormat::Yaml, path.clone());
+//! file.write_all_docs(&["first".to_owned(), "second".to_owned()])?;
+//! assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\n---\nsecond\n");
+//! assert_eq!(file.read_all_docs::<String>()?, ["first", "second"]);
+//! # std::fs::remove_file(path).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [JSON Lines]: https://jsonlines.org
+//! [`ConfigOption.extensions`]: crate::ConfigOption#structfield.extensions
+//! [`SingleDocument`]: crate::ConrigError::SingleDocument
+
+use crate::error::{ConrigResultExt, LangError, Operation};
+use crate::parser::ConfigFile;
+use crate::value::{to_value, Value};
+use crate::{ConfigPathMetadata, ConrigError, FileFormat};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+impl FileFormat {
+    /// Whether a file of the format can hold several documents.
+    pub fn supports_multi_docs(&self) -> bool {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => true,
+            #[cfg(feature = "yaml")]
+            Self::Yaml => true,
+            _ => false,
+        }
+    }
+
+    /// Deserialize every document from a given `&str`.
+    ///
+    /// Returns `None` if the format holds a single document.
+    #[allow(unused_variables)]
+    pub fn read_all_str<'de, T: Deserialize<'de>>(
+        &self,
+        input: &'de str,
+    ) -> Option<Result<Vec<T>, LangError>> {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => Some(
+                serde_json::Deserializer::from_str(input)
+                    .into_iter()
+                    .collect::<Result<_, _>>()
+                    .map_err(LangError::JsonError),
+            ),
+            // an empty YAML stream is read as a single null document.
+            #[cfg(feature = "yaml")]
+            Self::Yaml if input.trim().is_empty() => Some(Ok(Vec::new())),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => Some(
+                serde_yaml::Deserializer::from_str(input)
+                    .map(T::deserialize)
+                    .collect::<Result<_, _>>()
+                    .map_err(LangError::YamlError),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Serialize every document and write them to a writer.
+    ///
+    /// A [`SingleDocument`] error is returned if the format holds a single document.
+    ///
+    /// [`SingleDocument`]: crate::ConrigError::SingleDocument
+    #[allow(unreachable_code, unused_variables)]
+    pub fn write_all<T: Serialize>(
+        &self,
+        inputs: &[T],
+        writer: &mut impl Write,
+    ) -> Result<(), ConrigError> {
+        match self {
+            #[cfg(feature = "json")]
+            Self::Json => {
+                for input in inputs {
+                    serde_json::to_writer(&mut *writer, input).map_err(LangError::JsonError)?;
+                    writer
+                        .write_all(b"\n")
+                        .map_err(|e| LangError::JsonError(serde_json::Error::io(e)))?;
+                }
+            }
+            #[cfg(feature = "yaml")]
+            Self::Yaml => {
+                for (index, input) in inputs.iter().enumerate() {
+                    if index > 0 {
+                        writer
+                            .write_all(b"---\n")
+                            .map_err(|e| LangError::YamlError(serde::ser::Error::custom(e)))?;
+                    }
+                    serde_yaml::to_writer(&mut *writer, input).map_err(LangError::YamlError)?;
+                }
+            }
+            _ => return Err(ConrigError::SingleDocument(*self)),
+        }
+        Ok(())
+    }
+}
+
+impl ConfigFile {
+    /// Read and deserialize every document of the configuration file.
+    ///
+    /// Each document is checked against the [deprecations] and the [`strict_keys`],
+    /// like [`read`] does. A [`SingleDocument`] error is returned if the format of the file
+    /// holds a single document. See the [`documents`](crate::documents) module for more
+    /// information.
+    ///
+    /// [deprecations]: crate::ConfigOption#structfield.deprecations
+    /// [`strict_keys`]: crate::ConfigOption#structfield.strict_keys
+    /// [`read`]: crate::parser::ConfigFile::read
+    /// [`SingleDocument`]: crate::ConrigError::SingleDocument
+    pub fn read_all_docs<T: DeserializeOwned>(&self) -> Result<Vec<T>, ConrigError> {
+        self.with_contents(|contents| {
+            let documents: Vec<Value> = self
+                .file_format
+                .read_all_str(contents)
+                .ok_or(ConrigError::SingleDocument(self.file_format))?
+                .map_err(|err| {
+                    ConrigError::parse(err, self.file_format, Some(&self.path), contents, None)
+                })?;
+            documents
+                .into_iter()
+                .map(|document| self.decode(document))
+                .collect()
+        })
+        .context_path(Operation::Read, &self.path)
+    }
+
+    /// Serialize and write every document into the configuration file,
+    /// with the same guarantees as [`write`].
+    ///
+    /// Each document is checked against the [policy], if any. A [`SingleDocument`] error is
+    /// returned if the format of the file holds a single document.
+    /// See the [`documents`](crate::documents) module for more information.
+    ///
+    /// [`write`]: crate::parser::ConfigFile::write
+    /// [policy]: crate::ConfigOption#structfield.policy
+    /// [`SingleDocument`]: crate::ConrigError::SingleDocument
+    pub fn write_all_docs<T: Serialize>(&self, values: &[T]) -> Result<(), ConrigError> {
+        if !self.file_format.supports_multi_docs() {
+            return Err(ConrigError::SingleDocument(self.file_format))
+                .context_path(Operation::Write, &self.path);
+        }
+        let mut file = self.clone();
+        if let Some(policy) = file.policy.take() {
+            for value in values {
+                let value = to_value(value).context_path(Operation::Write, &self.path)?;
+                crate::policy::check(&policy, &value).context_path(Operation::Write, &self.path)?;
+            }
+        }
+        file.write_with(&values, |mut writer| {
+            self.file_format.write_all(values, &mut writer)
+        })
+    }
+}
+
+impl<T: DeserializeOwned> ConfigPathMetadata<'_, T> {
+    /// Read every document of a configuration file, using the default searching method.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_all_docs()`.
+    pub fn read_all_docs(&self) -> Result<Vec<T>, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_all_docs()
+    }
+}
+
+impl<T: Serialize> ConfigPathMetadata<'_, T> {
+    /// Write every document into a configuration file, using the default searching method.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.write_all_docs(&foo)`.
+    pub fn write_all_docs(&self, values: &[T]) -> Result<(), ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .write_all_docs(values)
+    }
+}
@@ -0,0 +1,352 @@
+//! Field-level encryption.
+//!
+//! Wrap the sensitive fields of your configuration in [`Encrypted`], and register a [`Cipher`]
+//! with [`set_cipher`]: those fields are encrypted when they're written, and decrypted when
+//! they're read, while the rest of the file stays plaintext and hand-editable.
+//!
+//! Encrypted values are written as `ENC[<ciphertext>]`. Any other string is taken as plaintext,
+//! so users can type a new secret into the file by hand: it's encrypted on the next write.
+//!
+//! Ciphers can tag their values with a [key id][Cipher::key_id], written as
+//! `ENC:<key id>[<ciphertext>]`, so values encrypted with a retired key are detected.
+//! [`ConfigFile::rotate_key`] re-encrypts a file with a new key.
+//!
+//! `conrig` doesn't ship any cryptography: the [`Cipher`] is yours, e.g. backed by a key from
+//! the system keyring or an environment variable.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use conrig::encrypt::{set_cipher, Cipher, Encrypted};
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! struct Reversed;
+//!
+//! impl Cipher for Reversed {
+//!     fn encrypt(&self, plaintext: &str) -> Result<String, conrig::encrypt::CipherError> {
+//!         Ok(plaintext.chars().rev().collect())
+//!     }
+//!
+//!     fn decrypt(&self, ciphertext: &str) -> Result<String, conrig::encrypt::CipherError> {
+//!         Ok(ciphertext.chars().rev().collect())
+//!     }
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     user: String,
+//!     password: Encrypted<String>,
+//! }
+//!
+//! set_cipher(Reversed);
+//! ```
+//!
+//! [`ConfigFile::rotate_key`]: crate::parser::ConfigFile::rotate_key
+
+use crate::error::{ConrigResultExt, Operation};
+use crate::parser::ConfigFile;
+use crate::redact::MASK;
+use crate::value::Value;
+use crate::ConrigError;
+use serde::de;
+use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// The error returned by a [`Cipher`].
+pub type CipherError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A cipher encrypting and decrypting the [`Encrypted`] values.
+pub trait Cipher: Send + Sync {
+    /// Encrypt a plaintext value.
+    ///
+    /// The ciphertext must be a valid string, e.g. base64-encoded.
+    fn encrypt(&self, plaintext: &str) -> Result<String, CipherError>;
+    /// Decrypt a value encrypted by [`Cipher::encrypt`].
+    fn decrypt(&self, ciphertext: &str) -> Result<String, CipherError>;
+    /// The identifier of the key used by the cipher, e.g. `v2`. Default: `None`.
+    ///
+    /// If this is set, encrypted values are written as `ENC:<key id>[<ciphertext>]`,
+    /// so values encrypted with another key are detected instead of being decrypted
+    /// into garbage. It must not contain any `[`.
+    fn key_id(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The cipher used by every [`Encrypted`] value.
+static CIPHER: RwLock<Option<Arc<dyn Cipher>>> = RwLock::new(None);
+
+/// Register the cipher used to encrypt and decrypt the [`Encrypted`] values,
+/// replacing the previous one.
+pub fn set_cipher(cipher: impl Cipher + 'static) {
+    *CIPHER.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(cipher));
+}
+
+/// Unregister the cipher. [`Encrypted`] values can't be read or written afterwards.
+pub fn clear_cipher() {
+    *CIPHER.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+fn cipher() -> Option<Arc<dyn Cipher>> {
+    CIPHER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+const PREFIX: &str = "ENC";
+const SUFFIX: &str = "]";
+
+/// Split an encrypted value into its key id, if any, and its ciphertext.
+///
+/// The key id precedes the brackets, so the ciphertext may hold any character.
+/// Returns `None` if the value is plaintext.
+fn split(text: &str) -> Option<(Option<&str>, &str)> {
+    let inner = text.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?;
+    if let Some(ciphertext) = inner.strip_prefix('[') {
+        return Some((None, ciphertext));
+    }
+    let (key_id, ciphertext) = inner.strip_prefix(':')?.split_once('[')?;
+    Some((Some(key_id), ciphertext))
+}
+
+/// Encrypt a plaintext value, and format it with the key id of the cipher.
+fn seal(cipher: &dyn Cipher, plaintext: &str) -> Result<String, CipherError> {
+    let ciphertext = cipher.encrypt(plaintext)?;
+    Ok(match cipher.key_id() {
+        Some(key_id) => format!("{PREFIX}:{key_id}[{ciphertext}{SUFFIX}"),
+        None => format!("{PREFIX}[{ciphertext}{SUFFIX}"),
+    })
+}
+
+/// Decrypt an encrypted value, checking it's encrypted with the key of the cipher.
+fn open(
+    cipher: &dyn Cipher,
+    key_id: Option<&str>,
+    ciphertext: &str,
+) -> Result<String, CipherError> {
+    if key_id != cipher.key_id() {
+        return Err(format!(
+            "the value is encrypted with the key `{}`, while the cipher uses the key `{}`",
+            key_id.unwrap_or_default(),
+            cipher.key_id().unwrap_or_default(),
+        )
+        .into());
+    }
+    cipher.decrypt(ciphertext)
+}
+
+/// Collect the key ids of the encrypted values of a document, without duplicates.
+///
+/// Values encrypted without any key id are reported as `None`.
+/// A document whose values are encrypted with several keys, e.g. after an interrupted manual
+/// edit, holds more than one key id.
+pub fn key_ids(document: &Value) -> Vec<Option<String>> {
+    let mut key_ids = Vec::new();
+    visit(document, &mut String::new(), &mut |_, key_id| {
+        let key_id = key_id.map(str::to_owned);
+        if !key_ids.contains(&key_id) {
+            key_ids.push(key_id);
+        }
+    });
+    key_ids
+}
+
+/// Decrypt every encrypted value of a document with `old`, and encrypt it again with `new`.
+///
+/// Values already encrypted with the key of `new` are left untouched,
+/// so the rotation can be resumed. If any value is encrypted with a third key,
+/// a [`MixedKeys`] error listing them is returned and the document is left unchanged.
+///
+/// Returns the number of values rotated.
+///
+/// [`MixedKeys`]: crate::ConrigError::MixedKeys
+pub fn rotate_key(
+    document: &mut Value,
+    old: &dyn Cipher,
+    new: &dyn Cipher,
+) -> Result<usize, ConrigError> {
+    let mut unexpected = Vec::new();
+    visit(document, &mut String::new(), &mut |path, key_id| {
+        if key_id != old.key_id() && key_id != new.key_id() {
+            unexpected.push(path.to_owned());
+        }
+    });
+    if !unexpected.is_empty() {
+        return Err(ConrigError::MixedKeys(unexpected));
+    }
+    let mut rotated = document.clone();
+    let count = reseal(&mut rotated, old, new).map_err(ConrigError::Cipher)?;
+    *document = rotated;
+    Ok(count)
+}
+
+/// Re-encrypt in place every value of a document encrypted with the key of `old`.
+///
+/// Returns the number of values re-encrypted.
+fn reseal(value: &mut Value, old: &dyn Cipher, new: &dyn Cipher) -> Result<usize, CipherError> {
+    match value {
+        Value::String(text) => match split(text) {
+            Some((key_id, ciphertext)) if key_id == old.key_id() => {
+                let plaintext = open(old, key_id, ciphertext)?;
+                *text = seal(new, &plaintext)?;
+                Ok(1)
+            }
+            _ => Ok(0),
+        },
+        Value::Table(table) => table
+            .values_mut()
+            .map(|value| reseal(value, old, new))
+            .sum(),
+        Value::Array(array) => array.iter_mut().map(|value| reseal(value, old, new)).sum(),
+        _ => Ok(0),
+    }
+}
+
+/// Call `f` with the path and the key id of every encrypted value of a document.
+fn visit(value: &Value, path: &mut String, f: &mut impl FnMut(&str, Option<&str>)) {
+    let mut child = |path: &mut String, key: &str, value: &Value| {
+        let len = path.len();
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(key);
+        visit(value, path, f);
+        path.truncate(len);
+    };
+    match value {
+        Value::String(text) => {
+            if let Some((key_id, _)) = split(text) {
+                f(path, key_id);
+            }
+        }
+        Value::Table(table) => table
+            .iter()
+            .for_each(|(key, value)| child(path, key, value)),
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .for_each(|(index, value)| child(path, &index.to_string(), value)),
+        _ => {}
+    }
+}
+
+/// A value encrypted in the configuration file.
+///
+/// The value is converted to a string with [`Display`][fmt::Display] before being encrypted,
+/// and parsed back with [`FromStr`] once decrypted.
+/// Writing it, or reading it once encrypted, fails if no [`Cipher`] has been
+/// [registered][set_cipher].
+///
+/// The [`Debug`][fmt::Debug] output of an `Encrypted` value is masked.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Encrypted<T>(pub T);
+
+impl<T> Encrypted<T> {
+    /// Wrap a plaintext value.
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Get the plaintext value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Encrypted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Encrypted<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Encrypted<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> fmt::Debug for Encrypted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Encrypted").field(&MASK).finish()
+    }
+}
+
+impl<T: fmt::Display> Serialize for Encrypted<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let cipher = cipher().ok_or_else(|| ser::Error::custom("no cipher is registered"))?;
+        let sealed = seal(&*cipher, &self.0.to_string())
+            .map_err(|e| ser::Error::custom(format!("cannot encrypt a value: {e}")))?;
+        serializer.serialize_str(&sealed)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Encrypted<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let plaintext = match split(&text) {
+            Some((key_id, ciphertext)) => {
+                let cipher =
+                    cipher().ok_or_else(|| de::Error::custom("no cipher is registered"))?;
+                open(&*cipher, key_id, ciphertext)
+                    .map_err(|e| de::Error::custom(format!("cannot decrypt a value: {e}")))?
+            }
+            None => text,
+        };
+        plaintext.parse().map(Self).map_err(de::Error::custom)
+    }
+}
+
+impl ConfigFile {
+    /// Re-encrypt the encrypted values of the configuration file with a new key.
+    ///
+    /// Every value encrypted by `old` is decrypted, then encrypted again by `new`,
+    /// and the file is replaced atomically, whatever [`atomic_write`] is.
+    /// Plaintext values are left untouched, and the included files are kept apart.
+    ///
+    /// Returns the number of values rotated. See [`rotate_key`] for more information.
+    ///
+    /// [`atomic_write`]: crate::ConfigOption#structfield.atomic_write
+    pub fn rotate_key(&self, old: &dyn Cipher, new: &dyn Cipher) -> Result<usize, ConrigError> {
+        let mut document = self
+            .read_contents()
+            .and_then(|contents| self.file_format.parse_str(&contents, Some(&self.path)))
+            .context_path(Operation::Read, &self.path)?;
+        let rotated =
+            rotate_key(&mut document, old, new).context_path(Operation::Write, &self.path)?;
+        if rotated > 0 {
+            let file = ConfigFile {
+                config_option: self.config_option.with_atomic_write(true),
+                ..self.clone()
+            };
+            file.write(&document)?;
+        }
+        Ok(rotated)
+    }
+
+    /// Collect the key ids of the encrypted values of the configuration file.
+    ///
+    /// See [`key_ids`] for more information.
+    pub fn key_ids(&self) -> Result<Vec<Option<String>>, ConrigError> {
+        let document: Value = self
+            .read_contents()
+            .and_then(|contents| self.file_format.parse_str(&contents, Some(&self.path)))
+            .context_path(Operation::Read, &self.path)?;
+        Ok(key_ids(&document))
+    }
+}
@@ -1,10 +1,15 @@
 //! The error types used by `conrig`.
 //!
-//! Note that `conrig` itself won't produce any error,
-//! and all error messages are come from either the parser/serializer
-//! or the backend file system (the os).
+//! Note that most error messages come from either the parser/serializer
+//! or the backend file system (the os). `conrig` itself only produces errors
+//! when a configuration breaks the rules set in the [`ConfigOption`].
+//!
+//! [`ConfigOption`]: crate::ConfigOption
 
 use cfg_if::cfg_if;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
 
 cfg_if! {
@@ -96,6 +101,31 @@ pub use serde_yaml::Error as YamlError;
 
 pub use std::io::Error as IoError;
 
+/// Error triggered by the built-in property list parser.
+#[cfg(feature = "plist")]
+#[cfg_attr(docsrs, doc(cfg(feature = "plist")))]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "{message}{}",
+    if *.line > 0 { format!(" at line {} column {}", .line, .column) } else { String::new() }
+)]
+pub struct PlistError {
+    /// The description of the error.
+    pub message: String,
+    /// The line of the error, starting from 1, or `0` for binary property lists.
+    pub line: usize,
+    /// The column of the error, starting from 1, or `0` for binary property lists.
+    pub column: usize,
+}
+
+pub use crate::value::ValueError;
+use crate::apply::ApplierError;
+use crate::deprecation::Deprecation;
+use crate::encrypt::CipherError;
+use crate::script::ScriptError;
+use crate::secret::SecretError;
+use crate::{FileFormat, SearchLocation};
+
 /// Any error triggerable by `conrig`.
 #[derive(Debug, Error)]
 pub enum ConrigError {
@@ -115,11 +145,780 @@ pub enum ConrigError {
         FileSystemError,
     ),
 
+    /// Error triggered while converting a configuration from or into a [`Value`].
+    ///
+    /// [`Value`]: crate::value::Value
+    #[error("Value conversion error: {0}")]
+    ValueError(
+        #[from]
+        #[source]
+        ValueError,
+    ),
+
+    /// The configuration contains a key which has been removed from your application.
+    ///
+    /// See [`deprecation`][crate::deprecation] for more information.
+    #[error(
+        "Configuration key `{}` has been removed after version {}.{}",
+        .0.key,
+        .0.removed_after,
+        .0.hint()
+    )]
+    RemovedKey(Deprecation),
+
+    /// The configuration contains keys unknown to your configuration structure.
+    ///
+    /// This is only triggered if [`ConfigOption.strict_keys`] is enabled.
+    ///
+    /// [`ConfigOption.strict_keys`]: crate::ConfigOption#structfield.strict_keys
+    #[error("Unknown configuration keys: {}", join_keys(.0))]
+    UnknownKeys(Vec<UnknownKey>),
+
+    /// The configuration doesn't match the JSON Schema of your configuration structure.
+    ///
+    /// See [`schema`][crate::schema] for more information.
+    #[error("Configuration doesn't match its schema: {}", join_violations(.0))]
+    SchemaViolation(Vec<Violation>),
+
+    /// The configuration has been rejected by its validation.
+    ///
+    /// See [`validate`][crate::validate] for more information.
+    #[error("Invalid configuration: {}", join_violations(.0))]
+    Validation(Vec<Violation>),
+
+    /// More than one configuration file is found.
+    ///
+    /// Contains the conflicting paths, in the order of priority.
+    ///
+    /// This is only triggered if [`ConfigOption.error_on_ambiguity`] is enabled.
+    ///
+    /// [`ConfigOption.error_on_ambiguity`]: crate::ConfigOption#structfield.error_on_ambiguity
+    #[error(
+        "Multiple configuration files found: {}. Remove all of them but one.",
+        join_paths(.0)
+    )]
+    AmbiguousConfig(Vec<PathBuf>),
+
+    /// The configuration file is read-only.
+    ///
+    /// This is only triggered if [`ConfigOption.read_only_sys`] is enabled.
+    ///
+    /// [`ConfigOption.read_only_sys`]: crate::ConfigOption#structfield.read_only_sys
+    #[error("Configuration file `{}` is read-only.", .0.display())]
+    ReadOnly(PathBuf),
+
+    /// Writing would change some keys locked by the administrators' policy.
+    ///
+    /// Contains the dotted key paths of these keys.
+    ///
+    /// See [`ConfigOption.policy`] for more information.
+    ///
+    /// [`ConfigOption.policy`]: crate::ConfigOption#structfield.policy
+    #[error("Keys locked by policy: {}", .0.join(", "))]
+    LockedKeys(Vec<String>),
+
+    /// The configuration file doesn't match its checksum: it has been tampered with or corrupted.
+    ///
+    /// See [`ConfigOption.checksum`] for more information.
+    ///
+    /// [`ConfigOption.checksum`]: crate::ConfigOption#structfield.checksum
+    #[error("Configuration file `{}` doesn't match its checksum.", .path.display())]
+    IntegrityError {
+        /// The path of the configuration file.
+        path: PathBuf,
+        /// The recorded checksum.
+        expected: String,
+        /// The checksum of the current content.
+        actual: String,
+    },
+
+    /// The configuration file is writable by other users.
+    ///
+    /// This is only triggered if [`ConfigOption.check_permissions`] is enabled.
+    ///
+    /// [`ConfigOption.check_permissions`]: crate::ConfigOption#structfield.check_permissions
+    #[error("Configuration file `{}` is writable by other users.", .0.display())]
+    InsecurePermissions(PathBuf),
+
     /// This error indicates that the configuration cannot be found by the file searcher.
     ///
+    /// Contains the paths probed by the search, in order, and why each of them was rejected.
+    /// This is empty if the configuration doesn't come from a file search.
+    ///
     /// Consider adding a default path or creating an empty configuration before reading it.
-    #[error("No configuration file found.")]
-    NoConfigurationFile,
+    #[error("No configuration file found.{}", join_searched(.0))]
+    NoConfigurationFile(Vec<SearchedPath>),
+
+    /// The [`config_name`] of a `ConfigPathMetadata` is empty,
+    /// so no default configuration file can be named.
+    ///
+    /// [`config_name`]: crate::ConfigPathMetadata#structfield.config_name
+    #[error("No configuration name is specified.")]
+    EmptyConfigName,
+
+    /// No [`DefaultProvider`] is registered to provide the default value.
+    ///
+    /// [`DefaultProvider`]: crate::provider::DefaultProvider
+    #[error("No default value provider is registered.")]
+    NoDefaultProvider,
+
+    /// No default template has the selected name.
+    ///
+    /// See [`Templates`] for more information.
+    ///
+    /// [`Templates`]: crate::provider::Templates
+    #[error("No default template is named `{0}`.")]
+    UnknownTemplate(String),
+
+    /// No preset has the selected name.
+    ///
+    /// See the [`preset`] module for more information.
+    ///
+    /// [`preset`]: crate::preset
+    #[error("No preset is named `{0}`.")]
+    UnknownPreset(String),
+
+    /// No variant of the configuration has the selected name.
+    ///
+    /// See [`ConfigOption.variant_key`] for more information.
+    ///
+    /// [`ConfigOption.variant_key`]: crate::ConfigOption#structfield.variant_key
+    #[error("No configuration variant is named `{0}`.")]
+    UnknownVariant(String),
+
+    /// This error indicates that a configuration file includes itself, directly or not.
+    ///
+    /// See [`ConfigOption.include_key`] for more information.
+    ///
+    /// [`ConfigOption.include_key`]: crate::ConfigOption#structfield.include_key
+    #[error("Configuration file `{}` includes itself.", .0.display())]
+    IncludeCycle(PathBuf),
+
+    /// The [`Cipher`] failed to encrypt or decrypt a value.
+    ///
+    /// [`Cipher`]: crate::encrypt::Cipher
+    #[error("Cannot encrypt or decrypt a value: {0}")]
+    Cipher(#[source] CipherError),
+
+    /// Some values are encrypted with a key other than the expected one.
+    ///
+    /// Contains the dotted key paths of these values.
+    ///
+    /// See [`ConfigFile::rotate_key`] for more information.
+    ///
+    /// [`ConfigFile::rotate_key`]: crate::parser::ConfigFile::rotate_key
+    #[error("Values encrypted with an unexpected key: {}", .0.join(", "))]
+    MixedKeys(Vec<String>),
+
+    /// The format of the configuration file can't hold several documents.
+    ///
+    /// See the [`documents`][crate::documents] module for more information.
+    #[error("`{0}` files can't hold several documents.")]
+    SingleDocument(FileFormat),
+
+    /// A remote configuration can't be fetched.
+    ///
+    /// See [`RemoteSource`] for more information.
+    ///
+    /// [`RemoteSource`]: crate::remote::RemoteSource
+    #[cfg(feature = "http")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+    #[error("Cannot fetch `{url}`: {source}")]
+    Remote {
+        /// The URL of the configuration.
+        url: String,
+        /// The underlying error.
+        #[source]
+        source: crate::remote::TransportError,
+    },
+
+    /// The applier of a configuration section failed.
+    ///
+    /// See [`Appliers`] for more information.
+    ///
+    /// [`Appliers`]: crate::apply::Appliers
+    #[error("Cannot apply the `{section}` settings: {source}")]
+    Apply {
+        /// The section of the failed applier.
+        section: String,
+        /// The underlying error.
+        #[source]
+        source: ApplierError,
+    },
+
+    /// The script post-processing the configuration failed.
+    ///
+    /// See [`ConfigOption.script`] for more information.
+    ///
+    /// [`ConfigOption.script`]: crate::ConfigOption#structfield.script
+    #[error("Script `{}` failed: {source}", .path.display())]
+    Script {
+        /// The path of the script.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: ScriptError,
+    },
+
+    /// The [`SecretStore`] failed to load or store a secret.
+    ///
+    /// [`SecretStore`]: crate::secret::SecretStore
+    #[error("Cannot access the secret `{key}`: {source}")]
+    Secret {
+        /// The dotted key path of the secret.
+        key: String,
+        /// The underlying error.
+        #[source]
+        source: SecretError,
+    },
+
+    /// An error annotated with the file and the operation which triggered it.
+    ///
+    /// See [`ConrigResultExt::context_path`] for more information.
+    #[error("Fail to {operation} `{}`: {source}", .path.display())]
+    Context {
+        /// The operation which failed.
+        operation: Operation,
+        /// The file involved.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: Box<ConrigError>,
+    },
+
+    /// A backend error annotated with its location in the document.
+    ///
+    /// Like a [`Context`], this is stripped by [`root`], which returns the [`LangError`],
+    /// or the [`ValueError`] of a merged document which doesn't match the configuration type.
+    /// It's usually wrapped in a `Context` carrying the path of the file.
+    /// See [`ConrigError::parse_error`] for more information.
+    ///
+    /// [`Context`]: crate::ConrigError::Context
+    /// [`root`]: crate::ConrigError::root
+    #[error("Parse error{}: {}", .error.location(), .error.message)]
+    Parse {
+        /// The location of the error.
+        error: Box<ParseError>,
+        /// The underlying [`LangError`] or [`ValueError`].
+        #[source]
+        source: Box<ConrigError>,
+    },
+}
+
+impl ConrigError {
+    /// Annotate the error with the file and the operation which triggered it.
+    ///
+    /// Errors which already carry a path are returned unchanged,
+    /// so the innermost, most precise context is kept.
+    pub fn context_path(self, operation: Operation, path: impl Into<PathBuf>) -> Self {
+        if self.path().is_some() {
+            return self;
+        }
+        Self::Context {
+            operation,
+            path: path.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The file involved in the error, if known.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Context { path, .. }
+            | Self::ReadOnly(path)
+            | Self::IntegrityError { path, .. }
+            | Self::InsecurePermissions(path)
+            | Self::IncludeCycle(path) => Some(path),
+            Self::FileSystemError(err) => err.path(),
+            _ => None,
+        }
+    }
+
+    /// Whether the error is caused by a missing configuration file.
+    ///
+    /// This is `true` for [`NoConfigurationFile`], and for the file system errors
+    /// whose kind is [`NotFound`][std::io::ErrorKind::NotFound].
+    ///
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn is_missing(&self) -> bool {
+        match self.root() {
+            Self::NoConfigurationFile(_) => true,
+            Self::FileSystemError(err) => err
+                .io_error()
+                .is_some_and(|err| err.kind() == std::io::ErrorKind::NotFound),
+            _ => false,
+        }
+    }
+
+    /// The underlying error, with every [`Context`] and [`Parse`] stripped.
+    ///
+    /// Match on this to handle a particular kind of error.
+    ///
+    /// [`Context`]: crate::ConrigError::Context
+    /// [`Parse`]: crate::ConrigError::Parse
+    pub fn root(&self) -> &ConrigError {
+        match self {
+            Self::Context { source, .. } | Self::Parse { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    /// The location of the error in the configuration document, if it can't be parsed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use conrig::parser::ConfigFile;
+    /// use conrig::FileFormat;
+    ///
+    /// let path = std::env::temp_dir().join("conrig-parse-error.toml");
+    /// std::fs::write(&path, "name = \"conrig\"\nport = \n").unwrap();
+    /// let error = ConfigFile::new(FileFormat::Toml, path.clone())
+    ///     .read::<toml::Table>()
+    ///     .unwrap_err();
+    /// let parse_error = error.parse_error().unwrap();
+    /// assert_eq!(parse_error.path.as_deref(), Some(path.as_path()));
+    /// assert_eq!((parse_error.line, parse_error.column), (Some(2), Some(8)));
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn parse_error(&self) -> Option<&ParseError> {
+        match self {
+            Self::Parse { error, .. } => Some(error),
+            Self::Context { source, .. } => source.parse_error(),
+            _ => None,
+        }
+    }
+
+    /// Annotate a backend error with its location in the document it's raised from,
+    /// and the dotted key path of the value failing to deserialize, if known.
+    pub(crate) fn parse(
+        source: LangError,
+        format: FileFormat,
+        path: Option<&Path>,
+        input: &str,
+        key: Option<String>,
+    ) -> Self {
+        let location = source.location(Some(input));
+        Self::Parse {
+            error: Box::new(ParseError {
+                path: path.map(Path::to_path_buf),
+                line: location.map(|(line, _)| line),
+                column: location.map(|(_, column)| column),
+                key: key.filter(|key| !key.is_empty()),
+                message: source.message(location),
+                format,
+            }),
+            source: Box::new(source.into()),
+        }
+    }
+
+    /// Annotate the error of a document which doesn't match the configuration type
+    /// with the dotted key path of the value failing to deserialize, if known.
+    pub(crate) fn decode(
+        source: ValueError,
+        format: FileFormat,
+        path: &Path,
+        key: Option<String>,
+    ) -> Self {
+        Self::Parse {
+            error: Box::new(ParseError {
+                path: Some(path.to_path_buf()),
+                line: None,
+                column: None,
+                key: key.filter(|key| !key.is_empty()),
+                message: source.to_string(),
+                format,
+            }),
+            source: Box::new(source.into()),
+        }
+    }
+}
+
+/// Extension methods for `Result`s whose error converts into a [`ConrigError`].
+pub trait ConrigResultExt<T> {
+    /// Annotate the error with the file and the operation which triggered it.
+    ///
+    /// See [`ConrigError::context_path`] for more information.
+    fn context_path(
+        self,
+        operation: Operation,
+        path: impl Into<PathBuf>,
+    ) -> Result<T, ConrigError>;
+
+    /// Replace the error with `T::default()` if it's caused by a missing configuration file,
+    /// and propagate any other error.
+    ///
+    /// See [`ConrigError::is_missing`] for more information.
+    fn or_default_on_missing(self) -> Result<T, ConrigError>
+    where
+        T: Default;
+
+    /// Replace the error with the value returned by `default` if it's caused by
+    /// a missing configuration file, and propagate any other error.
+    ///
+    /// See [`ConrigError::is_missing`] for more information.
+    fn or_else_on_missing(self, default: impl FnOnce() -> T) -> Result<T, ConrigError>;
+
+    /// Replace the error with `None` if it's caused by a missing configuration file,
+    /// and propagate any other error.
+    ///
+    /// See [`ConrigError::is_missing`] for more information.
+    fn ok_on_missing(self) -> Result<Option<T>, ConrigError>;
+}
+
+impl<T, E: Into<ConrigError>> ConrigResultExt<T> for Result<T, E> {
+    fn context_path(
+        self,
+        operation: Operation,
+        path: impl Into<PathBuf>,
+    ) -> Result<T, ConrigError> {
+        self.map_err(|err| err.into().context_path(operation, path))
+    }
+
+    fn or_default_on_missing(self) -> Result<T, ConrigError>
+    where
+        T: Default,
+    {
+        self.or_else_on_missing(T::default)
+    }
+
+    fn or_else_on_missing(self, default: impl FnOnce() -> T) -> Result<T, ConrigError> {
+        match self.ok_on_missing()? {
+            Some(value) => Ok(value),
+            None => Ok(default()),
+        }
+    }
+
+    fn ok_on_missing(self) -> Result<Option<T>, ConrigError> {
+        match self.map_err(Into::into) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.is_missing() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// The operation during which an error is triggered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Reading a configuration file.
+    Read,
+    /// Writing a configuration file.
+    Write,
+    /// Migrating a configuration file from a legacy location.
+    Migrate,
+    /// Importing a foreign configuration file.
+    Import,
+    /// Searching for a configuration file.
+    Search,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Migrate => "migrate",
+            Self::Import => "import",
+            Self::Search => "search",
+        })
+    }
+}
+
+fn join_keys(keys: &[UnknownKey]) -> String {
+    keys.iter()
+        .map(UnknownKey::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn join_violations(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(Violation::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("`{}`", path.display()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn join_searched(searched: &[SearchedPath]) -> String {
+    searched
+        .iter()
+        .map(|path| format!("\n  {path}"))
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+/// A path probed by the search of a configuration file.
+///
+/// See [`ConfigPathMetadata::searched_paths`] for more information.
+///
+/// [`ConfigPathMetadata::searched_paths`]: crate::ConfigPathMetadata::searched_paths
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchedPath {
+    /// The path of the candidate configuration file.
+    pub path: PathBuf,
+    /// Why the candidate was rejected.
+    pub reason: SearchMiss,
+}
+
+impl fmt::Display for SearchedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` ({})", self.path.display(), self.reason)
+    }
+}
+
+/// Why a candidate configuration file was rejected by the search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMiss {
+    /// Nothing exists at the path.
+    NotFound,
+    /// The path exists, but isn't a file, e.g. it's a directory.
+    NotAFile,
+    /// The file exists, but can't be opened.
+    Unreadable(std::io::ErrorKind),
+}
+
+impl fmt::Display for SearchMiss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => f.write_str("not found"),
+            Self::NotAFile => f.write_str("not a file"),
+            Self::Unreadable(kind) => write!(f, "unreadable: {kind}"),
+        }
+    }
+}
+
+/// A key unknown to your configuration structure.
+///
+/// See [`ConfigOption.strict_keys`] for more information.
+///
+/// [`ConfigOption.strict_keys`]: crate::ConfigOption#structfield.strict_keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// The dotted path of the key, e.g. `server.prot`.
+    pub key: String,
+    /// The dotted path of the closest known key, e.g. `server.port`, if any is close enough.
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.key)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean `{suggestion}`?)")?;
+        }
+        Ok(())
+    }
+}
+
+/// A rule broken by a configuration value.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{}{message}", if .path.is_empty() { String::new() } else { format!("`{}`: ", .path) })]
+pub struct Violation {
+    /// The dotted path of the offending value, e.g. `server.ports.0`.
+    ///
+    /// This is empty if the violation concerns the whole configuration.
+    pub path: String,
+    /// The description of the violation.
+    pub message: String,
+}
+
+impl Violation {
+    /// Create a new `Violation`.
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// The location of an error in a configuration document, whatever its format.
+///
+/// Applications can show it as is, e.g. ``Fail to parse `~/.config/app/conrig.toml` at line
+/// 12, column 8: invalid string``. See [`ConrigError::parse_error`] for more information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The file of the document, or `None` if it isn't read from a file.
+    pub path: Option<PathBuf>,
+    /// The line of the error, starting from `1`, if reported by the backend.
+    pub line: Option<usize>,
+    /// The column of the error, starting from `1`, if reported by the backend.
+    pub column: Option<usize>,
+    /// The dotted key path of the value which doesn't match the configuration type,
+    /// e.g. `server.listeners.2.port`, if any.
+    ///
+    /// See [`Value::get_path`] for the syntax.
+    ///
+    /// [`Value::get_path`]: crate::value::Value::get_path
+    pub key: Option<String>,
+    /// The description of the error, without its location.
+    pub message: String,
+    /// The format of the document.
+    pub format: FileFormat,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "Fail to parse `{}`", path.display())?,
+            None => write!(f, "Fail to parse {} document", self.format)?,
+        }
+        write!(f, "{}: {}", self.location(), self.message)
+    }
+}
+
+impl ParseError {
+    /// Describe the location, e.g. `` at line 12, column 8 in `server.port` ``,
+    /// or nothing if it's unknown.
+    fn location(&self) -> String {
+        let mut location = match (self.line, self.column) {
+            (Some(line), Some(column)) => format!(" at line {line}, column {column}"),
+            (Some(line), None) => format!(" at line {line}"),
+            _ => String::new(),
+        };
+        if let Some(key) = &self.key {
+            location += &format!(" in `{key}`");
+        }
+        location
+    }
+}
+
+impl LangError {
+    /// The line and the column of the error, starting from `1`, if reported by the backend.
+    ///
+    /// `toml` only reports byte offsets, which are located in the `input` if any.
+    pub(crate) fn location(&self, input: Option<&str>) -> Option<(usize, usize)> {
+        #[allow(unreachable_patterns)]
+        match self {
+            #[cfg(feature = "json")]
+            Self::JsonError(err) if err.line() > 0 => Some((err.line(), err.column())),
+            #[cfg(feature = "yaml")]
+            Self::YamlError(err) => err
+                .location()
+                .map(|location| (location.line(), location.column())),
+            #[cfg(feature = "ron")]
+            Self::RonError(RonError::Spanned(err)) if err.position.line > 0 => {
+                Some((err.position.line, err.position.col))
+            }
+            #[cfg(feature = "toml")]
+            Self::TomlError(TomlError::Deserialize(err)) => {
+                let before = input?.get(..err.span()?.start)?;
+                let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+                Some((
+                    before.matches('\n').count() + 1,
+                    before[line_start..].chars().count() + 1,
+                ))
+            }
+            #[cfg(feature = "plist")]
+            Self::PlistError(err) if err.line > 0 => Some((err.line, err.column)),
+            _ => None,
+        }
+    }
+
+    /// The description of the error given by the backend, without its location.
+    fn message(&self, location: Option<(usize, usize)>) -> String {
+        #[allow(unreachable_patterns)]
+        let message = match self {
+            #[cfg(feature = "toml")]
+            Self::TomlError(TomlError::Deserialize(err)) => return err.message().trim().to_owned(),
+            #[cfg(feature = "ron")]
+            Self::RonError(RonError::Spanned(err)) => return err.code.to_string(),
+            #[cfg(feature = "ron")]
+            Self::RonError(RonError::Raw(err)) => err.to_string(),
+            #[cfg(feature = "json")]
+            Self::JsonError(err) => err.to_string(),
+            #[cfg(feature = "yaml")]
+            Self::YamlError(err) => err.to_string(),
+            #[cfg(feature = "toml")]
+            Self::TomlError(err) => err.to_string(),
+            #[cfg(feature = "plist")]
+            Self::PlistError(err) => return err.message.clone(),
+            _ => self.to_string(),
+        };
+        // `serde_json` and `serde_yaml` append the location to the message.
+        match location {
+            Some((line, column)) => message
+                .strip_suffix(&format!(" at line {line} column {column}"))
+                .map_or_else(|| message.clone(), str::to_owned),
+            None => message,
+        }
+    }
+}
+
+/// An unknown name of a configuration format, e.g. `--format yml5`.
+///
+/// This is returned when parsing a [`FileFormat`][crate::FileFormat] from a string.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Unknown configuration format `{0}`.")]
+pub struct UnknownFormat(pub String);
+
+/// A non-fatal problem found by `conrig`.
+///
+/// Warnings are reported to the [`warning_handler`] of the options in use.
+///
+/// [`warning_handler`]: crate::ConfigOption#structfield.warning_handler
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ConrigWarning {
+    /// The configuration contains a deprecated key.
+    ///
+    /// See [`deprecation`][crate::deprecation] for more information.
+    #[error(
+        "Configuration key `{}` is deprecated and will be removed after version {}.{}",
+        .0.key,
+        .0.removed_after,
+        .0.hint()
+    )]
+    DeprecatedKey(Deprecation),
+
+    /// A file system operation took longer than the [`slow_io_threshold`].
+    ///
+    /// [`slow_io_threshold`]: crate::ConfigOption#structfield.slow_io_threshold
+    #[error("Slow file system: {operation} `{}` took {elapsed:?}.", .path.display())]
+    SlowIo {
+        /// The slow operation.
+        operation: Operation,
+        /// The file involved.
+        path: PathBuf,
+        /// The time taken by the operation.
+        elapsed: Duration,
+    },
+
+    /// A location couldn't be searched for configuration files, so it's skipped,
+    /// e.g. the system-level directory in a container without `HOME`.
+    ///
+    /// See [`SearchLocation`] for more information.
+    ///
+    /// [`SearchLocation`]: crate::SearchLocation
+    #[error("Skipped the {location:?} configuration files: {reason}")]
+    SkippedLocation {
+        /// The skipped location.
+        location: SearchLocation,
+        /// Why the location is skipped.
+        reason: String,
+    },
+
+    /// No remote URL is reachable, so the cached copy of the configuration is used.
+    ///
+    /// Contains the error of the last URL.
+    ///
+    /// See [`RemoteSource::fetch`] for more information.
+    ///
+    /// [`RemoteSource::fetch`]: crate::remote::RemoteSource::fetch
+    #[cfg(feature = "http")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+    #[error("No remote configuration is reachable, using the cached copy: {0}")]
+    RemoteUnreachable(String),
 }
 
 #[cfg(feature = "full-desc")]
@@ -169,6 +968,15 @@ pub enum LangError {
         #[source]
         TomlError,
     ),
+    #[cfg(feature = "plist")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "plist")))]
+    /// Error triggered by the property list parser.
+    #[error("Plist: {0}")]
+    PlistError(
+        #[from]
+        #[source]
+        PlistError,
+    ),
 }
 
 #[cfg(not(feature = "full-desc"))]
@@ -215,6 +1023,14 @@ pub enum ParserError {
         #[source]
         TomlError,
     ),
+    #[cfg(feature = "plist")]
+    /// Error triggered by the property list parser.
+    #[error("Bad plist data.")]
+    PlistError(
+        #[from]
+        #[source]
+        PlistError,
+    ),
 }
 
 #[cfg(feature = "full-desc")]
@@ -222,14 +1038,32 @@ pub enum ParserError {
 #[derive(Debug, Error)]
 pub enum FileSystemError {
     /// Error occurred during the opening of a file or directory.
-    #[error("{0}")]
-    OpenConfig(#[source] IoError),
+    #[error("Cannot open `{}`: {source}", .path.display())]
+    OpenConfig {
+        /// The file or directory involved.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: IoError,
+    },
     /// Error occurred during the reading of a file or directory.
-    #[error("{0}")]
-    ReadConfig(#[source] IoError),
+    #[error("Cannot read `{}`: {source}", .path.display())]
+    ReadConfig {
+        /// The file or directory involved.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: IoError,
+    },
     /// Error occurred during the writing of a file.
-    #[error("{0}")]
-    WriteConfig(#[source] IoError),
+    #[error("Cannot write `{}`: {source}", .path.display())]
+    WriteConfig {
+        /// The file involved.
+        path: PathBuf,
+        /// The underlying error.
+        #[source]
+        source: IoError,
+    },
     /// Error triggered by the [`directories`] library.
     /// 
     /// See [`directories::ProjectDirs::from`] for more information.
@@ -241,12 +1075,70 @@ pub enum FileSystemError {
 /// Error triggered by the file system, most probably by the operating system.
 #[derive(Debug, Error)]
 pub enum FileSystemError {
-    #[error("Cannot open configuration file.")]
-    OpenConfig(#[source] IoError),
-    #[error("Cannot read configuration file.")]
-    ReadConfig(#[source] IoError),
-    #[error("Cannot write configuration file.")]
-    WriteConfig(#[source] IoError),
+    #[error("Cannot open configuration file `{}`.", .path.display())]
+    OpenConfig {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+    #[error("Cannot read configuration file `{}`.", .path.display())]
+    ReadConfig {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+    #[error("Cannot write configuration file `{}`.", .path.display())]
+    WriteConfig {
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
     #[error("No project directory found.")]
     NoProjectDirectory,
 }
+
+impl FileSystemError {
+    /// The file or directory involved in the error, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::OpenConfig { path, .. }
+            | Self::ReadConfig { path, .. }
+            | Self::WriteConfig { path, .. } => Some(path),
+            Self::NoProjectDirectory => None,
+        }
+    }
+
+    /// Create an [`OpenConfig`][Self::OpenConfig] error of `path`, e.g. for `map_err`.
+    pub(crate) fn open_config(path: &Path) -> impl FnOnce(IoError) -> Self + '_ {
+        move |source| Self::OpenConfig {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Create a [`ReadConfig`][Self::ReadConfig] error of `path`, e.g. for `map_err`.
+    pub(crate) fn read_config(path: &Path) -> impl FnOnce(IoError) -> Self + '_ {
+        move |source| Self::ReadConfig {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Create a [`WriteConfig`][Self::WriteConfig] error of `path`, e.g. for `map_err`.
+    pub(crate) fn write_config(path: &Path) -> impl FnOnce(IoError) -> Self + '_ {
+        move |source| Self::WriteConfig {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// The underlying I/O error, if any.
+    pub fn io_error(&self) -> Option<&IoError> {
+        match self {
+            Self::OpenConfig { source, .. }
+            | Self::ReadConfig { source, .. }
+            | Self::WriteConfig { source, .. } => Some(source),
+            Self::NoProjectDirectory => None,
+        }
+    }
+}
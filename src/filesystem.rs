@@ -0,0 +1,414 @@
+//! The file system used to read and write configuration files.
+//!
+//! `conrig` uses [`std::fs`] by default. Register another [`FileSystem`], e.g. a virtual one
+//! for an embedded environment, with [`set_file_system`], or only while running a closure
+//! on the current thread with [`with_file_system`], e.g. to mock the files in tests.
+//!
+//! Writes through a custom file system are plain writes: [atomic writes], [backups] and
+//! [permission checks] are only supported by [`std::fs`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::filesystem::{with_file_system, FileSystem, MemoryFileSystem};
+//! use conrig::parser::ConfigFile;
+//! use conrig::FileFormat;
+//! use std::path::Path;
+//! use std::sync::Arc;
+//!
+//! let fs = Arc::new(MemoryFileSystem::new());
+//! let file = ConfigFile::new(FileFormat::Json, "/etc/conrig/conrig.json".into());
+//! with_file_system(fs.clone(), || file.write(&42))?;
+//! assert!(fs.exists(Path::new("/etc/conrig/conrig.json")));
+//! assert_eq!(with_file_system(fs, || file.read::<i32>())?, 42);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [atomic writes]: crate::ConfigOption#structfield.atomic_write
+//! [backups]: crate::ConfigOption#structfield.backup
+//! [permission checks]: crate::ConfigOption#structfield.check_permissions
+
+use crate::error::SearchMiss;
+use crate::error::FileSystemError;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// A file system storing configuration files.
+pub trait FileSystem: Send + Sync {
+    /// Open a file for reading.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read>>;
+
+    /// Read the whole content of a file.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        self.open(path)?.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Create or replace a file.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Create a directory and all its missing parents.
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Whether a file exists.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Whether a directory exists, e.g. one matched by a glob pattern of [`extra_folders`].
+    ///
+    /// Default: `false`.
+    ///
+    /// [`extra_folders`]: crate::ConfigPathMetadata#structfield.extra_folders
+    fn is_dir(&self, path: &Path) -> bool {
+        let _ = path;
+        false
+    }
+
+    /// Rename a file, replacing the destination if it exists, e.g. to set it aside.
+    ///
+    /// Default: an [`Unsupported`][io::ErrorKind::Unsupported] error.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let _ = (from, to);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Copy a file, replacing the destination if it exists, e.g. to back it up.
+    ///
+    /// Default: [`read`][FileSystem::read] the file, and [`write`][FileSystem::write] its copy.
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.write(to, &self.read(from)?)
+    }
+
+    /// Remove a file.
+    ///
+    /// Default: an [`Unsupported`][io::ErrorKind::Unsupported] error.
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let _ = path;
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// The canonical form of a path, used to detect the files including themselves.
+    ///
+    /// Default: an [`Unsupported`][io::ErrorKind::Unsupported] error,
+    /// in which case paths are compared as is.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let _ = path;
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// The paths of the files in a directory, used by the [drop-in] directories.
+    ///
+    /// Default: an [`Unsupported`][io::ErrorKind::Unsupported] error.
+    ///
+    /// [drop-in]: crate::ConfigOption#structfield.drop_ins
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let _ = path;
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// The [`std::fs`] file system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file() && fs::File::open(path).is_ok()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::copy(from, to).map(|_| ())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+}
+
+/// A file system kept in memory.
+///
+/// Directories are implicit: they exist as long as they contain a file.
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: RwLock<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryFileSystem {
+    /// Create an empty `MemoryFileSystem`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove a file, returning its content if it existed.
+    pub fn remove(&self, path: &Path) -> Option<Vec<u8>> {
+        self.files
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(path)
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(io::Cursor::new(self.read(path)?)))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.files
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .any(|file| file != path && file.starts_with(path))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut files = self.files.write().unwrap_or_else(PoisonError::into_inner);
+        let contents = files.remove(from).ok_or(io::ErrorKind::NotFound)?;
+        files.insert(to.to_path_buf(), contents);
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.remove(path)
+            .map(|_| ())
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .files
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .filter(|file| file.parent() == Some(path))
+            .cloned()
+            .collect())
+    }
+}
+
+/// The file system used by every thread.
+static FILE_SYSTEM: RwLock<Option<Arc<dyn FileSystem>>> = RwLock::new(None);
+
+thread_local! {
+    /// The file system used by the current thread, overriding the global one.
+    static SCOPED: RefCell<Option<Arc<dyn FileSystem>>> = const { RefCell::new(None) };
+}
+
+/// Register the file system used by every thread, replacing [`std::fs`].
+pub fn set_file_system(file_system: impl FileSystem + 'static) {
+    *FILE_SYSTEM.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(file_system));
+}
+
+/// Restore [`std::fs`] as the file system used by every thread.
+pub fn clear_file_system() {
+    *FILE_SYSTEM.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Run `f` with `file_system` used by the current thread.
+///
+/// The previous file system is restored afterwards, even if `f` panics.
+pub fn with_file_system<R>(file_system: Arc<dyn FileSystem>, f: impl FnOnce() -> R) -> R {
+    /// Restore the previous file system of the thread when dropped.
+    struct Restore(Option<Arc<dyn FileSystem>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            SCOPED.with(|scoped| *scoped.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(SCOPED.with(|scoped| scoped.replace(Some(file_system))));
+    f()
+}
+
+/// The custom file system in use, or `None` for [`std::fs`].
+pub(crate) fn custom() -> Option<Arc<dyn FileSystem>> {
+    SCOPED.with(|scoped| scoped.borrow().clone()).or_else(|| {
+        FILE_SYSTEM
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    })
+}
+
+/// Whether a readable file exists.
+pub(crate) fn exists(path: &Path) -> bool {
+    match custom() {
+        Some(file_system) => file_system.exists(path),
+        None => StdFileSystem.exists(path),
+    }
+}
+
+/// Why no readable file exists at `path`, or `None` if one does.
+///
+/// Custom file systems can only tell whether a file exists.
+pub(crate) fn miss_reason(path: &Path) -> Option<SearchMiss> {
+    if let Some(file_system) = custom() {
+        return (!file_system.exists(path)).then_some(SearchMiss::NotFound);
+    }
+    match fs::metadata(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Some(SearchMiss::NotFound),
+        Err(err) => Some(SearchMiss::Unreadable(err.kind())),
+        Ok(metadata) if !metadata.is_file() => Some(SearchMiss::NotAFile),
+        Ok(_) => fs::File::open(path)
+            .err()
+            .map(|err| SearchMiss::Unreadable(err.kind())),
+    }
+}
+
+/// Read the whole content of a file.
+pub(crate) fn read(path: &Path) -> Result<Vec<u8>, FileSystemError> {
+    let file_system = custom().unwrap_or_else(|| Arc::new(StdFileSystem));
+    let mut file = file_system
+        .open(path)
+        .map_err(FileSystemError::open_config(path))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)
+        .map_err(FileSystemError::read_config(path))?;
+    Ok(contents)
+}
+
+/// Create or replace a file, without the guarantees of [`ConfigFile::write`].
+///
+/// [`ConfigFile::write`]: crate::parser::ConfigFile::write
+pub(crate) fn write(path: &Path, contents: &[u8]) -> Result<(), FileSystemError> {
+    match custom() {
+        Some(file_system) => file_system.write(path, contents),
+        None => fs::write(path, contents),
+    }
+    .map_err(FileSystemError::write_config(path))
+}
+
+/// Create a directory and all its missing parents.
+pub(crate) fn create_dir_all(path: &Path) -> Result<(), FileSystemError> {
+    match custom() {
+        Some(file_system) => file_system.create_dir_all(path),
+        None => fs::create_dir_all(path),
+    }
+    .map_err(FileSystemError::write_config(path))
+}
+
+/// Whether a directory exists.
+pub(crate) fn is_dir(path: &Path) -> bool {
+    match custom() {
+        Some(file_system) => file_system.is_dir(path),
+        None => StdFileSystem.is_dir(path),
+    }
+}
+
+/// Rename a file, replacing the destination if it exists.
+pub(crate) fn rename(from: &Path, to: &Path) -> Result<(), FileSystemError> {
+    match custom() {
+        Some(file_system) => file_system.rename(from, to),
+        None => fs::rename(from, to),
+    }
+    .map_err(FileSystemError::write_config(to))
+}
+
+/// Copy a file, replacing the destination if it exists.
+pub(crate) fn copy(from: &Path, to: &Path) -> Result<(), FileSystemError> {
+    match custom() {
+        Some(file_system) => file_system.copy(from, to),
+        None => StdFileSystem.copy(from, to),
+    }
+    .map_err(FileSystemError::write_config(to))
+}
+
+/// Remove a file.
+pub(crate) fn remove_file(path: &Path) -> Result<(), FileSystemError> {
+    match custom() {
+        Some(file_system) => file_system.remove_file(path),
+        None => fs::remove_file(path),
+    }
+    .map_err(FileSystemError::write_config(path))
+}
+
+/// The canonical form of a path.
+pub(crate) fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    match custom() {
+        Some(file_system) => file_system.canonicalize(path),
+        None => fs::canonicalize(path),
+    }
+}
+
+/// The paths of the files in a directory.
+pub(crate) fn read_dir(path: &Path) -> io::Result<Vec<PathBuf>> {
+    match custom() {
+        Some(file_system) => file_system.read_dir(path),
+        None => StdFileSystem.read_dir(path),
+    }
+}
@@ -0,0 +1,224 @@
+//! Generation of new configuration files, with progressive disclosure.
+//!
+//! A first-run configuration file listing every option can be overwhelming. The
+//! [`Generation`] mode, selected per call of [`ConfigFile::write_generated`] or
+//! [`ConfigPathMetadata::read_or_generate`], controls how much of the configuration is written:
+//!
+//! - [`Minimal`][Generation::Minimal]: only the required keys, and the values differing from
+//!   `T::default()`;
+//! - [`Commented`][Generation::Commented]: the minimal configuration, followed by all the other
+//!   options commented out, for users to discover them;
+//! - [`Full`][Generation::Full]: everything.
+//!
+//! For example, with the `Commented` mode in TOML:
+//!
+//! ```toml
+//! theme = "dark"
+//!
+//! # Other available options, with their default values:
+//! # font_size = 12
+//! ```
+
+use crate::error::{ConrigResultExt, FileSystemError, Operation};
+use crate::parser::ConfigFile;
+use crate::value::{from_value, to_value, Table, Value};
+use crate::{filesystem, ConfigPathMetadata, ConrigError, FileFormat};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// How much of a configuration is written into a new file.
+///
+/// See the [module-level documentation](crate::generate) for more information.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Generation {
+    /// Only write the required keys, i.e. the ones `T` can't be deserialized without,
+    /// and the values differing from `T::default()`.
+    ///
+    /// The other fields are filled back when reading the file, typically by marking them
+    /// with `#[serde(default)]`.
+    Minimal,
+    /// Write the [`Minimal`] configuration, followed by all the other options commented out.
+    ///
+    /// Formats without comments, i.e. JSON and plist, fall back to [`Minimal`].
+    ///
+    /// [`Minimal`]: Generation::Minimal
+    Commented,
+    /// Write everything.
+    #[default]
+    Full,
+}
+
+/// The prefix of a line comment in a format, if it supports comments.
+pub(crate) fn comment_prefix(file_format: FileFormat) -> Option<&'static str> {
+    match file_format {
+        #[cfg(feature = "toml")]
+        FileFormat::Toml => Some("#"),
+        #[cfg(feature = "yaml")]
+        FileFormat::Yaml => Some("#"),
+        #[cfg(feature = "ron")]
+        FileFormat::Ron => Some("//"),
+        _ => None,
+    }
+}
+
+/// Get the parts of `value` which are equal to `base`, the opposite of [`Value::difference`].
+fn common(value: &Value, base: &Value) -> Option<Value> {
+    match (value, base) {
+        (Value::Table(this), Value::Table(base)) => {
+            let table: Table = this
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), common(value, base.get(key)?)?)))
+                .collect();
+            (!table.is_empty()).then_some(Value::Table(table))
+        }
+        (this, base) if this == base => Some(this.clone()),
+        _ => None,
+    }
+}
+
+/// Get the required keys of `value`, and the parts of it which differ from `default`,
+/// in the order of `value`.
+///
+/// A key is required if `T` can't be deserialized from `value` without it. The tables holding
+/// the written keys are checked for their own required keys as well.
+fn minimal<T: DeserializeOwned>(value: &Value, default: &Value) -> Value {
+    minimal_at::<T>(value, &mut Vec::new(), value, default)
+        .unwrap_or_else(|| Value::Table(Default::default()))
+}
+
+fn minimal_at<T: DeserializeOwned>(
+    root: &Value,
+    path: &mut Vec<String>,
+    value: &Value,
+    default: &Value,
+) -> Option<Value> {
+    let Value::Table(table) = value else {
+        return value.difference(default);
+    };
+    let mut minimal = Table::new();
+    for (key, child) in table {
+        let default = match default {
+            Value::Table(default) => default.get(key).unwrap_or(&Value::Null),
+            _ => &Value::Null,
+        };
+        let differs = child.difference(default).is_some();
+        if !differs && !is_required::<T>(root, path, key) {
+            continue;
+        }
+        let child = match child {
+            Value::Table(_) => {
+                path.push(key.clone());
+                let child = minimal_at::<T>(root, path, child, default);
+                path.pop();
+                child.unwrap_or_else(|| Value::Table(Default::default()))
+            }
+            child => child.clone(),
+        };
+        minimal.insert(key.clone(), child);
+    }
+    (!minimal.is_empty()).then_some(Value::Table(minimal))
+}
+
+/// Whether `T` can't be deserialized from `root` without the key at `path` and `key`.
+fn is_required<T: DeserializeOwned>(root: &Value, path: &[String], key: &str) -> bool {
+    let mut probe = root.clone();
+    let parent = path.iter().try_fold(&mut probe, |value, key| match value {
+        Value::Table(table) => table.get_mut(key),
+        _ => None,
+    });
+    if let Some(Value::Table(table)) = parent {
+        table.shift_remove(key);
+    }
+    from_value::<T>(probe).is_err()
+}
+
+impl ConfigFile {
+    /// Serialize and write a value into the configuration file, as much as the [`Generation`]
+    /// mode asks for.
+    ///
+    /// If the generated file can't be read back, e.g. because of flattened or untagged fields,
+    /// everything is written instead.
+    ///
+    /// See the [`generate`](crate::generate) module for more information.
+    pub fn write_generated<T: Serialize + DeserializeOwned + Default>(
+        &self,
+        value: &T,
+        generation: Generation,
+    ) -> Result<(), ConrigError> {
+        if generation == Generation::Full {
+            return self.write(value);
+        }
+        let (value, default) = to_value(value)
+            .and_then(|value| Ok((value, to_value(&T::default())?)))
+            .context_path(Operation::Write, &self.path)?;
+        let minimal = minimal::<T>(&value, &default);
+        if from_value::<T>(minimal.clone()).is_err() {
+            return self.write(&value);
+        }
+        let prefix = match comment_prefix(self.file_format) {
+            Some(prefix) if generation == Generation::Commented => prefix,
+            _ => return self.write(&minimal),
+        };
+        // the required keys equal to their default value are already written.
+        let Some(rest) = common(&value, &default).and_then(|rest| rest.difference(&minimal)) else {
+            return self.write(&minimal);
+        };
+
+        let mut commented = Vec::new();
+        self.file_format
+            .write(&rest, &mut commented)
+            .context_path(Operation::Write, &self.path)?;
+        let commented = String::from_utf8_lossy(&commented);
+        self.write_with(&value, |mut writer| {
+            if minimal != Value::Table(Default::default()) {
+                self.file_format.write(&minimal, &mut writer)?;
+                writer
+                    .write_all(b"\n")
+                    .map_err(FileSystemError::write_config(&self.path))?;
+            }
+            let mut block =
+                format!("{prefix} Other available options, with their default values:\n");
+            for line in commented.lines() {
+                match line {
+                    "" => block.push_str(&format!("{prefix}\n")),
+                    line => block.push_str(&format!("{prefix} {line}\n")),
+                }
+            }
+            writer
+                .write_all(block.as_bytes())
+                .map_err(FileSystemError::write_config(&self.path))?;
+            Ok(())
+        })
+    }
+
+    /// Read and deserialize the configuration file.
+    /// If it doesn't exist, a new one is generated from the default value,
+    /// as much as the [`Generation`] mode asks for.
+    pub fn read_or_generate<T: Serialize + DeserializeOwned + Default>(
+        &self,
+        default: impl FnOnce() -> Result<T, ConrigError>,
+        generation: Generation,
+    ) -> Result<T, ConrigError> {
+        if filesystem::exists(&self.path) {
+            return self.read();
+        }
+        let default = default()?;
+        self.write_generated(&default, generation)?;
+        Ok(default)
+    }
+}
+
+impl<'p, T: Serialize + DeserializeOwned + Default> ConfigPathMetadata<'p, T> {
+    /// Read a configuration file, or generate a new one from the [default value].
+    ///
+    /// This is like [`read_or_default`], but the new file only contains as much of the
+    /// configuration as the [`Generation`] mode asks for.
+    ///
+    /// [default value]: crate::ConfigPathMetadata::default_value
+    /// [`read_or_default`]: crate::ConfigPathMetadata::read_or_default
+    pub fn read_or_generate(&self, generation: Generation) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_or_generate(|| self.default_value(), generation)
+    }
+}
@@ -0,0 +1,345 @@
+//! Cached access to a configuration.
+//!
+//! A [`ConfigHandle`] keeps the deserialized configuration along with the modification time
+//! of its file, and only reads the file again when it has changed. This keeps hot paths
+//! from searching and parsing the configuration on every access.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Default, Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//! }
+//!
+//! conrig!(const CONFIG<Config> = {
+//!     project_path: ProjectPath {
+//!         qualifier: "org",
+//!         organization: "foo",
+//!         application: "conrig-handle",
+//!     },
+//!     config_name: &["conrig"],
+//!     config_option: ConfigOption::DEFAULT_CONFIG,
+//!     extra_files: &[],
+//!     extra_folders: &[],
+//!     default_format: FileFormat::Toml,
+//! });
+//!
+//! let handle = CONFIG.handle()?;
+//! # handle.file().write(&Config::default())?;
+//! for _ in 0..1000 {
+//!     let config = handle.get()?;
+//!     println!("{}", config.name);
+//! }
+//! # std::fs::remove_file(&handle.file().path).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A [`StaticConfig`], declared with `conrig!(static ...)`, goes one step further for the
+//! configurations living for the whole program: the file is searched only once, on first use.
+//! Its [`prefetch`] reads the configuration on a background thread at startup.
+//!
+//! [`prefetch`]: StaticConfig::prefetch
+
+use crate::parser::ConfigFile;
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::ops::Deref;
+use std::panic;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
+
+/// A configuration file, along with its cached value.
+///
+/// See the [module documentation][self] for more information.
+#[derive(Debug)]
+pub struct ConfigHandle<T> {
+    file: ConfigFile,
+    cached: Mutex<Option<Cached<T>>>,
+}
+
+#[derive(Debug)]
+struct Cached<T> {
+    stamp: Stamp,
+    value: Arc<T>,
+}
+
+/// The modification time and the size of a file.
+///
+/// The size catches most of the changes made within the resolution of the modification time.
+type Stamp = (SystemTime, u64);
+
+fn stamp(file: &ConfigFile) -> Option<Stamp> {
+    if crate::filesystem::custom().is_some() {
+        return None;
+    }
+    let metadata = fs::metadata(&file.path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl<T> ConfigHandle<T> {
+    /// Create a new `ConfigHandle`, without reading the file yet.
+    pub fn new(file: ConfigFile) -> Self {
+        Self {
+            file,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// The configuration file.
+    pub fn file(&self) -> &ConfigFile {
+        &self.file
+    }
+
+    /// Drop the cached value, so the next [`get`] reads the file again.
+    ///
+    /// [`get`]: ConfigHandle::get
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+impl<T: DeserializeOwned> ConfigHandle<T> {
+    /// Get the configuration, reading the file again only if it has changed.
+    ///
+    /// Only the modification time and the size of the file itself are checked:
+    /// call [`invalidate`] after changing the files it includes.
+    /// If they can't be known, e.g. with a custom [file system], the file is read every time.
+    ///
+    /// [`invalidate`]: ConfigHandle::invalidate
+    /// [file system]: crate::filesystem
+    pub fn get(&self) -> Result<Arc<T>, ConrigError> {
+        let mut cached = self.cached.lock().unwrap_or_else(PoisonError::into_inner);
+        let stamp = stamp(&self.file);
+        if let Some(cached) = cached.as_ref().filter(|cached| Some(cached.stamp) == stamp) {
+            return Ok(cached.value.clone());
+        }
+        let value = Arc::new(self.file.read::<T>()?);
+        // the file may have changed while it was read, so the stamp is taken beforehand.
+        *cached = stamp.map(|stamp| Cached {
+            stamp,
+            value: value.clone(),
+        });
+        Ok(value)
+    }
+}
+
+impl<'p, T> ConfigPathMetadata<'p, T> {
+    /// Get a cached handle of the configuration file.
+    ///
+    /// The file is searched once, as `self.search_config_file()?.fallback_default()?`.
+    pub fn handle(&self) -> Result<ConfigHandle<T>, ConrigError> {
+        Ok(ConfigHandle::new(
+            self.search_config_file()?.fallback_default()?,
+        ))
+    }
+}
+
+/// A configuration declared as a `static`, searching its file only once.
+///
+/// The path and format of the file are resolved on first use, then reused by every
+/// later access. Declare it with the [`conrig!`][crate::conrig] macro:
+///
+/// ```rust
+/// # fn main() -> Result<(), conrig::ConrigError> {
+/// use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+/// # use serde_derive::{Deserialize, Serialize};
+///
+/// #[derive(Default, Serialize, Deserialize)]
+/// struct Config {
+///     name: String,
+/// }
+///
+/// conrig!(static APP_CONFIG<Config> = {
+///     project_path: ProjectPath {
+///         qualifier: "org",
+///         organization: "foo",
+///         application: "conrig-static",
+///     },
+///     config_name: &["conrig"],
+///     config_option: ConfigOption::DEFAULT_CONFIG,
+///     extra_files: &[],
+///     extra_folders: &[],
+///     default_format: FileFormat::Toml,
+/// });
+///
+/// let config = APP_CONFIG.read_or_default()?;
+/// println!("{}", config.name);
+/// # std::fs::remove_file(&APP_CONFIG.config_file()?.path).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// It dereferences to its [`ConfigPathMetadata`], whose methods search the file again.
+#[derive(Debug)]
+pub struct StaticConfig<T: 'static> {
+    metadata: ConfigPathMetadata<'static, T>,
+    file: OnceLock<ConfigFile>,
+}
+
+impl<T> StaticConfig<T> {
+    /// Create a new `StaticConfig`, without searching the file yet.
+    pub const fn new(metadata: ConfigPathMetadata<'static, T>) -> Self {
+        Self {
+            metadata,
+            file: OnceLock::new(),
+        }
+    }
+
+    /// The metadata of the configuration.
+    pub const fn metadata(&self) -> &ConfigPathMetadata<'static, T> {
+        &self.metadata
+    }
+
+    /// The configuration file, searched on first use as
+    /// `self.search_config_file()?.fallback_default()?`.
+    ///
+    /// If the search fails, the error is returned and the next call searches again.
+    pub fn config_file(&self) -> Result<&ConfigFile, ConrigError> {
+        if let Some(file) = self.file.get() {
+            return Ok(file);
+        }
+        let file = self.metadata.search_config_file()?.fallback_default()?;
+        Ok(self.file.get_or_init(|| file))
+    }
+
+    /// Get a cached handle of the configuration file.
+    ///
+    /// See [`ConfigHandle`] for more information.
+    pub fn handle(&self) -> Result<ConfigHandle<T>, ConrigError> {
+        Ok(ConfigHandle::new(self.config_file()?.clone()))
+    }
+}
+
+impl<T: DeserializeOwned> StaticConfig<T> {
+    /// Read and deserialize the configuration file. Fail if the configuration doesn't exist.
+    pub fn read(&self) -> Result<T, ConrigError> {
+        self.config_file()?.read()
+    }
+}
+
+impl<T: Serialize> StaticConfig<T> {
+    /// Serialize and write a value into the configuration file.
+    pub fn write(&self, value: &T) -> Result<(), ConrigError> {
+        self.config_file()?.write(value)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> StaticConfig<T> {
+    /// Read and deserialize the configuration file.
+    /// If the configuration file doesn't exist, a new configuration file will be created,
+    /// and it will be filled with the default value provided.
+    pub fn read_or_new(&self, default: T) -> Result<T, ConrigError> {
+        self.config_file()?.read_or_new(default)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Default> StaticConfig<T> {
+    /// Read and deserialize the configuration file.
+    /// If the configuration file doesn't exist, a new configuration file will be created,
+    /// and it will be filled with the [default value] of your structure.
+    ///
+    /// [default value]: crate::ConfigPathMetadata::default_value
+    pub fn read_or_default(&self) -> Result<T, ConrigError> {
+        self.config_file()?
+            .read_or_try_else(|| self.metadata.default_value())
+    }
+}
+
+impl<T> Deref for StaticConfig<T> {
+    type Target = ConfigPathMetadata<'static, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.metadata
+    }
+}
+
+impl<T: DeserializeOwned + Send> StaticConfig<T> {
+    /// Resolve and read the configuration on a background thread, e.g. while the rest of the
+    /// application initializes.
+    ///
+    /// The first [`Prefetch::get`] waits for the thread, so the latency of the file system
+    /// is hidden behind the work done in the meantime.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), conrig::ConrigError> {
+    /// use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+    ///
+    /// conrig!(static APP_CONFIG<Vec<String>> = {
+    ///     project_path: ProjectPath::new("org", "foo", "conrig-prefetch"),
+    ///     config_name: &["conrig-prefetch"],
+    ///     config_option: ConfigOption::DEFAULT_CONFIG,
+    ///     extra_files: &[],
+    ///     extra_folders: &[],
+    ///     default_format: FileFormat::Json,
+    /// });
+    ///
+    /// # APP_CONFIG.write(&vec!["conrig".to_owned()])?;
+    /// let config = APP_CONFIG.prefetch();
+    /// // initialize the rest of the application...
+    /// assert_eq!(*config.get()?, ["conrig"]);
+    /// # std::fs::remove_file(&APP_CONFIG.config_file()?.path).unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn prefetch(&'static self) -> Prefetch<T> {
+        Prefetch {
+            config: self,
+            worker: Mutex::new(Some(thread::spawn(|| self.read()))),
+            value: OnceLock::new(),
+        }
+    }
+}
+
+/// A configuration read on a background thread.
+///
+/// See [`StaticConfig::prefetch`] for more information.
+#[derive(Debug)]
+pub struct Prefetch<T: 'static> {
+    config: &'static StaticConfig<T>,
+    worker: Mutex<Option<JoinHandle<Result<T, ConrigError>>>>,
+    value: OnceLock<Arc<T>>,
+}
+
+impl<T> Prefetch<T> {
+    /// Whether the configuration is available without waiting.
+    pub fn is_ready(&self) -> bool {
+        self.value.get().is_some()
+            || self
+                .worker
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .as_ref()
+                .is_none_or(JoinHandle::is_finished)
+    }
+}
+
+impl<T: DeserializeOwned> Prefetch<T> {
+    /// Get the configuration, waiting for the background thread on first call.
+    ///
+    /// The value is then kept for every later call. Errors aren't kept:
+    /// once returned, the next call reads the configuration again, on the current thread.
+    /// A panic of the background thread is propagated.
+    pub fn get(&self) -> Result<Arc<T>, ConrigError> {
+        if let Some(value) = self.value.get() {
+            return Ok(value.clone());
+        }
+        let mut worker = self.worker.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(value) = self.value.get() {
+            return Ok(value.clone());
+        }
+        let value = match worker.take() {
+            Some(worker) => worker.join().unwrap_or_else(|err| panic::resume_unwind(err)),
+            None => self.config.read(),
+        }?;
+        Ok(self.value.get_or_init(|| Arc::new(value)).clone())
+    }
+}
@@ -0,0 +1,133 @@
+//! Importers of foreign configuration files.
+//!
+//! If your application replaces another tool, or used a different file layout in the past,
+//! register [`Importer`]s in [`ConfigPathMetadata::importers`] so that the new
+//! configuration can be seeded from the existing files on first run.
+//!
+//! [`ConfigPathMetadata::importers`]: crate::ConfigPathMetadata#structfield.importers
+
+use crate::error::{ConrigResultExt, Operation};
+use crate::parser::ConfigFile;
+use crate::path::expand_home;
+use crate::value::{from_value, Value};
+use crate::{ConfigPathMetadata, ConrigError, FileFormat};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+
+/// A foreign configuration file to import settings from.
+pub struct Importer<'p, T> {
+    /// The name of the importer, reported once it's been used.
+    pub name: &'p str,
+    /// The path of the foreign file.
+    ///
+    /// A leading `~` is expanded to the user's home directory.
+    pub path: &'p str,
+    /// The format of the foreign file.
+    pub file_format: FileFormat,
+    /// Convert the foreign document into your configuration.
+    pub transform: fn(Value) -> Result<T, ConrigError>,
+}
+
+impl<'p, T> Importer<'p, T> {
+    /// Create a new `Importer`.
+    pub const fn new(
+        name: &'p str,
+        path: &'p str,
+        file_format: FileFormat,
+        transform: fn(Value) -> Result<T, ConrigError>,
+    ) -> Self {
+        Self {
+            name,
+            path,
+            file_format,
+            transform,
+        }
+    }
+}
+
+impl<T> Clone for Importer<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Importer<'_, T> {}
+
+impl<T> fmt::Debug for Importer<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Importer")
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .field("file_format", &self.file_format)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> PartialEq for Importer<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.path == other.path
+            && self.file_format == other.file_format
+            && std::ptr::fn_addr_eq(self.transform, other.transform)
+    }
+}
+
+impl<T> Eq for Importer<'_, T> {}
+
+/// A configuration seeded by an [`Importer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Imported<'p, T> {
+    /// The name of the importer used.
+    pub importer: &'p str,
+    /// The foreign file imported.
+    pub source: PathBuf,
+    /// The new configuration file written.
+    pub target: PathBuf,
+    /// The imported configuration.
+    pub value: T,
+}
+
+impl<'p, T: Serialize> ConfigPathMetadata<'p, T> {
+    /// Seed a new configuration file from the registered [`importers`].
+    ///
+    /// If no configuration file can be found, every importer is tried in order.
+    /// The first one whose foreign file exists is used,
+    /// and its result is written into the [default configuration file][default].
+    ///
+    /// Returns the report of the import, or `None` if a configuration file already exists
+    /// or no foreign file is available. Calling this on every startup is fine,
+    /// as nothing will be imported once the configuration file has been created.
+    ///
+    /// [`importers`]: crate::ConfigPathMetadata#structfield.importers
+    /// [default]: crate::ConfigPathMetadata::default_config_file
+    pub fn import(&self) -> Result<Option<Imported<'p, T>>, ConrigError> {
+        if self.search_config_file()?.path.is_some() {
+            return Ok(None);
+        }
+        for importer in self.importers {
+            let Some(source) = expand_home(importer.path).filter(|t| t.is_file()) else {
+                continue;
+            };
+            let document: Value = ConfigFile::new(importer.file_format, source.clone()).read()?;
+            let value = (importer.transform)(document).context_path(Operation::Import, &source)?;
+            let target = self.search_config_file()?.fallback_default()?;
+            target.write(&value)?;
+            return Ok(Some(Imported {
+                importer: importer.name,
+                source,
+                target: target.path,
+                value,
+            }));
+        }
+        Ok(None)
+    }
+}
+
+impl<'p, T: DeserializeOwned> Importer<'p, T> {
+    /// Create an `Importer` whose foreign file has the same structure as your configuration.
+    pub const fn direct(name: &'p str, path: &'p str, file_format: FileFormat) -> Self {
+        Self::new(name, path, file_format, |value| Ok(from_value(value)?))
+    }
+}
@@ -0,0 +1,330 @@
+//! Lazily loaded configuration values.
+//!
+//! Wrap the large, optional parts of your configuration, e.g. embedded datasets or themes,
+//! in [`Lazy`]: they're kept as an untyped [`Value`] while the configuration is read,
+//! and only deserialized on their first access. A `Lazy` value can also reference an external
+//! file, written as `{ "$file" = "themes/dark.toml" }`, which isn't even read until then.
+//! Relative paths are resolved from the directory of the configuration file.
+//!
+//! A [`FileRef`] formalizes such references: the configuration only stores the path of another
+//! configuration file, which is read and written on demand.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::lazy::Lazy;
+//! use conrig::FileFormat;
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Theme {
+//!     background: String,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     theme: Lazy<Theme>,
+//! }
+//!
+//! let config: Config = FileFormat::Toml.read_str(
+//!     r##"
+//!     name = "conrig"
+//!     theme = { background = "#000000" }
+//!     "##,
+//! )?;
+//! assert_eq!(config.theme.get()?.background, "#000000");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::parser::ConfigFile;
+use crate::value::{from_value, Value};
+use crate::{detect_file_format, ConrigError, FileFormat};
+use serde::de::DeserializeOwned;
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// The key of a reference to an external file.
+pub const FILE_KEY: &str = "$file";
+
+thread_local! {
+    /// The configuration file being read on this thread, if any.
+    static CURRENT: RefCell<Option<ConfigFile>> = const { RefCell::new(None) };
+}
+
+/// Run `f` while reading `file`, so the external files referenced by [`Lazy`] values are
+/// resolved from its directory.
+pub(crate) fn reading<R>(file: &ConfigFile, f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT.with(|current| current.replace(Some(file.clone())));
+    let result = f();
+    CURRENT.with(|current| *current.borrow_mut() = previous);
+    result
+}
+
+/// Where a [`Lazy`] value comes from.
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    /// A value created in the application.
+    Loaded,
+    /// A sub-document of the configuration file.
+    Document(Value),
+    /// An external file, along with its format and the path written in the configuration.
+    File(Box<ConfigFile>, String),
+}
+
+/// A configuration value deserialized on its first access.
+///
+/// Once loaded, the value is cached. Loading errors aren't cached,
+/// so a broken external file can be fixed and loaded again.
+///
+/// A value referencing an external file is written back as the same reference:
+/// the external file itself is never written.
+pub struct Lazy<T> {
+    source: Source,
+    value: OnceLock<T>,
+}
+
+impl<T> Lazy<T> {
+    /// Wrap a value, which is already loaded.
+    pub fn new(value: T) -> Self {
+        Self {
+            source: Source::Loaded,
+            value: OnceLock::from(value),
+        }
+    }
+
+    /// Check if the value has been loaded.
+    pub fn is_loaded(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// The external file referenced by the value, if any.
+    pub fn file(&self) -> Option<&ConfigFile> {
+        match &self.source {
+            Source::File(file, _) => Some(file),
+            _ => None,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Lazy<T> {
+    /// Get the value, loading it if it's not loaded yet.
+    pub fn get(&self) -> Result<&T, ConrigError> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let value = self.load()?;
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// Get a mutable reference to the value, loading it if it's not loaded yet.
+    pub fn get_mut(&mut self) -> Result<&mut T, ConrigError> {
+        self.get()?;
+        Ok(self.value.get_mut().expect("the value is loaded"))
+    }
+
+    /// Get the value, loading it if it's not loaded yet.
+    pub fn into_inner(self) -> Result<T, ConrigError> {
+        self.get()?;
+        Ok(self.value.into_inner().expect("the value is loaded"))
+    }
+
+    fn load(&self) -> Result<T, ConrigError> {
+        match &self.source {
+            Source::Loaded => unreachable!("a loaded value is always initialized"),
+            Source::Document(document) => Ok(from_value(document.clone())?),
+            Source::File(file, _) => file.read(),
+        }
+    }
+}
+
+impl<T: Default> Default for Lazy<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Clone> Clone for Lazy<T> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.value.get(), &self.source) {
+            (Some(value), _) => f.debug_tuple("Lazy").field(value).finish(),
+            (None, Source::File(file, _)) => f.debug_tuple("Lazy").field(&file.path).finish(),
+            (None, _) => f.write_str("Lazy(..)"),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Lazy<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (&self.source, self.value.get()) {
+            (Source::File(_, reference), _) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(FILE_KEY, reference)?;
+                map.end()
+            }
+            (_, Some(value)) => value.serialize(serializer),
+            (Source::Document(document), None) => document.serialize(serializer),
+            (Source::Loaded, None) => unreachable!("a loaded value is always initialized"),
+        }
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Lazy<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let document = Value::deserialize(deserializer)?;
+        let source = match document {
+            Value::Table(table) if table.len() == 1 && table.contains_key(FILE_KEY) => {
+                let Some(Value::String(reference)) = table.get(FILE_KEY) else {
+                    return Err(serde::de::Error::custom(format!(
+                        "`{FILE_KEY}` must be the path of a file"
+                    )));
+                };
+                Source::File(Box::new(resolve(reference)), reference.clone())
+            }
+            document => Source::Document(document),
+        };
+        Ok(Self {
+            source,
+            value: OnceLock::new(),
+        })
+    }
+}
+
+/// Resolve a referenced file from the configuration file being read.
+fn resolve(reference: &str) -> ConfigFile {
+    CURRENT.with(|current| resolve_from(current.borrow().as_ref(), reference))
+}
+
+/// Resolve a referenced file from the directory of `current`, or the current directory.
+///
+/// The format is taken from the extension of the file. If it has none,
+/// the file is [detected][detect_file_format], falling back to the format of `current`.
+fn resolve_from(current: Option<&ConfigFile>, reference: &str) -> ConfigFile {
+    let path = match current.and_then(|file| file.path.parent()) {
+        Some(dir) => dir.join(reference),
+        None => PathBuf::from(reference),
+    };
+    let fallback = current.map_or(FileFormat::DEFAULT_FILE_FORMAT, |file| file.file_format);
+    let (path, file_format) = match path
+        .extension()
+        .and_then(|ext| FileFormat::from_extension(ext.to_str()?))
+    {
+        Some(file_format) => (path, file_format),
+        None => detect_file_format(&path, fallback).unwrap_or((path, fallback)),
+    };
+    match current {
+        Some(file) => ConfigFile::new(file_format, path).with_config_option(file.config_option),
+        None => ConfigFile::new(file_format, path),
+    }
+}
+
+/// A reference to another configuration file.
+///
+/// The configuration stores the path of the referenced file, e.g. `servers = "servers.toml"`,
+/// and the file is only read or written on demand, with [`load`] and [`save`].
+/// Relative paths are resolved from the directory of the configuration file being read,
+/// or from the current directory for the references created with [`FileRef::new`].
+/// The format of the referenced file is detected from its extension.
+///
+/// Unlike a [`Lazy`] value, the content of a `FileRef` isn't cached.
+///
+/// [`load`]: FileRef::load
+/// [`save`]: FileRef::save
+pub struct FileRef<T> {
+    reference: String,
+    file: ConfigFile,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> FileRef<T> {
+    /// Reference a file, from the current directory if the path is relative.
+    pub fn new(reference: impl Into<String>) -> Self {
+        let reference = reference.into();
+        Self {
+            file: resolve_from(None, &reference),
+            reference,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The path of the referenced file, as written in the configuration.
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    /// The referenced file, resolved.
+    pub fn file(&self) -> &ConfigFile {
+        &self.file
+    }
+}
+
+impl<T: DeserializeOwned> FileRef<T> {
+    /// Read the referenced file.
+    pub fn load(&self) -> Result<T, ConrigError> {
+        self.file.read()
+    }
+}
+
+impl<T: Serialize> FileRef<T> {
+    /// Write into the referenced file.
+    pub fn save(&self, value: &T) -> Result<(), ConrigError> {
+        self.file.write(value)
+    }
+}
+
+impl<T> Clone for FileRef<T> {
+    fn clone(&self) -> Self {
+        Self {
+            reference: self.reference.clone(),
+            file: self.file.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for FileRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FileRef").field(&self.file.path).finish()
+    }
+}
+
+impl<T> PartialEq for FileRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.file == other.file
+    }
+}
+
+impl<T> Eq for FileRef<T> {}
+
+impl<T> Serialize for FileRef<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.reference)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for FileRef<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let reference = String::deserialize(deserializer)?;
+        Ok(Self {
+            file: resolve(&reference),
+            reference,
+            _marker: PhantomData,
+        })
+    }
+}
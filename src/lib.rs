@@ -66,6 +66,7 @@
 //!         allow_dot_prefix: true,       // allow parsing files like `.conrig.toml`.
 //!         config_sys_type: ConfigType::Config,
 //!         sys_override_local: false,    // make local configuration the top priority.
+//!         ..ConfigOption::DEFAULT_CONFIG // keep the defaults of the other options.
 //!     }
 //! });
 //! ```
@@ -104,10 +105,11 @@
 //! #         allow_dot_prefix: true,       // allow parsing files like `.conrig.toml`.
 //! #         config_sys_type: ConfigType::Config,
 //! #         sys_override_local: false,    // make local configuration the top priority.
+//! #         ..ConfigOption::DEFAULT_CONFIG
 //! #     },
 //! # });
 //! // read a config
-//! let config = TEST_APP_CONFIG
+//! let config: Config = TEST_APP_CONFIG
 //!     .search_config_file()? // search existing config files.
 //!     .fallback_default()?   // set fallback path to your current directory.
 //!     .read_or_default()?;   // read the config, or insert the default one.
@@ -144,19 +146,109 @@
 #![deny(rustdoc::broken_intra_doc_links)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub mod apply;
+pub mod backend;
+pub mod cell;
+pub mod checksum;
+pub mod comments;
+#[cfg(feature = "clap")]
+#[cfg_attr(docsrs, doc(cfg(feature = "clap")))]
+pub mod cli;
+pub mod deprecation;
+#[cfg(feature = "diagnostics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "diagnostics")))]
+pub mod diagnostics;
+pub mod doctor;
+pub mod documents;
+pub mod encrypt;
 pub mod error;
+pub mod filesystem;
+pub mod generate;
+pub mod handle;
+pub mod import;
+pub mod lazy;
+pub mod lock;
 pub mod parser;
+pub mod patch;
 pub mod path;
+pub mod platform;
+pub mod policy;
+mod pattern;
+#[cfg(feature = "plist")]
+mod plist;
 pub mod macros;
+#[cfg(feature = "mmap")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mmap")))]
+pub mod mmap;
+pub mod preset;
+pub mod provider;
+pub mod redact;
+#[cfg(all(windows, feature = "windows-registry"))]
+#[cfg_attr(docsrs, doc(cfg(all(windows, feature = "windows-registry"))))]
+pub mod registry;
+#[cfg(feature = "http")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+pub mod remote;
+pub mod report;
+pub mod restart;
+pub mod safe_mode;
+#[cfg(feature = "schemars")]
+#[cfg_attr(docsrs, doc(cfg(feature = "schemars")))]
+pub mod schema;
+pub mod script;
+pub mod secret;
+pub mod source;
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
+pub mod transaction;
+pub mod upgrade;
+pub mod validate;
+pub mod value;
+pub mod watch;
 
-pub use error::{ConrigError, LangError};
+pub use error::{
+    ConrigError, ConrigResultExt, ConrigWarning, FileSystemError, LangError, SearchMiss,
+    SearchedPath,
+};
 pub use parser::{detect_file_format, FileFormat};
-pub use path::{ConfigOption, ConfigPathMetadata, ProjectPath, ConfigType};
+pub use path::{
+    AppData, ConfigOption, ConfigPathMetadata, ConfigType, Migration, ProjectPath, SearchLocation,
+    SearchOrder,
+};
 
-#[cfg(not(feature = "collapse-io-error"))]
-pub use error::FileSystemError;
+/// Generate the `ConfigPathMetadata` of a configuration structure.
+///
+/// ```rust
+/// # fn main() -> Result<(), conrig::ConrigError> {
+/// use conrig::Conrig;
+/// # use serde_derive::{Deserialize, Serialize};
+///
+/// #[derive(Default, Serialize, Deserialize, Conrig)]
+/// #[conrig(app = "conrig-test", org = "my-organization", qualifier = "org")]
+/// struct Config {
+///     name: String,
+/// }
+///
+/// let path = Config::CONFIG.default_config_file()?;
+/// # let _ = std::fs::remove_file(&path);
+/// let config = Config::load()?;
+/// config.save()?;
+/// # std::fs::remove_file(&path).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub use conrig_derive::Conrig;
 
 pub use serde;
 
-#[cfg(not(any(feature = "json", feature = "toml", feature = "yaml", feature = "ron")))]
+#[cfg(not(any(
+    feature = "json",
+    feature = "toml",
+    feature = "yaml",
+    feature = "ron",
+    feature = "plist"
+)))]
 compile_error!("At least one file type must be enabled.");
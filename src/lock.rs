@@ -0,0 +1,113 @@
+//! Advisory locks serializing the updates of a configuration file.
+//!
+//! A read-modify-write cycle loses the changes made by another process in the meantime.
+//! [`ConfigFile::lock`] takes an exclusive lock on a `<file>.lock` file next to the
+//! configuration file, and [`ConfigPathMetadata::update`] holds it during the whole cycle.
+//!
+//! The locks are advisory: they only exclude the other lockers, not plain reads and writes.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Default, Serialize, Deserialize)]
+//! struct Config {
+//!     launches: u32,
+//! }
+//!
+//! conrig!(const CONFIG<Config> = {
+//!     project_path: ProjectPath {
+//!         qualifier: "org",
+//!         organization: "foo",
+//!         application: "conrig-lock",
+//!     },
+//!     config_name: &["conrig-lock"],
+//!     config_option: ConfigOption::DEFAULT_CONFIG,
+//!     extra_files: &[],
+//!     extra_folders: &[],
+//!     default_format: FileFormat::Toml,
+//! });
+//!
+//! let launches = CONFIG.update(|config| {
+//!     config.launches += 1;
+//!     config.launches
+//! })?;
+//! assert!(launches >= 1);
+//! # let file = CONFIG.search_config_file()?.fallback_default()?;
+//! # std::fs::remove_file(&file.path).unwrap();
+//! # std::fs::remove_file(conrig::lock::lock_path(&file.path)).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::FileSystemError;
+use crate::parser::ConfigFile;
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An exclusive lock on a configuration file, released when dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    /// `None` with a custom [file system][crate::filesystem], which can't be locked.
+    _file: Option<fs::File>,
+}
+
+/// The path of the lock file of a configuration file: `<file>.lock`.
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".lock");
+    path.with_file_name(file_name)
+}
+
+impl ConfigFile {
+    /// Take an exclusive lock on the configuration file, waiting for the current holder.
+    ///
+    /// See the [module documentation][crate::lock] for more information.
+    /// With a custom [file system][crate::filesystem], nothing is locked.
+    pub fn lock(&self) -> Result<FileLock, ConrigError> {
+        if crate::filesystem::custom().is_some() {
+            return Ok(FileLock { _file: None });
+        }
+        crate::filesystem::create_dir_all(
+            self.path
+                .parent()
+                .ok_or(FileSystemError::NoProjectDirectory)?,
+        )?;
+        let path = lock_path(&self.path);
+        let file = fs::File::options()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(FileSystemError::open_config(&path))?;
+        file.lock().map_err(FileSystemError::write_config(&path))?;
+        Ok(FileLock { _file: Some(file) })
+    }
+}
+
+impl<'p, T: Serialize + DeserializeOwned + Default> ConfigPathMetadata<'p, T> {
+    /// Read the configuration, or its [default value], modify it with `f`, and write it back,
+    /// all under the [lock] of the configuration file.
+    ///
+    /// Returns the result of `f`.
+    ///
+    /// [default value]: crate::ConfigPathMetadata::default_value
+    /// [lock]: crate::parser::ConfigFile::lock
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R, ConrigError> {
+        let file = self.search_config_file()?.fallback_default()?;
+        let _lock = file.lock()?;
+        let mut value = match file.read() {
+            Err(err) if err.is_missing() => self.default_value()?,
+            value => value?,
+        };
+        let result = f(&mut value);
+        file.write(&value)?;
+        Ok(result)
+    }
+}
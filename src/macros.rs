@@ -1,9 +1,21 @@
-//! Utility macro for building a `ConfigPathMetadata` struct.
+//! Utility macros for building a `ConfigPathMetadata` struct and its providers,
+//! and for checking configurations.
 
 /// Initializes a `ConfigPathMetadata` struct with the given fields.
 ///
 /// This will **automatically** fill the `_marker` field with a `PhantomData` marker.
+/// Optional fields, like [`importers`], [`default_provider`], [`fallback_identities`]
+/// and [`parent`], are left empty unless specified.
 ///
+/// [`importers`]: crate::ConfigPathMetadata#structfield.importers
+/// [`default_provider`]: crate::ConfigPathMetadata#structfield.default_provider
+/// [`fallback_identities`]: crate::ConfigPathMetadata#structfield.fallback_identities
+/// [`parent`]: crate::ConfigPathMetadata#structfield.parent
+///
+/// The `static` form wraps the metadata in a [`StaticConfig`], which searches the
+/// configuration file once and caches its resolved path and format.
+///
+/// [`StaticConfig`]: crate::handle::StaticConfig
 ///
 /// ## Example
 ///
@@ -26,6 +38,7 @@
 ///         allow_dot_prefix: true,
 ///         config_sys_type: ConfigType::Config,
 ///         sys_override_local: false,
+///         ..ConfigOption::DEFAULT_CONFIG
 ///     },
 ///     extra_files: &[],
 ///     extra_folders: &[],
@@ -40,6 +53,7 @@ macro_rules! conrig {
     }) => {
         let $ident: $crate::path::ConfigPathMetadata<'static, $type> = $crate::path::ConfigPathMetadata {
             $($field: $value,)*
+            ..$crate::path::ConfigPathMetadata::MACRO_BASE
         };
     };
     (const $ident:ident<$type:ty> = {
@@ -48,7 +62,98 @@ macro_rules! conrig {
     }) => {
         const $ident: $crate::path::ConfigPathMetadata<'static, $type> = $crate::path::ConfigPathMetadata {
             $($field: $value,)*
-            _marker: ::std::marker::PhantomData,
+            ..$crate::path::ConfigPathMetadata::MACRO_BASE
         };
     };
+    (static $ident:ident<$type:ty> = {
+        $($field:ident: $value:expr),*
+        $(,)?
+    }) => {
+        static $ident: $crate::handle::StaticConfig<$type> = $crate::handle::StaticConfig::new(
+            $crate::path::ConfigPathMetadata {
+                $($field: $value,)*
+                ..$crate::path::ConfigPathMetadata::MACRO_BASE
+            },
+        );
+    };
+}
+
+/// Initializes a [`Templates`] provider with the given named default files.
+///
+/// All the files share the same format.
+///
+/// [`Templates`]: crate::provider::Templates
+///
+/// ## Example
+///
+/// ```rust
+/// use conrig::provider::Templates;
+/// use conrig::{templates, FileFormat};
+///
+/// const TEMPLATES: Templates = templates!(FileFormat::Toml, {
+///     "dev" => "log_level = \"debug\"",
+///     "prod" => "log_level = \"warn\"",
+/// })
+/// .with_env("APP_ENV");
+/// ```
+#[macro_export]
+macro_rules! templates {
+    ($format:expr, {
+        $($name:literal => $content:expr),*
+        $(,)?
+    }) => {
+        $crate::provider::Templates::new(&[
+            $(($name, $crate::provider::Embedded::new($format, $content)),)*
+        ])
+    };
+}
+
+/// Checks a condition on a configuration, reporting a [`Violation`] if it doesn't hold.
+///
+/// Inside [`collect`], the violation is collected and the check goes on, so all the failures
+/// are reported at once. Outside of it, this panics like [`assert!`].
+///
+/// The key path of the violation is taken from the condition, e.g. `server.port` for
+/// `cfg.server.port > 0`, dropping the leading receiver and trailing method calls.
+/// Pass it explicitly with `"path" => condition` when the condition doesn't start with the
+/// checked value.
+///
+/// [`Violation`]: crate::error::Violation
+/// [`collect`]: crate::validate::collect
+///
+/// ## Example
+///
+/// ```rust
+/// use conrig::conrig_assert;
+/// use conrig::validate::collect;
+///
+/// struct Config {
+///     workers: u32,
+///     threads: u32,
+/// }
+///
+/// let cfg = Config { workers: 0, threads: 2 };
+/// let violations = collect(|| {
+///     conrig_assert!(cfg.workers >= 1, "workers must be >= 1");
+///     conrig_assert!("threads" => 1 <= cfg.threads, "threads must be >= 1");
+/// })
+/// .unwrap_err();
+/// assert_eq!(violations[0].path, "workers");
+/// assert_eq!(violations.len(), 1);
+/// ```
+#[macro_export]
+macro_rules! conrig_assert {
+    ($path:literal => $cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::validate::report($crate::error::Violation::new($path, format!($($arg)+)));
+        }
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            $crate::validate::report($crate::error::Violation::new(
+                $crate::validate::key_path(stringify!($cond)),
+                format!($($arg)+),
+            ));
+        }
+    };
 }
@@ -0,0 +1,141 @@
+//! Memory-mapped reads of large configuration files.
+//!
+//! Only Unix platforms map files: elsewhere, the file is read as usual.
+//!
+//! Both the text formats and the binary property lists are parsed straight from the mapping.
+//!
+//! See [`ConfigOption.mmap_threshold`] for more information.
+//!
+//! [`ConfigOption.mmap_threshold`]: crate::ConfigOption#structfield.mmap_threshold
+
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{c_int, c_long, c_void};
+
+    pub const PROT_READ: c_int = 1;
+    pub const MAP_PRIVATE: c_int = 2;
+    pub const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    extern "C" {
+        pub fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: c_long,
+        ) -> *mut c_void;
+        pub fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+}
+
+/// The size in bytes from which configuration files are read by memory mapping.
+///
+/// See [`ConfigOption.mmap_threshold`] for more information.
+///
+/// [`ConfigOption.mmap_threshold`]: crate::ConfigOption#structfield.mmap_threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MmapThreshold(u64);
+
+impl MmapThreshold {
+    /// Create a new `MmapThreshold` of `bytes`.
+    ///
+    /// # Safety
+    ///
+    /// A mapped file is parsed in place, borrowing the mapping, so its content must not
+    /// change while it's read. No process, including this one, may modify or truncate a
+    /// configuration file read with this threshold, e.g. by writing it in place through a
+    /// [`ConfigFile`] with [`atomic_write`] disabled. Otherwise, the behavior is undefined,
+    /// and the process may be killed by a `SIGBUS` signal.
+    ///
+    /// Files replaced by a rename, like [`atomic_write`] does, are safe to read.
+    ///
+    /// [`ConfigFile`]: crate::parser::ConfigFile
+    /// [`atomic_write`]: crate::ConfigOption#structfield.atomic_write
+    pub const unsafe fn new(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    /// The size in bytes from which configuration files are mapped.
+    pub const fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A read-only, private mapping of a whole file.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) struct Mmap {
+    ptr: *const u8,
+    len: usize,
+}
+
+impl Mmap {
+    /// Map a file if it's at least `threshold` bytes long.
+    ///
+    /// Returns `None` if the file is smaller, or if the platform doesn't support mappings.
+    /// The file must not be modified or truncated while it's mapped, see [`MmapThreshold::new`].
+    #[cfg(unix)]
+    pub(crate) fn open(path: &Path, threshold: MmapThreshold) -> io::Result<Option<Self>> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        // empty files can't be mapped.
+        if len < threshold.bytes().max(1) {
+            return Ok(None);
+        }
+        let len = usize::try_from(len).map_err(io::Error::other)?;
+        // SAFETY: the arguments describe a valid private, read-only mapping of an open file,
+        // and the result is checked before use.
+        let ptr = unsafe {
+            sys::mmap(
+                std::ptr::null_mut(),
+                len,
+                sys::PROT_READ,
+                sys::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == sys::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Some(Self {
+            ptr: ptr.cast(),
+            len,
+        }))
+    }
+
+    /// Map a file if it's at least `threshold` bytes long.
+    ///
+    /// Returns `None` if the file is smaller, or if the platform doesn't support mappings.
+    #[cfg(not(unix))]
+    pub(crate) fn open(path: &Path, threshold: MmapThreshold) -> io::Result<Option<Self>> {
+        let _ = (File::open(path)?, threshold);
+        Ok(None)
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the mapping is `len` bytes long, readable, and lives as long as `self`.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        // SAFETY: the mapping was created by `mmap` with this length, and is unmapped once.
+        unsafe {
+            sys::munmap(self.ptr as *mut _, self.len);
+        }
+    }
+}
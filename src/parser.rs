@@ -1,21 +1,34 @@
 //! Readers and writers.
 #![allow(unreachable_patterns)]
 
-use crate::{ConfigPathMetadata, ConrigError, FileSystemError, LangError};
+use crate::deprecation::check_deprecations;
+use crate::error::{ConrigResultExt, FileSystemError, Operation, UnknownFormat};
+use crate::filesystem;
+use crate::provider::Embedded;
+use crate::value::{from_value, from_value_tracked, to_value, Value, ValueError};
+use crate::watch::Fingerprint;
+use crate::{ConfigOption, ConfigPathMetadata, ConrigError, LangError};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fmt;
 use std::fs;
-use std::io::{BufReader, Read, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// The format of a configuration file.
 ///
-/// Currently, `conrig` supports [toml][toml], [json][json], [yaml][yaml] and [ron][ron] as possible languages.
+/// Currently, `conrig` supports [toml][toml], [json][json], [yaml][yaml], [ron][ron]
+/// and [plist][plist] as possible languages.
 ///
 /// [toml]: https://github.com/toml-rs/toml/
 /// [json]: https://www.json.org/json-en.html
 /// [yaml]: https://yaml.org/
 /// [ron]: https://github.com/ron-rs/ron/
+/// [plist]: https://developer.apple.com/documentation/bundleresources/information_property_list
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum FileFormat {
@@ -35,6 +48,12 @@ pub enum FileFormat {
     #[cfg(feature = "yaml")]
     #[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
     Yaml,
+    /// The Apple property list format, XML or binary. Supported by a built-in parser.
+    ///
+    /// Binary property lists are written back as XML.
+    #[cfg(feature = "plist")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "plist")))]
+    Plist,
 }
 
 impl Default for FileFormat {
@@ -43,6 +62,25 @@ impl Default for FileFormat {
     }
 }
 
+/// Formats the name of the language, i.e. its [extension][FileFormat::extension], e.g. `toml`.
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// Parses the name of a language, case-insensitively, e.g. `toml` or `YAML`.
+///
+/// The [extensions][FileFormat::from_extension] are accepted too, e.g. `yml`.
+impl FromStr for FileFormat {
+    type Err = UnknownFormat;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        FileFormat::from_extension(&input.to_ascii_lowercase())
+            .ok_or_else(|| UnknownFormat(input.to_owned()))
+    }
+}
+
 impl FileFormat {
     /// Default file format.
     ///
@@ -51,6 +89,7 @@ impl FileFormat {
     /// 2. Json;
     /// 3. Yaml;
     /// 4. Ron;
+    /// 5. Plist;
     #[cfg(feature = "toml")]
     pub const DEFAULT_FILE_FORMAT: FileFormat = FileFormat::Toml;
     #[cfg(not(feature = "toml"))]
@@ -62,6 +101,9 @@ impl FileFormat {
     #[cfg(feature = "ron")]
     #[cfg(not(any(feature = "toml", feature = "json", feature = "yaml")))]
     pub const DEFAULT_FILE_FORMAT: FileFormat = FileFormat::Ron;
+    #[cfg(feature = "plist")]
+    #[cfg(not(any(feature = "toml", feature = "json", feature = "yaml", feature = "ron")))]
+    pub const DEFAULT_FILE_FORMAT: FileFormat = FileFormat::Plist;
 
     /// Get the file extension of the given language.
     ///
@@ -76,11 +118,33 @@ impl FileFormat {
             Self::Toml => "toml",
             #[cfg(feature = "ron")]
             Self::Ron => "ron",
+            #[cfg(feature = "plist")]
+            Self::Plist => "plist",
 
             _ => unreachable!(),
         }
     }
 
+    /// Get the language of a file extension, e.g. `toml`, without the leading dot.
+    ///
+    /// Both `yaml` and `yml` are recognized as YAML. Returns `None` for unknown extensions,
+    /// or the ones of disabled languages.
+    pub fn from_extension(ext: &str) -> Option<FileFormat> {
+        match ext {
+            #[cfg(feature = "toml")]
+            "toml" => Some(FileFormat::Toml),
+            #[cfg(feature = "json")]
+            "json" => Some(FileFormat::Json),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(FileFormat::Yaml),
+            #[cfg(feature = "ron")]
+            "ron" => Some(FileFormat::Ron),
+            #[cfg(feature = "plist")]
+            "plist" => Some(FileFormat::Plist),
+            _ => None,
+        }
+    }
+
     /// Deserialize a value from a given `&str`.
     pub fn read_str<'de, T: Deserialize<'de>>(&self, input: &'de str) -> Result<T, LangError> {
         match self {
@@ -92,11 +156,38 @@ impl FileFormat {
             Self::Yaml => Ok(serde_yaml::from_str(input)?),
             #[cfg(feature = "ron")]
             Self::Ron => Ok(ron::from_str(input)?),
+            #[cfg(feature = "plist")]
+            Self::Plist => T::deserialize(crate::plist::from_str(input)?).map_err(|err| {
+                LangError::PlistError(crate::error::PlistError {
+                    message: err.to_string(),
+                    line: 0,
+                    column: 0,
+                })
+            }),
 
             _ => unreachable!(),
         }
     }
 
+    /// Deserialize a value from a document, locating its errors in the file at `path`, if any.
+    ///
+    /// See [`ConrigError::parse_error`] for more information.
+    pub(crate) fn parse_str<'de, T: Deserialize<'de>>(
+        &self,
+        input: &'de str,
+        path: Option<&Path>,
+    ) -> Result<T, ConrigError> {
+        self.read_str(input).map_err(|err| {
+            // the document is deserialized again to find the value in error.
+            let key = self
+                .read_str::<Value>(input)
+                .ok()
+                .and_then(|document| from_value_tracked::<T>(document, false).0.err())
+                .map(|(_, key)| key);
+            ConrigError::parse(err, *self, path, input, key)
+        })
+    }
+
     /// Serialize a value and writes it to a writer.
     ///
     /// **Note**: Toml and ron does not support directly writing into an io buffer,
@@ -110,9 +201,10 @@ impl FileFormat {
             #[cfg(feature = "toml")]
             Self::Toml => {
                 let res = toml::to_string(input).map_err(|e| LangError::TomlError(e.into()))?;
-                writer
-                    .write_all(res.as_bytes())
-                    .map_err(FileSystemError::WriteConfig)?;
+                writer.write_all(res.as_bytes()).map_err(|e| {
+                    let e = <toml::ser::Error as serde::ser::Error>::custom(e);
+                    LangError::TomlError(e.into())
+                })?;
             }
             #[cfg(feature = "json")]
             Self::Json => serde_json::to_writer(writer, input).map_err(LangError::JsonError)?,
@@ -123,7 +215,18 @@ impl FileFormat {
                 let res = ron::to_string(input).map_err(|e| LangError::RonError(e.into()))?;
                 writer
                     .write_all(res.as_bytes())
-                    .map_err(FileSystemError::WriteConfig)?;
+                    .map_err(|e| LangError::RonError(ron::Error::from(e).into()))?;
+            }
+            #[cfg(feature = "plist")]
+            Self::Plist => {
+                let res = crate::plist::to_string(&to_value(input)?);
+                writer.write_all(res.as_bytes()).map_err(|e| {
+                    LangError::PlistError(crate::error::PlistError {
+                        message: e.to_string(),
+                        line: 0,
+                        column: 0,
+                    })
+                })?;
             }
 
             _ => unreachable!(),
@@ -143,38 +246,97 @@ impl FileFormat {
 /// 3. `yaml` ;
 /// 4. `yml` ;
 /// 5. `ron` ;
+/// 6. `plist` ;
 pub fn detect_file_format(
     path: impl AsRef<Path>,
     default_format: FileFormat,
 ) -> Option<(PathBuf, FileFormat)> {
-    let path = path.as_ref().to_path_buf();
-
-    macro_rules! try_open {
-        ($($ext:literal)|+ => $ty:ident) => {$(
-            let mut ext = path.extension()?.to_os_string();
-            ext.push(".");
-            ext.push($ext);
-            let path = path.with_extension(ext);
-            if std::fs::File::open(&path).is_ok() {
-                return Some((path, FileFormat::$ty));
-            }
-        )+};
-    }
+    detect_mapped(path.as_ref(), default_format, &[])
+}
+
+/// Checks which configuration files named **name** exist, and returns them with their language.
+///
+/// Unlike [`detect_file_format`], every existing file is returned, in the same sequence.
+pub fn detect_file_formats(
+    path: impl AsRef<Path>,
+    default_format: FileFormat,
+) -> Vec<(PathBuf, FileFormat)> {
+    detect_all_mapped(path.as_ref(), default_format, &[])
+}
 
+/// The built-in file extensions, in the sequence of detection.
+const EXTENSIONS: &[(&str, FileFormat)] = &[
     #[cfg(feature = "toml")]
-    try_open!("toml" => Toml);
+    ("toml", FileFormat::Toml),
     #[cfg(feature = "json")]
-    try_open!("json" => Json);
+    ("json", FileFormat::Json),
+    #[cfg(feature = "yaml")]
+    ("yaml", FileFormat::Yaml),
     #[cfg(feature = "yaml")]
-    try_open!("yaml" | "yml" => Yaml);
+    ("yml", FileFormat::Yaml),
     #[cfg(feature = "ron")]
-    try_open!("ron" => Ron);
+    ("ron", FileFormat::Ron),
+    #[cfg(feature = "plist")]
+    ("plist", FileFormat::Plist),
+];
 
-    if std::fs::File::open(&path).is_ok() {
-        return Some((path, default_format));
-    }
+/// The possible files named **name**, with their language, in the sequence of detection:
+/// the built-in extensions, then the custom `extensions`, and finally the file itself.
+///
+/// See [`ConfigOption.extensions`] for more information.
+///
+/// [`ConfigOption.extensions`]: crate::ConfigOption#structfield.extensions
+pub(crate) fn candidates(
+    path: &Path,
+    default_format: FileFormat,
+    extensions: &[(&str, FileFormat)],
+) -> Vec<(PathBuf, FileFormat)> {
+    let Some(name) = path.file_name() else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<(PathBuf, FileFormat)> = EXTENSIONS
+        .iter()
+        .chain(extensions.iter().filter(|(ext, _)| !ext.is_empty()))
+        .map(|(ext, file_format)| {
+            let mut file_name = name.to_os_string();
+            file_name.push(".");
+            file_name.push(ext);
+            (path.with_file_name(file_name), *file_format)
+        })
+        .collect();
+    let bare_format = extensions
+        .iter()
+        .find(|(ext, _)| ext.is_empty())
+        .map_or(default_format, |(_, file_format)| *file_format);
+    candidates.push((path.to_path_buf(), bare_format));
+    candidates
+}
+
+/// Like [`detect_file_format`], with the custom `extensions` of [`ConfigOption.extensions`].
+///
+/// [`ConfigOption.extensions`]: crate::ConfigOption#structfield.extensions
+pub(crate) fn detect_mapped(
+    path: &Path,
+    default_format: FileFormat,
+    extensions: &[(&str, FileFormat)],
+) -> Option<(PathBuf, FileFormat)> {
+    candidates(path, default_format, extensions)
+        .into_iter()
+        .find(|(candidate, _)| filesystem::exists(candidate))
+}
 
-    None
+/// Like [`detect_file_formats`], with the custom `extensions` of [`ConfigOption.extensions`].
+///
+/// [`ConfigOption.extensions`]: crate::ConfigOption#structfield.extensions
+pub(crate) fn detect_all_mapped(
+    path: &Path,
+    default_format: FileFormat,
+    extensions: &[(&str, FileFormat)],
+) -> Vec<(PathBuf, FileFormat)> {
+    candidates(path, default_format, extensions)
+        .into_iter()
+        .filter(|(candidate, _)| filesystem::exists(candidate))
+        .collect()
 }
 
 /// A possibly existing configuration file.
@@ -187,9 +349,12 @@ pub fn detect_file_format(
 /// or may lead to undefined behavior (if you use `unsafe_xxx`)
 /// like unwrapping a `Option::None`.
 ///
+/// This is a [`ConfigSource`] without any configuration until a file is written.
+///
 /// [`ConfigPathMetaData`]: crate::ConfigPathMetadata
+/// [`ConfigSource`]: crate::source::ConfigSource
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub struct RawConfigFile<'a, 'p, T> {
+pub struct RawConfigSource<'a, 'p, T> {
     /// The format of the configuration file.
     pub file_format: FileFormat,
     /// The path of the configuration file.
@@ -212,8 +377,12 @@ pub struct RawConfigFile<'a, 'p, T> {
     config: &'a ConfigPathMetadata<'p, T>,
 }
 
-impl<'a, 'p, T> RawConfigFile<'a, 'p, T> {
-    /// Create a new `RawConfigFile`.
+/// The former name of [`RawConfigSource`].
+#[deprecated(note = "renamed to `RawConfigSource`")]
+pub type RawConfigFile<'a, 'p, T> = RawConfigSource<'a, 'p, T>;
+
+impl<'a, 'p, T> RawConfigSource<'a, 'p, T> {
+    /// Create a new `RawConfigSource`.
     ///
     /// This is never suggested to use, but still publicly available for special needs.
     pub fn new(
@@ -232,10 +401,8 @@ impl<'a, 'p, T> RawConfigFile<'a, 'p, T> {
     ///
     /// If the inner `path` is `None`, then overrides it.
     pub fn fallback_path(self, path: PathBuf) -> ConfigFile {
-        ConfigFile {
-            file_format: self.file_format,
-            path: self.path.ok_or(()).unwrap_or(path),
-        }
+        let path = self.path.clone().ok_or(()).unwrap_or(path);
+        self.to_config_file(path)
     }
 
     /// Set the default configuration file path of the system as a fallback path for the configuration file.
@@ -244,13 +411,12 @@ impl<'a, 'p, T> RawConfigFile<'a, 'p, T> {
     ///
     /// [`default_sys_config_file`]: crate::ConfigPathMetadata::default_sys_config_file
     pub fn fallback_default_sys(self) -> Result<ConfigFile, ConrigError> {
-        Ok(ConfigFile {
-            file_format: self.file_format,
-            path: self
-                .path
-                .ok_or(())
-                .or_else(|_| self.config.default_sys_config_file())?,
-        })
+        let path = self
+            .path
+            .clone()
+            .ok_or(())
+            .or_else(|_| self.config.default_sys_config_file())?;
+        Ok(self.to_config_file(path))
     }
 
     /// Set the default configuration file path of the local directory as a fallback path for the configuration file.
@@ -259,13 +425,12 @@ impl<'a, 'p, T> RawConfigFile<'a, 'p, T> {
     ///
     /// [`default_local_config_file`]: crate::ConfigPathMetadata::default_local_config_file
     pub fn fallback_default_local(self) -> Result<ConfigFile, ConrigError> {
-        Ok(ConfigFile {
-            file_format: self.file_format,
-            path: self
-                .path
-                .ok_or(())
-                .or_else(|_| self.config.default_local_config_file())?,
-        })
+        let path = self
+            .path
+            .clone()
+            .ok_or(())
+            .or_else(|_| self.config.default_local_config_file())?;
+        Ok(self.to_config_file(path))
     }
 
     /// Set the default configuration file path as a fallback path for the configuration file.
@@ -274,35 +439,58 @@ impl<'a, 'p, T> RawConfigFile<'a, 'p, T> {
     ///
     /// [`default_config_file`]: crate::ConfigPathMetadata::default_config_file
     pub fn fallback_default(self) -> Result<ConfigFile, ConrigError> {
-        Ok(ConfigFile {
+        let path = self
+            .path
+            .clone()
+            .ok_or(())
+            .or_else(|_| self.config.default_config_file())?;
+        Ok(self.to_config_file(path))
+    }
+
+    /// Convert into a [`ConfigFile`] without any fallback path.
+    ///
+    /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
+    ///
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn config_file(&self) -> Result<ConfigFile, ConrigError> {
+        let path = self
+            .path
+            .clone()
+            .ok_or_else(|| ConrigError::NoConfigurationFile(self.config.searched_paths()))?;
+        Ok(self.to_config_file(path))
+    }
+
+    /// Convert into a [`ConfigFile`] without checking the `path`.
+    ///
+    /// ## Safety
+    ///
+    /// The [`path`] field must not be `None`.
+    ///
+    /// [`path`]: crate::parser::RawConfigSource#structfield.path
+    unsafe fn config_file_unchecked(&self) -> ConfigFile {
+        let path = unsafe { self.path.clone().unwrap_unchecked() };
+        self.to_config_file(path)
+    }
+
+    fn to_config_file(&self, path: PathBuf) -> ConfigFile {
+        ConfigFile {
             file_format: self.file_format,
-            path: self
-                .path
-                .ok_or(())
-                .or_else(|_| self.config.default_config_file())?,
-        })
+            read_only: self.config.is_read_only(&path),
+            path,
+            config_option: self.config.config_option,
+            policy: self.config.search_policy_file().map(Box::new),
+        }
     }
 }
 
-impl<'a, 'p, T: DeserializeOwned> RawConfigFile<'a, 'p, T> {
+impl<'a, 'p, T: DeserializeOwned> RawConfigSource<'a, 'p, T> {
     /// Read and deserialize the configuration file. Fail if the configuration doesn't exist.
     ///
     /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
     ///
     /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
     pub fn read(&self) -> Result<T, ConrigError> {
-        if let Some(path) = &self.path {
-            let file = fs::File::open(path).map_err(FileSystemError::OpenConfig)?;
-            let mut buf_reader = BufReader::new(file);
-            let mut contents = String::new();
-            buf_reader
-                .read_to_string(&mut contents)
-                .map_err(FileSystemError::ReadConfig)?;
-            let result = self.file_format.read_str(&contents)?;
-            Ok(result)
-        } else {
-            Err(ConrigError::NoConfigurationFile)
-        }
+        self.config_file()?.read()
     }
 
     /// Read and deserialize the configuration file. Fail if the configuration doesn't exist.
@@ -314,38 +502,18 @@ impl<'a, 'p, T: DeserializeOwned> RawConfigFile<'a, 'p, T> {
     ///
     /// [`path`]: crate::parser::ConfigFile#structfield.path
     pub unsafe fn unsafe_read(&self) -> Result<T, ConrigError> {
-        let path = unsafe { self.path.as_ref().unwrap_unchecked() };
-        let file = fs::File::open(path).map_err(FileSystemError::OpenConfig)?;
-        let mut buf_reader = BufReader::new(file);
-        let mut contents = String::new();
-        buf_reader
-            .read_to_string(&mut contents)
-            .map_err(FileSystemError::ReadConfig)?;
-        let result = self.file_format.read_str(&contents)?;
-        Ok(result)
+        unsafe { self.config_file_unchecked() }.read()
     }
 }
 
-impl<'a, 'p, T: Serialize> RawConfigFile<'a, 'p, T> {
+impl<'a, 'p, T: Serialize> RawConfigSource<'a, 'p, T> {
     /// Serialize and write a value into the configuration file.
     ///
     /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
     ///
     /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
     pub fn write(&self, value: &T) -> Result<(), ConrigError> {
-        if let Some(path) = &self.path {
-            fs::create_dir_all(path.parent().ok_or(FileSystemError::NoProjectDirectory)?)
-                .map_err(FileSystemError::WriteConfig)?;
-            let mut file = fs::File::options()
-                .write(true)
-                .truncate(true)
-                .create(true)
-                .open(path)
-                .map_err(FileSystemError::OpenConfig)?;
-            self.file_format.write(value, &mut file)
-        } else {
-            Err(ConrigError::NoConfigurationFile)
-        }
+        self.config_file()?.write(value)
     }
 
     /// Serialize and write a value into the configuration file.
@@ -357,20 +525,37 @@ impl<'a, 'p, T: Serialize> RawConfigFile<'a, 'p, T> {
     ///
     /// [`path`]: crate::parser::ConfigFile#structfield.path
     pub unsafe fn unsafe_write(&self, value: &T) -> Result<(), ConrigError> {
-        let path = unsafe { self.path.as_ref().unwrap_unchecked() };
-        fs::create_dir_all(path.parent().ok_or(FileSystemError::NoProjectDirectory)?)
-            .map_err(FileSystemError::WriteConfig)?;
-        let mut file = fs::File::options()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(path)
-            .map_err(FileSystemError::OpenConfig)?;
-        self.file_format.write(value, &mut file)
+        unsafe { self.config_file_unchecked() }.write(value)
+    }
+}
+
+impl<'a, 'p, T: Serialize + Default> RawConfigSource<'a, 'p, T> {
+    /// Serialize and write a value into the configuration file,
+    /// omitting every field equal to its counterpart in `T::default()`.
+    ///
+    /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
+    ///
+    /// See [`ConfigFile::write_minimal`] for more information.
+    ///
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn write_minimal(&self, value: &T) -> Result<(), ConrigError> {
+        self.config_file()?.write_minimal(value)
     }
 }
 
-impl<'a, 'p, T: Serialize + DeserializeOwned> RawConfigFile<'a, 'p, T> {
+impl<'a, 'p, T: Serialize + DeserializeOwned> RawConfigSource<'a, 'p, T> {
+    /// Read and deserialize the configuration file, then write the whole value back
+    /// if the file doesn't contain it already.
+    ///
+    /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
+    ///
+    /// See [`ConfigFile::read_and_upgrade`] for more information.
+    ///
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn read_and_upgrade(&self) -> Result<T, ConrigError> {
+        self.config_file()?.read_and_upgrade()
+    }
+
     /// Read and deserialize the configuration file.
     /// If the configuration file doesn't exist, a new configuration file will be created,
     /// and it will be filled with the default value provided.
@@ -379,18 +564,7 @@ impl<'a, 'p, T: Serialize + DeserializeOwned> RawConfigFile<'a, 'p, T> {
     ///
     /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
     pub fn read_or_new(&self, default: T) -> Result<T, ConrigError> {
-        if let Some(path) = &self.path {
-            if path.exists() {
-                self.read()
-            } else {
-                fs::create_dir_all(path.parent().ok_or(FileSystemError::NoProjectDirectory)?)
-                    .map_err(FileSystemError::WriteConfig)?;
-                self.write(&default)?;
-                Ok(default)
-            }
-        } else {
-            Err(ConrigError::NoConfigurationFile)
-        }
+        self.config_file()?.read_or_new(default)
     }
 
     /// Read and deserialize the configuration file.
@@ -407,52 +581,95 @@ impl<'a, 'p, T: Serialize + DeserializeOwned> RawConfigFile<'a, 'p, T> {
         &self,
         default: T,
     ) -> Result<T, ConrigError> {
-        let path = unsafe { self.path.as_ref().unwrap_unchecked() };
-        if path.exists() {
-            self.read()
-        } else {
-            fs::create_dir_all(path.parent().ok_or(FileSystemError::NoProjectDirectory)?)
-                .map_err(FileSystemError::WriteConfig)?;
-            self.write(&default)?;
-            Ok(default)
-        }
+        unsafe { self.config_file_unchecked() }.read_or_new(default)
+    }
+
+    /// Read and deserialize the configuration file.
+    /// If the configuration file doesn't exist, a new configuration file will be created,
+    /// and it will be filled with the value returned by `default`.
+    ///
+    /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
+    ///
+    /// See [`ConfigFile::read_or_else`] for more information.
+    ///
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn read_or_else(&self, default: impl FnOnce() -> T) -> Result<T, ConrigError> {
+        self.config_file()?.read_or_else(default)
+    }
+
+    /// Read and deserialize the configuration file.
+    /// If the configuration file doesn't exist, a new configuration file will be created,
+    /// and it will be filled with the value returned by `default`.
+    ///
+    /// ## Safety
+    ///
+    /// This directly unwrap the [`path`] field. You must ensure that
+    /// the configuration file path is valid and exists.
+    ///
+    /// [`path`]: crate::parser::ConfigFile#structfield.path
+    pub unsafe fn unsafe_read_or_else(
+        &self,
+        default: impl FnOnce() -> T,
+    ) -> Result<T, ConrigError> {
+        unsafe { self.config_file_unchecked() }.read_or_else(default)
     }
 }
 
-impl<'a, 'p, T: Serialize + DeserializeOwned + Default> RawConfigFile<'a, 'p, T> {
+impl<'a, 'p, T: Serialize + DeserializeOwned> RawConfigSource<'a, 'p, T> {
     /// Read and deserialize the configuration file.
     /// If the configuration file doesn't exist, a new configuration file will be created,
-    /// and it will be filled with the default value of your structure.
+    /// and it will be filled with the value of the [`default_provider`].
     ///
     /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
+    /// If no provider is registered, a [`NoDefaultProvider`] error will be returned.
     ///
-    /// This calls [`read_or_new`] internally.
+    /// [`default_provider`]: crate::ConfigPathMetadata#structfield.default_provider
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    /// [`NoDefaultProvider`]: crate::ConrigError::NoDefaultProvider
+    pub fn read_or_provided(&self) -> Result<T, ConrigError> {
+        self.config_file()?
+            .read_or_try_else(|| self.config.provided_value())
+    }
+}
+
+impl<'a, 'p, T: Serialize + DeserializeOwned + Default> RawConfigSource<'a, 'p, T> {
+    /// Read and deserialize the configuration file.
+    /// If the configuration file doesn't exist, a new configuration file will be created,
+    /// and it will be filled with the [default value] of your structure.
     ///
+    /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
+    ///
+    /// This behaves like [`read_or_new`].
+    ///
+    /// [default value]: crate::ConfigPathMetadata::default_value
     /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
     /// [`read_or_new`]: crate::parser::ConfigFile::read_or_new
     pub fn read_or_default(
         &self,
     ) -> Result<T, ConrigError> {
-        self.read_or_new(T::default())
+        self.config_file()?
+            .read_or_try_else(|| self.config.default_value())
     }
 
     /// Read and deserialize the configuration file.
     /// If the configuration file doesn't exist, a new configuration file will be created,
-    /// and it will be filled with the default value of your structure.
+    /// and it will be filled with the [default value] of your structure.
     ///
-    /// This calls [`unsafe_read_or_new`] internally.
+    /// This behaves like [`unsafe_read_or_new`].
     ///
     /// ## Safety
     ///
     /// This directly unwrap the [`path`] field. You must ensure that
     /// the configuration file path is valid and exists.
     ///
+    /// [default value]: crate::ConfigPathMetadata::default_value
     /// [`path`]: crate::parser::ConfigFile#structfield.path
     /// [`unsafe_read_or_new`]: crate::parser::ConfigFile#method.unsafe_read_or_new
     pub unsafe fn unsafe_read_or_default(
         &self,
     ) -> Result<T, ConrigError> {
-        unsafe { self.unsafe_read_or_new(T::default()) }
+        unsafe { self.config_file_unchecked() }
+            .read_or_try_else(|| self.config.default_value())
     }
 }
 
@@ -461,23 +678,76 @@ impl<'a, 'p, T: Serialize + DeserializeOwned + Default> RawConfigFile<'a, 'p, T>
 /// Keeping this in your programmes is not suggested typically.
 /// Instead, construct it with [`ConfigPathMetaData`] where it's used.
 ///
-/// Different from [`RawConfigFile`], this contains a never nullable source
+/// Different from [`RawConfigSource`], this contains a never nullable source
 /// path.
 ///
 /// [`ConfigPathMetaData`]: crate::ConfigPathMetadata
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ConfigFile {
     /// The format of the configuration file.
     pub file_format: FileFormat,
     /// The path of the configuration file.
     pub path: PathBuf,
+    /// Extra configuration options.
+    ///
+    /// This is inherited from the [`ConfigPathMetaData`] which created this `ConfigFile`.
+    ///
+    /// [`ConfigPathMetaData`]: crate::ConfigPathMetadata
+    pub config_option: ConfigOption,
+    /// Forbid writing into the configuration file.
+    ///
+    /// If this is `true`, writing fails with a [`ReadOnly`] error.
+    ///
+    /// [`ReadOnly`]: crate::ConrigError::ReadOnly
+    pub read_only: bool,
+    /// The policy file locking some keys of the configuration file.
+    ///
+    /// If this is set, writing a value different from the policy fails with a
+    /// [`LockedKeys`] error. See [`ConfigOption.policy`] for more information.
+    ///
+    /// [`LockedKeys`]: crate::ConrigError::LockedKeys
+    /// [`ConfigOption.policy`]: crate::ConfigOption#structfield.policy
+    pub policy: Option<Box<ConfigFile>>,
 }
 
 impl ConfigFile {
-    /// Create a new `ConfigFile`.
+    /// Create a new `ConfigFile`, with the [default options][default].
     ///
     /// This is never suggested to use, but still publicly available for special needs.
+    ///
+    /// [default]: crate::ConfigOption::DEFAULT_CONFIG
     pub fn new(file_format: FileFormat, path: PathBuf) -> Self {
-        Self { file_format, path }
+        Self {
+            file_format,
+            path,
+            config_option: ConfigOption::DEFAULT_CONFIG,
+            read_only: false,
+            policy: None,
+        }
+    }
+
+    /// Modify the [`config_option`] field.
+    ///
+    /// [`config_option`]: crate::parser::ConfigFile#structfield.config_option
+    pub fn with_config_option(mut self, config_option: ConfigOption) -> Self {
+        self.config_option = config_option;
+        self
+    }
+
+    /// Modify the [`read_only`] field.
+    ///
+    /// [`read_only`]: crate::parser::ConfigFile#structfield.read_only
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Modify the [`policy`] field.
+    ///
+    /// [`policy`]: crate::parser::ConfigFile#structfield.policy
+    pub fn with_policy(mut self, policy: ConfigFile) -> Self {
+        self.policy = Some(Box::new(policy));
+        self
     }
 
     /// Read and deserialize the configuration file. Fail if the configuration doesn't exist.
@@ -485,16 +755,418 @@ impl ConfigFile {
     /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
     ///
     /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    ///
+    /// If any [deprecation][deprecations] is registered, the document is checked against them.
+    /// And if [`strict_keys`] is enabled, unknown keys are rejected.
+    ///
+    /// [deprecations]: crate::ConfigOption#structfield.deprecations
+    /// [`strict_keys`]: crate::ConfigOption#structfield.strict_keys
+    ///
+    /// If [`create_missing`] is enabled, a missing configuration file is created first,
+    /// holding an empty document.
+    ///
+    /// [`create_missing`]: crate::ConfigOption#structfield.create_missing
     pub fn read<T: DeserializeOwned>(&self) -> Result<T, ConrigError> {
+        if self.config_option.create_missing && !self.read_only && !filesystem::exists(&self.path) {
+            self.write(&Value::Table(Default::default()))?;
+        }
+        self.read_inner().context_path(Operation::Read, &self.path)
+    }
+
+    fn read_inner<T: DeserializeOwned>(&self) -> Result<T, ConrigError> {
+        self.with_raw(|contents| match contents {
+            Contents::Text(contents) => self.parse(contents),
+            #[cfg(feature = "plist")]
+            Contents::Document(document) => self.decode(self.resolve(document)?),
+        })
+    }
+
+    /// Deserialize the raw content of the configuration file.
+    pub(crate) fn parse<T: DeserializeOwned>(&self, contents: &str) -> Result<T, ConrigError> {
+        let option = &self.config_option;
+        if option.deprecations.is_empty()
+            && !option.strict_keys
+            && option.include_key.is_none()
+            && option.extends_key.is_none()
+            && option.profile_env.is_none()
+            && !option.drop_ins
+            && option.variant_key.is_none()
+            && option.script.is_none()
+        {
+            return crate::lazy::reading(self, || {
+                self.file_format.parse_str(contents, Some(&self.path))
+            });
+        }
+        self.decode(self.resolve(self.file_format.parse_str(contents, Some(&self.path))?)?)
+    }
+
+    /// Get a value of the configuration file by its dotted key path, e.g. `server.port`.
+    ///
+    /// This operates on the untyped [`Value`] tree, so tools can inspect configurations
+    /// without knowing their structure. Returns `None` if the key doesn't exist.
+    ///
+    /// See [`Value::get_path`] for more information.
+    pub fn get_value(&self, key: &str) -> Result<Option<Value>, ConrigError> {
+        let document: Value = self.read_document().context_path(Operation::Read, &self.path)?;
+        Ok(document.get_path(key).cloned())
+    }
+
+    /// Set a value of the configuration file by its dotted key path, e.g. `server.port`.
+    ///
+    /// This operates on the untyped [`Value`] tree, so tools can tweak configurations
+    /// without knowing their structure. The configuration file is created if it doesn't exist.
+    ///
+    /// See [`Value::set_path`] for more information.
+    pub fn set_value<T: Serialize>(&self, key: &str, value: T) -> Result<(), ConrigError> {
+        let mut document = if filesystem::exists(&self.path) {
+            // the included files are kept apart.
+            self.read_contents()
+                .and_then(|contents| self.file_format.parse_str(&contents, Some(&self.path)))
+                .context_path(Operation::Read, &self.path)?
+        } else {
+            Value::Table(Default::default())
+        };
+        to_value(&value)
+            .and_then(|value| document.set_path(key, value))
+            .context_path(Operation::Write, &self.path)?;
+        self.write(&document)
+    }
+
+    /// Read the configuration file as an untyped document,
+    /// merging the included files and the profile file.
+    pub(crate) fn read_document(&self) -> Result<Value, ConrigError> {
+        self.with_document(|document| self.resolve(document))
+    }
+
+    /// Merge the included files beneath a document of this file,
+    /// then its drop-in fragments and the profile file over it.
+    pub(crate) fn resolve(&self, document: Value) -> Result<Value, ConrigError> {
+        let mut document = self.include(document)?;
+        for fragment in self.drop_in_files() {
+            let overlay = fragment
+                .read_contents()
+                .and_then(|contents| fragment.file_format.parse_str(&contents, Some(&fragment.path)))
+                .and_then(|overlay| fragment.include(overlay))
+                .context_path(Operation::Read, &fragment.path)?;
+            document.merge_keyed(overlay, self.config_option.merge_keys);
+        }
+        if let Some(profile) = self.profile_file() {
+            let overlay = profile
+                .read_contents()
+                .and_then(|contents| profile.file_format.parse_str(&contents, Some(&profile.path)))
+                .and_then(|overlay| profile.include(overlay))
+                .context_path(Operation::Read, &profile.path)?;
+            document.merge_keyed(overlay, self.config_option.merge_keys);
+        }
+        if let Some(key) = self.config_option.variant_key {
+            self.select_variant(&mut document, key)?;
+        }
+        Ok(document)
+    }
+
+    /// Drop the variants table at `key`, merging the selected variant over the document.
+    ///
+    /// See [`ConfigOption.variant_key`] for more information.
+    ///
+    /// [`ConfigOption.variant_key`]: crate::ConfigOption#structfield.variant_key
+    fn select_variant(&self, document: &mut Value, key: &str) -> Result<(), ConrigError> {
+        let variants = match document {
+            Value::Table(table) => table.shift_remove(key),
+            _ => None,
+        };
+        let Some(name) = self.config_option.variant() else {
+            return Ok(());
+        };
+        let variant = match variants {
+            Some(Value::Table(mut variants)) => variants.shift_remove(&name),
+            _ => None,
+        };
+        let variant = variant.ok_or(ConrigError::UnknownVariant(name))?;
+        document.merge_keyed(variant, self.config_option.merge_keys);
+        Ok(())
+    }
+
+    /// The drop-in fragments of this file, e.g. `conrig.toml.d/10-network.toml`,
+    /// in lexical order.
+    ///
+    /// Only the files with a known extension are returned, so backups and editor swap files
+    /// are skipped.
+    /// See [`ConfigOption.drop_ins`] for more information.
+    ///
+    /// [`ConfigOption.drop_ins`]: crate::ConfigOption#structfield.drop_ins
+    pub fn drop_in_files(&self) -> Vec<ConfigFile> {
+        if !self.config_option.drop_ins {
+            return Vec::new();
+        }
+        let mut dir_name = self.path.as_os_str().to_os_string();
+        dir_name.push(".d");
+        let Ok(mut paths) = filesystem::read_dir(Path::new(&dir_name)) else {
+            return Vec::new();
+        };
+        paths.sort();
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                path.extension()?;
+                let file_format = self.config_option.format_of(&path)?;
+                Some(ConfigFile::new(file_format, path).with_config_option(self.config_option))
+            })
+            .collect()
+    }
+
+    /// The profile file of this file, e.g. `conrig.production.toml`, if it exists.
+    ///
+    /// See [`ConfigOption.profile_env`] for more information.
+    ///
+    /// [`ConfigOption.profile_env`]: crate::ConfigOption#structfield.profile_env
+    pub fn profile_file(&self) -> Option<ConfigFile> {
+        let profile = self.config_option.profile()?;
+        let mut file_name = self.path.file_stem()?.to_os_string();
+        file_name.push(".");
+        file_name.push(profile);
+        let (path, file_format) = detect_mapped(
+            &self.path.with_file_name(file_name),
+            self.file_format,
+            self.config_option.extensions,
+        )?;
+        Some(ConfigFile::new(file_format, path).with_config_option(self.config_option))
+    }
+
+    /// Whether a document of this file is merged with other documents when it's read,
+    /// i.e. it includes other files or presets, has drop-in fragments, a profile file,
+    /// or a variants table.
+    ///
+    /// Such a document can't be written back as is.
+    pub(crate) fn is_merged(&self, document: &Value) -> bool {
+        let option = &self.config_option;
+        let has_key = |key: Option<&str>| match (key, document) {
+            (Some(key), Value::Table(table)) => table.contains_key(key),
+            _ => false,
+        };
+        has_key(option.include_key)
+            || has_key(option.extends_key)
+            || has_key(option.variant_key)
+            || !self.drop_in_files().is_empty()
+            || self.profile_file().is_some()
+    }
+
+    /// Merge the files included by a document of this file, according to [`include_key`].
+    ///
+    /// [`include_key`]: crate::ConfigOption#structfield.include_key
+    pub(crate) fn include(&self, mut document: Value) -> Result<Value, ConrigError> {
+        if let Some(key) = self.config_option.extends_key {
+            let presets = self.config_option.presets;
+            document = self.include_at(key, presets, document, &mut vec![canonical(&self.path)])?;
+        }
+        match self.config_option.include_key {
+            Some(key) => self.include_at(key, &[], document, &mut vec![canonical(&self.path)]),
+            None => Ok(document),
+        }
+    }
+
+    /// Merge the documents referenced at `key`, either paths relative to this file,
+    /// or names of `presets`, beneath `document`.
+    fn include_at(
+        &self,
+        key: &str,
+        presets: &[(&str, Embedded)],
+        document: Value,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<Value, ConrigError> {
+        let Value::Table(mut table) = document else {
+            return Ok(document);
+        };
+        let includes = match table.shift_remove(key) {
+            None => return Ok(Value::Table(table)),
+            Some(Value::String(include)) => vec![Value::String(include)],
+            Some(Value::Array(includes)) => includes,
+            Some(_) => return Err(invalid_include(key).into()),
+        };
+
+        let mut merged = Value::Table(Default::default());
+        for include in includes {
+            let Value::String(include) = include else {
+                return Err(invalid_include(key).into());
+            };
+            if let Some(document) = crate::preset::find(presets, &include)? {
+                // presets have no path, so they're tracked by name for the cycle detection.
+                let id = PathBuf::from(format!("<preset {include}>"));
+                if stack.contains(&id) {
+                    return Err(ConrigError::IncludeCycle(id));
+                }
+                stack.push(id);
+                let document = self.include_at(key, presets, document, stack)?;
+                stack.pop();
+                merged.merge_keyed(document, self.config_option.merge_keys);
+                continue;
+            }
+            let path = self
+                .path
+                .parent()
+                .map_or_else(|| PathBuf::from(&include), |dir| dir.join(&include));
+            let canonical = canonical(&path);
+            if stack.contains(&canonical) {
+                return Err(ConrigError::IncludeCycle(path));
+            }
+            let file_format = self.config_option.format_of(&path).unwrap_or(self.file_format);
+            let file =
+                ConfigFile::new(file_format, path.clone()).with_config_option(self.config_option);
+            stack.push(canonical);
+            let document = file
+                .read_contents()
+                .and_then(|contents| file_format.parse_str(&contents, Some(&path)))
+                .and_then(|document| file.include_at(key, presets, document, stack))
+                .context_path(Operation::Read, &path)?;
+            stack.pop();
+            merged.merge_keyed(document, self.config_option.merge_keys);
+        }
+        merged.merge_keyed(Value::Table(table), self.config_option.merge_keys);
+        Ok(merged)
+    }
+
+    /// Read the raw content of the configuration file.
+    ///
+    /// Binary property lists are converted into text.
+    pub(crate) fn read_contents(&self) -> Result<String, ConrigError> {
+        let bytes = self.read_bytes()?;
+        #[cfg(feature = "plist")]
+        if let Some(document) = self.parse_binary(&bytes)? {
+            return Ok(crate::plist::to_string(&document));
+        }
+        self.text(bytes)
+    }
+
+    /// Read the raw bytes of the configuration file, verifying their checksum.
+    fn read_bytes(&self) -> Result<Vec<u8>, ConrigError> {
         let path = &self.path;
-        let file = fs::File::open(path).map_err(FileSystemError::OpenConfig)?;
-        let mut buf_reader = BufReader::new(file);
-        let mut contents = String::new();
-        buf_reader
-            .read_to_string(&mut contents)
-            .map_err(FileSystemError::ReadConfig)?;
-        let result = self.file_format.read_str(&contents)?;
-        Ok(result)
+        if self.config_option.check_permissions && filesystem::custom().is_none() {
+            check_permissions(path)?;
+        }
+        self.config_option.timed(Operation::Read, path, || {
+            let bytes = filesystem::read(path)?;
+            self.verify_checksum(&bytes)?;
+            Ok(bytes)
+        })
+    }
+
+    /// Check that the raw bytes of the configuration file are text.
+    fn text(&self, bytes: Vec<u8>) -> Result<String, ConrigError> {
+        String::from_utf8(bytes).map_err(|err| {
+            let err = std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+            FileSystemError::read_config(&self.path)(err).into()
+        })
+    }
+
+    /// Read the raw content of the configuration file, and process it.
+    ///
+    /// Binary property lists are converted into text first.
+    /// See [`with_raw`][Self::with_raw] for more information.
+    pub(crate) fn with_contents<R>(
+        &self,
+        f: impl FnOnce(&str) -> Result<R, ConrigError>,
+    ) -> Result<R, ConrigError> {
+        self.with_raw(|contents| match contents {
+            Contents::Text(contents) => f(contents),
+            #[cfg(feature = "plist")]
+            Contents::Document(document) => f(&crate::plist::to_string(&document)),
+        })
+    }
+
+    /// Read and parse the configuration file, without merging the other files, and process it.
+    ///
+    /// See [`with_raw`][Self::with_raw] for more information.
+    pub(crate) fn with_document<R>(
+        &self,
+        f: impl FnOnce(Value) -> Result<R, ConrigError>,
+    ) -> Result<R, ConrigError> {
+        self.with_raw(|contents| match contents {
+            Contents::Text(contents) => {
+                f(self.file_format.parse_str(contents, Some(&self.path))?)
+            }
+            #[cfg(feature = "plist")]
+            Contents::Document(document) => f(document),
+        })
+    }
+
+    /// Read the raw content of the configuration file, and process it.
+    ///
+    /// Files larger than the [`mmap_threshold`] are processed in place, without being copied:
+    /// text files are borrowed from the mapping, and binary property lists are parsed from it.
+    ///
+    /// [`mmap_threshold`]: crate::ConfigOption#structfield.mmap_threshold
+    fn with_raw<R>(
+        &self,
+        f: impl FnOnce(Contents<'_>) -> Result<R, ConrigError>,
+    ) -> Result<R, ConrigError> {
+        #[cfg(feature = "mmap")]
+        if let Some(threshold) = self.config_option.mmap_threshold {
+            let path = &self.path;
+            if filesystem::custom().is_none() {
+                if self.config_option.check_permissions {
+                    check_permissions(path)?;
+                }
+                let map = self.config_option.timed(Operation::Read, path, || {
+                    crate::mmap::Mmap::open(path, threshold)
+                        .map_err(FileSystemError::read_config(path))
+                })?;
+                if let Some(map) = map {
+                    self.verify_checksum(&map)?;
+                    #[cfg(feature = "plist")]
+                    if let Some(document) = self.parse_binary(&map)? {
+                        return f(Contents::Document(document));
+                    }
+                    let contents = std::str::from_utf8(&map).map_err(|err| {
+                        let err = std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+                        FileSystemError::read_config(path)(err)
+                    })?;
+                    return f(Contents::Text(contents));
+                }
+            }
+        }
+        let bytes = self.read_bytes()?;
+        #[cfg(feature = "plist")]
+        if let Some(document) = self.parse_binary(&bytes)? {
+            return f(Contents::Document(document));
+        }
+        f(Contents::Text(&self.text(bytes)?))
+    }
+
+    /// Verify the checksum of the raw content, if enabled.
+    fn verify_checksum(&self, bytes: &[u8]) -> Result<(), ConrigError> {
+        if self.config_option.checksum {
+            crate::checksum::verify(&self.path, bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Parse the raw content if it's a binary property list.
+    #[cfg(feature = "plist")]
+    fn parse_binary(&self, bytes: &[u8]) -> Result<Option<Value>, ConrigError> {
+        if self.file_format != FileFormat::Plist || !crate::plist::is_binary(bytes) {
+            return Ok(None);
+        }
+        crate::plist::from_binary(bytes).map(Some).map_err(|err| {
+            ConrigError::parse(err.into(), FileFormat::Plist, Some(&self.path), "", None)
+        })
+    }
+
+    /// Deserialize a parsed document, enforcing the deprecations and the strict keys.
+    pub(crate) fn decode<T: DeserializeOwned>(&self, document: Value) -> Result<T, ConrigError> {
+        crate::lazy::reading(self, || self.decode_inner(document))
+    }
+
+    fn decode_inner<T: DeserializeOwned>(&self, document: Value) -> Result<T, ConrigError> {
+        let document = crate::script::run(self, document)?;
+        check_deprecations(&document, &self.config_option)?;
+        match from_value_tracked(document, self.config_option.strict_keys) {
+            (Ok(value), unknown) if unknown.is_empty() => Ok(value),
+            // unknown keys are reported first, as they're most likely misspelled ones
+            // which also cause the deserialization to fail.
+            (_, unknown) if !unknown.is_empty() => Err(ConrigError::UnknownKeys(unknown)),
+            (result, _) => result.map_err(|(err, key)| {
+                ConrigError::decode(err, self.file_format, &self.path, Some(key))
+            }),
+        }
     }
 
     /// Serialize and write a value into the configuration file.
@@ -502,17 +1174,115 @@ impl ConfigFile {
     /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
     ///
     /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    ///
+    /// If [`atomic_write`] is enabled, the value is written into a temporary file first,
+    /// which then replaces the configuration file. So the configuration file
+    /// won't be left truncated if the application crashes during the writing.
+    ///
+    /// [`atomic_write`]: crate::ConfigOption#structfield.atomic_write
+    ///
+    /// If [`backup`] is not zero, the existing configuration file is backed up before being replaced.
+    ///
+    /// [`backup`]: crate::ConfigOption#structfield.backup
+    ///
+    /// If [`checksum`] is enabled, the checksum of the written file is recorded as well.
+    ///
+    /// [`checksum`]: crate::ConfigOption#structfield.checksum
+    ///
+    /// If [`keep_comments`] is enabled, the comments of the replaced file are kept.
+    ///
+    /// [`keep_comments`]: crate::ConfigOption#structfield.keep_comments
     pub fn write<T: Serialize>(&self, value: &T) -> Result<(), ConrigError> {
+        if self.config_option.keep_comments {
+            return self.write_commented(value, &self.comments()?);
+        }
+        self.write_with(value, |mut writer| self.file_format.write(value, &mut writer))
+    }
+
+    /// Write the content produced by `emit` into the configuration file,
+    /// with the same guarantees as [`write`].
+    ///
+    /// `value` is the configuration being written, to be checked against the policy.
+    ///
+    /// [`write`]: crate::parser::ConfigFile::write
+    pub(crate) fn write_with<T: Serialize>(
+        &self,
+        value: &T,
+        emit: impl FnOnce(&mut dyn Write) -> Result<(), ConrigError>,
+    ) -> Result<(), ConrigError> {
+        let header = self.version_header()?;
+        let emit = |writer: &mut dyn Write| {
+            if let Some(header) = header {
+                writer
+                    .write_all(header.as_bytes())
+                    .map_err(FileSystemError::write_config(&self.path))?;
+            }
+            emit(writer)
+        };
+        self.config_option
+            .timed(Operation::Write, &self.path, || self.write_inner(value, emit))
+            .context_path(Operation::Write, &self.path)?;
+        if self.config_option.checksum {
+            crate::checksum::seal(&self.path).context_path(Operation::Write, &self.path)?;
+        }
+        if let Some(fingerprint) = Fingerprint::of_file(&self.path) {
+            crate::watch::remember(&self.path, fingerprint);
+        }
+        Ok(())
+    }
+
+    fn write_inner<T: Serialize>(
+        &self,
+        value: &T,
+        emit: impl FnOnce(&mut dyn Write) -> Result<(), ConrigError>,
+    ) -> Result<(), ConrigError> {
         let path = &self.path;
-        fs::create_dir_all(path.parent().ok_or(FileSystemError::NoProjectDirectory)?)
-            .map_err(FileSystemError::WriteConfig)?;
+        if self.read_only {
+            return Err(ConrigError::ReadOnly(path.clone()));
+        }
+        if let Some(policy) = &self.policy {
+            crate::policy::check(policy, &to_value(value)?)?;
+        }
+        filesystem::create_dir_all(path.parent().ok_or(FileSystemError::NoProjectDirectory)?)?;
+        if let Some(file_system) = filesystem::custom() {
+            let mut contents = Vec::new();
+            emit(&mut contents)?;
+            return Ok(file_system
+                .write(path, &contents)
+                .map_err(FileSystemError::write_config(path))?);
+        }
+        if self.config_option.atomic_write {
+            return write_atomic(path, &self.config_option, |file| emit(file));
+        }
+        rotate_backups(path, self.config_option.backup)?;
         let mut file = fs::File::options()
             .write(true)
             .truncate(true)
             .create(true)
             .open(path)
-            .map_err(FileSystemError::OpenConfig)?;
-        self.file_format.write(value, &mut file)
+            .map_err(FileSystemError::open_config(path))?;
+        emit(&mut file)?;
+        if self.config_option.sync_write {
+            file.sync_all().map_err(FileSystemError::write_config(path))?;
+        }
+        Ok(())
+    }
+
+    /// Serialize and write a value into the configuration file,
+    /// omitting every field equal to its counterpart in `T::default()`.
+    ///
+    /// Tables are compared key by key, so only the modified leaves are written.
+    /// This keeps the file small and readable, and lets future changes of the
+    /// default value take effect for the keys the user never touched.
+    ///
+    /// **Note**: The omitted fields must be filled back when reading the file,
+    /// typically by marking your structure with `#[serde(default)]`.
+    pub fn write_minimal<T: Serialize + Default>(&self, value: &T) -> Result<(), ConrigError> {
+        let minimal = to_value(value)
+            .and_then(|value| Ok(value.difference(&to_value(&T::default())?)))
+            .context_path(Operation::Write, &self.path)?
+            .unwrap_or_else(|| Value::Table(Default::default()));
+        self.write(&minimal)
     }
 
     /// Read and deserialize the configuration file.
@@ -522,19 +1292,125 @@ impl ConfigFile {
     /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
     ///
     /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    ///
+    /// If [`repair_missing`] is enabled, the keys missing in the configuration file are filled
+    /// with the default value, and the repaired file is written back.
+    /// If [`lossy_read`] is enabled, the invalid parts of the configuration file are
+    /// replaced by the default value instead of failing.
+    ///
+    /// [`repair_missing`]: crate::ConfigOption#structfield.repair_missing
+    /// [`lossy_read`]: crate::ConfigOption#structfield.lossy_read
     pub fn read_or_new<T: Serialize + DeserializeOwned>(
         &self,
         default: T,
+    ) -> Result<T, ConrigError> {
+        self.read_or_try_else(|| Ok(default))
+    }
+
+    /// Read and deserialize the configuration file.
+    /// If the configuration file doesn't exist, a new configuration file will be created,
+    /// and it will be filled with the value returned by `default`.
+    ///
+    /// Different from [`read_or_new`], `default` is only called when the default value is needed.
+    /// This suits the types which can't implement [`Default`], e.g. the ones with mandatory fields.
+    ///
+    /// [`read_or_new`]: crate::parser::ConfigFile::read_or_new
+    pub fn read_or_else<T: Serialize + DeserializeOwned>(
+        &self,
+        default: impl FnOnce() -> T,
+    ) -> Result<T, ConrigError> {
+        self.read_or_try_else(|| Ok(default()))
+    }
+
+    /// Same as [`read_or_new`], but the default value is only created when it's needed.
+    ///
+    /// [`read_or_new`]: crate::parser::ConfigFile::read_or_new
+    pub(crate) fn read_or_try_else<T: Serialize + DeserializeOwned>(
+        &self,
+        default: impl FnOnce() -> Result<T, ConrigError>,
+    ) -> Result<T, ConrigError> {
+        self.read_or_try_else_inner(default).context_path(Operation::Read, &self.path)
+    }
+
+    fn read_or_try_else_inner<T: Serialize + DeserializeOwned>(
+        &self,
+        default: impl FnOnce() -> Result<T, ConrigError>,
     ) -> Result<T, ConrigError> {
         let path = &self.path;
-        if path.exists() {
-            self.read()
-        } else {
-            fs::create_dir_all(path.parent().ok_or(FileSystemError::NoProjectDirectory)?)
-                .map_err(FileSystemError::WriteConfig)?;
+        if !filesystem::exists(path) {
+            let default = default()?;
             self.write(&default)?;
-            Ok(default)
+            return Ok(default);
+        }
+        let option = &self.config_option;
+        if !option.repair_missing && !option.lossy_read {
+            return self.read();
         }
+
+        let contents = self.read_contents()?;
+        let document: Value = match self.file_format.parse_str(&contents, Some(&self.path)) {
+            Ok(document) => document,
+            Err(_) if option.lossy_read => return default(),
+            Err(err) => return Err(err),
+        };
+        let merged = self.is_merged(&document);
+        let mut document = self.resolve(document)?;
+        let default = to_value(&default()?)?;
+        let repaired = option.repair_missing && document.fill_missing(&default);
+        match self.decode(document.clone()) {
+            Ok(value) => {
+                if repaired && !merged && !self.read_only {
+                    self.write(&document)?;
+                }
+                Ok(value)
+            }
+            Err(err) if option.lossy_read && matches!(err.root(), ConrigError::ValueError(_)) => {
+                let mut salvaged = default;
+                salvage::<T>(&mut salvaged, document);
+                Ok(from_value(salvaged)?)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Read and deserialize the configuration file, then write the whole value back
+    /// if the file doesn't contain it already.
+    ///
+    /// The fields missing in the file, typically new options marked with `#[serde(default)]`,
+    /// are written back with their default value, so users can discover them.
+    /// The file is left untouched if it's [read-only] or nothing is missing,
+    /// and if it's merged with other documents, e.g. included files, drop-in fragments,
+    /// a profile file or variants, since the merged document can't be written back as is.
+    ///
+    /// [read-only]: crate::parser::ConfigFile#structfield.read_only
+    pub fn read_and_upgrade<T: Serialize + DeserializeOwned>(&self) -> Result<T, ConrigError> {
+        let (value, upgraded) = self
+            .read_and_upgrade_inner()
+            .context_path(Operation::Read, &self.path)?;
+        if let Some(upgraded) = upgraded {
+            self.write(&upgraded)?;
+        }
+        Ok(value)
+    }
+
+    fn read_and_upgrade_inner<T: Serialize + DeserializeOwned>(
+        &self,
+    ) -> Result<(T, Option<Value>), ConrigError> {
+        self.with_document(|mut document| {
+            let merged = self.is_merged(&document);
+            let value: T = self.decode(self.resolve(document.clone())?)?;
+            if self.read_only || merged {
+                return Ok((value, None));
+            }
+            // `None` fields are serialized as nulls, which the file never holds.
+            let mut upgraded = to_value(&value)?;
+            drop_nulls(&mut upgraded);
+            drop_nulls(&mut document);
+            if upgraded == document {
+                return Ok((value, None));
+            }
+            Ok((value, Some(upgraded)))
+        })
     }
 
     /// Read and deserialize the configuration file.
@@ -553,3 +1429,174 @@ impl ConfigFile {
         self.read_or_new(T::default())
     }
 }
+
+/// The raw content of a configuration file.
+enum Contents<'a> {
+    /// The text of the file.
+    Text(&'a str),
+    /// The document of a binary property list, which isn't text.
+    #[cfg(feature = "plist")]
+    Document(Value),
+}
+
+/// The canonical form of a path, used to compare included files.
+fn canonical(path: &Path) -> PathBuf {
+    filesystem::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Remove the nulls of every table, recursively.
+fn drop_nulls(value: &mut Value) {
+    match value {
+        Value::Table(table) => {
+            table.retain(|_, value| *value != Value::Null);
+            table.values_mut().for_each(drop_nulls);
+        }
+        Value::Array(array) => array.iter_mut().for_each(drop_nulls),
+        _ => {}
+    }
+}
+
+fn invalid_include(key: &str) -> ValueError {
+    serde::de::Error::custom(format!("`{key}` must be a path or a list of paths"))
+}
+
+/// Copy every leaf of `document` into `salvaged`, as long as `salvaged` stays deserializable.
+fn salvage<T: DeserializeOwned>(salvaged: &mut Value, document: Value) {
+    salvage_at::<T>(salvaged, &mut Vec::new(), document);
+}
+
+fn salvage_at<T: DeserializeOwned>(root: &mut Value, path: &mut Vec<String>, document: Value) {
+    let Value::Table(document) = document else {
+        return;
+    };
+    for (key, value) in document {
+        let Some(Value::Table(table)) = lookup(root, path) else {
+            return;
+        };
+        if let (Some(Value::Table(_)), Value::Table(_)) = (table.get(&key), &value) {
+            path.push(key);
+            salvage_at::<T>(root, path, value);
+            path.pop();
+            continue;
+        }
+        let previous = table.insert(key.clone(), value);
+        if from_value::<T>(root.clone()).is_ok() {
+            continue;
+        }
+        if let Some(Value::Table(table)) = lookup(root, path) {
+            match previous {
+                Some(previous) => table.insert(key, previous),
+                None => table.shift_remove(&key),
+            };
+        }
+    }
+}
+
+fn lookup<'v>(value: &'v mut Value, path: &[String]) -> Option<&'v mut Value> {
+    path.iter().try_fold(value, |value, key| match value {
+        Value::Table(table) => table.get_mut(key),
+        _ => None,
+    })
+}
+
+/// The number of temporary files created by this process, to give each of them a unique name.
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Write a file by writing a temporary file in the same directory,
+/// and then renaming it over the target.
+pub(crate) fn write_atomic(
+    path: &Path,
+    config_option: &ConfigOption,
+    write: impl FnOnce(&mut fs::File) -> Result<(), ConrigError>,
+) -> Result<(), ConrigError> {
+    let sync = config_option.sync_write;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(path.file_name().ok_or(FileSystemError::NoProjectDirectory)?);
+    let id = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+    temp_name.push(format!(".{}.{id}.tmp", process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    // a temporary file which couldn't be created belongs to another writer, and is left alone.
+    let mut file = fs::File::options()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .map_err(FileSystemError::open_config(&temp_path))?;
+    let result = (|| {
+        write(&mut file)?;
+        file.flush().map_err(FileSystemError::write_config(&temp_path))?;
+        if sync {
+            file.sync_all().map_err(FileSystemError::write_config(&temp_path))?;
+        }
+        if let Ok(metadata) = fs::metadata(path) {
+            // keep the permissions of the file being replaced.
+            file.set_permissions(metadata.permissions())
+                .map_err(FileSystemError::write_config(&temp_path))?;
+        }
+        drop(file);
+        rotate_backups(path, config_option.backup)?;
+        fs::rename(&temp_path, path).map_err(FileSystemError::write_config(path))?;
+        #[cfg(unix)]
+        if sync {
+            // flush the directory entry of the renamed file as well.
+            if let Some(parent) = path.parent() {
+                fs::File::open(parent)
+                    .and_then(|dir| dir.sync_all())
+                    .map_err(FileSystemError::write_config(parent))?;
+            }
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Copy a file into `<file>.bak`, and rotate the older backups.
+///
+/// At most `count` backups are kept.
+fn rotate_backups(path: &Path, count: usize) -> Result<(), ConrigError> {
+    if count == 0 || !filesystem::exists(path) {
+        return Ok(());
+    }
+    let file_name = path.file_name().ok_or(FileSystemError::NoProjectDirectory)?;
+    let backup = |index: usize| {
+        let mut backup_name = file_name.to_os_string();
+        backup_name.push(".bak");
+        if index > 0 {
+            backup_name.push(format!(".{index}"));
+        }
+        path.with_file_name(backup_name)
+    };
+
+    for index in (1..count).rev() {
+        let older = backup(index - 1);
+        if filesystem::exists(&older) {
+            filesystem::rename(&older, &backup(index))?;
+        }
+    }
+    filesystem::copy(path, &backup(0))?;
+    Ok(())
+}
+
+/// Check that a file is not writable by other users.
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<(), ConrigError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)
+        .map_err(FileSystemError::open_config(path))?
+        .permissions()
+        .mode();
+    if mode & 0o022 != 0 {
+        return Err(ConrigError::InsecurePermissions(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// Check that a file is not writable by other users.
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Result<(), ConrigError> {
+    Ok(())
+}
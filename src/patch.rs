@@ -0,0 +1,190 @@
+//! Partial configurations, used to express overlays as patches.
+//!
+//! A [`Partial<T>`] is a configuration of type `T` where every field is optional:
+//! only the keys it sets are changed when it's [applied][apply] over a complete configuration.
+//! Overlay files, environment variables and command-line flags can all be expressed as patches,
+//! then applied in order over the base configuration.
+//!
+//! Patches are deserializable from any [`FileFormat`][crate::FileFormat],
+//! so an overlay file is read with [`ConfigFile::read`][crate::parser::ConfigFile::read]
+//! like any other configuration.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::patch::{apply, Partial};
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     port: u16,
+//! }
+//!
+//! let base = Config { name: "conrig".to_owned(), port: 80 };
+//! let mut patch = Partial::<Config>::new();
+//! patch.set("port", 8080)?;
+//!
+//! let config = apply(base, patch)?;
+//! assert_eq!(config.name, "conrig");
+//! assert_eq!(config.port, 8080);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::value::{from_value, to_value, Table, Value, ValueError};
+use crate::ConrigError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A configuration of type `T` where every field is optional.
+///
+/// Nested tables are partial as well, while any other value, e.g. an array,
+/// replaces its counterpart as a whole.
+pub struct Partial<T> {
+    table: Table,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Partial<T> {
+    /// Create an empty patch, changing nothing.
+    pub fn new() -> Self {
+        Self {
+            table: Table::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a patch from a [`Value`].
+    ///
+    /// `Null` is an empty patch, while any value other than a table is rejected.
+    pub fn from_value(value: Value) -> Result<Self, ValueError> {
+        match value {
+            Value::Null => Ok(Self::new()),
+            Value::Table(table) => Ok(Self {
+                table,
+                _marker: PhantomData,
+            }),
+            _ => Err(serde::de::Error::custom("a patch must be a table")),
+        }
+    }
+
+    /// Convert the patch into a [`Value`] table.
+    pub fn into_value(self) -> Value {
+        Value::Table(self.table)
+    }
+
+    /// Check if the patch changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Get a value set by the patch, by its dotted key path.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let (key, rest) = match path.split_once('.') {
+            Some((key, rest)) => (key, Some(rest)),
+            None => (path, None),
+        };
+        let value = self.table.get(key)?;
+        match rest {
+            Some(rest) => value.get_path(rest),
+            None => Some(value),
+        }
+    }
+
+    /// Set a value by its dotted key path, e.g. `server.port`.
+    pub fn set<V: Serialize>(&mut self, path: &str, value: V) -> Result<(), ValueError> {
+        let mut this = Value::Table(std::mem::take(&mut self.table));
+        let result = to_value(&value).and_then(|value| this.set_path(path, value));
+        if let Value::Table(table) = this {
+            self.table = table;
+        }
+        result
+    }
+
+    /// Merge another patch over this one. The keys set by `other` take precedence.
+    pub fn merge(&mut self, other: Partial<T>) {
+        for (key, patch) in other.table {
+            match self.table.get_mut(&key) {
+                Some(value) => value.merge(patch),
+                None => {
+                    self.table.insert(key, patch);
+                }
+            }
+        }
+    }
+}
+
+impl<T: Serialize> Partial<T> {
+    /// Create a patch setting every field of a complete configuration.
+    pub fn from_config(config: &T) -> Result<Self, ValueError> {
+        Self::from_value(to_value(config)?)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Partial<T> {
+    /// Apply the patch over a complete configuration.
+    ///
+    /// See [`apply`].
+    pub fn apply_to(self, base: T) -> Result<T, ConrigError> {
+        apply(base, self)
+    }
+}
+
+/// Apply a patch over a complete configuration.
+///
+/// The keys set by the patch replace their counterparts in `base`, recursively,
+/// and the result is checked against `T` again.
+pub fn apply<T: Serialize + DeserializeOwned>(
+    base: T,
+    patch: Partial<T>,
+) -> Result<T, ConrigError> {
+    if patch.is_empty() {
+        return Ok(base);
+    }
+    let mut value = to_value(&base)?;
+    value.merge(patch.into_value());
+    Ok(from_value(value)?)
+}
+
+impl<T> Default for Partial<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for Partial<T> {
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Partial<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Partial").field(&self.table).finish()
+    }
+}
+
+impl<T> PartialEq for Partial<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.table == other.table
+    }
+}
+
+impl<T> Serialize for Partial<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(&self.table)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Partial<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::from_value(Value::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
@@ -1,13 +1,27 @@
 //! Path finder and metadata configuration.
 
-use crate::parser::{FileFormat, RawConfigFile};
-use crate::{detect_file_format, ConrigError, FileSystemError};
-use directories::ProjectDirs;
+use crate::deprecation::Deprecation;
+use crate::error::{ConrigResultExt, ConrigWarning, FileSystemError, Operation, SearchedPath};
+use crate::import::Importer;
+#[cfg(feature = "mmap")]
+use crate::mmap::MmapThreshold;
+use crate::parser::{
+    candidates, detect_all_mapped, detect_mapped, ConfigFile, FileFormat, RawConfigSource,
+};
+use crate::platform::PlatformPaths;
+use crate::provider::{DefaultProvider, Embedded};
+use crate::redact;
+use crate::upgrade::Addition;
+use crate::value::Value;
+use crate::{filesystem, ConrigError};
+use directories::{BaseDirs, ProjectDirs};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::env::current_dir;
+use std::fmt;
 use std::marker::PhantomData;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 /// The main entry point of `conrig`.
 ///
@@ -27,6 +41,10 @@ pub struct ConfigPathMetadata<'p, T> {
     /// The default language of your configuration files.
     pub default_format: FileFormat,
     /// Extra folders to find & save your config files.
+    ///
+    /// A leading `~` is expanded to the home directory. With the `glob` feature,
+    /// glob patterns like `~/.config/*/conrig` are expanded too, and every match is considered,
+    /// in lexicographic order.
     pub extra_folders: &'p [&'p str],
     /// Extra config file paths.
     ///
@@ -41,20 +59,68 @@ pub struct ConfigPathMetadata<'p, T> {
     /// | `config_name` | `&["conrig", "conrig-cfg"]` | `"/your/project/dir/conrig-cfg.toml"` |
     /// | `extra_files` | `&["/external/file/centre/cfg"]` | `"/external/file/centre/cfg.toml"` |
     ///
+    /// A leading `~` is expanded to the home directory. With the `glob` feature,
+    /// glob patterns like `~/.config/myapp/*.toml` are expanded too, and every matching file is
+    /// considered, in lexicographic order. The format of a match is taken from its extension.
+    ///
     /// [`config_name`]: crate::ConfigPathMetadata#structfield.config_name
     pub extra_files: &'p [&'p str],
     /// Extra configuration options.
     pub config_option: ConfigOption,
+    /// Importers used to seed a new configuration from foreign files.
+    ///
+    /// See [`ConfigPathMetadata::import`] for more information.
+    pub importers: &'p [Importer<'p, T>],
+    /// The provider of the default value, used by the `read_or_default` methods.
+    ///
+    /// If this is `None`, `T::default()` is used.
+    /// See [`provider`][crate::provider] for more information.
+    pub default_provider: Option<&'p dyn DefaultProvider<T>>,
+    /// Other identities sharing their settings with your application, e.g. a suite of tools.
+    ///
+    /// Their configuration files are merged beneath yours by [`ConfigPathMetadata::read_merged`],
+    /// in increasing order of precedence.
+    pub fallback_identities: &'p [ProjectPath<'p>],
+    /// The metadata whose configuration files are merged beneath yours, e.g. a suite of tools.
+    ///
+    /// This establishes an inheritance chain used by [`ConfigPathMetadata::read_merged`]:
+    /// the parent's files, including its own parents', take the lowest precedence.
+    pub parent: Option<&'p dyn ConfigLayers>,
+    /// The URLs to fetch the configuration from, in order of preference.
+    ///
+    /// This requires the `http` feature. See [`remote`][crate::remote] for more information.
+    pub remote_urls: &'p [&'p str],
+    /// The computation of the platform directories, if it's overridden.
+    ///
+    /// If this is `None`, [`DirectoriesPaths`] is used.
+    /// See [`platform`][crate::platform] for more information.
+    ///
+    /// [`DirectoriesPaths`]: crate::platform::DirectoriesPaths
+    pub platform_paths: Option<&'p dyn PlatformPaths>,
+    /// The directory of the local configuration files, instead of the current directory.
+    ///
+    /// This anchors the [local location][local] to a known directory, e.g. the one of a
+    /// project opened by the application, so the search doesn't depend on the working
+    /// directory of the process.
+    ///
+    /// [local]: crate::SearchLocation::Local
+    pub local_dir: Option<&'p Path>,
     /// A marker for the type of the configuration.
     /// 
     /// This will be automatically filled if you use the [`conrig!`][macro] macro.
     /// 
     /// [macro]: crate::conrig
-    pub _marker: PhantomData<T>,
+    pub _marker: PhantomData<fn() -> T>,
 }
 
 impl<'p, T> ConfigPathMetadata<'p, T> {
     /// Create a new `ConfigPathMetadata`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `config_name` is empty. See [`try_new`] for a fallible version.
+    ///
+    /// [`try_new`]: crate::ConfigPathMetadata::try_new
     pub const fn new(
         project_path: ProjectPath<'p>,
         config_name: &'p [&'p str],
@@ -74,10 +140,64 @@ impl<'p, T> ConfigPathMetadata<'p, T> {
             config_option,
             extra_folders,
             extra_files,
+            importers: &[],
+            default_provider: None,
+            fallback_identities: &[],
+            parent: None,
+            remote_urls: &[],
+            platform_paths: None,
+            local_dir: None,
             _marker: PhantomData,
         }
     }
 
+    /// Create a new `ConfigPathMetadata`.
+    ///
+    /// If `config_name` is empty, an [`EmptyConfigName`] error will be returned.
+    ///
+    /// [`EmptyConfigName`]: crate::ConrigError::EmptyConfigName
+    pub const fn try_new(
+        project_path: ProjectPath<'p>,
+        config_name: &'p [&'p str],
+        default_format: FileFormat,
+        extra_folders: &'p [&'p str],
+        extra_files: &'p [&'p str],
+        config_option: ConfigOption,
+    ) -> Result<Self, ConrigError> {
+        if config_name.is_empty() {
+            return Err(ConrigError::EmptyConfigName);
+        }
+        Ok(Self::new(
+            project_path,
+            config_name,
+            default_format,
+            extra_folders,
+            extra_files,
+            config_option,
+        ))
+    }
+
+    /// The base value used by the [`conrig!`][macro] macro to fill the fields not specified.
+    ///
+    /// [macro]: crate::conrig
+    #[doc(hidden)]
+    pub const MACRO_BASE: Self = Self {
+        project_path: ProjectPath::new("", "", ""),
+        config_name: &[],
+        default_format: FileFormat::DEFAULT_FILE_FORMAT,
+        extra_folders: &[],
+        extra_files: &[],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        importers: &[],
+        default_provider: None,
+        fallback_identities: &[],
+        parent: None,
+        remote_urls: &[],
+        platform_paths: None,
+        local_dir: None,
+        _marker: PhantomData,
+    };
+
     /// Modify the [`project_path`] field.
     ///
     /// [`project_path`]: crate::ConfigPathMetadata#structfield.project_path
@@ -135,24 +255,119 @@ impl<'p, T> ConfigPathMetadata<'p, T> {
         self
     }
 
+    /// Modify the [`importers`] field.
+    ///
+    /// [`importers`]: crate::ConfigPathMetadata#structfield.importers
+    pub const fn with_importers(mut self, importers: &'p [Importer<'p, T>]) -> Self {
+        self.importers = importers;
+        self
+    }
+
+    /// Modify the [`default_provider`] field.
+    ///
+    /// [`default_provider`]: crate::ConfigPathMetadata#structfield.default_provider
+    pub const fn with_default_provider(
+        mut self,
+        default_provider: &'p dyn DefaultProvider<T>,
+    ) -> Self {
+        self.default_provider = Some(default_provider);
+        self
+    }
+
+    /// Modify the [`fallback_identities`] field.
+    ///
+    /// [`fallback_identities`]: crate::ConfigPathMetadata#structfield.fallback_identities
+    pub const fn with_fallback_identities(
+        mut self,
+        fallback_identities: &'p [ProjectPath<'p>],
+    ) -> Self {
+        self.fallback_identities = fallback_identities;
+        self
+    }
+
+    /// Modify the [`parent`] field.
+    ///
+    /// [`parent`]: crate::ConfigPathMetadata#structfield.parent
+    pub const fn with_parent(mut self, parent: &'p dyn ConfigLayers) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Modify the [`remote_urls`] field.
+    ///
+    /// [`remote_urls`]: crate::ConfigPathMetadata#structfield.remote_urls
+    pub const fn with_remote_urls(mut self, remote_urls: &'p [&'p str]) -> Self {
+        self.remote_urls = remote_urls;
+        self
+    }
+
+    /// Modify the [`platform_paths`] field.
+    ///
+    /// [`platform_paths`]: crate::ConfigPathMetadata#structfield.platform_paths
+    pub const fn with_platform_paths(mut self, platform_paths: &'p dyn PlatformPaths) -> Self {
+        self.platform_paths = Some(platform_paths);
+        self
+    }
+
+    /// Modify the [`local_dir`] field.
+    ///
+    /// [`local_dir`]: crate::ConfigPathMetadata#structfield.local_dir
+    pub const fn with_local_dir(mut self, local_dir: &'p Path) -> Self {
+        self.local_dir = Some(local_dir);
+        self
+    }
+
+    /// Get the default value from the [`default_provider`].
+    ///
+    /// If no provider is registered, a [`NoDefaultProvider`] error will be returned.
+    ///
+    /// [`default_provider`]: crate::ConfigPathMetadata#structfield.default_provider
+    /// [`NoDefaultProvider`]: crate::ConrigError::NoDefaultProvider
+    pub fn provided_value(&self) -> Result<T, ConrigError> {
+        self.default_provider
+            .ok_or(ConrigError::NoDefaultProvider)?
+            .provide()
+    }
+
     /// Format the default system-level configuration file.
+    ///
+    /// In the [home directory][home], this is a dotfile, e.g. `~/.conrig.toml`.
+    ///
+    /// [home]: crate::ConfigType::Home
     pub fn default_sys_config_file(&self) -> Result<PathBuf, ConrigError> {
+        let name = self.default_config_name()?;
+        let name = if self.config_option.config_sys_type == ConfigType::Home {
+            format!(".{name}")
+        } else {
+            name.to_owned()
+        };
         Ok(self
-            .project_path
-            .sys_dir(self.config_option.config_sys_type)
+            .sys_dir(self.sys_type())
             .ok_or(FileSystemError::NoProjectDirectory)?
-            .join(self.config_name[0])
+            .join(name)
             .with_extension(self.default_format.extension()))
     }
 
-    /// Format the default configuration file in the current folder.
+    /// Format the default configuration file in the current folder,
+    /// or the [`local_dir`] if it's set.
+    ///
+    /// [`local_dir`]: crate::ConfigPathMetadata#structfield.local_dir
     pub fn default_local_config_file(&self) -> Result<PathBuf, ConrigError> {
-        Ok(current_dir()
-            .map_err(FileSystemError::OpenConfig)?
-            .join(self.config_name[0])
+        Ok(self
+            .local_root()
+            .map_err(current_dir_error)?
+            .join(self.default_config_name()?)
             .with_extension(self.default_format.extension()))
     }
 
+    /// The name used by the default configuration files.
+    pub(crate) fn default_config_name(&self) -> Result<&'p str, ConrigError> {
+        self.config_name
+            .first()
+            .copied()
+            .ok_or(ConrigError::EmptyConfigName)
+    }
+
     /// Format the default configuration file, depending on the [`ConfigOption.sys_override_local`].
     ///
     /// [`ConfigOption.sys_override_local`]: crate::ConfigOption#structfield.sys_override_local
@@ -170,227 +385,1986 @@ impl<'p, T> ConfigPathMetadata<'p, T> {
     /// - Your [system-level configuration directory][sys].
     /// - The current directory.
     ///
-    /// The sequence is determined by [`ConfigOption.sys_override_local`].
+    /// The sequence is determined by [`ConfigOption.search_order`], or
+    /// [`ConfigOption.sys_override_local`] if it isn't set.
+    /// The path held by the [`ConfigOption.path_env_var`] variable, if any, takes precedence,
+    /// while the [machine-wide directory][machine] comes last if [`ConfigOption.system_wide`]
+    /// is enabled.
+    ///
+    /// If the system-level directory is unavailable, e.g. in a container without `HOME`, it's
+    /// skipped with a [`SkippedLocation`] warning, and the other locations are still searched.
     ///
     /// [sys]: crate::ProjectPath::sys_dir
+    /// [`SkippedLocation`]: crate::ConrigWarning::SkippedLocation
+    /// [`ConfigOption.search_order`]: crate::ConfigOption#structfield.search_order
     /// [`ConfigOption.sys_override_local`]: crate::ConfigOption#structfield.sys_override_local
-    pub fn search_config_file<'a>(&'a self) -> Result<RawConfigFile<'a, 'p, T>, ConrigError> {
-        fn make_paths<'a>(
-            base: PathBuf,
-            names: &'a [&'a str],
-            with_dot: bool,
-        ) -> impl Iterator<Item = PathBuf> + 'a {
-            names.iter().flat_map(move |name| {
-                if with_dot {
-                    vec![base.join(name), base.join(".".to_owned() + name)]
-                } else {
-                    vec![base.join(name)]
-                }
-            })
+    /// [`ConfigOption.path_env_var`]: crate::ConfigOption#structfield.path_env_var
+    /// [machine]: crate::ProjectPath::machine_dir
+    /// [`ConfigOption.system_wide`]: crate::ConfigOption#structfield.system_wide
+    pub fn search_config_file<'a>(&'a self) -> Result<RawConfigSource<'a, 'p, T>, ConrigError> {
+        if let Some(path) = self.config_option.path_override() {
+            let file_format = self.format_of(&path);
+            return Ok(RawConfigSource::new(file_format, Some(path), self));
         }
-
-        let sys_dir = self
-            .project_path
-            .sys_dir(self.config_option.config_sys_type)
-            .ok_or(FileSystemError::NoProjectDirectory)?;
-        let sys_files = make_paths(
-            sys_dir,
-            self.config_name,
-            self.config_option.allow_dot_prefix,
-        );
-        let current_dir = current_dir().map_err(FileSystemError::OpenConfig)?;
-        let current_dir_files = make_paths(
-            current_dir,
-            self.config_name,
-            self.config_option.allow_dot_prefix,
-        );
-
-        let target = {
-            let last = self
-                .extra_files
-                .iter()
-                .filter_map(|t| detect_file_format(t, self.default_format))
-                .chain(
-                    self.extra_folders
-                        .iter()
-                        .flat_map(|t| {
-                            make_paths(
-                                PathBuf::from(t),
-                                self.config_name,
-                                self.config_option.allow_dot_prefix,
-                            )
-                        })
-                        .chain(if self.config_option.sys_override_local {
-                            sys_files.chain(current_dir_files)
-                        } else {
-                            current_dir_files.chain(sys_files)
-                        })
-                        .filter_map(|t| detect_file_format(t, self.default_format)),
-                )
-                .next();
-            if let Some((path, file_format)) = last {
-                RawConfigFile::new(file_format, Some(path), self)
-            } else {
-                RawConfigFile::new(self.default_format, None, self)
+        let target = if self.config_option.error_on_ambiguity {
+            let mut paths = self.search_all_config_files()?;
+            if paths.len() > 1 {
+                return Err(ConrigError::AmbiguousConfig(
+                    paths.into_iter().map(|t| t.0).collect(),
+                ));
             }
+            paths.pop()
+        } else {
+            self.config_files(false)?.next()
         };
 
-        Ok(target)
+        Ok(if let Some((path, file_format)) = target {
+            RawConfigSource::new(file_format, Some(path), self)
+        } else {
+            RawConfigSource::new(self.default_format, None, self)
+        })
     }
-}
-
-impl<'p, T: DeserializeOwned> ConfigPathMetadata<'p, T> {
-    // shortcut methods
 
-    /// Read a configuration file, using the default searching method.
+    /// List every existing configuration file, in the order of priority.
     ///
-    /// This is equivalent to
-    /// `self.search_config_file()?.fallback_default()?.read()`.
-    pub fn read(&self) -> Result<T, ConrigError> {
-        self.search_config_file()?.fallback_default()?.read()
-    }
-}
-impl<'p, T: Serialize> ConfigPathMetadata<'p, T> {
-    /// Write into a configuration file, using the default searching method.
+    /// Unlike [`search_config_file`], which only takes the first one, this returns all the files
+    /// it would consider, including the ones named alike with different extensions, e.g. both
+    /// `conrig.toml` and `conrig.json`. The path held by the [`ConfigOption.path_env_var`]
+    /// variable comes first if it exists. Duplicates are removed.
     ///
-    /// This is equivalent to
-    /// `self.search_config_file()?.fallback_default()?.write(&foo)`.
-    pub fn write(&self, value: &T) -> Result<(), ConrigError> {
-        self.search_config_file()?.fallback_default()?.write(value)
+    /// This is useful to warn users about the configuration files shadowed by the one in use.
+    ///
+    /// [`search_config_file`]: crate::ConfigPathMetadata::search_config_file
+    /// [`ConfigOption.path_env_var`]: crate::ConfigOption#structfield.path_env_var
+    pub fn search_all_config_files(&self) -> Result<Vec<(PathBuf, FileFormat)>, ConrigError> {
+        let mut paths: Vec<(PathBuf, FileFormat)> = Vec::new();
+        if let Some(path) = self.config_option.path_override() {
+            if filesystem::exists(&path) {
+                let file_format = self.format_of(&path);
+                paths.push((path, file_format));
+            }
+        }
+        for file in self.config_files(true)? {
+            if !paths.iter().any(|t| t.0 == file.0) {
+                paths.push(file);
+            }
+        }
+        Ok(paths)
     }
-}
 
-impl<'p, T: Serialize + DeserializeOwned> ConfigPathMetadata<'p, T> {
-    /// Read a configuration file,
-    /// or creating a new one with the default value provided.
+    /// Iterate over the existing configuration files, in the order of priority.
     ///
-    /// This is equivalent to
-    /// `self.search_config_file()?.fallback_default()?.read_or_default::<T>()`.
-    pub fn read_or_new(&self, default: T) -> Result<T, ConrigError> {
-        self.search_config_file()?
-            .fallback_default()?
-            .read_or_new(default)
+    /// If `all` is `false`, only the first file of each candidate name is considered,
+    /// following the sequence of [`detect_file_format`].
+    ///
+    /// The locations are searched in the [effective search order][order].
+    ///
+    /// [order]: crate::ConfigOption::effective_search_order
+    fn config_files(
+        &self,
+        all: bool,
+    ) -> Result<impl Iterator<Item = (PathBuf, FileFormat)> + '_, ConrigError> {
+        Ok(self.candidates(true).flat_map(move |t| match t {
+            Expanded::Literal(path) => self.detect_file_formats(path, all),
+            Expanded::Match(path) => filesystem::exists(&path)
+                .then(|| {
+                    let format = self.format_of(&path);
+                    (path, format)
+                })
+                .into_iter()
+                .collect(),
+        }))
     }
-}
 
-impl<'p, T: Serialize + DeserializeOwned + Default> ConfigPathMetadata<'p, T> {
-    /// Read a configuration file, or creating a new one with the `default` value.
+    /// The paths probed by [`search_config_file`], in order, with the reason each of them was
+    /// rejected.
     ///
-    /// This is equivalent to
-    /// `self.search_config_file()?.fallback_default()?.read_or_default::<T>()`.
-    pub fn read_or_default(&self) -> Result<T, ConrigError> {
-        self.search_config_file()?
-            .fallback_default()?
-            .read_or_default()
+    /// Every candidate name is listed with each file extension tried, so that it tells users
+    /// where to put their configuration file. Existing files are left out.
+    /// This is carried by the [`NoConfigurationFile`] error of a failed search.
+    ///
+    /// [`search_config_file`]: crate::ConfigPathMetadata::search_config_file
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn searched_paths(&self) -> Vec<SearchedPath> {
+        let mut searched = Vec::new();
+        for candidate in self.candidates(false) {
+            match candidate {
+                Expanded::Literal(path) => searched.extend(self.probe(&path)),
+                Expanded::Match(path) => searched.extend(
+                    filesystem::miss_reason(&path).map(|reason| SearchedPath { path, reason }),
+                ),
+            }
+        }
+        searched
     }
-}
 
-/// Extra options for the configuration file searcher.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct ConfigOption {
-    /// Allow configuration files to be prefixed with a dot. Default: `true`.
-    ///
-    /// If `allow_dot_prefix` is `true`,
-    /// both `.<app-name>.toml` and `<app-name>.toml` will be viewed as config files.
-    pub allow_dot_prefix: bool,
-    /// Allows system-level configuration files to override local's version. Default: `false`.
-    pub sys_override_local: bool,
-    /// The directory used to store configuration files in system-level.
-    pub config_sys_type: ConfigType,
-}
+    /// Probe the configuration files named after `path`, with each file extension.
+    fn probe(&self, path: &Path) -> Vec<SearchedPath> {
+        candidates(path, self.default_format, self.config_option.extensions)
+            .into_iter()
+            .filter_map(|(path, _)| {
+                filesystem::miss_reason(&path).map(|reason| SearchedPath { path, reason })
+            })
+            .collect()
+    }
 
-/// The directory used to store configuration files in system-level.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[non_exhaustive]
-pub enum ConfigType {
-    /// Save the files in the preference directory.
+    /// Iterate over the candidate configuration files, in the order of priority:
+    /// the paths without their file extensions, and the files matched by glob patterns.
     ///
-    /// See [`directories::ProjectDirs::preference_dir`] for more information.
-    Preference,
-    /// Save the files in the config directory.
+    /// Unavailable locations are skipped, with a [`SkippedLocation`] warning if `warn` is set.
     ///
-    /// See [`directories::ProjectDirs::config_dir`] for more information.
-    Config,
-}
-
-impl ConfigOption {
-    /// Default `ConfigOption` value.
-    pub const DEFAULT_CONFIG: ConfigOption = ConfigOption {
-        allow_dot_prefix: true,
-        sys_override_local: false,
-        config_sys_type: ConfigType::Config,
-    };
+    /// [`SkippedLocation`]: crate::ConrigWarning::SkippedLocation
+    fn candidates(&self, warn: bool) -> impl Iterator<Item = Expanded> + '_ {
+        let dot = self.config_option.allow_dot_prefix;
+        let mut locations: Vec<Box<dyn Iterator<Item = Expanded> + '_>> = Vec::new();
+        for location in self.config_option.effective_search_order().locations() {
+            match location {
+                SearchLocation::ExtraFiles => locations.push(Box::new(
+                    self.extra_files.iter().flat_map(|t| expand_pattern(t)),
+                )),
+                SearchLocation::ExtraFolders => locations.push(Box::new(
+                    self.extra_folders
+                        .iter()
+                        .flat_map(|t| expand_pattern(t))
+                        .filter_map(|t| match t {
+                            Expanded::Literal(path) => Some(path),
+                            Expanded::Match(path) => filesystem::is_dir(&path).then_some(path),
+                        })
+                        .flat_map(move |t| make_paths(t, self.config_name, dot))
+                        .map(Expanded::Literal),
+                )),
+                SearchLocation::Local => {
+                    let local_root = match self.local_root() {
+                        Ok(local_root) => local_root,
+                        Err(err) => {
+                            if warn {
+                                self.config_option.warn(ConrigWarning::SkippedLocation {
+                                    location: SearchLocation::Local,
+                                    reason: format!("Cannot get the current directory: {err}"),
+                                });
+                            }
+                            continue;
+                        }
+                    };
+                    locations.push(Box::new(
+                        self.local_dirs_from(local_root)
+                            .into_iter()
+                            .flat_map(move |dir| make_paths(dir, self.config_name, dot))
+                            .map(Expanded::Literal),
+                    ))
+                }
+                SearchLocation::System => {
+                    let sys_dirs = self.sys_dirs();
+                    if sys_dirs.is_empty() && warn {
+                        self.config_option.warn(ConrigWarning::SkippedLocation {
+                            location: SearchLocation::System,
+                            reason: FileSystemError::NoProjectDirectory.to_string(),
+                        });
+                    }
+                    locations.push(Box::new(
+                        sys_dirs
+                            .into_iter()
+                            .flat_map(move |dir| self.sys_paths(dir))
+                            .map(Expanded::Literal),
+                    ))
+                }
+                SearchLocation::Machine => {
+                    if let Some(machine_dir) = self
+                        .machine_dir()
+                        .filter(|_| self.config_option.system_wide)
+                    {
+                        locations.push(Box::new(
+                            make_paths(machine_dir, self.config_name, dot).map(Expanded::Literal),
+                        ))
+                    }
+                }
+            }
+        }
+        locations.into_iter().flatten()
+    }
 
-    /// Modify the [`allow_dot_prefix`] field.
+    /// The local directories to search, from the nearest one.
     ///
-    /// [`allow_dot_prefix`]: crate::ConfigOption#structfield.allow_dot_prefix
-    pub const fn with_allow_dot_prefix(mut self, allow_dot_prefix: bool) -> Self {
-        self.allow_dot_prefix = allow_dot_prefix;
-        self
+    /// This is the current directory, followed by its ancestors if
+    /// [`ConfigOption.search_ancestors`] is enabled.
+    ///
+    /// [`ConfigOption.search_ancestors`]: crate::ConfigOption#structfield.search_ancestors
+    pub(crate) fn local_dirs(&self) -> Result<Vec<PathBuf>, ConrigError> {
+        let local_root = self.local_root().map_err(current_dir_error)?;
+        Ok(self.local_dirs_from(local_root))
     }
 
-    /// Modify the [`sys_override_local`] field.
+    /// The local directories to search, from `local_root`.
+    fn local_dirs_from(&self, local_root: PathBuf) -> Vec<PathBuf> {
+        if !self.config_option.search_ancestors {
+            return vec![local_root];
+        }
+        self.ancestor_dirs(&local_root)
+    }
+
+    /// The root of the local directories: the [`local_dir`] if it's set,
+    /// or the current directory.
     ///
-    /// [`sys_override_local`]: crate::ConfigOption#structfield.sys_override_local
-    pub const fn with_sys_override_local(mut self, sys_override_local: bool) -> Self {
-        self.sys_override_local = sys_override_local;
-        self
+    /// [`local_dir`]: crate::ConfigPathMetadata#structfield.local_dir
+    fn local_root(&self) -> std::io::Result<PathBuf> {
+        match (self.local_dir, sandbox_root()) {
+            (Some(local_dir), _) => Ok(local_dir.to_path_buf()),
+            (None, Some(root)) => Ok(root.join("work")),
+            (None, None) => current_dir(),
+        }
     }
 
-    /// Modify the [`config_sys_type`] field.
+    /// The `local_root` and its ancestors, from the nearest one,
+    /// up to the [`ConfigOption.ancestor_marker`].
     ///
-    /// [`config_sys_type`]: crate::ConfigOption#structfield.config_sys_type
-    pub const fn with_config_sys_type(mut self, config_sys_type: ConfigType) -> Self {
-        self.config_sys_type = config_sys_type;
-        self
+    /// [`ConfigOption.ancestor_marker`]: crate::ConfigOption#structfield.ancestor_marker
+    fn ancestor_dirs(&self, local_root: &Path) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        for dir in local_root.ancestors() {
+            dirs.push(dir.to_path_buf());
+            if self
+                .config_option
+                .ancestor_marker
+                .is_some_and(|marker| {
+                    let marker = dir.join(marker);
+                    filesystem::exists(&marker) || filesystem::is_dir(&marker)
+                })
+            {
+                break;
+            }
+        }
+        dirs
     }
-}
 
-/// Your application's metadata.
-///
-/// This is mainly used to figure out the system-level storage directory for your application.
-///
-/// See [`directories::ProjectDirs`] for more information.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct ProjectPath<'a> {
-    /// The qualifier of your application.
+    /// Collect the rc-file chain, in increasing order of precedence.
     ///
-    /// E.g. `com` in `com.GitHub.application`.
-    pub qualifier: &'a str,
-    /// The organization name of your application.
+    /// The first configuration file found in each of these directories is collected:
+    /// 1. the [machine-wide directory][machine], e.g. `/etc/<application>`;
+    /// 2. the [system-level directory][sys];
+    /// 3. the [home directory][home], where only dotfiles are considered,
+    ///    e.g. `~/.conrig.toml` and `~/.conrigrc`;
+    /// 4. the ancestors of the current directory, from the farthest one,
+    ///    up to the [`ConfigOption.ancestor_marker`];
+    /// 5. the current directory.
     ///
-    /// E.g. `GitHub` in `com.GitHub.application`.
-    pub organization: &'a str,
-    /// Your application's name.
+    /// [machine]: crate::ProjectPath::machine_dir
+    /// [sys]: crate::ProjectPath::sys_dir
+    /// [home]: crate::ProjectPath::home_dir
+    /// [`ConfigOption.ancestor_marker`]: crate::ConfigOption#structfield.ancestor_marker
+    pub fn rc_files(&self) -> Result<Vec<ConfigFile>, ConrigError> {
+        let mut files: Vec<ConfigFile> = Vec::new();
+        for (path, file_format) in self
+            .rc_candidates()?
+            .into_iter()
+            .filter_map(|paths| paths.into_iter().find_map(|t| self.detect_file_format(t)))
+        {
+            if files.iter().all(|file| file.path != path) {
+                let file = ConfigFile::new(file_format, path);
+                files.push(file.with_config_option(self.config_option));
+            }
+        }
+        Ok(files)
+    }
+
+    /// The candidate files of each directory of the rc-file chain, without the file extensions,
+    /// in increasing order of precedence.
+    fn rc_candidates(&self) -> Result<Vec<Vec<PathBuf>>, ConrigError> {
+        let local_files = |dir: PathBuf| {
+            make_paths(dir, self.config_name, self.config_option.allow_dot_prefix)
+                .collect::<Vec<_>>()
+        };
+        let mut candidates = Vec::new();
+        candidates.extend(self.machine_dir().map(local_files));
+        candidates.extend(
+            self.sys_dir(self.sys_type())
+                .map(|dir| self.sys_paths(dir)),
+        );
+        candidates.extend(
+            self.project_path
+                .home_dir()
+                .map(|dir| dotfile_paths(&dir, self.config_name)),
+        );
+        let local_root = self.local_root().map_err(current_dir_error)?;
+        candidates.extend(self.ancestor_dirs(&local_root).into_iter().rev().map(local_files));
+        Ok(candidates)
+    }
+
+    /// The format of a configuration file, taken from its extension,
+    /// falling back to the [default format][default].
     ///
-    /// E.g. `application` in `com.GitHub.application`.
-    pub application: &'a str,
-}
+    /// [default]: crate::ConfigPathMetadata#structfield.default_format
+    fn format_of(&self, path: &Path) -> FileFormat {
+        self.config_option
+            .format_of(path)
+            .unwrap_or(self.default_format)
+    }
 
-impl<'a> ProjectPath<'a> {
-    /// Create a new `ProjectPath`.
-    pub const fn new(qualifier: &'a str, organization: &'a str, application: &'a str) -> Self {
-        Self {
-            qualifier,
-            organization,
-            application,
+    /// Detect a configuration file, reporting it if it's slow.
+    fn detect_file_format(&self, path: impl AsRef<Path>) -> Option<(PathBuf, FileFormat)> {
+        let path = path.as_ref();
+        self.config_option.timed(Operation::Search, path, || {
+            detect_mapped(path, self.default_format, self.config_option.extensions)
+        })
+    }
+
+    /// Detect the configuration files named after `path`: only the first one, or `all` of them.
+    fn detect_file_formats(
+        &self,
+        path: impl AsRef<Path>,
+        all: bool,
+    ) -> Vec<(PathBuf, FileFormat)> {
+        if !all {
+            return self.detect_file_format(path).into_iter().collect();
         }
+        let path = path.as_ref();
+        self.config_option.timed(Operation::Search, path, || {
+            detect_all_mapped(path, self.default_format, self.config_option.extensions)
+        })
     }
 
-    /// Modify the [`qualifier`] field.
+    /// Check if a configuration file is read-only, according to [`ConfigOption.read_only_sys`].
     ///
-    /// [`qualifier`]: crate::ProjectPath#structfield.qualifier
-    pub const fn with_qualifier(mut self, qualifier: &'a str) -> Self {
-        self.qualifier = qualifier;
-        self
+    /// [`ConfigOption.read_only_sys`]: crate::ConfigOption#structfield.read_only_sys
+    pub(crate) fn is_read_only(&self, path: &Path) -> bool {
+        self.config_option.read_only_sys
+            && self
+                .sys_dirs()
+                .into_iter()
+                .any(|sys_dir| path.starts_with(sys_dir))
     }
 
-    /// Modify the [`organization`] field.
+    /// Search for a configuration file in the machine-wide directory, if it's enabled.
     ///
-    /// [`organization`]: crate::ProjectPath#structfield.organization
-    pub const fn with_organization(mut self, organization: &'a str) -> Self {
-        self.organization = organization;
-        self
+    /// See [`ConfigOption.system_wide`] for more information.
+    ///
+    /// [`ConfigOption.system_wide`]: crate::ConfigOption#structfield.system_wide
+    fn search_machine_dir(&self) -> Option<(PathBuf, FileFormat)> {
+        self.machine_dir_files(false).into_iter().next()
+    }
+
+    /// The configuration files in the machine-wide directory, if it's enabled:
+    /// only the first one, or `all` of them.
+    fn machine_dir_files(&self, all: bool) -> Vec<(PathBuf, FileFormat)> {
+        let machine_dir = match self.machine_dir() {
+            Some(machine_dir) if self.config_option.system_wide => machine_dir,
+            _ => return Vec::new(),
+        };
+        let files = make_paths(
+            machine_dir,
+            self.config_name,
+            self.config_option.allow_dot_prefix,
+        )
+        .flat_map(|t| self.detect_file_formats(t, all));
+        if all {
+            files.collect()
+        } else {
+            files.take(1).collect()
+        }
+    }
+
+    /// The type of the system-level directory in use.
+    ///
+    /// This is [`ConfigOption.config_sys_type`], unless it's [`Auto`]: then this reports which
+    /// of the [candidates] is used, i.e. the first one whose directory contains a configuration
+    /// file, or the conventional one if none does.
+    ///
+    /// [`ConfigOption.config_sys_type`]: crate::ConfigOption#structfield.config_sys_type
+    /// [`Auto`]: crate::ConfigType::Auto
+    /// [candidates]: crate::ConfigType::auto_candidates
+    pub fn sys_type(&self) -> ConfigType {
+        let config_type = self.config_option.config_sys_type;
+        if config_type != ConfigType::Auto {
+            return config_type;
+        }
+        let candidates = ConfigType::auto_candidates();
+        candidates
+            .into_iter()
+            .find(|t| {
+                self.sys_dir(*t).is_some_and(|dir| {
+                    self.sys_paths(dir)
+                        .into_iter()
+                        .any(|t| self.detect_file_format(t).is_some())
+                })
+            })
+            .unwrap_or(candidates[0])
+    }
+
+    /// The system-level directories to search, in order.
+    ///
+    /// This is only the one of [`ConfigOption.config_sys_type`], unless it's [`Auto`].
+    ///
+    /// [`ConfigOption.config_sys_type`]: crate::ConfigOption#structfield.config_sys_type
+    /// [`Auto`]: crate::ConfigType::Auto
+    pub(crate) fn sys_dirs(&self) -> Vec<PathBuf> {
+        let config_type = self.config_option.config_sys_type;
+        if config_type != ConfigType::Auto {
+            return self.sys_dir(config_type).into_iter().collect();
+        }
+        let mut sys_dirs: Vec<PathBuf> = Vec::new();
+        for dir in ConfigType::auto_candidates()
+            .into_iter()
+            .filter_map(|t| self.sys_dir(t))
+        {
+            if !sys_dirs.contains(&dir) {
+                sys_dirs.push(dir);
+            }
+        }
+        sys_dirs
+    }
+
+    /// Search for a configuration file in the system-level directory of the given project.
+    fn search_sys_dir(&self, project_path: &ProjectPath) -> Option<(PathBuf, FileFormat)> {
+        let sys_dir = self
+            .platform_paths()
+            .sys_dir(project_path, self.config_option.config_sys_type)?;
+        self.sys_paths(sys_dir)
+            .into_iter()
+            .find_map(|t| self.detect_file_format(t))
+    }
+
+    /// Generate the possible configuration file paths in a system-level directory,
+    /// without the file extensions.
+    ///
+    /// In the [home directory][home], only dotfiles are considered, e.g. `.conrig.toml` and
+    /// `.conrigrc`.
+    ///
+    /// [home]: crate::ConfigType::Home
+    fn sys_paths(&self, sys_dir: PathBuf) -> Vec<PathBuf> {
+        if self.config_option.config_sys_type != ConfigType::Home {
+            return make_paths(
+                sys_dir,
+                self.config_name,
+                self.config_option.allow_dot_prefix,
+            )
+            .collect();
+        }
+        dotfile_paths(&sys_dir, self.config_name)
+    }
+
+    /// Move a configuration file from a legacy location to the current one.
+    ///
+    /// This is useful if your application has been renamed,
+    /// or its organization has changed, so that the users' settings won't get lost after an update.
+    ///
+    /// If the [system-level configuration directory][sys] of the current [`project_path`]
+    /// doesn't contain any configuration file, the ones of `legacy` projects are checked in order.
+    /// The first one found is moved into the current directory, keeping its file name.
+    ///
+    /// Returns the [`Migration`] performed, or `None` if there's nothing to migrate.
+    ///
+    /// **Note**: The moved file is the only trace of the migration, which is why calling this
+    /// again is a no-op. `conrig` doesn't persist the [`Migration`] anywhere else: record it
+    /// yourself if your application needs to remember it, e.g. to tell the users where their
+    /// settings went.
+    ///
+    /// [sys]: crate::ProjectPath::sys_dir
+    /// [`project_path`]: crate::ConfigPathMetadata#structfield.project_path
+    pub fn migrate_from(&self, legacy: &[ProjectPath]) -> Result<Option<Migration>, ConrigError> {
+        if self.search_sys_dir(&self.project_path).is_some() {
+            return Ok(None);
+        }
+        let Some((from, file_format)) = legacy.iter().find_map(|t| self.search_sys_dir(t)) else {
+            return Ok(None);
+        };
+
+        let to = self
+            .sys_dir(self.sys_type())
+            .ok_or(FileSystemError::NoProjectDirectory)?
+            .join(from.file_name().ok_or(FileSystemError::NoProjectDirectory)?);
+        let parent = to.parent().ok_or(FileSystemError::NoProjectDirectory)?;
+        filesystem::create_dir_all(parent).context_path(Operation::Migrate, &to)?;
+        if filesystem::rename(&from, &to).is_err() {
+            // `rename` fails across file systems, so fall back to copying the file.
+            filesystem::copy(&from, &to)
+                .and_then(|_| filesystem::remove_file(&from))
+                .context_path(Operation::Migrate, &from)?;
+        }
+
+        Ok(Some(Migration {
+            from,
+            to,
+            file_format,
+        }))
+    }
+}
+
+/// The root of the sandbox of the current thread, if any.
+#[cfg(feature = "testing")]
+pub(crate) fn sandbox_root() -> Option<PathBuf> {
+    crate::testing::root()
+}
+
+/// The root of the sandbox of the current thread, if any.
+///
+/// Sandboxes only exist with the `testing` feature.
+#[cfg(not(feature = "testing"))]
+#[inline(always)]
+pub(crate) fn sandbox_root() -> Option<PathBuf> {
+    None
+}
+
+/// Expand a leading `~` in the path to the user's home directory.
+pub(crate) fn expand_home(path: &str) -> Option<PathBuf> {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => Some(
+            BaseDirs::new()?
+                .home_dir()
+                .join(rest.trim_start_matches(['/', '\\'])),
+        ),
+        _ => Some(PathBuf::from(path)),
+    }
+}
+
+/// A path of [`extra_files`] or [`extra_folders`], after expansion.
+///
+/// [`extra_files`]: crate::ConfigPathMetadata#structfield.extra_files
+/// [`extra_folders`]: crate::ConfigPathMetadata#structfield.extra_folders
+enum Expanded {
+    /// A plain path, possibly without its file extension.
+    Literal(PathBuf),
+    /// An existing path matched by a glob pattern.
+    #[cfg_attr(not(feature = "glob"), allow(dead_code))]
+    Match(PathBuf),
+}
+
+/// Expand the leading `~` of a path and, with the `glob` feature, its glob pattern.
+///
+/// Matches are sorted, so that they're considered in a deterministic order.
+/// Invalid patterns are taken literally.
+fn expand_pattern(path: &str) -> Vec<Expanded> {
+    let Some(path) = expand_home(path) else {
+        return Vec::new();
+    };
+    #[cfg(feature = "glob")]
+    if let Some(pattern) = path.to_str().filter(|t| t.contains(['*', '?', '['])) {
+        if let Ok(paths) = glob::glob(pattern) {
+            let mut matches: Vec<_> = paths.filter_map(Result::ok).collect();
+            matches.sort();
+            return matches.into_iter().map(Expanded::Match).collect();
+        }
+    }
+    vec![Expanded::Literal(path)]
+}
+
+/// The error of an unavailable current directory.
+fn current_dir_error(source: std::io::Error) -> FileSystemError {
+    FileSystemError::open_config(Path::new("."))(source)
+}
+
+/// Generate the possible configuration file paths in a directory, without the file extensions.
+fn make_paths<'a>(base: PathBuf, names: &'a [&'a str], with_dot: bool) -> MakePaths<'a> {
+    MakePaths {
+        base,
+        names: names.iter(),
+        with_dot,
+        dotted: None,
+    }
+}
+
+/// The iterator returned by [`make_paths`].
+///
+/// Each path is allocated once, with its exact capacity.
+struct MakePaths<'a> {
+    base: PathBuf,
+    names: std::slice::Iter<'a, &'a str>,
+    with_dot: bool,
+    /// The name whose dotted variant comes next.
+    dotted: Option<&'a str>,
+}
+
+impl MakePaths<'_> {
+    fn join(&self, name: &str, dot: bool) -> PathBuf {
+        let base = self.base.as_os_str();
+        let mut path = PathBuf::with_capacity(base.len() + name.len() + 2);
+        path.push(base);
+        if dot {
+            // pushing an empty component only appends the separator.
+            path.push("");
+            path.as_mut_os_string().push(".");
+            path.as_mut_os_string().push(name);
+        } else {
+            path.push(name);
+        }
+        path
+    }
+}
+
+impl Iterator for MakePaths<'_> {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        if let Some(name) = self.dotted.take() {
+            return Some(self.join(name, true));
+        }
+        let name = self.names.next()?;
+        if self.with_dot {
+            self.dotted = Some(name);
+        }
+        Some(self.join(name, false))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.names.len() * (1 + usize::from(self.with_dot))
+            + usize::from(self.dotted.is_some());
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for MakePaths<'_> {}
+
+/// Generate the possible dotfile paths in a directory, e.g. `.conrig` and `.conrigrc`,
+/// without the file extensions.
+fn dotfile_paths(base: &Path, names: &[&str]) -> Vec<PathBuf> {
+    names
+        .iter()
+        .flat_map(|name| [base.join(format!(".{name}")), base.join(format!(".{name}rc"))])
+        .collect()
+}
+
+/// A configuration file moved by [`ConfigPathMetadata::migrate_from`].
+///
+/// This is only returned to the caller, and never persisted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// The legacy path of the configuration file.
+    pub from: PathBuf,
+    /// The new path of the configuration file.
+    pub to: PathBuf,
+    /// The format of the configuration file.
+    pub file_format: FileFormat,
+}
+
+impl<'p, T: DeserializeOwned> ConfigPathMetadata<'p, T> {
+    // shortcut methods
+
+    /// Read a configuration file, using the default searching method.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read()`.
+    pub fn read(&self) -> Result<T, ConrigError> {
+        self.search_config_file()?.fallback_default()?.read()
+    }
+
+    /// Read the configuration files of your application and its [`fallback_identities`],
+    /// merged together.
+    ///
+    /// The files of the [`parent`] metadata are merged first, then the configuration files found
+    /// in the [system-level directories][sys] of the fallback identities, in order.
+    /// At last, the file found by [`search_config_file`] is merged over them,
+    /// so your application's own settings take precedence.
+    /// Missing files are skipped, but a [`NoConfigurationFile`] error is returned if none exists.
+    ///
+    /// [`fallback_identities`]: crate::ConfigPathMetadata#structfield.fallback_identities
+    /// [`parent`]: crate::ConfigPathMetadata#structfield.parent
+    /// [sys]: crate::ProjectPath::sys_dir
+    /// [`search_config_file`]: crate::ConfigPathMetadata::search_config_file
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn read_merged(&self) -> Result<T, ConrigError> {
+        let file = self.search_config_file()?.fallback_default()?;
+        self.merge_layers(&file, self.config_layers()?)
+    }
+
+    /// Read the rc-file chain, like `npm` and `git` do.
+    ///
+    /// The files returned by [`rc_files`] are merged in order,
+    /// so the nearest one takes precedence.
+    /// A [`NoConfigurationFile`] error is returned if there's none.
+    ///
+    /// [`rc_files`]: crate::ConfigPathMetadata::rc_files
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn read_rc_chain(&self) -> Result<T, ConrigError> {
+        let layers = self.rc_files()?;
+        let Some(file) = layers.last().cloned() else {
+            let searched = self
+                .rc_candidates()?
+                .into_iter()
+                .flatten()
+                .flat_map(|t| self.probe(&t))
+                .collect();
+            return Err(ConrigError::NoConfigurationFile(searched));
+        };
+        self.merge_layers(&file, layers)
+    }
+
+    /// Merge the documents of some configuration files, then deserialize the result as `file`.
+    fn merge_layers(&self, file: &ConfigFile, layers: Vec<ConfigFile>) -> Result<T, ConrigError> {
+        let mut merged = None;
+        for layer in layers {
+            let document = layer
+                .read_document()
+                .context_path(Operation::Read, &layer.path)?;
+            merged
+                .get_or_insert_with(|| Value::Table(Default::default()))
+                .merge_keyed(document, self.config_option.merge_keys);
+        }
+        let merged =
+            merged.ok_or_else(|| ConrigError::NoConfigurationFile(self.searched_paths()))?;
+        file.decode(merged).context_path(Operation::Read, &file.path)
+    }
+}
+
+/// A source of configuration files merged by [`ConfigPathMetadata::read_merged`].
+///
+/// This is implemented for every [`ConfigPathMetadata`], whatever its configuration type,
+/// so it can be the [`parent`] of another one.
+///
+/// [`parent`]: crate::ConfigPathMetadata#structfield.parent
+pub trait ConfigLayers: Sync {
+    /// The existing configuration files, in increasing order of precedence.
+    fn config_layers(&self) -> Result<Vec<ConfigFile>, ConrigError>;
+}
+
+impl<T> ConfigLayers for ConfigPathMetadata<'_, T> {
+    fn config_layers(&self) -> Result<Vec<ConfigFile>, ConrigError> {
+        let mut layers = match self.parent {
+            Some(parent) => parent.config_layers()?,
+            None => Vec::new(),
+        };
+        let machine_file = self.search_machine_dir();
+        layers.extend(
+            machine_file
+                .clone()
+                .into_iter()
+                .chain(
+                    self.fallback_identities
+                        .iter()
+                        .filter_map(|t| self.search_sys_dir(t)),
+                )
+                .map(|(path, file_format)| {
+                    ConfigFile::new(file_format, path).with_config_option(self.config_option)
+                }),
+        );
+        let file = self.search_config_file()?.fallback_default()?;
+        if crate::filesystem::exists(&file.path) && machine_file.is_none_or(|(path, _)| path != file.path) {
+            layers.push(file);
+        }
+        layers.extend(self.search_policy_file());
+        Ok(layers)
+    }
+}
+
+impl fmt::Debug for dyn ConfigLayers + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConfigLayers")
+    }
+}
+
+/// Parents are compared by address.
+impl PartialEq for dyn ConfigLayers + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+impl Eq for dyn ConfigLayers + '_ {}
+
+impl<'p, T: Serialize> ConfigPathMetadata<'p, T> {
+    /// Write into a configuration file, using the default searching method.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.write(&foo)`.
+    pub fn write(&self, value: &T) -> Result<(), ConrigError> {
+        self.search_config_file()?.fallback_default()?.write(value)
+    }
+}
+
+impl<'p, T: Serialize + Default> ConfigPathMetadata<'p, T> {
+    /// Write into a configuration file, omitting the fields equal to the default value.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.write_minimal(&foo)`.
+    ///
+    /// See [`ConfigFile::write_minimal`] for more information.
+    ///
+    /// [`ConfigFile::write_minimal`]: crate::parser::ConfigFile::write_minimal
+    pub fn write_minimal(&self, value: &T) -> Result<(), ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .write_minimal(value)
+    }
+}
+
+impl<'p, T: Serialize + DeserializeOwned> ConfigPathMetadata<'p, T> {
+    /// Read a configuration file, writing the fields missing in the file back.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_and_upgrade::<T>()`.
+    ///
+    /// See [`ConfigFile::read_and_upgrade`] for more information.
+    ///
+    /// [`ConfigFile::read_and_upgrade`]: crate::parser::ConfigFile::read_and_upgrade
+    pub fn read_and_upgrade(&self) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_and_upgrade()
+    }
+
+    /// Read a configuration file,
+    /// or creating a new one with the default value provided.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_or_default::<T>()`.
+    pub fn read_or_new(&self, default: T) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_or_new(default)
+    }
+
+    /// Read a configuration file,
+    /// or creating a new one with the value returned by `default`.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_or_else(default)`.
+    ///
+    /// See [`ConfigFile::read_or_else`] for more information.
+    ///
+    /// [`ConfigFile::read_or_else`]: crate::parser::ConfigFile::read_or_else
+    pub fn read_or_else(&self, default: impl FnOnce() -> T) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_or_else(default)
+    }
+}
+
+impl<'p, T: Serialize + DeserializeOwned> ConfigPathMetadata<'p, T> {
+    /// Read a configuration file,
+    /// or creating a new one with the value of the [`default_provider`].
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_or_provided()`.
+    ///
+    /// [`default_provider`]: crate::ConfigPathMetadata#structfield.default_provider
+    pub fn read_or_provided(&self) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_or_try_else(|| self.provided_value())
+    }
+}
+
+impl<'p, T: Default> ConfigPathMetadata<'p, T> {
+    /// Get the default value.
+    ///
+    /// This is the value of the [`default_provider`] if any, or `T::default()`.
+    ///
+    /// [`default_provider`]: crate::ConfigPathMetadata#structfield.default_provider
+    pub fn default_value(&self) -> Result<T, ConrigError> {
+        match self.default_provider {
+            Some(provider) => provider.provide(),
+            None => Ok(T::default()),
+        }
+    }
+}
+
+impl<'p, T: Serialize + DeserializeOwned + Default> ConfigPathMetadata<'p, T> {
+    /// Read a configuration file, or creating a new one with the [default value].
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_or_default::<T>()`.
+    ///
+    /// [default value]: crate::ConfigPathMetadata::default_value
+    pub fn read_or_default(&self) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_or_try_else(|| self.default_value())
+    }
+}
+
+/// Extra options for the configuration file searcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Handlers are only compared to tell whether two options are configured the same way,
+// where comparing the function addresses is good enough.
+#[allow(unpredictable_function_pointer_comparisons)]
+pub struct ConfigOption {
+    /// Allow configuration files to be prefixed with a dot. Default: `true`.
+    ///
+    /// If `allow_dot_prefix` is `true`,
+    /// both `.<app-name>.toml` and `<app-name>.toml` will be viewed as config files.
+    pub allow_dot_prefix: bool,
+    /// Allows system-level configuration files to override local's version. Default: `false`.
+    ///
+    /// This is ignored if [`search_order`] is set.
+    ///
+    /// [`search_order`]: crate::ConfigOption#structfield.search_order
+    pub sys_override_local: bool,
+    /// The order in which the locations are searched, from the highest precedence.
+    /// Default: `None`.
+    ///
+    /// If this is `None`, the order is the one of [`SearchOrder::LOCAL_FIRST`], or
+    /// [`SearchOrder::SYSTEM_FIRST`] if [`sys_override_local`] is enabled. The locations left
+    /// out aren't searched at all.
+    ///
+    /// [`sys_override_local`]: crate::ConfigOption#structfield.sys_override_local
+    pub search_order: Option<SearchOrder>,
+    /// The directory used to store configuration files in system-level.
+    pub config_sys_type: ConfigType,
+    /// The `AppData` folder of the system-level directory on Windows.
+    /// Default: [`AppData::Roaming`].
+    ///
+    /// This applies to the [`Config`], [`Preference`] and [`Data`] types, and is ignored on the
+    /// other platforms, or if the [`platform_paths`] are overridden.
+    ///
+    /// [`Config`]: crate::ConfigType::Config
+    /// [`Preference`]: crate::ConfigType::Preference
+    /// [`Data`]: crate::ConfigType::Data
+    /// [`platform_paths`]: crate::ConfigPathMetadata#structfield.platform_paths
+    pub app_data: AppData,
+    /// Search the ancestors of the current directory as well. Default: `false`.
+    ///
+    /// If `search_ancestors` is `true`, local configuration files are searched from the current
+    /// directory up to the file system root, like `cargo` and `git` do, so a project-level
+    /// configuration file is found from any subdirectory. The nearest file takes precedence.
+    pub search_ancestors: bool,
+    /// The file or directory marking the root of a project, e.g. `.git`. Default: `None`.
+    ///
+    /// If this is set, the [ancestor search][search_ancestors] stops at the first directory
+    /// containing it, instead of the file system root.
+    ///
+    /// [search_ancestors]: crate::ConfigOption#structfield.search_ancestors
+    pub ancestor_marker: Option<&'static str>,
+    /// Search the machine-wide configuration directory as well. Default: `false`.
+    ///
+    /// If `system_wide` is `true`, the [machine-wide directory][machine], e.g.
+    /// `/etc/<application>`, is searched after the user-level and local ones. So administrators
+    /// can manage the defaults of every user, which take the lowest precedence: they're
+    /// only used if no other file is found, and are merged beneath the others by [`read_merged`].
+    ///
+    /// [machine]: crate::ProjectPath::machine_dir
+    /// [`read_merged`]: crate::ConfigPathMetadata::read_merged
+    pub system_wide: bool,
+    /// Enforce the administrators' policy file. Default: `false`.
+    ///
+    /// If `policy` is `true`, the [policy file], e.g. `/etc/<application>/<name>.policy.toml`,
+    /// locks every key it contains: [`read_merged`] always takes its values, merging it over
+    /// all the other files, and writing a different value for any of them fails with a
+    /// [`LockedKeys`] error. This enables managed settings, like the enterprise policies
+    /// of browsers.
+    ///
+    /// [policy file]: crate::ConfigPathMetadata::search_policy_file
+    /// [`read_merged`]: crate::ConfigPathMetadata::read_merged
+    /// [`LockedKeys`]: crate::ConrigError::LockedKeys
+    pub policy: bool,
+    /// The environment variable overriding the configuration file path, e.g. `APP_CONFIG`.
+    /// Default: `None`.
+    ///
+    /// If this is set and the variable holds a path, [`search_config_file`] uses that path,
+    /// before any other location, even if the file doesn't exist yet.
+    /// This lets containers and CI deployments mount the configuration anywhere.
+    /// A leading `~` is expanded to the home directory, and the format is taken from the extension.
+    ///
+    /// [`search_config_file`]: crate::ConfigPathMetadata::search_config_file
+    pub path_env_var: Option<&'static str>,
+    /// Custom file extensions, mapped to their format, e.g. `&[("conf", FileFormat::Toml)]`.
+    /// Default: `&[]`.
+    ///
+    /// The extensions are given without their leading dot. They're searched after the built-in
+    /// ones, in order, so `app.conf` is recognized as TOML without being renamed. The empty
+    /// extension `""` sets the format of the files without any extension, instead of
+    /// the [default format][default]. A custom mapping of a built-in extension takes precedence
+    /// when a format is taken from a path, e.g. with the [`path_env_var`].
+    ///
+    /// [default]: crate::ConfigPathMetadata#structfield.default_format
+    /// [`path_env_var`]: crate::ConfigOption#structfield.path_env_var
+    pub extensions: &'static [(&'static str, FileFormat)],
+    /// Write configuration files atomically. Default: `true`.
+    ///
+    /// If `atomic_write` is `true`, values are written into a temporary file in the same directory,
+    /// which then replaces the configuration file,
+    /// so a crash during the writing won't leave the configuration file truncated.
+    pub atomic_write: bool,
+    /// Flush configuration files to the disk before returning from the writing. Default: `false`.
+    ///
+    /// If [`atomic_write`] is enabled as well, the directory containing the file is flushed too.
+    ///
+    /// [`atomic_write`]: crate::ConfigOption#structfield.atomic_write
+    pub sync_write: bool,
+    /// The number of backups kept when overwriting a configuration file. Default: `0`.
+    ///
+    /// Before a configuration file is overwritten, its content is copied into `<file>.bak`,
+    /// while the older backups are rotated into `<file>.bak.1`, `<file>.bak.2`, etc.
+    /// At most `backup` backups are kept.
+    pub backup: usize,
+    /// Record and verify the integrity checksums of the configuration file. Default: `false`.
+    ///
+    /// If `checksum` is `true`, every write records the checksum of the configuration file in
+    /// `<file>.sha256`, and every read verifies it, failing with an [`IntegrityError`] if the
+    /// file has been tampered with or corrupted. See the [`checksum`] module for more information.
+    ///
+    /// [`IntegrityError`]: crate::ConrigError::IntegrityError
+    /// [`checksum`]: crate::checksum
+    pub checksum: bool,
+    /// Keep the users' comments when rewriting configuration files. Default: `false`.
+    ///
+    /// If `keep_comments` is `true`, [`write`] puts the comments of the file being replaced back
+    /// above their keys. The formats without comments, e.g. JSON, keep them in a `<file>.comments`
+    /// sidecar instead. See the [`comments`] module for more information.
+    ///
+    /// [`write`]: crate::parser::ConfigFile::write
+    /// [`comments`]: crate::comments
+    pub keep_comments: bool,
+    /// Fill the keys missing in a configuration file with the default value. Default: `false`.
+    ///
+    /// If `repair_missing` is `true`, [`read_or_new`] and [`read_or_default`] fill the keys
+    /// missing in the configuration file with the default value, and write the repaired file back.
+    ///
+    /// [`read_or_new`]: crate::parser::ConfigFile::read_or_new
+    /// [`read_or_default`]: crate::parser::ConfigFile::read_or_default
+    pub repair_missing: bool,
+    /// Replace the invalid parts of a configuration file with the default value. Default: `false`.
+    ///
+    /// If `lossy_read` is `true`, [`read_or_new`] and [`read_or_default`] don't fail on a
+    /// malformed configuration file. Every value which can't be deserialized is replaced by its
+    /// counterpart in the default value, and a file which can't be parsed at all is replaced
+    /// by the default value as a whole. The configuration file itself is left untouched.
+    ///
+    /// [`read_or_new`]: crate::parser::ConfigFile::read_or_new
+    /// [`read_or_default`]: crate::parser::ConfigFile::read_or_default
+    pub lossy_read: bool,
+    /// Create a missing configuration file when it's read. Default: `false`.
+    ///
+    /// If `create_missing` is `true`, [`read`] creates a missing configuration file and its
+    /// directories, holding an empty document, instead of failing. The configuration structure
+    /// must then be deserializable from an empty document, e.g. with `#[serde(default)]`.
+    /// Read-only files are never created.
+    ///
+    /// [`read`]: crate::parser::ConfigFile::read
+    pub create_missing: bool,
+    /// Reject keys unknown to your configuration structure. Default: `false`.
+    ///
+    /// By default, `serde` silently ignores unknown keys, so a misspelled key is never noticed.
+    /// If `strict_keys` is `true`, reading a configuration containing unknown keys
+    /// fails with an [`UnknownKeys`] error instead.
+    /// Each unknown key comes with the closest known key, if any, as a "did you mean" suggestion.
+    ///
+    /// [`UnknownKeys`]: crate::ConrigError::UnknownKeys
+    pub strict_keys: bool,
+    /// Reject ambiguous configuration files. Default: `false`.
+    ///
+    /// If `error_on_ambiguity` is `true` and more than one configuration file is found,
+    /// the searching fails with an [`AmbiguousConfig`] error instead of picking the first one.
+    /// Every candidate counts, e.g. both `conrig.toml` and `.conrig.yaml` in the same directory,
+    /// or a system-level copy along with a local one: see [`search_all_config_files`].
+    /// A path given through the [`path_env_var`] is never ambiguous.
+    ///
+    /// [`AmbiguousConfig`]: crate::ConrigError::AmbiguousConfig
+    /// [`search_all_config_files`]: crate::ConfigPathMetadata::search_all_config_files
+    /// [`path_env_var`]: crate::ConfigOption#structfield.path_env_var
+    pub error_on_ambiguity: bool,
+    /// Forbid writing into the system-level configuration directory. Default: `false`.
+    ///
+    /// If `read_only_sys` is `true`, writing into a configuration file in the
+    /// [system-level configuration directory][sys] fails with a [`ReadOnly`] error.
+    ///
+    /// [sys]: crate::ProjectPath::sys_dir
+    /// [`ReadOnly`]: crate::ConrigError::ReadOnly
+    pub read_only_sys: bool,
+    /// Reject configuration files writable by other users. Default: `false`.
+    ///
+    /// If `check_permissions` is `true`, reading a configuration file writable by its group
+    /// or by anyone fails with an [`InsecurePermissions`] error.
+    /// This only takes effect on Unix.
+    ///
+    /// [`InsecurePermissions`]: crate::ConrigError::InsecurePermissions
+    pub check_permissions: bool,
+    /// The version of your application. Default: `None`.
+    ///
+    /// This is used to enforce the [`deprecations`], and to [stamp] configuration files.
+    ///
+    /// [`deprecations`]: crate::ConfigOption#structfield.deprecations
+    /// [stamp]: crate::ConfigOption#structfield.version_stamp
+    pub app_version: Option<&'static str>,
+    /// Deprecated configuration keys. Default: `&[]`.
+    ///
+    /// See [`deprecation`][crate::deprecation] for more information.
+    pub deprecations: &'static [Deprecation],
+    /// Stamp configuration files with the version of your application. Default: `false`.
+    ///
+    /// If `version_stamp` is `true` and the [`app_version`] is set, every write puts the version
+    /// in a header comment. Rewriting a file stamped by an older version lists the
+    /// [`additions`] since then in the header. See the [`upgrade`] module for more information.
+    ///
+    /// [`app_version`]: crate::ConfigOption#structfield.app_version
+    /// [`additions`]: crate::ConfigOption#structfield.additions
+    /// [`upgrade`]: crate::upgrade
+    pub version_stamp: bool,
+    /// Configuration keys added by the versions of your application. Default: `&[]`.
+    ///
+    /// See [`upgrade`][crate::upgrade] for more information.
+    pub additions: &'static [Addition],
+    /// The key listing the files included by a configuration file. Default: `None`.
+    ///
+    /// If this is set, e.g. to `"include"`, a configuration file can reference other files
+    /// with `include = ["other.toml"]`. The paths are relative to the including file,
+    /// and their format is detected from their extension.
+    /// The included files are merged in order, then the including file is merged over them,
+    /// before the configuration is deserialized. Included files can include other files,
+    /// but a file including itself fails with an [`IncludeCycle`] error.
+    ///
+    /// [`IncludeCycle`]: crate::ConrigError::IncludeCycle
+    pub include_key: Option<&'static str>,
+    /// The key naming the configuration extended by a configuration file. Default: `None`.
+    ///
+    /// If this is set, e.g. to `"extends"`, a configuration file can inherit another one with
+    /// `extends = "base.toml"`, or with `extends = "recommended"` one of the [`presets`]
+    /// shipped with your application, or [registered] at runtime. Paths are relative to the extending file. The extended
+    /// configurations are merged in order, then the extending file is merged over them, like
+    /// [included files][include_key]. Extended configurations can extend other ones, but
+    /// a cycle fails with an [`IncludeCycle`] error.
+    ///
+    /// [`presets`]: crate::ConfigOption#structfield.presets
+    /// [registered]: crate::preset::register_preset
+    /// [include_key]: crate::ConfigOption#structfield.include_key
+    /// [`IncludeCycle`]: crate::ConrigError::IncludeCycle
+    pub extends_key: Option<&'static str>,
+    /// The named configurations shipped with your application, e.g. `recommended`.
+    /// Default: `&[]`.
+    ///
+    /// See [`extends_key`] for more information.
+    ///
+    /// [`extends_key`]: crate::ConfigOption#structfield.extends_key
+    pub presets: &'static [(&'static str, Embedded<'static>)],
+    /// The environment variable selecting the profile, e.g. `APP_ENV`. Default: `None`.
+    ///
+    /// If this is set and the variable holds a profile name, e.g. `production`,
+    /// the profile file next to the configuration file, e.g. `conrig.production.toml`,
+    /// is merged over it when it's read. So one metadata definition covers all environments.
+    /// The profile file can be written in any format.
+    pub profile_env: Option<&'static str>,
+    /// Merge the drop-in fragments of configuration files. Default: `false`.
+    ///
+    /// If `drop_ins` is `true` and a `<file>.d` directory exists next to a configuration file,
+    /// e.g. `conrig.toml.d/`, the files it contains are merged over the configuration file,
+    /// in lexical order, when it's read. So administrators can compose a configuration from
+    /// several fragments, e.g. `10-network.toml` and `20-logging.toml`.
+    pub drop_ins: bool,
+    /// The key of the table holding the named variants, e.g. `variant`. Default: `None`.
+    ///
+    /// If this is set, a configuration file can define variants of itself, e.g. `[variant.fast]`
+    /// and `[variant.safe]`, for experiments or "safe mode" launches. The [selected] variant is
+    /// merged over the rest of the configuration when it's read, once the included files, the
+    /// drop-in fragments and the profile file are merged. The variants table itself is always
+    /// dropped before the configuration is deserialized.
+    ///
+    /// An [`UnknownVariant`] error is returned if no variant has the selected name.
+    ///
+    /// [selected]: crate::ConfigOption::variant
+    /// [`UnknownVariant`]: crate::ConrigError::UnknownVariant
+    pub variant_key: Option<&'static str>,
+    /// The environment variable selecting the variant, e.g. `APP_VARIANT`. Default: `None`.
+    ///
+    /// See [`variant_key`] for more information.
+    ///
+    /// [`variant_key`]: crate::ConfigOption#structfield.variant_key
+    pub variant_env: Option<&'static str>,
+    /// The name of the variant selected explicitly, overriding the [`variant_env`] variable.
+    /// Default: `None`.
+    ///
+    /// See [`variant_key`] for more information.
+    ///
+    /// [`variant_env`]: crate::ConfigOption#structfield.variant_env
+    /// [`variant_key`]: crate::ConfigOption#structfield.variant_key
+    pub variant: Option<&'static str>,
+    /// The identity keys of the arrays of tables, e.g. `&[("servers", "name")]`. Default: `&[]`.
+    ///
+    /// When configuration files are merged, e.g. by [`read_merged`], the included files or the
+    /// profile file, the elements of these arrays are matched by their identity key, so an
+    /// overlay can modify a single element instead of replacing the whole array.
+    /// See [`Value::merge_keyed`] for more information.
+    ///
+    /// [`read_merged`]: crate::ConfigPathMetadata::read_merged
+    /// [`Value::merge_keyed`]: crate::value::Value::merge_keyed
+    pub merge_keys: &'static [(&'static str, &'static str)],
+    /// The file name of the script post-processing the configuration, e.g. `conrig.rhai`.
+    /// Default: `None`.
+    ///
+    /// If this is set and the script exists next to the configuration file, the document read
+    /// is passed through the registered [`ScriptEngine`] before being deserialized, so power
+    /// users can compute derived keys or apply conditional tweaks. The file itself is left
+    /// untouched. See the [`script`][crate::script] module for more information.
+    ///
+    /// [`ScriptEngine`]: crate::script::ScriptEngine
+    pub script: Option<&'static str>,
+    /// The duration above which a file system operation is reported as slow. Default: `None`.
+    ///
+    /// If this is set, every file searched, read or written taking longer than the threshold
+    /// triggers a [`SlowIo`] warning, which helps diagnosing configurations living on slow
+    /// network shares.
+    ///
+    /// [`SlowIo`]: crate::ConrigWarning::SlowIo
+    pub slow_io_threshold: Option<Duration>,
+    /// The size in bytes from which configuration files are read by memory mapping.
+    /// Default: `None`.
+    ///
+    /// Large state files, in a text format or binary property lists, are then parsed in place,
+    /// instead of being copied into memory first. Mappings are only supported on Unix platforms, and not through a
+    /// custom [file system](crate::filesystem): other files are read as usual.
+    ///
+    /// **Note**: mapped files must not be modified while they're read, so a threshold can only
+    /// be created by the unsafe [`MmapThreshold::new`].
+    ///
+    /// [`MmapThreshold::new`]: crate::mmap::MmapThreshold::new
+    #[cfg(feature = "mmap")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "mmap")))]
+    pub mmap_threshold: Option<MmapThreshold>,
+    /// The key path patterns of the sensitive values, e.g. `*.password`. Default: `&[]`.
+    ///
+    /// Values matching any of them are masked by [`redact`] before being displayed or exported.
+    /// See the [`redact`][crate::redact] module for the pattern syntax.
+    ///
+    /// [`redact`]: crate::ConfigOption::redact
+    pub redactions: &'static [&'static str],
+    /// The function receiving the warnings triggered by `conrig`. Default: `None`.
+    ///
+    /// If this is `None`, warnings are silently ignored.
+    pub warning_handler: Option<fn(&ConrigWarning)>,
+}
+
+/// The directory used to store configuration files in system-level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigType {
+    /// Save the files in the preference directory.
+    ///
+    /// See [`directories::ProjectDirs::preference_dir`] for more information.
+    Preference,
+    /// Save the files in the config directory.
+    ///
+    /// See [`directories::ProjectDirs::config_dir`] for more information.
+    Config,
+    /// Save the files in the data directory.
+    ///
+    /// See [`directories::ProjectDirs::data_dir`] for more information.
+    Data,
+    /// Save the files in the cache directory.
+    ///
+    /// See [`directories::ProjectDirs::cache_dir`] for more information.
+    Cache,
+    /// Save the files in the state directory. Only available on Linux.
+    ///
+    /// See [`directories::ProjectDirs::state_dir`] for more information.
+    State,
+    /// Save the files as dotfiles in the home directory, e.g. `~/.conrig.toml`,
+    /// following the traditional Unix convention. `~/.conrigrc` files are searched as well,
+    /// and read in the [default format][default].
+    ///
+    /// See [`ProjectPath::home_dir`] for more information.
+    ///
+    /// [default]: crate::ConfigPathMetadata#structfield.default_format
+    Home,
+    /// Save the files next to the executable, e.g. for an application shipped on a USB stick.
+    ///
+    /// See [`ProjectPath::portable_dir`] for more information.
+    Portable,
+    /// Search both the preference and the config directories, which differ on macOS,
+    /// in the order of the platform's convention: see [`ConfigType::auto_candidates`].
+    ///
+    /// The directory in use is the first one containing a configuration file, or the
+    /// conventional one if none does. See [`ConfigPathMetadata::sys_type`] for more information.
+    Auto,
+}
+
+impl ConfigType {
+    /// The types searched by [`Auto`], in the order of the platform's convention:
+    /// [`Preference`] first on macOS, [`Config`] first elsewhere.
+    ///
+    /// [`Auto`]: crate::ConfigType::Auto
+    /// [`Preference`]: crate::ConfigType::Preference
+    /// [`Config`]: crate::ConfigType::Config
+    pub const fn auto_candidates() -> [ConfigType; 2] {
+        if cfg!(target_os = "macos") {
+            [ConfigType::Preference, ConfigType::Config]
+        } else {
+            [ConfigType::Config, ConfigType::Preference]
+        }
+    }
+}
+
+/// The `AppData` folder holding the system-level directory on Windows.
+///
+/// See [`ConfigOption.app_data`] for more information.
+///
+/// [`ConfigOption.app_data`]: crate::ConfigOption#structfield.app_data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AppData {
+    /// `%APPDATA%`, e.g. `C:\Users\<user>\AppData\Roaming`, which follows the user across the
+    /// machines of a domain. This is the choice of [`directories`].
+    #[default]
+    Roaming,
+    /// `%LOCALAPPDATA%`, e.g. `C:\Users\<user>\AppData\Local`, for large or machine-specific
+    /// settings.
+    Local,
+    /// `AppData\LocalLow`, next to `%LOCALAPPDATA%`, for applications running with a low
+    /// integrity level, e.g. sandboxed ones.
+    LocalLow,
+}
+
+/// A location searched for configuration files.
+///
+/// See [`SearchOrder`] for more information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SearchLocation {
+    /// The [`extra_files`] of the metadata.
+    ///
+    /// [`extra_files`]: crate::ConfigPathMetadata#structfield.extra_files
+    ExtraFiles,
+    /// The [`extra_folders`] of the metadata.
+    ///
+    /// [`extra_folders`]: crate::ConfigPathMetadata#structfield.extra_folders
+    ExtraFolders,
+    /// The current directory, or the [`local_dir`] if it's set, and its ancestors if
+    /// [`search_ancestors`] is enabled.
+    ///
+    /// If the current directory is unavailable, e.g. it has been deleted, this is skipped with
+    /// a [`SkippedLocation`] warning.
+    ///
+    /// [`local_dir`]: crate::ConfigPathMetadata#structfield.local_dir
+    /// [`search_ancestors`]: crate::ConfigOption#structfield.search_ancestors
+    /// [`SkippedLocation`]: crate::ConrigWarning::SkippedLocation
+    Local,
+    /// The [system-level directory][sys], selected by [`config_sys_type`].
+    ///
+    /// [sys]: crate::ProjectPath::sys_dir
+    /// [`config_sys_type`]: crate::ConfigOption#structfield.config_sys_type
+    System,
+    /// The [machine-wide directory][machine], if [`system_wide`] is enabled.
+    ///
+    /// [machine]: crate::ProjectPath::machine_dir
+    /// [`system_wide`]: crate::ConfigOption#structfield.system_wide
+    Machine,
+}
+
+/// The order in which the locations are searched for configuration files,
+/// from the highest precedence.
+///
+/// The path held by the [`ConfigOption.path_env_var`] variable, if any, always comes first.
+///
+/// ## Example
+///
+/// ```rust
+/// use conrig::{ConfigOption, SearchLocation, SearchOrder};
+///
+/// // only search the system-level directory, then the extra folders.
+/// const OPTION: ConfigOption = ConfigOption::DEFAULT_CONFIG.with_search_order(SearchOrder(&[
+///     SearchLocation::System,
+///     SearchLocation::ExtraFolders,
+/// ]));
+/// ```
+///
+/// [`ConfigOption.path_env_var`]: crate::ConfigOption#structfield.path_env_var
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SearchOrder(pub &'static [SearchLocation]);
+
+impl SearchOrder {
+    /// The default order: the extra files and folders, then the local directory
+    /// before the system-level one, and finally the machine-wide one.
+    pub const LOCAL_FIRST: SearchOrder = SearchOrder(&[
+        SearchLocation::ExtraFiles,
+        SearchLocation::ExtraFolders,
+        SearchLocation::Local,
+        SearchLocation::System,
+        SearchLocation::Machine,
+    ]);
+    /// The order used if [`sys_override_local`] is enabled: like [`LOCAL_FIRST`], but the
+    /// system-level directory comes before the local one.
+    ///
+    /// [`sys_override_local`]: crate::ConfigOption#structfield.sys_override_local
+    /// [`LOCAL_FIRST`]: crate::SearchOrder::LOCAL_FIRST
+    pub const SYSTEM_FIRST: SearchOrder = SearchOrder(&[
+        SearchLocation::ExtraFiles,
+        SearchLocation::ExtraFolders,
+        SearchLocation::System,
+        SearchLocation::Local,
+        SearchLocation::Machine,
+    ]);
+
+    /// The locations, from the highest precedence.
+    pub const fn locations(&self) -> &'static [SearchLocation] {
+        self.0
+    }
+}
+
+impl ConfigOption {
+    /// Default `ConfigOption` value.
+    pub const DEFAULT_CONFIG: ConfigOption = ConfigOption {
+        allow_dot_prefix: true,
+        sys_override_local: false,
+        search_order: None,
+        config_sys_type: ConfigType::Config,
+        app_data: AppData::Roaming,
+        search_ancestors: false,
+        ancestor_marker: None,
+        system_wide: false,
+        policy: false,
+        path_env_var: None,
+        extensions: &[],
+        atomic_write: true,
+        sync_write: false,
+        backup: 0,
+        checksum: false,
+        keep_comments: false,
+        repair_missing: false,
+        lossy_read: false,
+        create_missing: false,
+        strict_keys: false,
+        error_on_ambiguity: false,
+        read_only_sys: false,
+        check_permissions: false,
+        app_version: None,
+        deprecations: &[],
+        version_stamp: false,
+        additions: &[],
+        include_key: None,
+        extends_key: None,
+        presets: &[],
+        profile_env: None,
+        drop_ins: false,
+        variant_key: None,
+        variant_env: None,
+        variant: None,
+        merge_keys: &[],
+        script: None,
+        slow_io_threshold: None,
+        #[cfg(feature = "mmap")]
+        mmap_threshold: None,
+        redactions: &[],
+        warning_handler: None,
+    };
+
+    /// A strict preset, for the users who want maximum safety.
+    ///
+    /// Based on [`DEFAULT_CONFIG`], this enables:
+    /// - [`strict_keys`];
+    /// - [`error_on_ambiguity`];
+    /// - [`read_only_sys`];
+    /// - [`atomic_write`] and [`sync_write`];
+    /// - [`check_permissions`].
+    ///
+    /// [`DEFAULT_CONFIG`]: crate::ConfigOption::DEFAULT_CONFIG
+    /// [`strict_keys`]: crate::ConfigOption#structfield.strict_keys
+    /// [`error_on_ambiguity`]: crate::ConfigOption#structfield.error_on_ambiguity
+    /// [`read_only_sys`]: crate::ConfigOption#structfield.read_only_sys
+    /// [`atomic_write`]: crate::ConfigOption#structfield.atomic_write
+    /// [`sync_write`]: crate::ConfigOption#structfield.sync_write
+    /// [`check_permissions`]: crate::ConfigOption#structfield.check_permissions
+    pub const STRICT: ConfigOption = ConfigOption {
+        strict_keys: true,
+        error_on_ambiguity: true,
+        read_only_sys: true,
+        atomic_write: true,
+        sync_write: true,
+        check_permissions: true,
+        ..Self::DEFAULT_CONFIG
+    };
+
+    /// A relaxed preset, for prototypes and internal tools.
+    ///
+    /// Based on [`DEFAULT_CONFIG`], this enables [`repair_missing`] and [`lossy_read`],
+    /// so that [`read_or_default`] keeps working as the configuration structure evolves,
+    /// and [`create_missing`], so that even [`read`] creates the missing files and directories.
+    ///
+    /// [`DEFAULT_CONFIG`]: crate::ConfigOption::DEFAULT_CONFIG
+    /// [`repair_missing`]: crate::ConfigOption#structfield.repair_missing
+    /// [`lossy_read`]: crate::ConfigOption#structfield.lossy_read
+    /// [`create_missing`]: crate::ConfigOption#structfield.create_missing
+    /// [`read_or_default`]: crate::ConfigPathMetadata::read_or_default
+    /// [`read`]: crate::ConfigPathMetadata::read
+    pub const RELAXED: ConfigOption = ConfigOption {
+        repair_missing: true,
+        lossy_read: true,
+        create_missing: true,
+        ..Self::DEFAULT_CONFIG
+    };
+
+    /// Modify the [`allow_dot_prefix`] field.
+    ///
+    /// [`allow_dot_prefix`]: crate::ConfigOption#structfield.allow_dot_prefix
+    pub const fn with_allow_dot_prefix(mut self, allow_dot_prefix: bool) -> Self {
+        self.allow_dot_prefix = allow_dot_prefix;
+        self
+    }
+
+    /// Modify the [`sys_override_local`] field.
+    ///
+    /// [`sys_override_local`]: crate::ConfigOption#structfield.sys_override_local
+    pub const fn with_sys_override_local(mut self, sys_override_local: bool) -> Self {
+        self.sys_override_local = sys_override_local;
+        self
+    }
+
+    /// Modify the [`search_order`] field.
+    ///
+    /// [`search_order`]: crate::ConfigOption#structfield.search_order
+    pub const fn with_search_order(mut self, search_order: SearchOrder) -> Self {
+        self.search_order = Some(search_order);
+        self
+    }
+
+    /// The effective [`search_order`], taking [`sys_override_local`] into account.
+    ///
+    /// [`search_order`]: crate::ConfigOption#structfield.search_order
+    /// [`sys_override_local`]: crate::ConfigOption#structfield.sys_override_local
+    pub const fn effective_search_order(&self) -> SearchOrder {
+        match self.search_order {
+            Some(search_order) => search_order,
+            None if self.sys_override_local => SearchOrder::SYSTEM_FIRST,
+            None => SearchOrder::LOCAL_FIRST,
+        }
+    }
+
+    /// Modify the [`config_sys_type`] field.
+    ///
+    /// [`config_sys_type`]: crate::ConfigOption#structfield.config_sys_type
+    pub const fn with_config_sys_type(mut self, config_sys_type: ConfigType) -> Self {
+        self.config_sys_type = config_sys_type;
+        self
+    }
+
+    /// Modify the [`app_data`] field.
+    ///
+    /// [`app_data`]: crate::ConfigOption#structfield.app_data
+    pub const fn with_app_data(mut self, app_data: AppData) -> Self {
+        self.app_data = app_data;
+        self
+    }
+
+    /// Modify the [`search_ancestors`] field.
+    ///
+    /// [`search_ancestors`]: crate::ConfigOption#structfield.search_ancestors
+    pub const fn with_search_ancestors(mut self, search_ancestors: bool) -> Self {
+        self.search_ancestors = search_ancestors;
+        self
+    }
+
+    /// Modify the [`ancestor_marker`] field.
+    ///
+    /// [`ancestor_marker`]: crate::ConfigOption#structfield.ancestor_marker
+    pub const fn with_ancestor_marker(mut self, ancestor_marker: &'static str) -> Self {
+        self.ancestor_marker = Some(ancestor_marker);
+        self
+    }
+
+    /// Modify the [`system_wide`] field.
+    ///
+    /// [`system_wide`]: crate::ConfigOption#structfield.system_wide
+    pub const fn with_system_wide(mut self, system_wide: bool) -> Self {
+        self.system_wide = system_wide;
+        self
+    }
+
+    /// Modify the [`policy`] field.
+    ///
+    /// [`policy`]: crate::ConfigOption#structfield.policy
+    pub const fn with_policy(mut self, policy: bool) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Modify the [`path_env_var`] field.
+    ///
+    /// [`path_env_var`]: crate::ConfigOption#structfield.path_env_var
+    pub const fn with_path_env_var(mut self, path_env_var: &'static str) -> Self {
+        self.path_env_var = Some(path_env_var);
+        self
+    }
+
+    /// Modify the [`extensions`] field.
+    ///
+    /// [`extensions`]: crate::ConfigOption#structfield.extensions
+    pub const fn with_extensions(
+        mut self,
+        extensions: &'static [(&'static str, FileFormat)],
+    ) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// The format of a file, taken from its extension, if it's known.
+    ///
+    /// The custom [`extensions`] are looked up first, then the built-in ones.
+    ///
+    /// [`extensions`]: crate::ConfigOption#structfield.extensions
+    pub fn format_of(&self, path: &Path) -> Option<FileFormat> {
+        let ext = path.extension().map_or("".into(), |ext| ext.to_string_lossy());
+        self.extensions
+            .iter()
+            .find(|(custom, _)| *custom == ext)
+            .map(|(_, file_format)| *file_format)
+            .or_else(|| FileFormat::from_extension(&ext))
+    }
+
+    /// Modify the [`atomic_write`] field.
+    ///
+    /// [`atomic_write`]: crate::ConfigOption#structfield.atomic_write
+    pub const fn with_atomic_write(mut self, atomic_write: bool) -> Self {
+        self.atomic_write = atomic_write;
+        self
+    }
+
+    /// Modify the [`sync_write`] field.
+    ///
+    /// [`sync_write`]: crate::ConfigOption#structfield.sync_write
+    pub const fn with_sync_write(mut self, sync_write: bool) -> Self {
+        self.sync_write = sync_write;
+        self
+    }
+
+    /// Modify the [`backup`] field.
+    ///
+    /// [`backup`]: crate::ConfigOption#structfield.backup
+    pub const fn with_backup(mut self, backup: usize) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Modify the [`checksum`] field.
+    ///
+    /// [`checksum`]: crate::ConfigOption#structfield.checksum
+    pub const fn with_checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Modify the [`keep_comments`] field.
+    ///
+    /// [`keep_comments`]: crate::ConfigOption#structfield.keep_comments
+    pub const fn with_keep_comments(mut self, keep_comments: bool) -> Self {
+        self.keep_comments = keep_comments;
+        self
+    }
+
+    /// Modify the [`repair_missing`] field.
+    ///
+    /// [`repair_missing`]: crate::ConfigOption#structfield.repair_missing
+    pub const fn with_repair_missing(mut self, repair_missing: bool) -> Self {
+        self.repair_missing = repair_missing;
+        self
+    }
+
+    /// Modify the [`lossy_read`] field.
+    ///
+    /// [`lossy_read`]: crate::ConfigOption#structfield.lossy_read
+    pub const fn with_lossy_read(mut self, lossy_read: bool) -> Self {
+        self.lossy_read = lossy_read;
+        self
+    }
+
+    /// Modify the [`create_missing`] field.
+    ///
+    /// [`create_missing`]: crate::ConfigOption#structfield.create_missing
+    pub const fn with_create_missing(mut self, create_missing: bool) -> Self {
+        self.create_missing = create_missing;
+        self
+    }
+
+    /// Modify the [`strict_keys`] field.
+    ///
+    /// [`strict_keys`]: crate::ConfigOption#structfield.strict_keys
+    pub const fn with_strict_keys(mut self, strict_keys: bool) -> Self {
+        self.strict_keys = strict_keys;
+        self
+    }
+
+    /// Modify the [`error_on_ambiguity`] field.
+    ///
+    /// [`error_on_ambiguity`]: crate::ConfigOption#structfield.error_on_ambiguity
+    pub const fn with_error_on_ambiguity(mut self, error_on_ambiguity: bool) -> Self {
+        self.error_on_ambiguity = error_on_ambiguity;
+        self
+    }
+
+    /// Modify the [`read_only_sys`] field.
+    ///
+    /// [`read_only_sys`]: crate::ConfigOption#structfield.read_only_sys
+    pub const fn with_read_only_sys(mut self, read_only_sys: bool) -> Self {
+        self.read_only_sys = read_only_sys;
+        self
+    }
+
+    /// Modify the [`check_permissions`] field.
+    ///
+    /// [`check_permissions`]: crate::ConfigOption#structfield.check_permissions
+    pub const fn with_check_permissions(mut self, check_permissions: bool) -> Self {
+        self.check_permissions = check_permissions;
+        self
+    }
+
+    /// Modify the [`app_version`] field.
+    ///
+    /// [`app_version`]: crate::ConfigOption#structfield.app_version
+    pub const fn with_app_version(mut self, app_version: &'static str) -> Self {
+        self.app_version = Some(app_version);
+        self
+    }
+
+    /// Modify the [`deprecations`] field.
+    ///
+    /// [`deprecations`]: crate::ConfigOption#structfield.deprecations
+    pub const fn with_deprecations(mut self, deprecations: &'static [Deprecation]) -> Self {
+        self.deprecations = deprecations;
+        self
+    }
+
+    /// Modify the [`version_stamp`] field.
+    ///
+    /// [`version_stamp`]: crate::ConfigOption#structfield.version_stamp
+    pub const fn with_version_stamp(mut self, version_stamp: bool) -> Self {
+        self.version_stamp = version_stamp;
+        self
+    }
+
+    /// Modify the [`additions`] field.
+    ///
+    /// [`additions`]: crate::ConfigOption#structfield.additions
+    pub const fn with_additions(mut self, additions: &'static [Addition]) -> Self {
+        self.additions = additions;
+        self
+    }
+
+    /// Modify the [`include_key`] field.
+    ///
+    /// [`include_key`]: crate::ConfigOption#structfield.include_key
+    pub const fn with_include_key(mut self, include_key: &'static str) -> Self {
+        self.include_key = Some(include_key);
+        self
+    }
+
+    /// Modify the [`extends_key`] field.
+    ///
+    /// [`extends_key`]: crate::ConfigOption#structfield.extends_key
+    pub const fn with_extends_key(mut self, extends_key: &'static str) -> Self {
+        self.extends_key = Some(extends_key);
+        self
+    }
+
+    /// Modify the [`presets`] field.
+    ///
+    /// [`presets`]: crate::ConfigOption#structfield.presets
+    pub const fn with_presets(
+        mut self,
+        presets: &'static [(&'static str, Embedded<'static>)],
+    ) -> Self {
+        self.presets = presets;
+        self
+    }
+
+    /// Modify the [`profile_env`] field.
+    ///
+    /// [`profile_env`]: crate::ConfigOption#structfield.profile_env
+    pub const fn with_profile_env(mut self, profile_env: &'static str) -> Self {
+        self.profile_env = Some(profile_env);
+        self
+    }
+
+    /// Modify the [`drop_ins`] field.
+    ///
+    /// [`drop_ins`]: crate::ConfigOption#structfield.drop_ins
+    pub const fn with_drop_ins(mut self, drop_ins: bool) -> Self {
+        self.drop_ins = drop_ins;
+        self
+    }
+
+    /// Modify the [`variant_key`] field.
+    ///
+    /// [`variant_key`]: crate::ConfigOption#structfield.variant_key
+    pub const fn with_variant_key(mut self, variant_key: &'static str) -> Self {
+        self.variant_key = Some(variant_key);
+        self
+    }
+
+    /// Modify the [`variant_env`] field.
+    ///
+    /// [`variant_env`]: crate::ConfigOption#structfield.variant_env
+    pub const fn with_variant_env(mut self, variant_env: &'static str) -> Self {
+        self.variant_env = Some(variant_env);
+        self
+    }
+
+    /// Modify the [`variant`] field.
+    ///
+    /// [`variant`]: crate::ConfigOption#structfield.variant
+    pub const fn with_variant(mut self, variant: &'static str) -> Self {
+        self.variant = Some(variant);
+        self
+    }
+
+    /// Modify the [`merge_keys`] field.
+    ///
+    /// [`merge_keys`]: crate::ConfigOption#structfield.merge_keys
+    pub const fn with_merge_keys(
+        mut self,
+        merge_keys: &'static [(&'static str, &'static str)],
+    ) -> Self {
+        self.merge_keys = merge_keys;
+        self
+    }
+
+    /// Modify the [`script`] field.
+    ///
+    /// [`script`]: crate::ConfigOption#structfield.script
+    pub const fn with_script(mut self, script: &'static str) -> Self {
+        self.script = Some(script);
+        self
+    }
+
+    /// Modify the [`slow_io_threshold`] field.
+    ///
+    /// [`slow_io_threshold`]: crate::ConfigOption#structfield.slow_io_threshold
+    pub const fn with_slow_io_threshold(mut self, slow_io_threshold: Duration) -> Self {
+        self.slow_io_threshold = Some(slow_io_threshold);
+        self
+    }
+
+    /// Modify the [`mmap_threshold`] field.
+    ///
+    /// [`mmap_threshold`]: crate::ConfigOption#structfield.mmap_threshold
+    #[cfg(feature = "mmap")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "mmap")))]
+    pub const fn with_mmap_threshold(mut self, mmap_threshold: MmapThreshold) -> Self {
+        self.mmap_threshold = Some(mmap_threshold);
+        self
+    }
+
+    /// Modify the [`redactions`] field.
+    ///
+    /// [`redactions`]: crate::ConfigOption#structfield.redactions
+    pub const fn with_redactions(mut self, redactions: &'static [&'static str]) -> Self {
+        self.redactions = redactions;
+        self
+    }
+
+    /// Modify the [`warning_handler`] field.
+    ///
+    /// [`warning_handler`]: crate::ConfigOption#structfield.warning_handler
+    pub const fn with_warning_handler(mut self, warning_handler: fn(&ConrigWarning)) -> Self {
+        self.warning_handler = Some(warning_handler);
+        self
+    }
+
+    /// The active profile, read from the [`profile_env`] variable.
+    ///
+    /// Returns `None` if no variable is specified, or if it's unset or empty.
+    ///
+    /// [`profile_env`]: crate::ConfigOption#structfield.profile_env
+    pub fn profile(&self) -> Option<String> {
+        std::env::var(self.profile_env?)
+            .ok()
+            .filter(|profile| !profile.is_empty())
+    }
+
+    /// The selected variant: the [`variant`] field if it's set, or the [`variant_env`] variable.
+    ///
+    /// Returns `None` if no variant is selected, or if the variable is unset or empty.
+    ///
+    /// [`variant`]: crate::ConfigOption#structfield.variant
+    /// [`variant_env`]: crate::ConfigOption#structfield.variant_env
+    pub fn variant(&self) -> Option<String> {
+        match self.variant {
+            Some(variant) => Some(variant.to_owned()),
+            None => std::env::var(self.variant_env?)
+                .ok()
+                .filter(|variant| !variant.is_empty()),
+        }
+    }
+
+    /// Mask the values matching the [`redactions`] rules.
+    ///
+    /// Returns the number of masked values. See [`redact::redact`] for more information.
+    ///
+    /// [`redactions`]: crate::ConfigOption#structfield.redactions
+    pub fn redact(&self, value: &mut Value) -> usize {
+        redact::redact(value, self.redactions)
+    }
+
+    /// The configuration file path, read from the [`path_env_var`] variable.
+    ///
+    /// Returns `None` if no variable is specified, or if it's unset or empty.
+    ///
+    /// [`path_env_var`]: crate::ConfigOption#structfield.path_env_var
+    pub fn path_override(&self) -> Option<PathBuf> {
+        std::env::var(self.path_env_var?)
+            .ok()
+            .filter(|path| !path.is_empty())
+            .and_then(|path| expand_home(&path))
+    }
+
+    /// Report a warning to the [`warning_handler`].
+    ///
+    /// [`warning_handler`]: crate::ConfigOption#structfield.warning_handler
+    pub(crate) fn warn(&self, warning: ConrigWarning) {
+        if let Some(warning_handler) = self.warning_handler {
+            warning_handler(&warning);
+        }
+    }
+
+    /// Run a file system operation, reporting it if it exceeds the [`slow_io_threshold`].
+    ///
+    /// [`slow_io_threshold`]: crate::ConfigOption#structfield.slow_io_threshold
+    pub(crate) fn timed<R>(&self, operation: Operation, path: &Path, f: impl FnOnce() -> R) -> R {
+        let Some(threshold) = self.slow_io_threshold else {
+            return f();
+        };
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        if elapsed > threshold {
+            self.warn(ConrigWarning::SlowIo {
+                operation,
+                path: path.to_path_buf(),
+                elapsed,
+            });
+        }
+        result
+    }
+}
+
+/// Your application's metadata.
+///
+/// This is mainly used to figure out the system-level storage directory for your application.
+/// With the `testing` feature, the directories are redirected into the `testing::Sandbox`
+/// of the current thread, if any.
+///
+/// See [`directories::ProjectDirs`] for more information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectPath<'a> {
+    /// The qualifier of your application.
+    ///
+    /// E.g. `com` in `com.GitHub.application`.
+    pub qualifier: &'a str,
+    /// The organization name of your application.
+    ///
+    /// E.g. `GitHub` in `com.GitHub.application`.
+    pub organization: &'a str,
+    /// Your application's name.
+    ///
+    /// E.g. `application` in `com.GitHub.application`.
+    pub application: &'a str,
+}
+
+impl<'a> ProjectPath<'a> {
+    /// Create a new `ProjectPath`.
+    pub const fn new(qualifier: &'a str, organization: &'a str, application: &'a str) -> Self {
+        Self {
+            qualifier,
+            organization,
+            application,
+        }
+    }
+
+    /// Modify the [`qualifier`] field.
+    ///
+    /// [`qualifier`]: crate::ProjectPath#structfield.qualifier
+    pub const fn with_qualifier(mut self, qualifier: &'a str) -> Self {
+        self.qualifier = qualifier;
+        self
+    }
+
+    /// Modify the [`organization`] field.
+    ///
+    /// [`organization`]: crate::ProjectPath#structfield.organization
+    pub const fn with_organization(mut self, organization: &'a str) -> Self {
+        self.organization = organization;
+        self
     }
 
     /// Modify the [`application`] field.
@@ -405,6 +2379,9 @@ impl<'a> ProjectPath<'a> {
     ///
     /// See [`directories::ProjectDirs::config_dir`] for more information.
     pub fn sys_config_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = self.sandbox_dir("config") {
+            return Some(dir);
+        }
         Some(
             ProjectDirs::from(self.qualifier, self.organization, self.application)?
                 .config_dir()
@@ -416,6 +2393,9 @@ impl<'a> ProjectPath<'a> {
     ///
     /// See [`directories::ProjectDirs::preference_dir`] for more information.
     pub fn sys_preference_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = self.sandbox_dir("preference") {
+            return Some(dir);
+        }
         Some(
             ProjectDirs::from(self.qualifier, self.organization, self.application)?
                 .preference_dir()
@@ -423,21 +2403,149 @@ impl<'a> ProjectPath<'a> {
         )
     }
 
+    /// Get the data directory of your application.
+    ///
+    /// See [`directories::ProjectDirs::data_dir`] for more information.
+    pub fn sys_data_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = self.sandbox_dir("data") {
+            return Some(dir);
+        }
+        Some(
+            ProjectDirs::from(self.qualifier, self.organization, self.application)?
+                .data_dir()
+                .into(),
+        )
+    }
+
+    /// Get the cache directory of your application.
+    ///
+    /// See [`directories::ProjectDirs::cache_dir`] for more information.
+    pub fn sys_cache_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = self.sandbox_dir("cache") {
+            return Some(dir);
+        }
+        Some(
+            ProjectDirs::from(self.qualifier, self.organization, self.application)?
+                .cache_dir()
+                .into(),
+        )
+    }
+
+    /// Get the state directory of your application. Only available on Linux.
+    ///
+    /// See [`directories::ProjectDirs::state_dir`] for more information.
+    pub fn sys_state_dir(&self) -> Option<PathBuf> {
+        if let Some(dir) = self.sandbox_dir("state") {
+            return Some(dir);
+        }
+        Some(
+            ProjectDirs::from(self.qualifier, self.organization, self.application)?
+                .state_dir()?
+                .into(),
+        )
+    }
+
+    /// Get the home directory of the current user.
+    ///
+    /// See [`directories::BaseDirs::home_dir`] for more information.
+    pub fn home_dir(&self) -> Option<PathBuf> {
+        if let Some(root) = sandbox_root() {
+            return Some(root.join("home"));
+        }
+        Some(BaseDirs::new()?.home_dir().into())
+    }
+
+    /// Get the machine-wide configuration directory of your application,
+    /// managed by the administrators.
+    ///
+    /// This is `/etc/<application>` on Unix, and `%PROGRAMDATA%\<organization>\<application>`
+    /// on Windows.
+    pub fn machine_dir(&self) -> Option<PathBuf> {
+        if let Some(root) = sandbox_root() {
+            return Some(root.join("machine").join(self.application));
+        }
+        if cfg!(windows) {
+            let program_data = std::env::var_os("PROGRAMDATA")?;
+            Some(
+                PathBuf::from(program_data)
+                    .join(self.organization)
+                    .join(self.application),
+            )
+        } else if cfg!(unix) {
+            Some(Path::new("/etc").join(self.application))
+        } else {
+            None
+        }
+    }
+
+    /// Get the directory containing the executable of your application.
+    ///
+    /// See [`std::env::current_exe`] for more information.
+    pub fn portable_dir(&self) -> Option<PathBuf> {
+        if let Some(root) = sandbox_root() {
+            return Some(root.join("portable"));
+        }
+        Some(std::env::current_exe().ok()?.parent()?.to_path_buf())
+    }
+
+    /// The system-level directory of a type replacing the real one in the sandbox of the
+    /// current thread, if any.
+    fn sandbox_dir(&self, config_type: &str) -> Option<PathBuf> {
+        Some(
+            sandbox_root()?
+                .join("sys")
+                .join(config_type)
+                .join(self.application),
+        )
+    }
+
+    /// Get the registry key of your application, relative to `HKEY_CURRENT_USER`.
+    ///
+    /// This is `Software\<organization>\<application>`, used by the `registry` module
+    /// with the `windows-registry` feature on Windows.
+    pub fn registry_path(&self) -> String {
+        format!("Software\\{}\\{}", self.organization, self.application)
+    }
+
     /// Get the system-level config directory of your application.
     ///
     /// Depends on [`ConfigOption.config_sys_type`]:
     /// - [`Preference`][pref]: [`sys_preference_dir`].
     /// - [`Config`][config]: [`sys_config_dir`].
+    /// - [`Data`][data]: [`sys_data_dir`].
+    /// - [`Cache`][cache]: [`sys_cache_dir`].
+    /// - [`State`][state]: [`sys_state_dir`].
+    /// - [`Home`][home]: [`home_dir`].
+    /// - [`Portable`][portable]: [`portable_dir`].
+    /// - [`Auto`][auto]: the conventional one of the platform, see
+    ///   [`ConfigType::auto_candidates`].
     ///
     /// [`ConfigOption.config_sys_type`]: crate::ConfigOption#strutfield.config_sys_type
     /// [pref]: crate::ConfigType::Preference
     /// [config]: crate::ConfigType::Config
+    /// [data]: crate::ConfigType::Data
+    /// [cache]: crate::ConfigType::Cache
+    /// [state]: crate::ConfigType::State
+    /// [home]: crate::ConfigType::Home
+    /// [portable]: crate::ConfigType::Portable
+    /// [auto]: crate::ConfigType::Auto
     /// [`sys_preference_dir`]: crate::ProjectPath::sys_preference_dir
     /// [`sys_config_dir`]: crate::ProjectPath::sys_config_dir
+    /// [`sys_data_dir`]: crate::ProjectPath::sys_data_dir
+    /// [`sys_cache_dir`]: crate::ProjectPath::sys_cache_dir
+    /// [`sys_state_dir`]: crate::ProjectPath::sys_state_dir
+    /// [`home_dir`]: crate::ProjectPath::home_dir
+    /// [`portable_dir`]: crate::ProjectPath::portable_dir
     pub fn sys_dir(&self, cfg_sys_type: ConfigType) -> Option<PathBuf> {
         match cfg_sys_type {
             ConfigType::Preference => self.sys_preference_dir(),
             ConfigType::Config => self.sys_config_dir(),
+            ConfigType::Data => self.sys_data_dir(),
+            ConfigType::Cache => self.sys_cache_dir(),
+            ConfigType::State => self.sys_state_dir(),
+            ConfigType::Home => self.home_dir(),
+            ConfigType::Portable => self.portable_dir(),
+            ConfigType::Auto => self.sys_dir(ConfigType::auto_candidates()[0]),
         }
     }
 }
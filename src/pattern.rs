@@ -0,0 +1,316 @@
+//! A small regular expression matcher, used by the `regex` constraints and the `pattern`
+//! keyword of JSON Schemas.
+//!
+//! The supported syntax is:
+//! - literals, and `\` escaping the special characters;
+//! - `.`, the classes `[a-z]` and `[^a-z]`, and the escapes `\d`, `\w`, `\s` and their negations;
+//! - the anchors `^` and `$`;
+//! - the groups `(...)` and `(?:...)`, and the alternation `|`;
+//! - the quantifiers `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`, and their lazy forms.
+//!
+//! Like the `regex` crate and JSON Schema, a pattern matches if it's found anywhere in the text,
+//! so use the anchors to match the whole text.
+
+/// A compiled regular expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pattern {
+    root: Vec<Vec<Node>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        lazy: bool,
+    },
+}
+
+impl Pattern {
+    /// Compile a pattern, describing why it's invalid if it is.
+    pub(crate) fn new(pattern: &str) -> Result<Pattern, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let root = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("unmatched `)` at {}", parser.pos));
+        }
+        Ok(Pattern { root })
+    }
+
+    /// Whether the pattern is found in `text`.
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        (0..=text.len()).any(|start| alternation(&self.root, &text, start, &mut |_| true))
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or("unexpected end of pattern")?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn alternation(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut branches = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.sequence()?);
+        }
+        Ok(branches)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let node = self.atom()?;
+            nodes.push(self.quantified(node)?);
+        }
+        Ok(nodes)
+    }
+
+    fn quantified(&mut self, node: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.number()?.ok_or("expected a number after `{`")?;
+                let max = if self.peek() == Some(',') {
+                    self.pos += 1;
+                    self.number()?
+                } else {
+                    Some(min)
+                };
+                if self.next()? != '}' {
+                    return Err("expected `}`".to_owned());
+                }
+                self.pos -= 1;
+                (min, max)
+            }
+            _ => return Ok(node),
+        };
+        self.pos += 1;
+        if matches!(node, Node::Start | Node::End) {
+            return Err("an anchor can't be repeated".to_owned());
+        }
+        let lazy = self.peek() == Some('?');
+        if lazy {
+            self.pos += 1;
+        }
+        let node = Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            lazy,
+        };
+        if matches!(self.peek(), Some('*' | '+' | '?' | '{')) {
+            return Err("nothing to repeat".to_owned());
+        }
+        Ok(node)
+    }
+
+    fn number(&mut self) -> Result<Option<usize>, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits
+            .parse()
+            .map(Some)
+            .map_err(|_| "repetition is too large".to_owned())
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        Ok(match self.next()? {
+            '(' => {
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                let group = self.alternation()?;
+                if self.peek() != Some(')') {
+                    return Err("unclosed group".to_owned());
+                }
+                self.pos += 1;
+                Node::Group(group)
+            }
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '[' => self.class()?,
+            '\\' => self.escape()?,
+            c @ ('*' | '+' | '?' | '{') => return Err(format!("nothing to repeat with `{c}`")),
+            c => Node::Char(c),
+        })
+    }
+
+    fn escape(&mut self) -> Result<Node, String> {
+        let c = self.next()?;
+        Ok(match shorthand(c) {
+            Some(ranges) => Node::Class {
+                ranges,
+                negated: c.is_ascii_uppercase(),
+            },
+            None => Node::Char(literal(c)),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.next().map_err(|_| "unclosed class".to_owned())?;
+            let start = match c {
+                ']' if !first => break,
+                '\\' => {
+                    let c = self.next()?;
+                    match shorthand(c) {
+                        Some(_) if c.is_ascii_uppercase() => {
+                            return Err(format!("`\\{c}` isn't supported in a class"))
+                        }
+                        Some(shorthand) => {
+                            ranges.extend(shorthand);
+                            first = false;
+                            continue;
+                        }
+                        None => literal(c),
+                    }
+                }
+                c => c,
+            };
+            first = false;
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']') {
+                self.pos += 1;
+                let end = match self.next()? {
+                    '\\' => literal(self.next()?),
+                    end => end,
+                };
+                if end < start {
+                    return Err(format!("invalid range `{start}-{end}`"));
+                }
+                ranges.push((start, end));
+            } else {
+                ranges.push((start, start));
+            }
+        }
+        Ok(Node::Class { ranges, negated })
+    }
+}
+
+/// The ranges of `\d`, `\w` and `\s`, and of their negations.
+fn shorthand(c: char) -> Option<Vec<(char, char)>> {
+    Some(match c.to_ascii_lowercase() {
+        'd' => vec![('0', '9')],
+        'w' => vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')],
+        's' => vec![(' ', ' '), ('\t', '\r')],
+        _ => return None,
+    })
+}
+
+fn literal(c: char) -> char {
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        c => c,
+    }
+}
+
+fn alternation(
+    branches: &[Vec<Node>],
+    text: &[char],
+    pos: usize,
+    k: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    branches.iter().any(|nodes| sequence(nodes, text, pos, k))
+}
+
+fn sequence(nodes: &[Node], text: &[char], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+    match nodes.split_first() {
+        None => k(pos),
+        Some((node, rest)) => single(node, text, pos, &mut |pos| sequence(rest, text, pos, k)),
+    }
+}
+
+fn single(node: &Node, text: &[char], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+    match node {
+        Node::Char(expected) => text.get(pos) == Some(expected) && k(pos + 1),
+        Node::Any => text.get(pos).is_some_and(|c| *c != '\n') && k(pos + 1),
+        Node::Class { ranges, negated } => {
+            text.get(pos).is_some_and(|c| {
+                ranges.iter().any(|(start, end)| (start..=end).contains(&c)) != *negated
+            }) && k(pos + 1)
+        }
+        Node::Start => pos == 0 && k(pos),
+        Node::End => pos == text.len() && k(pos),
+        Node::Group(branches) => alternation(branches, text, pos, k),
+        Node::Repeat {
+            node,
+            min,
+            max,
+            lazy,
+        } => repeat(node, (*min, *max, *lazy), 0, text, pos, k),
+    }
+}
+
+fn repeat(
+    node: &Node,
+    (min, max, lazy): (usize, Option<usize>, bool),
+    count: usize,
+    text: &[char],
+    pos: usize,
+    k: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    let can_stop = count >= min;
+    if lazy && can_stop && k(pos) {
+        return true;
+    }
+    if max.is_none_or(|max| count < max) {
+        let matched = single(node, text, pos, &mut |next| {
+            // An empty match can be repeated endlessly, so it satisfies the minimum at once.
+            if next == pos {
+                k(next)
+            } else {
+                repeat(node, (min, max, lazy), count + 1, text, next, k)
+            }
+        });
+        if matched {
+            return true;
+        }
+    }
+    !lazy && can_stop && k(pos)
+}
@@ -0,0 +1,166 @@
+//! Pluggable computation of the platform directories.
+//!
+//! By default, the [system-level directory][sys] of a configuration comes from the
+//! [`directories`] crate, following the conventions of each platform. Some deployments need
+//! something else, e.g. corporate roaming profiles, or a NAS mounted at a custom location.
+//! Implement [`PlatformPaths`] and inject it with [`ConfigPathMetadata::with_platform_paths`]
+//! to override just that computation, while the rest of the search stays the same.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use conrig::platform::{DirectoriesPaths, PlatformPaths};
+//! use conrig::{conrig, ConfigOption, ConfigType, FileFormat, ProjectPath};
+//! use std::path::{Path, PathBuf};
+//!
+//! struct Nas;
+//!
+//! impl PlatformPaths for Nas {
+//!     fn sys_dir(&self, project_path: &ProjectPath, config_type: ConfigType) -> Option<PathBuf> {
+//!         match config_type {
+//!             ConfigType::Config => Some(Path::new("/mnt/nas").join(project_path.application)),
+//!             _ => DirectoriesPaths::default().sys_dir(project_path, config_type),
+//!         }
+//!     }
+//! }
+//!
+//! conrig!(const CONFIG<()> = {
+//!     project_path: ProjectPath::new("org", "foo", "conrig-platform"),
+//!     config_name: &["conrig-platform"],
+//!     config_option: ConfigOption::DEFAULT_CONFIG,
+//!     extra_files: &[],
+//!     extra_folders: &[],
+//!     default_format: FileFormat::Toml,
+//! });
+//!
+//! let metadata = CONFIG.with_platform_paths(&Nas);
+//! assert_eq!(
+//!     metadata.default_sys_config_file().unwrap(),
+//!     PathBuf::from("/mnt/nas/conrig-platform/conrig-platform.toml"),
+//! );
+//! ```
+//!
+//! [sys]: crate::ConfigPathMetadata::sys_dir
+
+use crate::path::sandbox_root;
+use crate::{AppData, ConfigPathMetadata, ConfigType, ProjectPath};
+use directories::BaseDirs;
+use std::fmt;
+use std::path::PathBuf;
+
+/// The computation of the platform directories of a project.
+///
+/// Implementations are `Sync`, so a [`ConfigPathMetadata`] referring to one can be a `static`.
+pub trait PlatformPaths: Sync {
+    /// Get the system-level directory of a project, for a type of directory.
+    ///
+    /// See [`ProjectPath::sys_dir`] for the default implementation.
+    fn sys_dir(&self, project_path: &ProjectPath, config_type: ConfigType) -> Option<PathBuf>;
+
+    /// Get the machine-wide configuration directory of a project.
+    ///
+    /// See [`ProjectPath::machine_dir`] for the default implementation.
+    fn machine_dir(&self, project_path: &ProjectPath) -> Option<PathBuf> {
+        project_path.machine_dir()
+    }
+}
+
+/// The default [`PlatformPaths`], delegating to the [`directories`] crate.
+///
+/// On Windows, the `AppData` folder of the [`Config`], [`Preference`] and [`Data`] directories
+/// can be changed, e.g. `%LOCALAPPDATA%\<organization>\<application>\config`.
+/// See [`ConfigOption.app_data`] for more information.
+///
+/// [`Config`]: crate::ConfigType::Config
+/// [`Preference`]: crate::ConfigType::Preference
+/// [`Data`]: crate::ConfigType::Data
+/// [`ConfigOption.app_data`]: crate::ConfigOption#structfield.app_data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirectoriesPaths {
+    /// The `AppData` folder of the system-level directory on Windows.
+    pub app_data: AppData,
+}
+
+impl DirectoriesPaths {
+    /// Create a new `DirectoriesPaths`, using an `AppData` folder on Windows.
+    pub const fn new(app_data: AppData) -> Self {
+        Self { app_data }
+    }
+}
+
+impl PlatformPaths for DirectoriesPaths {
+    fn sys_dir(&self, project_path: &ProjectPath, config_type: ConfigType) -> Option<PathBuf> {
+        let sub_dir = match config_type {
+            ConfigType::Config | ConfigType::Preference | ConfigType::Auto => "config",
+            ConfigType::Data => "data",
+            _ => return project_path.sys_dir(config_type),
+        };
+        if !cfg!(windows) || self.app_data == AppData::Roaming || sandbox_root().is_some() {
+            return project_path.sys_dir(config_type);
+        }
+        let local = BaseDirs::new()?.data_local_dir().to_path_buf();
+        let app_data = match self.app_data {
+            AppData::LocalLow => local.parent()?.join("LocalLow"),
+            _ => local,
+        };
+        Some(
+            app_data
+                .join(project_path.organization)
+                .join(project_path.application)
+                .join(sub_dir),
+        )
+    }
+}
+
+impl fmt::Debug for dyn PlatformPaths + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PlatformPaths")
+    }
+}
+
+/// Platform paths are compared by address.
+impl PartialEq for dyn PlatformPaths + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+impl Eq for dyn PlatformPaths + '_ {}
+
+impl<'p, T> ConfigPathMetadata<'p, T> {
+    /// The [`PlatformPaths`] in use: the injected [`platform_paths`], or [`DirectoriesPaths`]
+    /// with the [`app_data`] of the options.
+    ///
+    /// [`platform_paths`]: crate::ConfigPathMetadata#structfield.platform_paths
+    /// [`app_data`]: crate::ConfigOption#structfield.app_data
+    pub fn platform_paths(&self) -> &'p dyn PlatformPaths {
+        const ROAMING: DirectoriesPaths = DirectoriesPaths::new(AppData::Roaming);
+        const LOCAL: DirectoriesPaths = DirectoriesPaths::new(AppData::Local);
+        const LOCAL_LOW: DirectoriesPaths = DirectoriesPaths::new(AppData::LocalLow);
+        self.platform_paths
+            .unwrap_or(match self.config_option.app_data {
+                AppData::Roaming => &ROAMING,
+                AppData::Local => &LOCAL,
+                AppData::LocalLow => &LOCAL_LOW,
+            })
+    }
+
+    /// Get the system-level directory of your application, for a type of directory.
+    ///
+    /// This is [`ProjectPath::sys_dir`], unless overridden by the [`platform_paths`].
+    ///
+    /// [`platform_paths`]: crate::ConfigPathMetadata#structfield.platform_paths
+    pub fn sys_dir(&self, config_type: ConfigType) -> Option<PathBuf> {
+        self.platform_paths()
+            .sys_dir(&self.project_path, config_type)
+    }
+
+    /// Get the machine-wide configuration directory of your application.
+    ///
+    /// This is [`ProjectPath::machine_dir`], unless overridden by the [`platform_paths`].
+    ///
+    /// [`platform_paths`]: crate::ConfigPathMetadata#structfield.platform_paths
+    pub fn machine_dir(&self) -> Option<PathBuf> {
+        self.platform_paths().machine_dir(&self.project_path)
+    }
+}
@@ -0,0 +1,488 @@
+//! A reader and writer of Apple property lists.
+//!
+//! XML property lists are read and written as text. Binary property lists (`bplist00`) are
+//! read too, and written back as XML.
+//!
+//! `<date>` values are read as RFC 3339 strings, and `<data>` values as base64 strings.
+
+use crate::error::PlistError;
+use crate::value::{Table, Value};
+use std::fmt::Write as _;
+
+/// The header of a binary property list.
+const BINARY_MAGIC: &[u8] = b"bplist00";
+
+/// The maximum nesting of a binary property list, which also guards against reference cycles.
+const MAX_DEPTH: usize = 512;
+
+/// Seconds between the Unix epoch and the property list epoch, `2001-01-01T00:00:00Z`.
+const EPOCH_OFFSET: f64 = 978_307_200.0;
+
+/// Whether `bytes` is a binary property list.
+pub(crate) fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(BINARY_MAGIC)
+}
+
+/// Parse an XML property list.
+pub(crate) fn from_str(input: &str) -> Result<Value, PlistError> {
+    let mut reader = Reader { input, pos: 0 };
+    let tag = reader.tag()?;
+    let value = match tag {
+        Tag::Empty("plist") => Value::Table(Table::new()),
+        Tag::Open("plist") => {
+            let tag = reader.tag()?;
+            let value = reader.value(tag)?;
+            reader.close("plist")?;
+            value
+        }
+        tag => reader.value(tag)?,
+    };
+    reader.skip_misc();
+    if reader.pos < input.len() {
+        return Err(reader.error("unexpected content after the property list"));
+    }
+    Ok(value)
+}
+
+/// Write a document as an XML property list.
+///
+/// `None` values are skipped, as property lists can't express them.
+pub(crate) fn to_string(value: &Value) -> String {
+    let mut output = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" ",
+        "\"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+        "<plist version=\"1.0\">\n",
+    ));
+    match value {
+        Value::Null => output.push_str("<dict/>\n"),
+        value => write_value(&mut output, value, 0),
+    }
+    output.push_str("</plist>\n");
+    output
+}
+
+fn write_value(output: &mut String, value: &Value, depth: usize) {
+    let indent = "\t".repeat(depth);
+    match value {
+        Value::Null => {}
+        Value::Bool(true) => writeln!(output, "{indent}<true/>").unwrap(),
+        Value::Bool(false) => writeln!(output, "{indent}<false/>").unwrap(),
+        Value::Integer(v) => writeln!(output, "{indent}<integer>{v}</integer>").unwrap(),
+        Value::Float(v) => writeln!(output, "{indent}<real>{v:?}</real>").unwrap(),
+        Value::String(v) => writeln!(output, "{indent}<string>{}</string>", escape(v)).unwrap(),
+        Value::Array(items) if items.is_empty() => writeln!(output, "{indent}<array/>").unwrap(),
+        Value::Array(items) => {
+            writeln!(output, "{indent}<array>").unwrap();
+            for item in items {
+                write_value(output, item, depth + 1);
+            }
+            writeln!(output, "{indent}</array>").unwrap();
+        }
+        Value::Table(table) if table.is_empty() => writeln!(output, "{indent}<dict/>").unwrap(),
+        Value::Table(table) => {
+            writeln!(output, "{indent}<dict>").unwrap();
+            for (key, value) in table {
+                if value == &Value::Null {
+                    continue;
+                }
+                writeln!(output, "{indent}\t<key>{}</key>", escape(key)).unwrap();
+                write_value(output, value, depth + 1);
+            }
+            writeln!(output, "{indent}</dict>").unwrap();
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// An XML element tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tag<'a> {
+    /// `<name>`
+    Open(&'a str),
+    /// `</name>`
+    Close(&'a str),
+    /// `<name/>`
+    Empty(&'a str),
+}
+
+/// A cursor over an XML property list.
+struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn error(&self, message: impl Into<String>) -> PlistError {
+        let before = &self.input[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+        PlistError {
+            message: message.into(),
+            line,
+            column,
+        }
+    }
+
+    /// Skip the whitespaces, the declarations, the doctype and the comments.
+    fn skip_misc(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            let end = if trimmed.starts_with("<?") {
+                trimmed.find("?>").map(|end| end + 2)
+            } else if trimmed.starts_with("<!--") {
+                trimmed.find("-->").map(|end| end + 3)
+            } else if trimmed.starts_with("<!") {
+                trimmed.find('>').map(|end| end + 1)
+            } else {
+                None
+            };
+            match end {
+                Some(end) => self.pos += end,
+                None => return,
+            }
+        }
+    }
+
+    fn tag(&mut self) -> Result<Tag<'a>, PlistError> {
+        self.skip_misc();
+        let rest = self.rest();
+        if !rest.starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        let end = rest
+            .find('>')
+            .ok_or_else(|| self.error("unterminated element"))?;
+        let inner = &rest[1..end];
+        self.pos += end + 1;
+        let name = |inner: &'a str| inner.split_whitespace().next().unwrap_or_default();
+        Ok(if let Some(inner) = inner.strip_prefix('/') {
+            Tag::Close(name(inner))
+        } else if let Some(inner) = inner.strip_suffix('/') {
+            Tag::Empty(name(inner))
+        } else {
+            Tag::Open(name(inner))
+        })
+    }
+
+    fn close(&mut self, name: &str) -> Result<(), PlistError> {
+        match self.tag()? {
+            Tag::Close(closed) if closed == name => Ok(()),
+            _ => Err(self.error(format!("expected `</{name}>`"))),
+        }
+    }
+
+    fn text(&mut self, name: &str) -> Result<String, PlistError> {
+        let rest = self.rest();
+        let end = rest
+            .find('<')
+            .ok_or_else(|| self.error(format!("unterminated `<{name}>`")))?;
+        let text = unescape(&rest[..end]).ok_or_else(|| self.error("invalid entity"))?;
+        self.pos += end;
+        self.close(name)?;
+        Ok(text)
+    }
+
+    fn value(&mut self, tag: Tag<'a>) -> Result<Value, PlistError> {
+        Ok(match tag {
+            Tag::Empty("true") => Value::Bool(true),
+            Tag::Empty("false") => Value::Bool(false),
+            Tag::Open(name @ ("true" | "false")) => {
+                self.close(name)?;
+                Value::Bool(name == "true")
+            }
+            Tag::Empty("string" | "date" | "data") => Value::String(String::new()),
+            Tag::Open(name @ ("string" | "date")) => Value::String(self.text(name)?),
+            Tag::Open("data") => {
+                Value::String(self.text("data")?.split_whitespace().collect::<String>())
+            }
+            Tag::Open("integer") => {
+                let text = self.text("integer")?;
+                let text = text.trim();
+                let parsed = match text.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16),
+                    None => text.parse(),
+                };
+                Value::Integer(parsed.map_err(|_| self.error("invalid integer"))?)
+            }
+            Tag::Open("real") => Value::Float(
+                self.text("real")?
+                    .trim()
+                    .parse()
+                    .map_err(|_| self.error("invalid real"))?,
+            ),
+            Tag::Empty("array") => Value::Array(Vec::new()),
+            Tag::Open("array") => {
+                let mut items = Vec::new();
+                loop {
+                    match self.tag()? {
+                        Tag::Close("array") => break,
+                        tag => items.push(self.value(tag)?),
+                    }
+                }
+                Value::Array(items)
+            }
+            Tag::Empty("dict") => Value::Table(Table::new()),
+            Tag::Open("dict") => {
+                let mut table = Table::new();
+                loop {
+                    match self.tag()? {
+                        Tag::Close("dict") => break,
+                        Tag::Open("key") => {
+                            let key = self.text("key")?;
+                            let tag = self.tag()?;
+                            table.insert(key, self.value(tag)?);
+                        }
+                        Tag::Empty("key") => {
+                            let tag = self.tag()?;
+                            table.insert(String::new(), self.value(tag)?);
+                        }
+                        _ => return Err(self.error("expected `<key>`")),
+                    }
+                }
+                Value::Table(table)
+            }
+            Tag::Open(name) | Tag::Empty(name) => {
+                return Err(self.error(format!("unexpected element `<{name}>`")))
+            }
+            Tag::Close(name) => return Err(self.error(format!("unexpected `</{name}>`"))),
+        })
+    }
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        let end = rest[start..].find(';')? + start;
+        let entity = &rest[start + 1..end];
+        output.push(match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => entity.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)?
+            }
+        });
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    Some(output)
+}
+
+/// Parse a binary property list.
+pub(crate) fn from_binary(bytes: &[u8]) -> Result<Value, PlistError> {
+    if !is_binary(bytes) || bytes.len() < BINARY_MAGIC.len() + 32 {
+        return Err(binary_error("not a binary property list"));
+    }
+    let trailer = &bytes[bytes.len() - 32..];
+    let offset_size = trailer[6] as usize;
+    let ref_size = trailer[7] as usize;
+    if !(1..=8).contains(&offset_size) || !(1..=8).contains(&ref_size) {
+        return Err(binary_error("invalid trailer"));
+    }
+    let mut reader = Binary {
+        bytes,
+        offsets: Vec::new(),
+        ref_size,
+    };
+    let count = reader.uint(bytes.len() - 24, 8)? as usize;
+    let top = reader.uint(bytes.len() - 16, 8)? as usize;
+    let table = reader.uint(bytes.len() - 8, 8)? as usize;
+    if count > bytes.len() {
+        return Err(binary_error("invalid object count"));
+    }
+    reader.offsets = (0..count)
+        .map(|index| Ok(reader.uint(table + index * offset_size, offset_size)? as usize))
+        .collect::<Result<_, PlistError>>()?;
+    reader.object(top, 0)
+}
+
+fn binary_error(message: &str) -> PlistError {
+    PlistError {
+        message: message.to_owned(),
+        line: 0,
+        column: 0,
+    }
+}
+
+/// A reader of a binary property list.
+struct Binary<'a> {
+    bytes: &'a [u8],
+    offsets: Vec<usize>,
+    ref_size: usize,
+}
+
+impl Binary<'_> {
+    fn slice(&self, at: usize, len: usize) -> Result<&[u8], PlistError> {
+        at.checked_add(len)
+            .and_then(|end| self.bytes.get(at..end))
+            .ok_or_else(|| binary_error("unexpected end of data"))
+    }
+
+    fn uint(&self, at: usize, size: usize) -> Result<u64, PlistError> {
+        Ok(self
+            .slice(at, size)?
+            .iter()
+            .fold(0, |uint, byte| uint << 8 | u64::from(*byte)))
+    }
+
+    /// The length of a variable-sized object, and where its content starts.
+    fn length(&self, at: usize, low: u8) -> Result<(usize, usize), PlistError> {
+        if low != 0xF {
+            return Ok((low as usize, at + 1));
+        }
+        let marker = self.slice(at + 1, 1)?[0];
+        if marker >> 4 != 1 {
+            return Err(binary_error("invalid length"));
+        }
+        let size = 1 << (marker & 0xF);
+        let len = self.uint(at + 2, size)? as usize;
+        if len > self.bytes.len() {
+            return Err(binary_error("invalid length"));
+        }
+        Ok((len, at + 2 + size))
+    }
+
+    fn object(&self, index: usize, depth: usize) -> Result<Value, PlistError> {
+        if depth > MAX_DEPTH {
+            return Err(binary_error("objects are nested too deeply"));
+        }
+        let at = *self
+            .offsets
+            .get(index)
+            .ok_or_else(|| binary_error("invalid object reference"))?;
+        let marker = self.slice(at, 1)?[0];
+        let (high, low) = (marker >> 4, marker & 0xF);
+        Ok(match (high, low) {
+            (0x0, 0x0) => Value::Null,
+            (0x0, 0x8) => Value::Bool(false),
+            (0x0, 0x9) => Value::Bool(true),
+            (0x1, 0..=4) => {
+                let size = 1 << low;
+                let data = self.slice(at + 1, size)?;
+                // 16-byte integers only carry 64 bits.
+                let data = &data[size.saturating_sub(8)..];
+                let uint = data
+                    .iter()
+                    .fold(0, |uint, byte| uint << 8 | u64::from(*byte));
+                Value::Integer(uint as i64)
+            }
+            (0x2, 2) => Value::Float(f32::from_be_bytes(array(self.slice(at + 1, 4)?)).into()),
+            (0x2, 3) => Value::Float(f64::from_be_bytes(array(self.slice(at + 1, 8)?))),
+            (0x3, 3) => {
+                let seconds = f64::from_be_bytes(array(self.slice(at + 1, 8)?));
+                Value::String(rfc3339(seconds))
+            }
+            (0x4, _) => {
+                let (len, start) = self.length(at, low)?;
+                Value::String(base64(self.slice(start, len)?))
+            }
+            (0x5, _) => {
+                let (len, start) = self.length(at, low)?;
+                Value::String(String::from_utf8_lossy(self.slice(start, len)?).into_owned())
+            }
+            (0x6, _) => {
+                let (len, start) = self.length(at, low)?;
+                let units: Vec<u16> = self
+                    .slice(start, len * 2)?
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect();
+                Value::String(String::from_utf16_lossy(&units))
+            }
+            (0x8, _) => Value::Integer(self.uint(at + 1, low as usize + 1)? as i64),
+            (0xA, _) => {
+                let (len, start) = self.length(at, low)?;
+                Value::Array(
+                    (0..len)
+                        .map(|index| self.child(start + index * self.ref_size, depth))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            (0xD, _) => {
+                let (len, start) = self.length(at, low)?;
+                let mut table = Table::new();
+                for index in 0..len {
+                    let Value::String(key) = self.child(start + index * self.ref_size, depth)?
+                    else {
+                        return Err(binary_error("dictionary keys must be strings"));
+                    };
+                    let value = self.child(start + (len + index) * self.ref_size, depth)?;
+                    table.insert(key, value);
+                }
+                Value::Table(table)
+            }
+            _ => return Err(binary_error("unsupported object")),
+        })
+    }
+
+    fn child(&self, at: usize, depth: usize) -> Result<Value, PlistError> {
+        let index = self.uint(at, self.ref_size)? as usize;
+        self.object(index, depth + 1)
+    }
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut array = [0; N];
+    array.copy_from_slice(bytes);
+    array
+}
+
+/// Format a property list date, in seconds since `2001-01-01T00:00:00Z`.
+fn rfc3339(seconds: f64) -> String {
+    let unix = (seconds + EPOCH_OFFSET).floor() as i64;
+    let (days, time) = (unix.div_euclid(86_400), unix.rem_euclid(86_400));
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, byte)| {
+            bits | u32::from(*byte) << (16 - index * 8)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                output.push(ALPHABET[(bits >> (18 - index * 6) & 0x3F) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
@@ -0,0 +1,90 @@
+//! Settings managed by the administrators.
+//!
+//! If [`ConfigOption.policy`] is enabled, the administrators can lock some settings in a
+//! policy file, next to the [machine-wide] configuration. Every key of the policy file is
+//! locked: [`read_merged`] takes its value whatever the other files say, and writing the
+//! configuration file with a different value fails with a [`LockedKeys`] error.
+//!
+//! For example, with `/etc/<application>/<name>.policy.toml`:
+//!
+//! ```toml
+//! [update]
+//! channel = "stable"
+//! ```
+//!
+//! [`ConfigOption.policy`]: crate::ConfigOption#structfield.policy
+//! [machine-wide]: crate::ProjectPath::machine_dir
+//! [`read_merged`]: crate::ConfigPathMetadata::read_merged
+//! [`LockedKeys`]: crate::ConrigError::LockedKeys
+
+use crate::error::{ConrigResultExt, Operation};
+use crate::parser::ConfigFile;
+use crate::value::Value;
+use crate::{ConfigPathMetadata, ConrigError};
+
+impl<'p, T> ConfigPathMetadata<'p, T> {
+    /// Search for the policy file, if [`ConfigOption.policy`] is enabled.
+    ///
+    /// It is named after the [`config_name`] with a `.policy` suffix, e.g. `<name>.policy.toml`,
+    /// in the [machine-wide directory][machine]. The returned file is read-only.
+    ///
+    /// [`ConfigOption.policy`]: crate::ConfigOption#structfield.policy
+    /// [`config_name`]: crate::ConfigPathMetadata#structfield.config_name
+    /// [machine]: crate::ProjectPath::machine_dir
+    pub fn search_policy_file(&self) -> Option<ConfigFile> {
+        if !self.config_option.policy {
+            return None;
+        }
+        let machine_dir = self.machine_dir()?;
+        let (path, file_format) = self.config_name.iter().find_map(|name| {
+            crate::parser::detect_mapped(
+                &machine_dir.join(format!("{name}.policy")),
+                self.default_format,
+                self.config_option.extensions,
+            )
+        })?;
+        Some(
+            ConfigFile::new(file_format, path)
+                .with_config_option(self.config_option)
+                .with_read_only(true),
+        )
+    }
+}
+
+/// Check that `value` doesn't change any key locked by `policy`.
+///
+/// The keys missing from `value` are left to the policy. A missing policy file locks nothing.
+pub(crate) fn check(policy: &ConfigFile, value: &Value) -> Result<(), ConrigError> {
+    let document = match policy.read_document() {
+        Err(err) if err.is_missing() => return Ok(()),
+        document => document.context_path(Operation::Read, &policy.path)?,
+    };
+    let mut locked = Vec::new();
+    collect_changed(&document, value, "", &mut locked);
+    if locked.is_empty() {
+        Ok(())
+    } else {
+        Err(ConrigError::LockedKeys(locked))
+    }
+}
+
+/// Collect the paths of the leaves of `policy` whose value differs in `value`.
+fn collect_changed(policy: &Value, value: &Value, prefix: &str, locked: &mut Vec<String>) {
+    let (Value::Table(policy), Value::Table(value)) = (policy, value) else {
+        if value != policy {
+            locked.push(prefix.to_owned());
+        }
+        return;
+    };
+    for (key, policy) in policy {
+        let Some(value) = value.get(key) else {
+            continue;
+        };
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        collect_changed(policy, value, &path, locked);
+    }
+}
@@ -0,0 +1,143 @@
+//! Named configurations shipped with the application, e.g. `minimal` or `recommended`.
+//!
+//! Presets are either embedded at compile time, with [`ConfigOption.presets`], or registered
+//! at runtime with [`register_preset`], e.g. when they depend on the machine. A configuration
+//! file can then use one as its base through the [`extends_key`], e.g.
+//! `extends = "recommended"`, and [`init_preset`] creates a new configuration file from one,
+//! e.g. for a `myapp init --preset recommended` command.
+//!
+//! The embedded presets take precedence over the registered ones with the same name.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::preset::{preset, register_preset};
+//! use conrig::value::Value;
+//! # use serde_derive::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Config {
+//!     threads: u32,
+//! }
+//!
+//! register_preset("doc-minimal", &Config { threads: 1 })?;
+//! assert_eq!(
+//!     preset("doc-minimal").unwrap().get_path("threads"),
+//!     Some(&Value::Integer(1)),
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ConfigOption.presets`]: crate::ConfigOption#structfield.presets
+//! [`extends_key`]: crate::ConfigOption#structfield.extends_key
+//! [`init_preset`]: crate::ConfigPathMetadata::init_preset
+
+use crate::error::FileSystemError;
+use crate::filesystem;
+use crate::parser::ConfigFile;
+use crate::provider::Embedded;
+use crate::value::{to_value, Value};
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{PoisonError, RwLock};
+
+/// The presets registered at runtime.
+static PRESETS: RwLock<BTreeMap<String, Value>> = RwLock::new(BTreeMap::new());
+
+/// Register a preset, replacing the previous one with the same name.
+pub fn register_preset<T: Serialize + ?Sized>(
+    name: impl Into<String>,
+    value: &T,
+) -> Result<(), ConrigError> {
+    let value = to_value(value)?;
+    PRESETS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(name.into(), value);
+    Ok(())
+}
+
+/// Unregister a preset, returning its value if it was registered.
+pub fn unregister_preset(name: &str) -> Option<Value> {
+    PRESETS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(name)
+}
+
+/// Get a registered preset by its name.
+pub fn preset(name: &str) -> Option<Value> {
+    PRESETS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(name)
+        .cloned()
+}
+
+/// The names of the registered presets, in alphabetical order.
+pub fn preset_names() -> Vec<String> {
+    PRESETS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Find a preset by its name, among the embedded `presets` first, then the registered ones.
+pub(crate) fn find(presets: &[(&str, Embedded)], name: &str) -> Result<Option<Value>, ConrigError> {
+    match presets.iter().find(|(preset, _)| *preset == name) {
+        Some((_, embedded)) => embedded.file_format.parse_str(embedded.content, None).map(Some),
+        None => Ok(preset(name)),
+    }
+}
+
+impl<'p, T> ConfigPathMetadata<'p, T> {
+    /// Get a preset by its name, among the [embedded] presets first, then the [registered] ones.
+    ///
+    /// An [`UnknownPreset`] error is returned if no preset has that name.
+    ///
+    /// [embedded]: crate::ConfigOption#structfield.presets
+    /// [registered]: crate::preset::register_preset
+    /// [`UnknownPreset`]: crate::ConrigError::UnknownPreset
+    pub fn preset(&self, name: &str) -> Result<Value, ConrigError> {
+        find(self.config_option.presets, name)?
+            .ok_or_else(|| ConrigError::UnknownPreset(name.to_owned()))
+    }
+
+    /// Create a new configuration file, filled with a preset.
+    ///
+    /// The file is created where [`fallback_default`] would, and returned.
+    /// Existing configuration files are never overwritten: a [`WriteConfig`] error of kind
+    /// [`AlreadyExists`] is returned instead.
+    ///
+    /// [`fallback_default`]: crate::parser::RawConfigSource::fallback_default
+    /// [`WriteConfig`]: crate::error::FileSystemError::WriteConfig
+    /// [`AlreadyExists`]: std::io::ErrorKind::AlreadyExists
+    pub fn init_preset(&self, name: &str) -> Result<ConfigFile, ConrigError> {
+        let value = self.preset(name)?;
+        self.init_with(&value, false)
+    }
+
+    /// Create a new configuration file, filled with `value`.
+    pub(crate) fn init_with(
+        &self,
+        value: &Value,
+        overwrite: bool,
+    ) -> Result<ConfigFile, ConrigError> {
+        let file = self.search_config_file()?.fallback_default()?;
+        if !overwrite && filesystem::exists(&file.path) {
+            let err = io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "the configuration file already exists",
+            );
+            return Err(FileSystemError::write_config(&file.path)(err).into());
+        }
+        file.write(value)?;
+        Ok(file)
+    }
+}
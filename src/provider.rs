@@ -0,0 +1,193 @@
+//! Providers of default configuration values.
+//!
+//! By default, the `read_or_default` methods fill a new configuration file with `T::default()`.
+//! Register a [`DefaultProvider`] in [`ConfigPathMetadata::default_provider`] to decide where
+//! the default value comes from instead, e.g. a closure, an [embedded file][Embedded],
+//! or a remote server.
+//!
+//! Ship one default file per deployment environment with [`Templates`], selected at runtime.
+//!
+//! Providers also make [`read_or_provided`] available to the types which don't implement
+//! [`Default`], such as the ones with mandatory fields.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use conrig::provider::{DefaultProvider, Embedded};
+//! use conrig::{ConfigPathMetadata, ConrigError, FileFormat, ProjectPath, conrig};
+//! # use serde_derive::Deserialize;
+//! # #[derive(Deserialize)]
+//! # struct Config {
+//! #     name: String,
+//! # }
+//!
+//! // a default file shipped within your binary.
+//! const EMBEDDED: Embedded = Embedded::new(FileFormat::Toml, r#"name = "conrig""#);
+//!
+//! // or fetch the default value from wherever you want.
+//! struct Remote;
+//!
+//! impl DefaultProvider<Config> for Remote {
+//!     fn provide(&self) -> Result<Config, ConrigError> {
+//!         // download the configuration...
+//!         # Ok(Config { name: "conrig".to_owned() })
+//!     }
+//! }
+//!
+//! const CONFIG: ConfigPathMetadata<Config> = ConfigPathMetadata::new(
+//!     ProjectPath::new("org", "my-organization", "conrig-test"),
+//!     &["conrig"],
+//!     FileFormat::Toml,
+//!     &[],
+//!     &[],
+//!     conrig::ConfigOption::DEFAULT_CONFIG,
+//! )
+//! .with_default_provider(&EMBEDDED);
+//! ```
+//!
+//! [`ConfigPathMetadata::default_provider`]: crate::ConfigPathMetadata#structfield.default_provider
+//! [`read_or_provided`]: crate::ConfigPathMetadata::read_or_provided
+
+use crate::{ConrigError, FileFormat};
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// A source of default configuration values.
+///
+/// This is implemented for every closure returning a configuration.
+/// Implement it for your own types to provide fallible defaults, e.g. from a remote server.
+///
+/// Providers are `Sync`, so a [`ConfigPathMetadata`] referring to one can be a `static`.
+///
+/// [`ConfigPathMetadata`]: crate::ConfigPathMetadata
+pub trait DefaultProvider<T>: Sync {
+    /// Provide the default configuration value.
+    fn provide(&self) -> Result<T, ConrigError>;
+}
+
+impl<T, F: Fn() -> T + Sync> DefaultProvider<T> for F {
+    fn provide(&self) -> Result<T, ConrigError> {
+        Ok(self())
+    }
+}
+
+impl<T> fmt::Debug for dyn DefaultProvider<T> + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DefaultProvider")
+    }
+}
+
+/// Providers are compared by address.
+impl<T> PartialEq for dyn DefaultProvider<T> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self, other)
+    }
+}
+
+impl<T> Eq for dyn DefaultProvider<T> + '_ {}
+
+/// A default configuration file embedded in your binary, typically with [`include_str!`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Embedded<'p> {
+    /// The format of the embedded file.
+    pub file_format: FileFormat,
+    /// The content of the embedded file.
+    pub content: &'p str,
+}
+
+impl<'p> Embedded<'p> {
+    /// Create a new `Embedded` provider.
+    pub const fn new(file_format: FileFormat, content: &'p str) -> Self {
+        Self {
+            file_format,
+            content,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> DefaultProvider<T> for Embedded<'_> {
+    fn provide(&self) -> Result<T, ConrigError> {
+        self.file_format.parse_str(self.content, None)
+    }
+}
+
+/// Named default configuration files embedded in your binary, one per deployment environment,
+/// typically built with the [`templates!`] macro.
+///
+/// The template used is, in order:
+/// 1. the one [selected] explicitly, e.g. from a command-line flag;
+/// 2. the one named by the [environment variable][env], if it's set and not empty;
+/// 3. the first one.
+///
+/// An [`UnknownTemplate`] error is returned if no template has the selected name.
+///
+/// [`templates!`]: crate::templates
+/// [selected]: crate::provider::Templates#structfield.selected
+/// [env]: crate::provider::Templates#structfield.env
+/// [`UnknownTemplate`]: crate::ConrigError::UnknownTemplate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Templates<'p> {
+    /// The templates, along with their names, e.g. `dev` and `prod`.
+    pub templates: &'p [(&'p str, Embedded<'p>)],
+    /// The environment variable selecting the template, e.g. `APP_ENV`. Default: `None`.
+    pub env: Option<&'p str>,
+    /// The name of the template selected explicitly. Default: `None`.
+    pub selected: Option<&'p str>,
+}
+
+impl<'p> Templates<'p> {
+    /// Create a new `Templates` provider.
+    pub const fn new(templates: &'p [(&'p str, Embedded<'p>)]) -> Self {
+        Self {
+            templates,
+            env: None,
+            selected: None,
+        }
+    }
+
+    /// Modify the [`env`] field.
+    ///
+    /// [`env`]: crate::provider::Templates#structfield.env
+    pub const fn with_env(mut self, env: &'p str) -> Self {
+        self.env = Some(env);
+        self
+    }
+
+    /// Modify the [`selected`] field.
+    ///
+    /// [`selected`]: crate::provider::Templates#structfield.selected
+    pub const fn with_selected(mut self, selected: &'p str) -> Self {
+        self.selected = Some(selected);
+        self
+    }
+
+    /// Get the template to use.
+    pub fn select(&self) -> Result<&Embedded<'p>, ConrigError> {
+        let name = match self.selected {
+            Some(selected) => Some(selected.to_owned()),
+            None => self
+                .env
+                .and_then(|env| std::env::var(env).ok())
+                .filter(|name| !name.is_empty()),
+        };
+        match name {
+            Some(name) => self
+                .templates
+                .iter()
+                .find(|(template, _)| *template == name)
+                .map(|(_, embedded)| embedded)
+                .ok_or(ConrigError::UnknownTemplate(name)),
+            None => self
+                .templates
+                .first()
+                .map(|(_, embedded)| embedded)
+                .ok_or(ConrigError::NoDefaultProvider),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> DefaultProvider<T> for Templates<'_> {
+    fn provide(&self) -> Result<T, ConrigError> {
+        self.select()?.provide()
+    }
+}
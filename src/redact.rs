@@ -0,0 +1,135 @@
+//! Redaction of sensitive values.
+//!
+//! Redaction rules are dotted key path patterns, e.g. `auth.token`, where `*` matches any single
+//! segment, e.g. `*.password`. Unlike a wrapper type, rules also cover the keys of dynamic maps,
+//! whose names aren't known at compile time.
+//!
+//! Rules are registered with [`ConfigOption::redactions`], and applied to any [`Value`] displayed
+//! or exported with [`ConfigOption::redact`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! use conrig::redact::{redact, MASK};
+//! use conrig::value::Value;
+//!
+//! let mut value = Value::Table(Default::default());
+//! value.set_path("database.password", Value::String("hunter2".to_owned())).unwrap();
+//! value.set_path("database.host", Value::String("localhost".to_owned())).unwrap();
+//!
+//! assert_eq!(redact(&mut value, &["*.password"]), 1);
+//! assert_eq!(value.get_path("database.password"), Some(&Value::String(MASK.to_owned())));
+//! assert_eq!(value.get_path("database.host"), Some(&Value::String("localhost".to_owned())));
+//! ```
+//!
+//! To print the effective configuration in logs or bug reports, [`ConfigFile::dump_redacted`]
+//! serializes it with both the redaction rules and the [secret](crate::secret) fields masked.
+//!
+//! [`ConfigOption::redactions`]: crate::ConfigOption#structfield.redactions
+//! [`ConfigOption::redact`]: crate::ConfigOption::redact
+
+use crate::parser::ConfigFile;
+use crate::secret::Secrets;
+use crate::value::{to_value, Value};
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::Serialize;
+
+/// The string replacing redacted values.
+pub const MASK: &str = "********";
+
+/// Check if a dotted key path, e.g. `database.password`, matches a redaction pattern.
+///
+/// Segments are compared one by one, and `*` matches any single segment,
+/// including the indices of arrays.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let mut pattern = pattern.split('.');
+    let mut path = path.split('.');
+    loop {
+        match (pattern.next(), path.next()) {
+            (Some(expected), Some(key)) if expected == "*" || expected == key => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Mask the values matching any of the patterns, recursively.
+///
+/// A matching value is replaced as a whole by [`MASK`], even if it's a table or an array.
+/// Null values are left untouched, as there's nothing to hide.
+///
+/// Returns the number of masked values.
+pub fn redact(value: &mut Value, patterns: &[&str]) -> usize {
+    if patterns.is_empty() {
+        return 0;
+    }
+    redact_at(value, &mut String::new(), patterns)
+}
+
+fn redact_at(value: &mut Value, path: &mut String, patterns: &[&str]) -> usize {
+    let visit = |path: &mut String, key: &str, value: &mut Value| {
+        let len = path.len();
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(key);
+        let masked = if *value == Value::Null {
+            0
+        } else if patterns.iter().any(|pattern| matches(pattern, path)) {
+            *value = Value::String(MASK.to_owned());
+            1
+        } else {
+            redact_at(value, path, patterns)
+        };
+        path.truncate(len);
+        masked
+    };
+    match value {
+        Value::Table(table) => table
+            .iter_mut()
+            .map(|(key, value)| visit(path, key, value))
+            .sum(),
+        Value::Array(array) => array
+            .iter_mut()
+            .enumerate()
+            .map(|(index, value)| visit(path, &index.to_string(), value))
+            .sum(),
+        _ => 0,
+    }
+}
+
+impl ConfigFile {
+    /// Serialize a value in the format of this file, with the values matching the
+    /// [`redactions`] rules and the [secrets] of `T` masked.
+    ///
+    /// Nothing is written: the result is meant to be printed, e.g. in logs or bug reports.
+    ///
+    /// [`redactions`]: crate::ConfigOption#structfield.redactions
+    /// [secrets]: crate::secret::Secrets
+    pub fn dump_redacted<T: Serialize + Secrets>(&self, value: &T) -> Result<String, ConrigError> {
+        let mut document = to_value(value)?;
+        let patterns = self
+            .config_option
+            .redactions
+            .iter()
+            .chain(T::SECRETS)
+            .copied()
+            .collect::<Vec<_>>();
+        redact(&mut document, &patterns);
+        let mut buffer = Vec::new();
+        self.file_format.write(&document, &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+impl<'p, T: Serialize + Secrets> ConfigPathMetadata<'p, T> {
+    /// Serialize a value with its sensitive values masked.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.dump_redacted(value)`.
+    pub fn dump_redacted(&self, value: &T) -> Result<String, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .dump_redacted(value)
+    }
+}
@@ -0,0 +1,475 @@
+//! The Windows registry backend.
+//!
+//! A [`RegistryKey`] stores a configuration under `HKEY_CURRENT_USER`,
+//! usually `HKCU\Software\<organization>\<application>`:
+//!
+//! - Tables are stored as subkeys.
+//! - Strings are stored as `REG_SZ` values.
+//! - Integers are stored as `REG_QWORD` values.
+//! - Booleans are stored as `REG_DWORD` values, `0` or `1`, and read back as integers:
+//!   deserializing them into a `bool` turns them back into booleans.
+//! - Floating point numbers are stored as 8-byte little-endian `REG_BINARY` values.
+//! - Arrays of non-empty strings are stored as `REG_MULTI_SZ` values.
+//!   Other arrays are stored as subkeys whose entries are named `0`, `1`, ...
+//! - `None` values are skipped.
+//!
+//! See [`ConfigBackend`] to use the registry and the files through the same API.
+//!
+//! [`ConfigBackend`]: crate::backend::ConfigBackend
+
+use crate::error::{ConrigResultExt, FileSystemError, Operation};
+use crate::value::{self, Table, Value, ValueError};
+use crate::ConrigError;
+use serde::de::DeserializeOwned;
+use serde::ser;
+use serde::Serialize;
+use std::ffi::c_void;
+use std::io;
+use std::path::PathBuf;
+use std::ptr;
+
+type Hkey = isize;
+
+const HKEY_CURRENT_USER: Hkey = 0x8000_0001_u32 as i32 as isize;
+const KEY_READ: u32 = 0x20019;
+const KEY_WRITE: u32 = 0x20006;
+const REG_OPTION_NON_VOLATILE: u32 = 0;
+const REG_SZ: u32 = 1;
+const REG_EXPAND_SZ: u32 = 2;
+const REG_BINARY: u32 = 3;
+const REG_DWORD: u32 = 4;
+const REG_MULTI_SZ: u32 = 7;
+const REG_QWORD: u32 = 11;
+const ERROR_SUCCESS: i32 = 0;
+const ERROR_FILE_NOT_FOUND: i32 = 2;
+const ERROR_NO_MORE_ITEMS: i32 = 259;
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegCreateKeyExW(
+        key: Hkey,
+        sub_key: *const u16,
+        reserved: u32,
+        class: *const u16,
+        options: u32,
+        sam_desired: u32,
+        security_attributes: *const c_void,
+        result: *mut Hkey,
+        disposition: *mut u32,
+    ) -> i32;
+    fn RegOpenKeyExW(
+        key: Hkey,
+        sub_key: *const u16,
+        options: u32,
+        sam_desired: u32,
+        result: *mut Hkey,
+    ) -> i32;
+    fn RegCloseKey(key: Hkey) -> i32;
+    fn RegDeleteTreeW(key: Hkey, sub_key: *const u16) -> i32;
+    fn RegSetValueExW(
+        key: Hkey,
+        value_name: *const u16,
+        reserved: u32,
+        value_type: u32,
+        data: *const u8,
+        data_len: u32,
+    ) -> i32;
+    fn RegQueryInfoKeyW(
+        key: Hkey,
+        class: *mut u16,
+        class_len: *mut u32,
+        reserved: *mut u32,
+        sub_keys: *mut u32,
+        max_sub_key_len: *mut u32,
+        max_class_len: *mut u32,
+        values: *mut u32,
+        max_value_name_len: *mut u32,
+        max_value_len: *mut u32,
+        security_descriptor_len: *mut u32,
+        last_write_time: *mut c_void,
+    ) -> i32;
+    fn RegEnumKeyExW(
+        key: Hkey,
+        index: u32,
+        name: *mut u16,
+        name_len: *mut u32,
+        reserved: *mut u32,
+        class: *mut u16,
+        class_len: *mut u32,
+        last_write_time: *mut c_void,
+    ) -> i32;
+    fn RegEnumValueW(
+        key: Hkey,
+        index: u32,
+        value_name: *mut u16,
+        value_name_len: *mut u32,
+        reserved: *mut u32,
+        value_type: *mut u32,
+        data: *mut u8,
+        data_len: *mut u32,
+    ) -> i32;
+}
+
+/// A configuration stored in a key of the Windows registry, under `HKEY_CURRENT_USER`.
+///
+/// See the [module documentation][crate::registry] for how the values are stored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RegistryKey {
+    /// The path of the key, relative to `HKEY_CURRENT_USER`, e.g. `Software\foo\conrig`.
+    pub path: String,
+}
+
+impl RegistryKey {
+    /// Create a new `RegistryKey`.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The full path of the key, e.g. `HKCU\Software\foo\conrig`, used in error messages.
+    pub fn display_path(&self) -> PathBuf {
+        PathBuf::from(format!("HKCU\\{}", self.path))
+    }
+
+    /// Whether the key exists.
+    pub fn exists(&self) -> bool {
+        Key::open(HKEY_CURRENT_USER, &self.path, KEY_READ).is_ok()
+    }
+
+    /// Read the key as a document.
+    ///
+    /// If the key doesn't exist, a [`FileSystemError`] whose kind is
+    /// [`NotFound`][std::io::ErrorKind::NotFound] will be returned.
+    pub fn read_value(&self) -> Result<Value, ConrigError> {
+        self.read_value_inner()
+            .context_path(Operation::Read, self.display_path())
+    }
+
+    fn read_value_inner(&self) -> Result<Value, ConrigError> {
+        let path = self.display_path();
+        let key = Key::open(HKEY_CURRENT_USER, &self.path, KEY_READ)
+            .map_err(FileSystemError::open_config(&path))?;
+        Ok(key.read().map_err(FileSystemError::read_config(&path))?)
+    }
+
+    /// Read and deserialize the key.
+    pub fn read<T: DeserializeOwned>(&self) -> Result<T, ConrigError> {
+        value::from_value(self.read_value()?)
+            .map_err(ConrigError::from)
+            .context_path(Operation::Read, self.display_path())
+    }
+
+    /// Serialize and write a value into the key, replacing its previous content.
+    pub fn write<T: Serialize>(&self, value: &T) -> Result<(), ConrigError> {
+        self.write_inner(value)
+            .context_path(Operation::Write, self.display_path())
+    }
+
+    fn write_inner<T: Serialize>(&self, value: &T) -> Result<(), ConrigError> {
+        let Value::Table(table) = value::to_value(value)? else {
+            let err: ValueError = ser::Error::custom("only a table can be stored in the registry");
+            return Err(err.into());
+        };
+        self.delete_inner()
+            .and_then(|()| Key::create(HKEY_CURRENT_USER, &self.path))
+            .and_then(|key| key.write_table(&table))
+            .map_err(FileSystemError::write_config(&self.display_path()))?;
+        Ok(())
+    }
+
+    /// Delete the key, along with its values and subkeys.
+    ///
+    /// Nothing happens if the key doesn't exist.
+    pub fn delete(&self) -> Result<(), ConrigError> {
+        self.delete_inner()
+            .map_err(|err| {
+                ConrigError::from(FileSystemError::write_config(&self.display_path())(err))
+            })
+            .context_path(Operation::Write, self.display_path())
+    }
+
+    fn delete_inner(&self) -> io::Result<()> {
+        let path = wide(&self.path);
+        // SAFETY: `path` is a null-terminated wide string.
+        let status = unsafe { RegDeleteTreeW(HKEY_CURRENT_USER, path.as_ptr()) };
+        match status {
+            ERROR_FILE_NOT_FOUND => Ok(()),
+            status => check(status),
+        }
+    }
+
+    /// Read and deserialize the key, or write `default` into it if it doesn't exist.
+    pub fn read_or_new<T: Serialize + DeserializeOwned>(
+        &self,
+        default: T,
+    ) -> Result<T, ConrigError> {
+        match self.read() {
+            Err(err) if err.is_missing() => {
+                self.write(&default)?;
+                Ok(default)
+            }
+            result => result,
+        }
+    }
+
+    /// Read and deserialize the key, or write the default value into it if it doesn't exist.
+    ///
+    /// This calls [`read_or_new`] internally.
+    ///
+    /// [`read_or_new`]: crate::registry::RegistryKey::read_or_new
+    pub fn read_or_default<T: Serialize + DeserializeOwned + Default>(
+        &self,
+    ) -> Result<T, ConrigError> {
+        self.read_or_new(T::default())
+    }
+}
+
+/// An open registry key, closed on drop.
+struct Key(Hkey);
+
+impl Key {
+    fn open(parent: Hkey, path: &str, access: u32) -> io::Result<Key> {
+        let path = wide(path);
+        let mut key = 0;
+        // SAFETY: `path` is a null-terminated wide string and `key` is a valid out pointer.
+        let status = unsafe { RegOpenKeyExW(parent, path.as_ptr(), 0, access, &mut key) };
+        check(status)?;
+        Ok(Key(key))
+    }
+
+    fn create(parent: Hkey, path: &str) -> io::Result<Key> {
+        let path = wide(path);
+        let mut key = 0;
+        // SAFETY: `path` is a null-terminated wide string and `key` is a valid out pointer.
+        let status = unsafe {
+            RegCreateKeyExW(
+                parent,
+                path.as_ptr(),
+                0,
+                ptr::null(),
+                REG_OPTION_NON_VOLATILE,
+                KEY_READ | KEY_WRITE,
+                ptr::null(),
+                &mut key,
+                ptr::null_mut(),
+            )
+        };
+        check(status)?;
+        Ok(Key(key))
+    }
+
+    fn read(&self) -> io::Result<Value> {
+        let (mut sub_keys, mut max_sub_key_len) = (0, 0);
+        let (mut values, mut max_value_name_len, mut max_value_len) = (0, 0, 0);
+        // SAFETY: every pointer is either null or a valid out pointer.
+        let status = unsafe {
+            RegQueryInfoKeyW(
+                self.0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut sub_keys,
+                &mut max_sub_key_len,
+                ptr::null_mut(),
+                &mut values,
+                &mut max_value_name_len,
+                &mut max_value_len,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        check(status)?;
+
+        let mut table = Table::new();
+        let mut name = vec![0u16; max_value_name_len as usize + 1];
+        let mut data = vec![0u8; max_value_len as usize];
+        for index in 0..values {
+            let mut name_len = name.len() as u32;
+            let mut data_len = data.len() as u32;
+            let mut value_type = 0;
+            // SAFETY: the buffers are as large as their lengths tell.
+            let status = unsafe {
+                RegEnumValueW(
+                    self.0,
+                    index,
+                    name.as_mut_ptr(),
+                    &mut name_len,
+                    ptr::null_mut(),
+                    &mut value_type,
+                    data.as_mut_ptr(),
+                    &mut data_len,
+                )
+            };
+            if status == ERROR_NO_MORE_ITEMS {
+                break;
+            }
+            check(status)?;
+            let name = String::from_utf16_lossy(&name[..name_len as usize]);
+            if name.is_empty() {
+                continue;
+            }
+            if let Some(value) = decode(value_type, &data[..data_len as usize])? {
+                table.insert(name, value);
+            }
+        }
+
+        let mut name = vec![0u16; max_sub_key_len as usize + 1];
+        for index in 0..sub_keys {
+            let mut name_len = name.len() as u32;
+            // SAFETY: the buffer is as large as its length tells.
+            let status = unsafe {
+                RegEnumKeyExW(
+                    self.0,
+                    index,
+                    name.as_mut_ptr(),
+                    &mut name_len,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+            if status == ERROR_NO_MORE_ITEMS {
+                break;
+            }
+            check(status)?;
+            let name = String::from_utf16_lossy(&name[..name_len as usize]);
+            let value = Key::open(self.0, &name, KEY_READ)?.read()?;
+            table.insert(name, value);
+        }
+
+        Ok(as_array(table))
+    }
+
+    fn write_table(&self, table: &Table) -> io::Result<()> {
+        for (name, value) in table {
+            self.write_entry(name, value)?;
+        }
+        Ok(())
+    }
+
+    fn write_entry(&self, name: &str, value: &Value) -> io::Result<()> {
+        match value {
+            Value::Null => Ok(()),
+            Value::Bool(v) => self.set(name, REG_DWORD, &u32::from(*v).to_le_bytes()),
+            Value::Integer(v) => self.set(name, REG_QWORD, &v.to_le_bytes()),
+            Value::Float(v) => self.set(name, REG_BINARY, &v.to_le_bytes()),
+            Value::String(v) => self.set(name, REG_SZ, &bytes(&wide(v))),
+            Value::Array(items) if items.iter().all(is_multi_sz_item) => {
+                let mut data = Vec::new();
+                for item in items {
+                    if let Value::String(item) = item {
+                        data.extend(wide(item));
+                    }
+                }
+                data.push(0);
+                self.set(name, REG_MULTI_SZ, &bytes(&data))
+            }
+            Value::Array(items) => {
+                let key = Key::create(self.0, name)?;
+                for (index, item) in items.iter().enumerate() {
+                    key.write_entry(&index.to_string(), item)?;
+                }
+                Ok(())
+            }
+            Value::Table(table) => Key::create(self.0, name)?.write_table(table),
+        }
+    }
+
+    fn set(&self, name: &str, value_type: u32, data: &[u8]) -> io::Result<()> {
+        let name = wide(name);
+        // SAFETY: `name` is a null-terminated wide string and `data` is a valid buffer.
+        let status = unsafe {
+            RegSetValueExW(
+                self.0,
+                name.as_ptr(),
+                0,
+                value_type,
+                data.as_ptr(),
+                data.len() as u32,
+            )
+        };
+        check(status)
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        // SAFETY: the key is open, and is never used again.
+        unsafe {
+            RegCloseKey(self.0);
+        }
+    }
+}
+
+/// Decode the data of a registry value, skipping the types unknown to a [`Value`].
+///
+/// `REG_BINARY` values are only known as floating point numbers, if they're 8 bytes long.
+fn decode(value_type: u32, data: &[u8]) -> io::Result<Option<Value>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed registry value");
+    Ok(Some(match value_type {
+        REG_SZ | REG_EXPAND_SZ => Value::String(string(data)),
+        REG_DWORD => {
+            Value::Integer(u32::from_le_bytes(data.try_into().map_err(|_| invalid())?).into())
+        }
+        REG_QWORD => Value::Integer(i64::from_le_bytes(data.try_into().map_err(|_| invalid())?)),
+        REG_BINARY => match data.try_into() {
+            Ok(data) => Value::Float(f64::from_le_bytes(data)),
+            Err(_) => return Ok(None),
+        },
+        REG_MULTI_SZ => Value::Array(
+            string(data)
+                .split('\0')
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_owned()))
+                .collect(),
+        ),
+        _ => return Ok(None),
+    }))
+}
+
+/// Whether an array item can be stored in a `REG_MULTI_SZ` value, which can't hold empty
+/// strings.
+fn is_multi_sz_item(item: &Value) -> bool {
+    matches!(item, Value::String(item) if !item.is_empty())
+}
+
+/// Turn a table whose keys are `0`, `1`, ... into an array.
+fn as_array(mut table: Table) -> Value {
+    let is_array =
+        !table.is_empty() && (0..table.len()).all(|index| table.contains_key(&index.to_string()));
+    if !is_array {
+        return Value::Table(table);
+    }
+    Value::Array(
+        (0..table.len())
+            .filter_map(|index| table.swap_remove(&index.to_string()))
+            .collect(),
+    )
+}
+
+/// A null-terminated wide string.
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain([0]).collect()
+}
+
+fn bytes(data: &[u16]) -> Vec<u8> {
+    data.iter().flat_map(|unit| unit.to_le_bytes()).collect()
+}
+
+fn string(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+        .trim_end_matches('\0')
+        .to_owned()
+}
+
+fn check(status: i32) -> io::Result<()> {
+    if status == ERROR_SUCCESS {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(status))
+    }
+}
@@ -0,0 +1,489 @@
+//! Remote configuration sources.
+//!
+//! A [`RemoteSource`] fetches the configuration from a list of URLs, and caches it in a local
+//! file. The cached copy is revalidated with `If-None-Match` and `If-Modified-Since`, so an
+//! unchanged configuration isn't downloaded again, and it's used as is when every URL is
+//! unreachable, e.g. offline.
+//!
+//! The URLs of a configuration are listed in [`ConfigPathMetadata.remote_urls`], and cached
+//! in the [cache directory][cache] of your application.
+//!
+//! The built-in [`Transport`] speaks plain HTTP/1.1 only: `conrig` doesn't ship any TLS
+//! implementation. To fetch `https://` URLs, register your own transport with
+//! [`set_transport`], e.g. backed by the HTTP client your application already uses.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//! }
+//!
+//! conrig!(const CONFIG<Config> = {
+//!     project_path: ProjectPath {
+//!         qualifier: "org",
+//!         organization: "foo",
+//!         application: "conrig-remote",
+//!     },
+//!     config_name: &["conrig"],
+//!     config_option: ConfigOption::DEFAULT_CONFIG,
+//!     extra_files: &[],
+//!     extra_folders: &[],
+//!     default_format: FileFormat::Toml,
+//!     remote_urls: &["http://config.example.com/conrig.toml"],
+//! });
+//!
+//! let config = CONFIG.read_remote()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ConfigPathMetadata.remote_urls`]: crate::ConfigPathMetadata#structfield.remote_urls
+//! [cache]: crate::ProjectPath::sys_cache_dir
+
+use crate::error::{ConrigResultExt, FileSystemError, Operation};
+use crate::parser::{write_atomic, ConfigFile};
+use crate::value::Value;
+use crate::{ConfigPathMetadata, ConfigType, ConrigError, ConrigWarning};
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+/// The error returned by a [`Transport`].
+pub type TransportError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A response to an HTTP `GET` request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// The status code, e.g. `200`.
+    pub status: u16,
+    /// The headers, in the order they were received.
+    pub headers: Vec<(String, String)>,
+    /// The decoded body.
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Get the value of a header, ignoring the case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A client sending the HTTP requests of [`RemoteSource`]s.
+pub trait Transport: Send + Sync {
+    /// Send a `GET` request with the extra `headers`, following the redirections.
+    ///
+    /// Only transport failures are errors: any received response, whatever its status,
+    /// should be returned.
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        timeout: Duration,
+    ) -> Result<Response, TransportError>;
+}
+
+/// The transport used by every [`RemoteSource`].
+static TRANSPORT: RwLock<Option<Arc<dyn Transport>>> = RwLock::new(None);
+
+/// Register the transport used by every [`RemoteSource`], replacing the built-in one.
+pub fn set_transport(transport: impl Transport + 'static) {
+    *TRANSPORT.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(transport));
+}
+
+/// Restore the built-in [`HttpTransport`].
+pub fn clear_transport() {
+    *TRANSPORT.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+fn transport() -> Arc<dyn Transport> {
+    TRANSPORT
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_else(|| Arc::new(HttpTransport))
+}
+
+/// The built-in transport, speaking plain HTTP/1.1 over TCP.
+///
+/// `https://` URLs are rejected: register a TLS-capable [`Transport`] to fetch them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HttpTransport;
+
+/// The number of redirections followed before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+impl Transport for HttpTransport {
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        timeout: Duration,
+    ) -> Result<Response, TransportError> {
+        let mut url = Url::parse(url)?;
+        for _ in 0..=MAX_REDIRECTS {
+            let response = url.get(headers, timeout)?;
+            if !matches!(response.status, 301 | 302 | 303 | 307 | 308) {
+                return Ok(response);
+            }
+            let location = response
+                .header("location")
+                .ok_or("redirection without a `Location` header")?;
+            url = match location.strip_prefix('/') {
+                Some(_) => Url {
+                    path: location.to_owned(),
+                    ..url
+                },
+                None => Url::parse(location)?,
+            };
+        }
+        Err(format!("more than {MAX_REDIRECTS} redirections").into())
+    }
+}
+
+const NO_TLS: &str =
+    "the built-in transport doesn't support TLS, register one with `set_transport`";
+
+/// A parsed `http://` URL.
+struct Url {
+    /// The host and the optional port, as written in the URL.
+    authority: String,
+    host: String,
+    port: u16,
+    /// The path and the query.
+    path: String,
+}
+
+impl Url {
+    fn parse(url: &str) -> Result<Url, TransportError> {
+        let rest = match url.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http") => rest,
+            Some((scheme, _)) if scheme.eq_ignore_ascii_case("https") => return Err(NO_TLS.into()),
+            _ => return Err(format!("unsupported URL `{url}`").into()),
+        };
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => (&rest[..index], rest[index..].to_owned()),
+            None => (rest, "/".to_owned()),
+        };
+        let path = if path.starts_with('?') {
+            format!("/{path}")
+        } else {
+            path
+        };
+        // the port follows the last `:`, unless it's inside an IPv6 literal.
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => (
+                host,
+                port.parse().map_err(|_| format!("invalid port `{port}`"))?,
+            ),
+            _ => (authority, 80),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(format!("no host in `{url}`").into());
+        }
+        Ok(Url {
+            authority: authority.to_owned(),
+            host: host.to_owned(),
+            port,
+            path,
+        })
+    }
+
+    fn connect(&self, timeout: Duration) -> Result<TcpStream, TransportError> {
+        let mut last_error = None;
+        for addr in (self.host.as_str(), self.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(match last_error {
+            Some(err) => err.into(),
+            None => format!("cannot resolve `{}`", self.host).into(),
+        })
+    }
+
+    fn get(&self, headers: &[(&str, &str)], timeout: Duration) -> Result<Response, TransportError> {
+        let mut stream = self.connect(timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: conrig/{}\r\n\
+             Accept-Encoding: identity\r\nConnection: close\r\n",
+            self.path,
+            self.authority,
+            env!("CARGO_PKG_VERSION"),
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes())?;
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw)?;
+        parse_response(&raw)
+    }
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response, TransportError> {
+    let end = raw
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or("truncated response head")?;
+    let head = std::str::from_utf8(&raw[..end])?;
+    let mut lines = head.split("\r\n");
+    let status_line = lines.next().unwrap_or_default();
+    let status = status_line
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| format!("invalid status line `{status_line}`"))?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_owned(), value.trim().to_owned()))
+        .collect();
+    let mut response = Response {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+    let body = &raw[end + 4..];
+    response.body = if response
+        .header("transfer-encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        decode_chunked(body)?
+    } else if let Some(length) = response.header("content-length") {
+        let length: usize = length.parse()?;
+        body.get(..length)
+            .ok_or("truncated response body")?
+            .to_vec()
+    } else {
+        body.to_vec()
+    };
+    Ok(response)
+}
+
+fn decode_chunked(mut raw: &[u8]) -> Result<Vec<u8>, TransportError> {
+    let mut body = Vec::new();
+    loop {
+        let end = raw
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or("truncated chunk")?;
+        let size = std::str::from_utf8(&raw[..end])?;
+        // drop the chunk extensions.
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)?;
+        if size == 0 {
+            return Ok(body);
+        }
+        let chunk = raw.get(end + 2..end + 2 + size).ok_or("truncated chunk")?;
+        body.extend_from_slice(chunk);
+        raw = raw.get(end + 4 + size..).ok_or("truncated chunk")?;
+    }
+}
+
+/// Where the configuration of a [`RemoteSource`] comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchStatus {
+    /// A new configuration was downloaded from this URL, and cached.
+    Downloaded(String),
+    /// The cached configuration is up to date, as told by this URL.
+    NotModified(String),
+    /// No URL is reachable, and the cached configuration is used as is.
+    Cached,
+}
+
+/// A configuration fetched from remote URLs, and cached in a local file.
+///
+/// See the [module documentation][self] for more information.
+#[derive(Debug, Clone)]
+pub struct RemoteSource {
+    /// The URLs to fetch the configuration from, in order of preference.
+    pub urls: Vec<String>,
+    /// The local copy of the configuration.
+    ///
+    /// The response bodies must be written in its format.
+    pub cache: ConfigFile,
+    /// The timeout of each network operation. Default: 10 seconds.
+    pub timeout: Duration,
+}
+
+impl RemoteSource {
+    /// Create a new `RemoteSource`.
+    pub fn new(urls: impl IntoIterator<Item = impl Into<String>>, cache: ConfigFile) -> Self {
+        Self {
+            urls: urls.into_iter().map(Into::into).collect(),
+            cache,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Modify the [`timeout`] field.
+    ///
+    /// [`timeout`]: crate::remote::RemoteSource#structfield.timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The file storing the `ETag` and the `Last-Modified` date of the cached configuration.
+    ///
+    /// This is the cache file, with an extra `.http` extension.
+    pub fn validators_path(&self) -> PathBuf {
+        let mut path = self.cache.path.clone().into_os_string();
+        path.push(".http");
+        PathBuf::from(path)
+    }
+
+    /// Refresh the cached configuration.
+    ///
+    /// The URLs are tried in order, until one of them answers. If none does, the cached copy
+    /// is used as is, and a [`RemoteUnreachable`] warning is reported. If there's no cached
+    /// copy either, the error of the last URL is returned.
+    ///
+    /// A response which can't be parsed never replaces the cached copy.
+    ///
+    /// [`RemoteUnreachable`]: crate::ConrigWarning::RemoteUnreachable
+    pub fn fetch(&self) -> Result<FetchStatus, ConrigError> {
+        let cached = self.cache.path.is_file();
+        let validators = if cached {
+            fs::read_to_string(self.validators_path()).unwrap_or_default()
+        } else {
+            String::new()
+        };
+        let headers: Vec<(&str, &str)> = validators
+            .lines()
+            .filter_map(|line| match line.split_once(':')? {
+                ("etag", etag) => Some(("If-None-Match", etag.trim())),
+                ("last-modified", date) => Some(("If-Modified-Since", date.trim())),
+                _ => None,
+            })
+            .collect();
+        let mut last_error = None;
+        for url in &self.urls {
+            match self.fetch_from(url, &headers, cached) {
+                Ok(status) => return Ok(status),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        match last_error {
+            Some(err) if cached => {
+                self.cache
+                    .config_option
+                    .warn(ConrigWarning::RemoteUnreachable(err.to_string()));
+                Ok(FetchStatus::Cached)
+            }
+            Some(err) => Err(err),
+            None if cached => Ok(FetchStatus::Cached),
+            None => Err(ConrigError::NoConfigurationFile(Vec::new())),
+        }
+    }
+
+    fn fetch_from(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        cached: bool,
+    ) -> Result<FetchStatus, ConrigError> {
+        let remote = |source: TransportError| ConrigError::Remote {
+            url: url.to_owned(),
+            source,
+        };
+        let response = transport()
+            .get(url, headers, self.timeout)
+            .map_err(remote)?;
+        match response.status {
+            304 if cached => return Ok(FetchStatus::NotModified(url.to_owned())),
+            200 => {}
+            status => return Err(remote(format!("unexpected HTTP status {status}").into())),
+        }
+        let body = String::from_utf8(response.body.clone()).map_err(|err| remote(err.into()))?;
+        self.cache
+            .file_format
+            .read_str::<Value>(&body)
+            .map_err(|err| remote(err.into()))?;
+        self.store(&body, &response)
+            .context_path(Operation::Write, &self.cache.path)?;
+        Ok(FetchStatus::Downloaded(url.to_owned()))
+    }
+
+    fn store(&self, body: &str, response: &Response) -> Result<(), ConrigError> {
+        let path = &self.cache.path;
+        let parent = path.parent().ok_or(FileSystemError::NoProjectDirectory)?;
+        fs::create_dir_all(parent).map_err(FileSystemError::write_config(parent))?;
+        write_atomic(path, &self.cache.config_option, |file| {
+            file.write_all(body.as_bytes())
+                .map_err(|err| FileSystemError::write_config(path)(err).into())
+        })?;
+        let mut validators = String::new();
+        if let Some(etag) = response.header("etag") {
+            validators.push_str(&format!("etag: {etag}\n"));
+        }
+        if let Some(date) = response.header("last-modified") {
+            validators.push_str(&format!("last-modified: {date}\n"));
+        }
+        let validators_path = self.validators_path();
+        fs::write(&validators_path, validators)
+            .map_err(FileSystemError::write_config(&validators_path))?;
+        Ok(())
+    }
+
+    /// Refresh the cached configuration, and then read it.
+    ///
+    /// See [`RemoteSource::fetch`] for more information.
+    pub fn read<T: DeserializeOwned>(&self) -> Result<T, ConrigError> {
+        self.fetch()?;
+        self.cache.read()
+    }
+}
+
+impl<'p, T> ConfigPathMetadata<'p, T> {
+    /// Get the source fetching the configuration from the [`remote_urls`].
+    ///
+    /// The configuration is cached in the [cache directory][cache],
+    /// in a file named after the first [`config_name`], in the [`default_format`].
+    ///
+    /// [`remote_urls`]: crate::ConfigPathMetadata#structfield.remote_urls
+    /// [`config_name`]: crate::ConfigPathMetadata#structfield.config_name
+    /// [`default_format`]: crate::ConfigPathMetadata#structfield.default_format
+    /// [cache]: crate::ProjectPath::sys_cache_dir
+    pub fn remote_source(&self) -> Result<RemoteSource, ConrigError> {
+        let path = self
+            .sys_dir(ConfigType::Cache)
+            .ok_or(FileSystemError::NoProjectDirectory)?
+            .join(self.default_config_name()?)
+            .with_extension(self.default_format.extension());
+        let cache =
+            ConfigFile::new(self.default_format, path).with_config_option(self.config_option);
+        Ok(RemoteSource::new(self.remote_urls.iter().copied(), cache))
+    }
+}
+
+impl<'p, T: DeserializeOwned> ConfigPathMetadata<'p, T> {
+    /// Fetch the configuration from the [`remote_urls`], or from its cached copy if they are
+    /// unreachable.
+    ///
+    /// See [`RemoteSource::fetch`] for more information.
+    ///
+    /// [`remote_urls`]: crate::ConfigPathMetadata#structfield.remote_urls
+    pub fn read_remote(&self) -> Result<T, ConrigError> {
+        self.remote_source()?.read()
+    }
+}
@@ -0,0 +1,265 @@
+//! Serializable error reports.
+//!
+//! [`ConrigError`] wraps the errors of the backends, which can't cross process boundaries.
+//! An [`ErrorReport`] is a sanitized snapshot of an error, made of plain data only,
+//! so GUI applications and RPC services can forward it to display it to users.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use conrig::error::UnknownKey;
+//! use conrig::ConrigError;
+//!
+//! let error = ConrigError::UnknownKeys(vec![UnknownKey {
+//!     key: "server.prot".to_owned(),
+//!     suggestion: Some("server.port".to_owned()),
+//! }]);
+//! let report = error.report();
+//! assert_eq!(report.kind.as_str(), "unknown_keys");
+//! assert_eq!(report.keys, ["server.prot"]);
+//! ```
+
+use crate::error::{ConrigError, Operation, ParseError};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::path::PathBuf;
+
+/// A sanitized, serializable snapshot of a [`ConrigError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    /// The kind of the error.
+    pub kind: ErrorKind,
+    /// The operation which failed, if known.
+    pub operation: Option<Operation>,
+    /// The file involved, if known.
+    pub path: Option<PathBuf>,
+    /// The human-readable description of the error.
+    pub message: String,
+    /// The dotted paths of the configuration keys involved, e.g. the unknown keys.
+    pub keys: Vec<String>,
+    /// The locations of the error in the configuration file, if reported by the backend.
+    pub spans: Vec<Span>,
+}
+
+/// A location in a configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// The line, starting from `1`.
+    pub line: usize,
+    /// The column, starting from `1`.
+    pub column: usize,
+}
+
+/// The kind of a [`ConrigError`], as reported by an [`ErrorReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// See [`ConrigError::LangError`].
+    Lang,
+    /// See [`ConrigError::FileSystemError`].
+    FileSystem,
+    /// See [`ConrigError::ValueError`].
+    Value,
+    /// See [`ConrigError::RemovedKey`].
+    RemovedKey,
+    /// See [`ConrigError::UnknownKeys`].
+    UnknownKeys,
+    /// See [`ConrigError::SchemaViolation`].
+    SchemaViolation,
+    /// See [`ConrigError::Validation`].
+    Validation,
+    /// See [`ConrigError::AmbiguousConfig`].
+    AmbiguousConfig,
+    /// See [`ConrigError::ReadOnly`].
+    ReadOnly,
+    /// See [`ConrigError::LockedKeys`].
+    LockedKeys,
+    /// See [`ConrigError::IntegrityError`].
+    IntegrityError,
+    /// See [`ConrigError::InsecurePermissions`].
+    InsecurePermissions,
+    /// See [`ConrigError::NoConfigurationFile`].
+    NoConfigurationFile,
+    /// See [`ConrigError::EmptyConfigName`].
+    EmptyConfigName,
+    /// See [`ConrigError::NoDefaultProvider`].
+    NoDefaultProvider,
+    /// See [`ConrigError::UnknownTemplate`].
+    UnknownTemplate,
+    /// See [`ConrigError::UnknownPreset`].
+    UnknownPreset,
+    /// See [`ConrigError::UnknownVariant`].
+    UnknownVariant,
+    /// See [`ConrigError::IncludeCycle`].
+    IncludeCycle,
+    /// See [`ConrigError::Cipher`].
+    Cipher,
+    /// See [`ConrigError::MixedKeys`].
+    MixedKeys,
+    /// See [`ConrigError::SingleDocument`].
+    SingleDocument,
+    /// See `ConrigError::Remote`, with the `http` feature.
+    Remote,
+    /// See [`ConrigError::Apply`].
+    Apply,
+    /// See [`ConrigError::Script`].
+    Script,
+    /// See [`ConrigError::Secret`].
+    Secret,
+}
+
+impl ErrorKind {
+    /// The `snake_case` name of the kind, used when serializing it.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lang => "lang",
+            Self::FileSystem => "file_system",
+            Self::Value => "value",
+            Self::RemovedKey => "removed_key",
+            Self::UnknownKeys => "unknown_keys",
+            Self::SchemaViolation => "schema_violation",
+            Self::Validation => "validation",
+            Self::AmbiguousConfig => "ambiguous_config",
+            Self::ReadOnly => "read_only",
+            Self::LockedKeys => "locked_keys",
+            Self::IntegrityError => "integrity_error",
+            Self::InsecurePermissions => "insecure_permissions",
+            Self::NoConfigurationFile => "no_configuration_file",
+            Self::EmptyConfigName => "empty_config_name",
+            Self::NoDefaultProvider => "no_default_provider",
+            Self::UnknownTemplate => "unknown_template",
+            Self::UnknownPreset => "unknown_preset",
+            Self::UnknownVariant => "unknown_variant",
+            Self::IncludeCycle => "include_cycle",
+            Self::Cipher => "cipher",
+            Self::MixedKeys => "mixed_keys",
+            Self::SingleDocument => "single_document",
+            Self::Remote => "remote",
+            Self::Apply => "apply",
+            Self::Script => "script",
+            Self::Secret => "secret",
+        }
+    }
+}
+
+impl ConrigError {
+    /// Create a serializable [`ErrorReport`] of the error.
+    pub fn report(&self) -> ErrorReport {
+        let operation = match self {
+            Self::Context { operation, .. } => Some(*operation),
+            _ => None,
+        };
+        let root = self.root();
+        // the key of the value which doesn't match the configuration type, if any.
+        let parse_keys = || {
+            self.parse_error()
+                .and_then(|error| error.key.clone())
+                .into_iter()
+                .collect()
+        };
+        let (kind, keys, spans) = match root {
+            Self::LangError(err) => {
+                // the parse error is located in the document, even for `toml`.
+                let location = match self.parse_error() {
+                    Some(ParseError {
+                        line: Some(line),
+                        column: Some(column),
+                        ..
+                    }) => Some((*line, *column)),
+                    _ => err.location(None),
+                };
+                let spans = location.map(|(line, column)| Span { line, column });
+                (ErrorKind::Lang, parse_keys(), spans.into_iter().collect())
+            }
+            Self::FileSystemError(_) => (ErrorKind::FileSystem, vec![], vec![]),
+            Self::ValueError(_) => (ErrorKind::Value, parse_keys(), vec![]),
+            Self::RemovedKey(deprecation) => (
+                ErrorKind::RemovedKey,
+                vec![deprecation.key.to_owned()],
+                vec![],
+            ),
+            Self::UnknownKeys(keys) => (
+                ErrorKind::UnknownKeys,
+                keys.iter().map(|t| t.key.clone()).collect(),
+                vec![],
+            ),
+            Self::SchemaViolation(violations) => (
+                ErrorKind::SchemaViolation,
+                violations.iter().map(|t| t.path.clone()).collect(),
+                vec![],
+            ),
+            Self::Validation(violations) => (
+                ErrorKind::Validation,
+                violations.iter().map(|t| t.path.clone()).collect(),
+                vec![],
+            ),
+            Self::AmbiguousConfig(_) => (ErrorKind::AmbiguousConfig, vec![], vec![]),
+            Self::ReadOnly(_) => (ErrorKind::ReadOnly, vec![], vec![]),
+            Self::LockedKeys(keys) => (ErrorKind::LockedKeys, keys.clone(), vec![]),
+            Self::IntegrityError { .. } => (ErrorKind::IntegrityError, vec![], vec![]),
+            Self::InsecurePermissions(_) => (ErrorKind::InsecurePermissions, vec![], vec![]),
+            Self::NoConfigurationFile(_) => (ErrorKind::NoConfigurationFile, vec![], vec![]),
+            Self::EmptyConfigName => (ErrorKind::EmptyConfigName, vec![], vec![]),
+            Self::NoDefaultProvider => (ErrorKind::NoDefaultProvider, vec![], vec![]),
+            Self::UnknownTemplate(_) => (ErrorKind::UnknownTemplate, vec![], vec![]),
+            Self::UnknownPreset(_) => (ErrorKind::UnknownPreset, vec![], vec![]),
+            Self::UnknownVariant(_) => (ErrorKind::UnknownVariant, vec![], vec![]),
+            Self::IncludeCycle(_) => (ErrorKind::IncludeCycle, vec![], vec![]),
+            Self::Cipher(_) => (ErrorKind::Cipher, vec![], vec![]),
+            Self::MixedKeys(keys) => (ErrorKind::MixedKeys, keys.clone(), vec![]),
+            Self::SingleDocument(_) => (ErrorKind::SingleDocument, vec![], vec![]),
+            #[cfg(feature = "http")]
+            Self::Remote { .. } => (ErrorKind::Remote, vec![], vec![]),
+            Self::Apply { section, .. } => (ErrorKind::Apply, vec![section.clone()], vec![]),
+            Self::Script { .. } => (ErrorKind::Script, vec![], vec![]),
+            Self::Secret { key, .. } => (ErrorKind::Secret, vec![key.clone()], vec![]),
+            // `root` never returns a context, but stay panic-free anyway.
+            Self::Context { source, .. } | Self::Parse { source, .. } => {
+                let report = source.report();
+                (report.kind, report.keys, report.spans)
+            }
+        };
+        ErrorReport {
+            kind,
+            operation,
+            path: self.path().map(PathBuf::from),
+            message: root.to_string(),
+            keys,
+            spans,
+        }
+    }
+}
+
+impl From<&ConrigError> for ErrorReport {
+    fn from(value: &ConrigError) -> Self {
+        value.report()
+    }
+}
+
+impl Serialize for ErrorReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut report = serializer.serialize_struct("ErrorReport", 6)?;
+        report.serialize_field("kind", self.kind.as_str())?;
+        report.serialize_field(
+            "operation",
+            &self.operation.map(|operation| operation.to_string()),
+        )?;
+        report.serialize_field(
+            "path",
+            &self.path.as_ref().map(|path| path.to_string_lossy()),
+        )?;
+        report.serialize_field("message", &self.message)?;
+        report.serialize_field("keys", &self.keys)?;
+        report.serialize_field("spans", &self.spans)?;
+        report.end()
+    }
+}
+
+impl Serialize for Span {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut span = serializer.serialize_struct("Span", 2)?;
+        span.serialize_field("line", &self.line)?;
+        span.serialize_field("column", &self.column)?;
+        span.end()
+    }
+}
@@ -0,0 +1,147 @@
+//! Telling the changes which can be hot-applied from those which need a restart.
+//!
+//! Some settings are only read once, at startup: a listening port, a thread pool size...
+//! Mark their fields with `#[conrig(restart_required)]`, and [`diff`] the configuration
+//! before and after a change, or reload it with [`ConfigFile::reload_diff`]: the resulting
+//! [`ConfigDiff`] tells whether the change can be applied at once, or needs a restart.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use conrig::restart::{diff, RestartRequired};
+//! # use serde_derive::Serialize;
+//!
+//! #[derive(Clone, Serialize)]
+//! struct Config {
+//!     port: u16,
+//!     log_level: String,
+//! }
+//!
+//! impl RestartRequired for Config {
+//!     const RESTART_REQUIRED: &'static [&'static str] = &["port"];
+//! }
+//!
+//! let old = Config { port: 8080, log_level: "info".into() };
+//! let mut new = old.clone();
+//! new.log_level = "debug".into();
+//! assert!(!diff(&old, &new)?.needs_restart());
+//!
+//! new.port = 8081;
+//! assert_eq!(diff(&old, &new)?.restart_required, ["port"]);
+//! # Ok::<(), conrig::ConrigError>(())
+//! ```
+//!
+//! [`ConfigFile::reload_diff`]: crate::parser::ConfigFile::reload_diff
+
+use crate::value::{to_value, Value};
+use crate::ConrigError;
+use serde::Serialize;
+
+/// A configuration with settings which can't be applied without a restart.
+///
+/// This is implemented by `#[derive(Conrig)]`, from the `#[conrig(restart_required)]` fields.
+pub trait RestartRequired {
+    /// The dotted paths of the settings requiring a restart. Default: none.
+    ///
+    /// A path covers every key nested inside it.
+    const RESTART_REQUIRED: &'static [&'static str] = &[];
+}
+
+/// The changes between two versions of a configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    /// The dotted paths of the changed keys, including the added and removed ones.
+    pub changed: Vec<String>,
+    /// The changed keys requiring a restart, a subset of [`changed`].
+    ///
+    /// [`changed`]: crate::restart::ConfigDiff#structfield.changed
+    pub restart_required: Vec<String>,
+}
+
+impl ConfigDiff {
+    /// Whether nothing has changed.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+    }
+
+    /// Whether some changes only take effect after a restart.
+    pub fn needs_restart(&self) -> bool {
+        !self.restart_required.is_empty()
+    }
+
+    /// Whether every change can be applied without a restart.
+    pub fn can_hot_apply(&self) -> bool {
+        !self.needs_restart()
+    }
+
+    /// Whether the section at the dotted path `section` has changes which don't require
+    /// a restart. An empty `section` matches any change.
+    pub(crate) fn is_hot_applicable(&self, section: &str) -> bool {
+        self.changed
+            .iter()
+            .filter(|path| !self.restart_required.contains(path))
+            .any(|path| is_within(path, section) || is_within(section, path))
+    }
+}
+
+/// Compare two versions of a configuration.
+pub fn diff<T: Serialize + RestartRequired>(old: &T, new: &T) -> Result<ConfigDiff, ConrigError> {
+    Ok(diff_values(
+        &to_value(old)?,
+        &to_value(new)?,
+        T::RESTART_REQUIRED,
+    ))
+}
+
+pub(crate) fn diff_values(old: &Value, new: &Value, restart_required: &[&str]) -> ConfigDiff {
+    let mut changed = Vec::new();
+    changed_paths(old, new, "", &mut changed);
+    let restart_required = changed
+        .iter()
+        .filter(|path| {
+            restart_required
+                .iter()
+                .any(|key| is_within(path, key) || is_within(key, path))
+        })
+        .cloned()
+        .collect();
+    ConfigDiff {
+        changed,
+        restart_required,
+    }
+}
+
+/// Whether `path` is `key`, or nested inside it.
+fn is_within(path: &str, key: &str) -> bool {
+    key.is_empty()
+        || path
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+fn changed_paths(old: &Value, new: &Value, prefix: &str, changed: &mut Vec<String>) {
+    let (Value::Table(old), Value::Table(new)) = (old, new) else {
+        if old != new {
+            changed.push(prefix.to_owned());
+        }
+        return;
+    };
+    let join = |key: &str| {
+        if prefix.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{prefix}.{key}")
+        }
+    };
+    for (key, old_value) in old {
+        match new.get(key) {
+            Some(new_value) => changed_paths(old_value, new_value, &join(key), changed),
+            None => changed.push(join(key)),
+        }
+    }
+    changed.extend(
+        new.keys()
+            .filter(|key| !old.contains_key(*key))
+            .map(|key| join(key)),
+    );
+}
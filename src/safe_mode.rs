@@ -0,0 +1,209 @@
+//! Starting with the default configuration after a configuration-caused failure.
+//!
+//! When a broken configuration makes an application crash at startup, restarting it only
+//! crashes it again. [`ConfigPathMetadata::read_safe_mode`] is the way out: it sets the
+//! configuration file aside, and returns the [default value] instead, so the application
+//! starts, and can tell the user with [`is_active`] that their configuration was ignored.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::{conrig, safe_mode, ConfigOption, FileFormat, ProjectPath};
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Default, Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//! }
+//!
+//! conrig!(const CONFIG<Config> = {
+//!     project_path: ProjectPath {
+//!         qualifier: "org",
+//!         organization: "foo",
+//!         application: "conrig-safe-mode",
+//!     },
+//!     config_name: &["conrig-safe-mode"],
+//!     config_option: ConfigOption::DEFAULT_CONFIG,
+//!     extra_files: &[],
+//!     extra_folders: &[],
+//!     default_format: FileFormat::Toml,
+//! });
+//!
+//! let safe = CONFIG.read_safe_mode()?;
+//! assert!(safe_mode::is_active());
+//! if let Some(path) = &safe.moved_aside {
+//!     println!("Started in safe mode, your configuration was moved to {}", path.display());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [default value]: crate::ConfigPathMetadata::default_value
+//!
+//! ## Crash loops
+//!
+//! A [`CrashGuard`] counts the consecutive starts which didn't end with a clean exit, in a
+//! small file managed by `conrig`. Call [`CrashGuard::mark_start`] at startup, and
+//! [`CrashGuard::mark_clean_exit`] before exiting normally: after [`threshold`] unclean
+//! starts in a row, safe mode is recommended, or triggered by
+//! [`ConfigPathMetadata::read_guarded`].
+//!
+//! [`threshold`]: CrashGuard::with_threshold
+
+use crate::error::FileSystemError;
+use crate::{ConfigPathMetadata, ConfigType, ConrigError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the configuration has been read in safe mode by this process.
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// A configuration read in safe mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafeMode<T> {
+    /// The default configuration.
+    pub value: T,
+    /// Where the configuration file has been moved, if there was one.
+    pub moved_aside: Option<PathBuf>,
+}
+
+/// The path a configuration file is set aside to: `<file>.broken`.
+pub fn aside_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".broken");
+    path.with_file_name(file_name)
+}
+
+impl<'p, T: Default> ConfigPathMetadata<'p, T> {
+    /// Ignore the configuration file, and get the [default value] instead.
+    ///
+    /// The configuration file, if any, is renamed to its [`aside_path`], replacing an
+    /// older one, so the next start doesn't read it either. Afterwards, [`is_active`]
+    /// returns `true`.
+    ///
+    /// [default value]: crate::ConfigPathMetadata::default_value
+    pub fn read_safe_mode(&self) -> Result<SafeMode<T>, ConrigError> {
+        let file = self.search_config_file()?.fallback_default()?;
+        let value = self.default_value()?;
+        let moved_aside = if crate::filesystem::exists(&file.path) {
+            let aside = aside_path(&file.path);
+            crate::filesystem::rename(&file.path, &aside)?;
+            Some(aside)
+        } else {
+            None
+        };
+        ACTIVE.store(true, Ordering::Relaxed);
+        Ok(SafeMode { value, moved_aside })
+    }
+}
+
+/// A counter of the consecutive unclean starts of an application.
+///
+/// See the [module documentation][self] for more information.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashGuard {
+    /// The path of the counter file.
+    pub path: PathBuf,
+    threshold: u32,
+}
+
+/// The outcome of [`CrashGuard::mark_start`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartCheck {
+    /// The number of consecutive previous starts which didn't exit cleanly.
+    pub unclean_starts: u32,
+    /// Whether the configuration should be read in safe mode.
+    pub safe_mode_recommended: bool,
+}
+
+impl CrashGuard {
+    /// Create a new `CrashGuard` keeping its counter at `path`.
+    pub const fn new(path: PathBuf) -> Self {
+        Self { path, threshold: 3 }
+    }
+
+    /// Set the number of consecutive unclean starts recommending safe mode. Default: `3`.
+    pub const fn with_threshold(mut self, threshold: u32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// The number of consecutive unclean starts recommending safe mode.
+    pub const fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Record a start, which is unclean until [`mark_clean_exit`] is called.
+    ///
+    /// A missing or unreadable counter counts as no unclean start.
+    ///
+    /// [`mark_clean_exit`]: CrashGuard::mark_clean_exit
+    pub fn mark_start(&self) -> Result<StartCheck, ConrigError> {
+        let unclean_starts = crate::filesystem::read(&self.path)
+            .ok()
+            .and_then(|contents| String::from_utf8(contents).ok())
+            .and_then(|contents| contents.trim().parse::<u32>().ok())
+            .unwrap_or(0);
+        self.store(unclean_starts.saturating_add(1))?;
+        Ok(StartCheck {
+            unclean_starts,
+            safe_mode_recommended: unclean_starts >= self.threshold,
+        })
+    }
+
+    /// Record a clean exit, resetting the counter.
+    pub fn mark_clean_exit(&self) -> Result<(), ConrigError> {
+        self.store(0)
+    }
+
+    fn store(&self, count: u32) -> Result<(), ConrigError> {
+        if let Some(parent) = self.path.parent() {
+            crate::filesystem::create_dir_all(parent)?;
+        }
+        crate::filesystem::write(&self.path, count.to_string().as_bytes())?;
+        Ok(())
+    }
+}
+
+impl<'p, T> ConfigPathMetadata<'p, T> {
+    /// Get the crash guard of the configuration.
+    ///
+    /// Its counter is kept in the [state directory], or the data directory if there's none,
+    /// as `<config_name>.starts`.
+    ///
+    /// [state directory]: crate::ConfigType::State
+    pub fn crash_guard(&self) -> Result<CrashGuard, ConrigError> {
+        let dir = self
+            .sys_dir(ConfigType::State)
+            .or_else(|| self.sys_dir(ConfigType::Data))
+            .ok_or(FileSystemError::NoProjectDirectory)?;
+        let file_name = format!("{}.starts", self.default_config_name()?);
+        Ok(CrashGuard::new(dir.join(file_name)))
+    }
+}
+
+impl<'p, T: Serialize + DeserializeOwned + Default> ConfigPathMetadata<'p, T> {
+    /// Record a start with `guard`, then read the configuration, in safe mode if the
+    /// application is crash looping.
+    ///
+    /// Otherwise, this behaves like [`read_or_default`], and [`moved_aside`] is `None`.
+    ///
+    /// [`read_or_default`]: crate::ConfigPathMetadata::read_or_default
+    /// [`moved_aside`]: crate::safe_mode::SafeMode#structfield.moved_aside
+    pub fn read_guarded(&self, guard: &CrashGuard) -> Result<SafeMode<T>, ConrigError> {
+        if guard.mark_start()?.safe_mode_recommended {
+            return self.read_safe_mode();
+        }
+        Ok(SafeMode {
+            value: self.read_or_default()?,
+            moved_aside: None,
+        })
+    }
+}
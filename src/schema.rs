@@ -0,0 +1,547 @@
+//! JSON Schema generation and validation, powered by [`schemars`].
+//!
+//! Derive [`JsonSchema`] for your configuration structure, then:
+//! - [`ConfigPathMetadata::schema`] emits the JSON Schema of your configuration,
+//!   which can be shipped to editors to provide completion and linting;
+//! - [`ConfigPathMetadata::read_validated`] checks the configuration file against the schema
+//!   before deserializing it, and reports every [`Violation`] at once;
+//! - [`ConfigPathMetadata::keys`] lists the settable keys with their types, e.g. to generate
+//!   the shell completions of a `config set <key>` command.
+//!
+//! The validation covers the keywords emitted by `schemars`: `$ref`, `type`, `enum`, `const`,
+//! the numeric, length and size bounds, `pattern`, `properties`, `required`,
+//! `additionalProperties`, `items`, `prefixItems`, `allOf`, `anyOf`, `oneOf` and `not`.
+//! Other keywords, like `format`, are ignored.
+//!
+//! To export the constraints declared with `#[derive(Conrig)]`, pass [`constrain`]
+//! to `schemars`: `#[schemars(transform = conrig::schema::constrain::<Self>)]`.
+//!
+//! [`ConfigPathMetadata::schema`]: crate::ConfigPathMetadata::schema
+//! [`ConfigPathMetadata::read_validated`]: crate::ConfigPathMetadata::read_validated
+//! [`ConfigPathMetadata::keys`]: crate::ConfigPathMetadata::keys
+
+use crate::error::{ConrigResultExt, Operation, Violation};
+use crate::parser::{ConfigFile, RawConfigSource};
+use crate::pattern::Pattern;
+use crate::validate::{Constrained, Constraint};
+use crate::value::{from_value, to_value, Table, Value};
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::de::DeserializeOwned;
+
+pub use schemars::{JsonSchema, Schema};
+
+impl<'p, T: JsonSchema> ConfigPathMetadata<'p, T> {
+    /// Generate the JSON Schema of your configuration.
+    pub fn schema(&self) -> Schema {
+        schemars::schema_for!(T)
+    }
+
+    /// List the keys of your configuration, as described by its [schema].
+    ///
+    /// See [`keys`] for more information.
+    ///
+    /// [schema]: crate::ConfigPathMetadata::schema
+    pub fn keys(&self) -> Result<Vec<KeyInfo>, ConrigError> {
+        Ok(keys(&to_value(&self.schema())?))
+    }
+}
+
+impl<'p, T: DeserializeOwned + JsonSchema> ConfigPathMetadata<'p, T> {
+    /// Read a configuration file, validating it against the [schema] of your configuration.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_validated::<T>()`.
+    ///
+    /// [schema]: crate::ConfigPathMetadata::schema
+    pub fn read_validated(&self) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_validated()
+    }
+}
+
+impl<'a, 'p, T: DeserializeOwned + JsonSchema> RawConfigSource<'a, 'p, T> {
+    /// Read and deserialize the configuration file,
+    /// validating it against the [schema] of your configuration.
+    ///
+    /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
+    ///
+    /// [schema]: crate::ConfigPathMetadata::schema
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn read_validated(&self) -> Result<T, ConrigError> {
+        self.config_file()?.read_validated()
+    }
+}
+
+impl ConfigFile {
+    /// Read and deserialize the configuration file,
+    /// validating it against the JSON Schema of `T` first.
+    ///
+    /// If the document doesn't match the schema, a [`SchemaViolation`] error listing
+    /// every violation will be returned.
+    ///
+    /// [`SchemaViolation`]: crate::ConrigError::SchemaViolation
+    pub fn read_validated<T: DeserializeOwned + JsonSchema>(&self) -> Result<T, ConrigError> {
+        self.read_validated_inner()
+            .context_path(Operation::Read, &self.path)
+    }
+
+    fn read_validated_inner<T: DeserializeOwned + JsonSchema>(&self) -> Result<T, ConrigError> {
+        let document = self.read_document()?;
+        let violations = validate(&to_value(&schemars::schema_for!(T))?, &document);
+        if !violations.is_empty() {
+            return Err(ConrigError::SchemaViolation(violations));
+        }
+        self.decode(document)
+    }
+}
+
+/// Add the [constraints][Constrained::CONSTRAINTS] of `T` to its schema.
+///
+/// The constraints become the `minimum`, `maximum`, `pattern` and `enum` keywords of the
+/// properties. Use it as a `schemars` transform:
+///
+/// ```rust
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use conrig::schema::JsonSchema;
+/// use conrig::Conrig;
+/// # use serde_derive::{Deserialize, Serialize};
+///
+/// #[derive(Default, Serialize, Deserialize, JsonSchema, Conrig)]
+/// #[conrig(app = "conrig-test", org = "my-organization", qualifier = "org")]
+/// #[schemars(transform = conrig::schema::constrain::<Self>)]
+/// struct Config {
+///     #[conrig(min = 1, max = 65535)]
+///     port: u16,
+/// }
+///
+/// let schema = Config::CONFIG.schema();
+/// assert_eq!(schema.get("properties").unwrap()["port"]["maximum"], 65535.0);
+/// # }
+/// ```
+pub fn constrain<T: Constrained>(schema: &mut Schema) {
+    let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) else {
+        return;
+    };
+    for (field, constraint) in T::CONSTRAINTS {
+        let Some(property) = properties.get_mut(*field).and_then(|p| p.as_object_mut()) else {
+            continue;
+        };
+        let (keyword, value) = match constraint {
+            Constraint::Min(min) => ("minimum", Value::Float(*min)),
+            Constraint::Max(max) => ("maximum", Value::Float(*max)),
+            Constraint::Regex(regex) => ("pattern", Value::String((*regex).to_owned())),
+            Constraint::OneOf(values) => {
+                let mut values: Vec<Value> = values
+                    .iter()
+                    .map(|value| Value::String((*value).to_owned()))
+                    .collect();
+                // `None` must stay allowed for optional fields.
+                let nullable = property
+                    .get("type")
+                    .and_then(|types| types.as_array())
+                    .is_some_and(|types| types.iter().any(|name| name == "null"));
+                if nullable {
+                    values.push(Value::Null);
+                }
+                ("enum", Value::Array(values))
+            }
+            Constraint::Requires(_) => continue,
+        };
+        if let Ok(value) = from_value(value) {
+            property.insert(keyword.to_owned(), value);
+        }
+    }
+}
+
+/// A key of a configuration, as described by its schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyInfo {
+    /// The dotted path of the key, e.g. `server.port`.
+    pub path: String,
+    /// The JSON Schema types of the value, e.g. `["integer"]`, or empty if unrestricted.
+    pub types: Vec<String>,
+    /// The allowed values, if the schema restricts them with `enum`.
+    pub allowed: Vec<Value>,
+    /// The description of the key, taken from its doc comment by `schemars`.
+    pub description: Option<String>,
+}
+
+/// List the keys of a configuration from its JSON Schema, in order.
+///
+/// Only the leaves are listed: the objects with declared `properties` are walked into,
+/// while any other value, including arrays and maps, is a single key. References are
+/// followed, and the types of the `anyOf` and `oneOf` alternatives are merged.
+pub fn keys(schema: &Value) -> Vec<KeyInfo> {
+    let mut keys = Vec::new();
+    collect_keys(schema, schema, "", &mut keys);
+    keys
+}
+
+fn collect_keys(root: &Value, schema: &Value, path: &str, keys: &mut Vec<KeyInfo>) {
+    let schema = follow_references(root, schema);
+    if let Some(Value::Table(properties)) = schema.get_path("properties") {
+        for (key, property) in properties {
+            collect_keys(root, property, &join(path, key), keys);
+        }
+        return;
+    }
+    if path.is_empty() {
+        return;
+    }
+    let mut key = KeyInfo {
+        path: path.to_owned(),
+        types: Vec::new(),
+        allowed: Vec::new(),
+        description: None,
+    };
+    describe_key(root, schema, &mut key);
+    keys.push(key);
+}
+
+/// Fill the types, allowed values and description of a key from its schema.
+fn describe_key(root: &Value, schema: &Value, key: &mut KeyInfo) {
+    if key.description.is_none() {
+        if let Some(Value::String(description)) = schema.get_path("description") {
+            key.description = Some(description.clone());
+        }
+    }
+    let schema = follow_references(root, schema);
+    match schema.get_path("type") {
+        Some(Value::String(name)) => push_unique(&mut key.types, name),
+        Some(Value::Array(names)) => {
+            for name in names {
+                if let Value::String(name) = name {
+                    push_unique(&mut key.types, name);
+                }
+            }
+        }
+        _ => {}
+    }
+    if let Some(Value::Array(allowed)) = schema.get_path("enum") {
+        key.allowed.extend(allowed.iter().cloned());
+    }
+    if let Some(allowed) = schema.get_path("const") {
+        key.allowed.push(allowed.clone());
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(schemas)) = schema.get_path(keyword) {
+            for schema in schemas {
+                describe_key(root, schema, key);
+            }
+        }
+    }
+}
+
+fn push_unique(names: &mut Vec<String>, name: &str) {
+    if !names.iter().any(|known| known == name) {
+        names.push(name.to_owned());
+    }
+}
+
+/// Follow the `$ref` of a schema, keeping it if the reference can't be resolved.
+fn follow_references<'s>(root: &'s Value, mut schema: &'s Value) -> &'s Value {
+    // a reference cycle without any `properties` is the only way to loop forever.
+    for _ in 0..32 {
+        match schema.get_path("$ref") {
+            Some(Value::String(reference)) => match resolve(root, reference) {
+                Some(resolved) => schema = resolved,
+                None => break,
+            },
+            _ => break,
+        }
+    }
+    schema
+}
+
+/// Resolve a local JSON pointer reference, e.g. `#/$defs/Listener`.
+fn resolve<'s>(root: &'s Value, reference: &str) -> Option<&'s Value> {
+    let pointer = reference.strip_prefix('#')?;
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .try_fold(root, |schema, segment| match schema {
+            Value::Table(table) => table.get(&segment),
+            Value::Array(array) => array.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// Validate a document against a JSON Schema, returning every violation found.
+pub fn validate(schema: &Value, document: &Value) -> Vec<Violation> {
+    let mut validator = Validator {
+        root: schema,
+        violations: Vec::new(),
+    };
+    validator.check(schema, document, "");
+    validator.violations
+}
+
+struct Validator<'s> {
+    root: &'s Value,
+    violations: Vec<Violation>,
+}
+
+impl<'s> Validator<'s> {
+    fn report(&mut self, path: &str, message: String) {
+        self.violations.push(Violation::new(path, message));
+    }
+
+    /// Check a value against a sub-schema without reporting anything.
+    fn matches(&self, schema: &'s Value, value: &Value, path: &str) -> bool {
+        let mut validator = Validator {
+            root: self.root,
+            violations: Vec::new(),
+        };
+        validator.check(schema, value, path);
+        validator.violations.is_empty()
+    }
+
+    fn check(&mut self, schema: &'s Value, value: &Value, path: &str) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return self.report(path, "no value is allowed".to_owned()),
+            Value::Table(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(Value::String(reference)) = schema.get("$ref") {
+            match self.resolve(reference) {
+                Some(resolved) => self.check(resolved, value, path),
+                None => self.report(path, format!("unresolvable schema reference `{reference}`")),
+            }
+        }
+        if let Some(expected) = schema.get("type") {
+            let names: Vec<&str> = match expected {
+                Value::String(name) => vec![name],
+                Value::Array(names) => names
+                    .iter()
+                    .filter_map(|name| match name {
+                        Value::String(name) => Some(name.as_str()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            if !names.is_empty() && !names.iter().any(|name| type_matches(name, value)) {
+                return self.report(
+                    path,
+                    format!(
+                        "expected {}, found {}",
+                        names.join(" or "),
+                        type_name(value)
+                    ),
+                );
+            }
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            if !allowed.iter().any(|allowed| values_equal(allowed, value)) {
+                self.report(path, format!("{} is not an allowed value", describe(value)));
+            }
+        }
+        if let Some(allowed) = schema.get("const") {
+            if !values_equal(allowed, value) {
+                self.report(path, format!("expected {}", describe(allowed)));
+            }
+        }
+
+        self.check_bounds(schema, value, path);
+        match value {
+            Value::Table(table) => self.check_table(schema, table, path),
+            Value::Array(array) => self.check_array(schema, array, path),
+            _ => {}
+        }
+
+        if let Some(Value::Array(schemas)) = schema.get("allOf") {
+            for schema in schemas {
+                self.check(schema, value, path);
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("anyOf") {
+            if !schemas
+                .iter()
+                .any(|schema| self.matches(schema, value, path))
+            {
+                self.report(path, "doesn't match any of the allowed schemas".to_owned());
+            }
+        }
+        if let Some(Value::Array(schemas)) = schema.get("oneOf") {
+            let matched = schemas
+                .iter()
+                .filter(|schema| self.matches(schema, value, path))
+                .count();
+            if matched != 1 {
+                self.report(
+                    path,
+                    format!("expected to match exactly one schema, matched {matched}"),
+                );
+            }
+        }
+        if let Some(schema) = schema.get("not") {
+            if self.matches(schema, value, path) {
+                self.report(path, "matches a forbidden schema".to_owned());
+            }
+        }
+    }
+
+    fn check_bounds(&mut self, schema: &Table, value: &Value, path: &str) {
+        let bound = |key: &str| schema.get(key).and_then(as_number);
+        if let Some(number) = as_number(value) {
+            if let Some(minimum) = bound("minimum").filter(|minimum| number < *minimum) {
+                self.report(path, format!("{number} is less than the minimum {minimum}"));
+            }
+            if let Some(maximum) = bound("maximum").filter(|maximum| number > *maximum) {
+                self.report(
+                    path,
+                    format!("{number} is greater than the maximum {maximum}"),
+                );
+            }
+            if let Some(minimum) = bound("exclusiveMinimum").filter(|minimum| number <= *minimum) {
+                self.report(path, format!("{number} is not greater than {minimum}"));
+            }
+            if let Some(maximum) = bound("exclusiveMaximum").filter(|maximum| number >= *maximum) {
+                self.report(path, format!("{number} is not less than {maximum}"));
+            }
+        }
+
+        if let (Some(Value::String(pattern)), Value::String(string)) =
+            (schema.get("pattern"), value)
+        {
+            match Pattern::new(pattern) {
+                Ok(compiled) if !compiled.is_match(string) => self.report(
+                    path,
+                    format!("{} doesn't match the pattern `{pattern}`", describe(value)),
+                ),
+                Err(err) => self.report(path, format!("invalid pattern `{pattern}`: {err}")),
+                Ok(_) => {}
+            }
+        }
+
+        let (length, unit) = match value {
+            Value::String(string) => (string.chars().count(), ("minLength", "maxLength")),
+            Value::Array(array) => (array.len(), ("minItems", "maxItems")),
+            Value::Table(table) => (table.len(), ("minProperties", "maxProperties")),
+            _ => return,
+        };
+        let length = length as f64;
+        if let Some(minimum) = bound(unit.0).filter(|minimum| length < *minimum) {
+            self.report(
+                path,
+                format!("length {length} is less than the minimum {minimum}"),
+            );
+        }
+        if let Some(maximum) = bound(unit.1).filter(|maximum| length > *maximum) {
+            self.report(
+                path,
+                format!("length {length} is greater than the maximum {maximum}"),
+            );
+        }
+    }
+
+    fn check_table(&mut self, schema: &'s Table, table: &Table, path: &str) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required {
+                if let Value::String(key) = key {
+                    if !table.contains_key(key) {
+                        self.report(&join(path, key), "missing required key".to_owned());
+                    }
+                }
+            }
+        }
+        let properties = match schema.get("properties") {
+            Some(Value::Table(properties)) => Some(properties),
+            _ => None,
+        };
+        for (key, value) in table {
+            let path = join(path, key);
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(schema) => self.check(schema, value, &path),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => self.report(&path, "unknown key".to_owned()),
+                    Some(schema) => self.check(schema, value, &path),
+                    None => {}
+                },
+            }
+        }
+    }
+
+    fn check_array(&mut self, schema: &'s Table, array: &[Value], path: &str) {
+        let prefix = match schema.get("prefixItems") {
+            Some(Value::Array(prefix)) => prefix.as_slice(),
+            _ => &[],
+        };
+        for (index, value) in array.iter().enumerate() {
+            let path = join(path, &index.to_string());
+            if let Some(schema) = prefix.get(index).or_else(|| schema.get("items")) {
+                self.check(schema, value, &path);
+            }
+        }
+    }
+
+    /// Resolve a local reference, e.g. `#/$defs/Config`.
+    fn resolve(&self, reference: &str) -> Option<&'s Value> {
+        resolve(self.root, reference)
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn type_matches(name: &str, value: &Value) -> bool {
+    match (name, value) {
+        ("null", Value::Null)
+        | ("boolean", Value::Bool(_))
+        | ("integer", Value::Integer(_))
+        | ("number", Value::Integer(_) | Value::Float(_))
+        | ("string", Value::String(_))
+        | ("array", Value::Array(_))
+        | ("object", Value::Table(_)) => true,
+        ("integer", Value::Float(float)) => float.fract() == 0.0,
+        _ => false,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Integer(_) => "integer",
+        Value::Float(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Table(_) => "object",
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_owned(),
+        Value::Bool(bool) => bool.to_string(),
+        Value::Integer(integer) => integer.to_string(),
+        Value::Float(float) => float.to_string(),
+        Value::String(string) => format!("{string:?}"),
+        other => type_name(other).to_owned(),
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(integer) => Some(*integer as f64),
+        Value::Float(float) => Some(*float),
+        _ => None,
+    }
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (as_number(left), as_number(right)) {
+        (Some(left), Some(right)) => left == right,
+        _ => left == right,
+    }
+}
@@ -0,0 +1,107 @@
+//! Scripts post-processing the configuration before it's deserialized.
+//!
+//! If [`ConfigOption.script`] is set, e.g. to `conrig.rhai`, and that script exists next to
+//! the configuration file, every document read from the file is passed through the registered
+//! [`ScriptEngine`] first. Power users can compute derived keys or apply conditional tweaks,
+//! without the application hard-coding that logic. The configuration file itself is left
+//! untouched, and so are the values written back into it.
+//!
+//! `conrig` doesn't embed any scripting language: the [`ScriptEngine`] is yours, e.g. a thin
+//! wrapper around [`rhai`](https://docs.rs/rhai) converting the [`Value`] from and into its
+//! own dynamic values.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::parser::ConfigFile;
+//! use conrig::script::{set_script_engine, ScriptEngine, ScriptError};
+//! use conrig::value::Value;
+//! use conrig::{ConfigOption, FileFormat};
+//!
+//! /// A toy engine, whose scripts are `<key> = <other key>` aliases.
+//! struct Aliases;
+//!
+//! impl ScriptEngine for Aliases {
+//!     fn run(&self, script: &str, mut document: Value) -> Result<Value, ScriptError> {
+//!         for line in script.lines() {
+//!             let (key, source) = line.split_once(" = ").ok_or("expected `key = key`")?;
+//!             let value = document.get_path(source).cloned().unwrap_or_default();
+//!             document.set_path(key, value)?;
+//!         }
+//!         Ok(document)
+//!     }
+//! }
+//!
+//! set_script_engine(Aliases);
+//!
+//! # let dir = std::env::temp_dir().join("conrig-script-doc");
+//! # std::fs::create_dir_all(&dir).unwrap();
+//! std::fs::write(dir.join("conrig.rhai"), "mirror = port").unwrap();
+//! let file = ConfigFile::new(FileFormat::Toml, dir.join("conrig.toml"))
+//!     .with_config_option(ConfigOption::DEFAULT_CONFIG.with_script("conrig.rhai"));
+//! std::fs::write(&file.path, "port = 80").unwrap();
+//! assert_eq!(file.read::<Value>()?.get_path("mirror"), Some(&Value::Integer(80)));
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ConfigOption.script`]: crate::ConfigOption#structfield.script
+
+use crate::error::FileSystemError;
+use crate::parser::ConfigFile;
+use crate::value::Value;
+use crate::ConrigError;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// The error returned by a [`ScriptEngine`].
+pub type ScriptError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A scripting engine running the scripts which post-process the configuration.
+pub trait ScriptEngine: Send + Sync {
+    /// Run `script` on the document read from the configuration file,
+    /// returning the document to deserialize.
+    fn run(&self, script: &str, document: Value) -> Result<Value, ScriptError>;
+}
+
+/// The engine running every script.
+static SCRIPT_ENGINE: RwLock<Option<Arc<dyn ScriptEngine>>> = RwLock::new(None);
+
+/// Register the engine running the scripts, replacing the previous one.
+pub fn set_script_engine(engine: impl ScriptEngine + 'static) {
+    *SCRIPT_ENGINE
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(engine));
+}
+
+/// Unregister the script engine. Configurations with a script can't be read afterwards.
+pub fn clear_script_engine() {
+    *SCRIPT_ENGINE
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Run the script of a configuration file on its document, if there's one.
+pub(crate) fn run(file: &ConfigFile, document: Value) -> Result<Value, ConrigError> {
+    let Some(name) = file.config_option.script else {
+        return Ok(document);
+    };
+    let path = file.path.with_file_name(name);
+    if !crate::filesystem::exists(&path) {
+        return Ok(document);
+    }
+    let script = String::from_utf8(crate::filesystem::read(&path)?).map_err(|err| {
+        let err = std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+        FileSystemError::read_config(&path)(err)
+    })?;
+    let engine = SCRIPT_ENGINE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    let result = match engine {
+        Some(engine) => engine.run(&script, document),
+        None => Err("no script engine is registered".into()),
+    };
+    result.map_err(|source| ConrigError::Script { path, source })
+}
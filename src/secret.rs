@@ -0,0 +1,234 @@
+//! Secrets kept out of the configuration file, e.g. in the OS keyring.
+//!
+//! Mark the sensitive fields of your configuration with `#[conrig(secret)]`, or implement
+//! [`Secrets`] by hand, and register a [`SecretStore`] with [`set_secret_store`]:
+//! [`ConfigFile::write_secrets`] moves those fields into the store instead of the plain-text
+//! file, and [`ConfigFile::read_secrets`] injects them back before deserializing.
+//!
+//! Each secret is stored under the path of the configuration file, as the service, and its
+//! dotted key path. Only string secrets are supported.
+//!
+//! `conrig` doesn't talk to any keyring itself: the [`SecretStore`] is yours, e.g. a thin
+//! wrapper around the [`keyring`](https://docs.rs/keyring) crate.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::parser::ConfigFile;
+//! use conrig::secret::{set_secret_store, MemorySecretStore, Secrets};
+//! use conrig::FileFormat;
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Config {
+//!     user: String,
+//!     token: String,
+//! }
+//!
+//! impl Secrets for Config {
+//!     const SECRETS: &'static [&'static str] = &["token"];
+//! }
+//!
+//! set_secret_store(MemorySecretStore::new());
+//!
+//! # let path = std::env::temp_dir().join("conrig-secret-doc.toml");
+//! let file = ConfigFile::new(FileFormat::Toml, path);
+//! let config = Config { user: "alice".into(), token: "hunter2".into() };
+//! file.write_secrets(&config)?;
+//! assert!(!std::fs::read_to_string(&file.path).unwrap().contains("hunter2"));
+//! assert_eq!(file.read_secrets::<Config>()?, config);
+//! # std::fs::remove_file(&file.path).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{ConrigResultExt, Operation};
+use crate::parser::ConfigFile;
+use crate::value::{to_value, Value};
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// The error returned by a [`SecretStore`].
+pub type SecretError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A configuration with fields kept in the [`SecretStore`].
+///
+/// This is implemented by `#[derive(Conrig)]`, from the `#[conrig(secret)]` fields.
+pub trait Secrets {
+    /// The dotted paths of the secret fields. Default: none.
+    const SECRETS: &'static [&'static str] = &[];
+}
+
+/// A storage of secrets, e.g. the OS keyring.
+pub trait SecretStore: Send + Sync {
+    /// Load a secret, or `None` if it doesn't exist.
+    fn get(&self, service: &str, key: &str) -> Result<Option<String>, SecretError>;
+    /// Store a secret, replacing the previous one.
+    fn set(&self, service: &str, key: &str, secret: &str) -> Result<(), SecretError>;
+}
+
+/// A secret store kept in memory, e.g. for tests.
+#[derive(Debug, Default)]
+pub struct MemorySecretStore {
+    secrets: RwLock<BTreeMap<(String, String), String>>,
+}
+
+impl MemorySecretStore {
+    /// Create an empty `MemorySecretStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SecretStore for MemorySecretStore {
+    fn get(&self, service: &str, key: &str) -> Result<Option<String>, SecretError> {
+        Ok(self
+            .secrets
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(service.to_owned(), key.to_owned()))
+            .cloned())
+    }
+
+    fn set(&self, service: &str, key: &str, secret: &str) -> Result<(), SecretError> {
+        self.secrets
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert((service.to_owned(), key.to_owned()), secret.to_owned());
+        Ok(())
+    }
+}
+
+/// The secret store used by every configuration.
+static SECRET_STORE: RwLock<Option<Arc<dyn SecretStore>>> = RwLock::new(None);
+
+/// Register the secret store, replacing the previous one.
+pub fn set_secret_store(store: impl SecretStore + 'static) {
+    *SECRET_STORE.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(store));
+}
+
+/// Unregister the secret store. Secrets can't be read or written afterwards.
+pub fn clear_secret_store() {
+    *SECRET_STORE.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+fn secret_store(key: &str) -> Result<Arc<dyn SecretStore>, ConrigError> {
+    SECRET_STORE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .ok_or_else(|| ConrigError::Secret {
+            key: key.to_owned(),
+            source: "no secret store is registered".into(),
+        })
+}
+
+/// Remove a value by its dotted key path, returning it.
+fn take_path(document: &mut Value, path: &str) -> Option<Value> {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (parent.split('.').collect(), key),
+        None => (Vec::new(), path),
+    };
+    let mut current = document;
+    for segment in parent {
+        current = match current {
+            Value::Table(table) => table.get_mut(segment)?,
+            _ => return None,
+        };
+    }
+    match current {
+        Value::Table(table) => table.shift_remove(key),
+        _ => None,
+    }
+}
+
+impl ConfigFile {
+    /// The service name of the secrets of this file: its path.
+    fn secret_service(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    /// Read and deserialize the configuration file, injecting the [secrets] of `T` from the
+    /// [`SecretStore`] first.
+    ///
+    /// The secrets missing from the store are left as they are in the file.
+    ///
+    /// [secrets]: crate::secret::Secrets
+    pub fn read_secrets<T: DeserializeOwned + Secrets>(&self) -> Result<T, ConrigError> {
+        let mut document = self
+            .read_document()
+            .context_path(Operation::Read, &self.path)?;
+        let service = self.secret_service();
+        for key in T::SECRETS {
+            let secret =
+                secret_store(key)?
+                    .get(&service, key)
+                    .map_err(|source| ConrigError::Secret {
+                        key: (*key).to_owned(),
+                        source,
+                    })?;
+            if let Some(secret) = secret {
+                document
+                    .set_path(key, Value::String(secret))
+                    .context_path(Operation::Read, &self.path)?;
+            }
+        }
+        self.decode(document)
+            .context_path(Operation::Read, &self.path)
+    }
+
+    /// Serialize and write a value into the configuration file, moving the [secrets] of `T`
+    /// into the [`SecretStore`] instead.
+    ///
+    /// [secrets]: crate::secret::Secrets
+    pub fn write_secrets<T: Serialize + Secrets>(&self, value: &T) -> Result<(), ConrigError> {
+        let mut document = to_value(value).context_path(Operation::Write, &self.path)?;
+        let service = self.secret_service();
+        for key in T::SECRETS {
+            match take_path(&mut document, key) {
+                Some(Value::String(secret)) => secret_store(key)?
+                    .set(&service, key, &secret)
+                    .map_err(|source| ConrigError::Secret {
+                        key: (*key).to_owned(),
+                        source,
+                    })?,
+                None | Some(Value::Null) => {}
+                Some(_) => {
+                    return Err(ConrigError::Secret {
+                        key: (*key).to_owned(),
+                        source: "only strings can be kept as secrets".into(),
+                    })
+                }
+            }
+        }
+        self.write(&document)
+    }
+}
+
+impl<'p, T: DeserializeOwned + Secrets> ConfigPathMetadata<'p, T> {
+    /// Read a configuration file, injecting its secrets.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_secrets()`.
+    pub fn read_secrets(&self) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_secrets()
+    }
+}
+
+impl<'p, T: Serialize + Secrets> ConfigPathMetadata<'p, T> {
+    /// Write a configuration file, moving its secrets into the [`SecretStore`].
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.write_secrets(value)`.
+    pub fn write_secrets(&self, value: &T) -> Result<(), ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .write_secrets(value)
+    }
+}
@@ -0,0 +1,234 @@
+//! Pluggable configuration sources.
+//!
+//! A [`ConfigSource`] is anywhere a configuration document can be loaded from and stored into:
+//! a [file][ConfigFile], [memory][MemorySource], the Windows registry, a
+//! remote server... Implement it to plug in your own storage, e.g. etcd, Consul or S3,
+//! and read and write your configuration through [`ConfigSourceExt`].
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::source::{ConfigSource, ConfigSourceExt, MemorySource};
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//! }
+//!
+//! let source = MemorySource::new();
+//! let config: Config = source.read_or_default()?;
+//! assert_eq!(config, Config::default());
+//! assert!(source.load_value()?.is_some());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::backend::ConfigBackend;
+use crate::parser::{ConfigFile, RawConfigSource};
+use crate::value::{from_value, to_value, Value};
+use crate::ConrigError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::{PoisonError, RwLock};
+
+/// A storage of configuration documents.
+pub trait ConfigSource {
+    /// A human-readable description of the source, e.g. its path or URL.
+    fn describe(&self) -> String;
+
+    /// Load the configuration document, or `None` if there's none yet.
+    fn load_value(&self) -> Result<Option<Value>, ConrigError>;
+
+    /// Store the configuration document, replacing the previous one.
+    fn store_value(&self, value: &Value) -> Result<(), ConrigError>;
+}
+
+/// Typed reads and writes of any [`ConfigSource`].
+pub trait ConfigSourceExt: ConfigSource {
+    /// Load and deserialize the configuration.
+    ///
+    /// If there's no configuration, a [`NoConfigurationFile`] error will be returned.
+    ///
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    fn read<T: DeserializeOwned>(&self) -> Result<T, ConrigError> {
+        let value = self
+            .load_value()?
+            .ok_or(ConrigError::NoConfigurationFile(Vec::new()))?;
+        Ok(from_value(value)?)
+    }
+
+    /// Serialize and store the configuration.
+    fn write<T: Serialize>(&self, value: &T) -> Result<(), ConrigError> {
+        self.store_value(&to_value(value)?)
+    }
+
+    /// Load and deserialize the configuration, or store `default` if there's none yet.
+    fn read_or_new<T: Serialize + DeserializeOwned>(&self, default: T) -> Result<T, ConrigError> {
+        match self.load_value()? {
+            Some(value) => Ok(from_value(value)?),
+            None => {
+                self.write(&default)?;
+                Ok(default)
+            }
+        }
+    }
+
+    /// Load and deserialize the configuration, or store the default value if there's none yet.
+    fn read_or_default<T: Serialize + DeserializeOwned + Default>(&self) -> Result<T, ConrigError> {
+        self.read_or_new(T::default())
+    }
+}
+
+impl<S: ConfigSource + ?Sized> ConfigSourceExt for S {}
+
+/// A configuration kept in memory, e.g. for tests.
+#[derive(Debug, Default)]
+pub struct MemorySource {
+    value: RwLock<Option<Value>>,
+}
+
+impl MemorySource {
+    /// Create an empty `MemorySource`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a `MemorySource` holding `value`.
+    pub fn from_value(value: Value) -> Self {
+        Self {
+            value: RwLock::new(Some(value)),
+        }
+    }
+
+    /// Remove the configuration.
+    pub fn clear(&self) {
+        *self.value.write().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+impl ConfigSource for MemorySource {
+    fn describe(&self) -> String {
+        "memory".to_owned()
+    }
+
+    fn load_value(&self) -> Result<Option<Value>, ConrigError> {
+        Ok(self
+            .value
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone())
+    }
+
+    fn store_value(&self, value: &Value) -> Result<(), ConrigError> {
+        *self.value.write().unwrap_or_else(PoisonError::into_inner) = Some(value.clone());
+        Ok(())
+    }
+}
+
+impl ConfigSource for ConfigFile {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn load_value(&self) -> Result<Option<Value>, ConrigError> {
+        match self.read() {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.is_missing() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn store_value(&self, value: &Value) -> Result<(), ConrigError> {
+        self.write(value)
+    }
+}
+
+impl<T> ConfigSource for RawConfigSource<'_, '_, T> {
+    fn describe(&self) -> String {
+        match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "no configuration file".to_owned(),
+        }
+    }
+
+    fn load_value(&self) -> Result<Option<Value>, ConrigError> {
+        match self.config_file() {
+            Ok(file) => file.load_value(),
+            Err(err) if err.is_missing() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn store_value(&self, value: &Value) -> Result<(), ConrigError> {
+        self.config_file()?.store_value(value)
+    }
+}
+
+impl ConfigSource for ConfigBackend {
+    fn describe(&self) -> String {
+        match self {
+            Self::File(file) => file.describe(),
+            #[cfg(all(windows, feature = "windows-registry"))]
+            Self::Registry(key) => key.describe(),
+        }
+    }
+
+    fn load_value(&self) -> Result<Option<Value>, ConrigError> {
+        match self {
+            Self::File(file) => file.load_value(),
+            #[cfg(all(windows, feature = "windows-registry"))]
+            Self::Registry(key) => key.load_value(),
+        }
+    }
+
+    fn store_value(&self, value: &Value) -> Result<(), ConrigError> {
+        match self {
+            Self::File(file) => file.store_value(value),
+            #[cfg(all(windows, feature = "windows-registry"))]
+            Self::Registry(key) => key.store_value(value),
+        }
+    }
+}
+
+#[cfg(all(windows, feature = "windows-registry"))]
+impl ConfigSource for crate::registry::RegistryKey {
+    fn describe(&self) -> String {
+        self.display_path().display().to_string()
+    }
+
+    fn load_value(&self) -> Result<Option<Value>, ConrigError> {
+        match self.read_value() {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.is_missing() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn store_value(&self, value: &Value) -> Result<(), ConrigError> {
+        self.write(value)
+    }
+}
+
+/// A remote configuration is read-only: storing into it fails with a [`ReadOnly`] error.
+///
+/// [`ReadOnly`]: crate::ConrigError::ReadOnly
+#[cfg(feature = "http")]
+impl ConfigSource for crate::remote::RemoteSource {
+    fn describe(&self) -> String {
+        self.urls.join(", ")
+    }
+
+    fn load_value(&self) -> Result<Option<Value>, ConrigError> {
+        match self.read() {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.is_missing() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn store_value(&self, _value: &Value) -> Result<(), ConrigError> {
+        Err(ConrigError::ReadOnly(self.cache.path.clone()))
+    }
+}
@@ -0,0 +1,118 @@
+//! Utilities for testing applications using `conrig`.
+//!
+//! This module is only available with the `testing` feature, typically enabled in the
+//! `dev-dependencies` of your application, so release builds don't look for sandboxes.
+//!
+//! A [`Sandbox`] redirects every directory `conrig` uses into a fresh temporary directory,
+//! for as long as it's alive: the system-level, home, machine-wide and portable directories of
+//! every [`ProjectPath`], and the current directory of the [local location][local]. So tests
+//! never read nor modify the real configuration of the user running them.
+//!
+//! The redirection is local to the current thread, so tests running in parallel don't
+//! interfere with each other, and neither the environment variables nor the working directory
+//! of the process are modified. It doesn't apply to the threads spawned by the test.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use conrig::testing::Sandbox;
+//! use conrig::{conrig, ConfigOption, FileFormat, ProjectPath};
+//!
+//! conrig!(const CONFIG<u32> = {
+//!     project_path: ProjectPath::new("org", "foo", "conrig-sandbox"),
+//!     config_name: &["conrig-sandbox"],
+//!     config_option: ConfigOption::DEFAULT_CONFIG.with_sys_override_local(true),
+//!     extra_files: &[],
+//!     extra_folders: &[],
+//!     default_format: FileFormat::Json,
+//! });
+//!
+//! let sandbox = Sandbox::new()?;
+//! let file = CONFIG.search_config_file()?.fallback_default()?;
+//! assert!(file.path.starts_with(sandbox.root()));
+//! file.write(&42)?;
+//! // the sandbox is deleted when dropped.
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ProjectPath`]: crate::ProjectPath
+//! [local]: crate::SearchLocation::Local
+
+use std::cell::RefCell;
+use std::io;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+thread_local! {
+    /// The root of the innermost sandbox of the current thread.
+    static ROOT: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// A counter making the sandboxes of a process unique.
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary directory replacing the directories used by `conrig` on the current thread,
+/// until it's dropped.
+///
+/// Inside the [`root`] of the sandbox:
+/// - `sys/<type>/<application>` replaces the system-level directories, e.g. `sys/config/app`;
+/// - `home` replaces the home directory;
+/// - `machine/<application>` replaces the machine-wide directory;
+/// - `portable` replaces the directory of the executable;
+/// - `work` replaces the current directory, unless a [`local_dir`] is set.
+///
+/// Sandboxes can be nested: dropping the inner one restores the outer one.
+/// See the [`testing`](crate::testing) module for more information.
+///
+/// [`root`]: crate::testing::Sandbox::root
+/// [`local_dir`]: crate::ConfigPathMetadata#structfield.local_dir
+#[derive(Debug)]
+pub struct Sandbox {
+    root: PathBuf,
+    previous: Option<PathBuf>,
+    // the redirection belongs to the current thread.
+    _marker: PhantomData<*const ()>,
+}
+
+impl Sandbox {
+    /// Create a new sandbox in the temporary directory of the system, and enter it.
+    pub fn new() -> io::Result<Self> {
+        let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let root = std::env::temp_dir().join(format!("conrig-sandbox-{}-{id}", std::process::id()));
+        if root.exists() {
+            std::fs::remove_dir_all(&root)?;
+        }
+        std::fs::create_dir_all(root.join("work"))?;
+        let previous = ROOT.with(|current| current.replace(Some(root.clone())));
+        Ok(Self {
+            root,
+            previous,
+            _marker: PhantomData,
+        })
+    }
+
+    /// The root directory of the sandbox.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory replacing the current directory.
+    pub fn current_dir(&self) -> PathBuf {
+        self.root.join("work")
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        ROOT.with(|current| current.replace(self.previous.take()));
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// The root of the sandbox of the current thread, if any.
+pub(crate) fn root() -> Option<PathBuf> {
+    ROOT.with(|current| current.borrow().clone())
+}
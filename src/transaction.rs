@@ -0,0 +1,102 @@
+//! Modifying a configuration file all at once, or not at all.
+//!
+//! [`ConfigFile::transaction`] loads the current configuration into a [`Transaction`] guard,
+//! which dereferences to it. The file is only written, atomically, on [`commit`]: dropping
+//! the guard without committing leaves the file untouched. The file is [locked] for the
+//! lifetime of the guard, so concurrent transactions don't overwrite each other.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::parser::ConfigFile;
+//! use conrig::FileFormat;
+//! # use serde_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     width: u32,
+//!     height: u32,
+//! }
+//!
+//! # let path = std::env::temp_dir().join("conrig-transaction-doc.toml");
+//! let file = ConfigFile::new(FileFormat::Toml, path);
+//! file.write(&Config { width: 800, height: 600 })?;
+//!
+//! let mut config = file.transaction::<Config>()?;
+//! config.width = 1920;
+//! config.height = 1080;
+//! config.commit()?;
+//! # std::fs::remove_file(&file.path).unwrap();
+//! # std::fs::remove_file(conrig::lock::lock_path(&file.path)).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`commit`]: Transaction::commit
+//! [locked]: crate::parser::ConfigFile::lock
+
+use crate::lock::FileLock;
+use crate::parser::ConfigFile;
+use crate::ConrigError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ops::{Deref, DerefMut};
+
+/// A modification of a configuration file, written only when committed.
+///
+/// See the [module documentation][self] for more information.
+#[derive(Debug)]
+#[must_use = "the changes are discarded unless the transaction is committed"]
+pub struct Transaction<'f, T> {
+    file: &'f ConfigFile,
+    value: T,
+    _lock: FileLock,
+}
+
+impl ConfigFile {
+    /// Lock the configuration file and load its current value into a [`Transaction`].
+    pub fn transaction<T: DeserializeOwned>(&self) -> Result<Transaction<'_, T>, ConrigError> {
+        let lock = self.lock()?;
+        Ok(Transaction {
+            file: self,
+            value: self.read()?,
+            _lock: lock,
+        })
+    }
+}
+
+impl<T> Transaction<'_, T> {
+    /// Discard the changes, leaving the file untouched.
+    ///
+    /// This is the same as dropping the transaction.
+    pub fn rollback(self) {}
+}
+
+impl<T: Serialize> Transaction<'_, T> {
+    /// Write the modified value into the file atomically, whatever the [`atomic_write`]
+    /// option of the file, and release the lock.
+    ///
+    /// [`atomic_write`]: crate::ConfigOption#structfield.atomic_write
+    pub fn commit(self) -> Result<(), ConrigError> {
+        let file = ConfigFile {
+            config_option: self.file.config_option.with_atomic_write(true),
+            ..self.file.clone()
+        };
+        file.write(&self.value)
+    }
+}
+
+impl<T> Deref for Transaction<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Transaction<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
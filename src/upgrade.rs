@@ -0,0 +1,184 @@
+//! Version stamps and upgrade notices of configuration files.
+//!
+//! A configuration file written years ago by an older version of your application doesn't
+//! mention the options added since then. If [`ConfigOption.version_stamp`] is enabled, every
+//! write stamps the file with the [version of your application][version] in a header comment:
+//!
+//! ```toml
+//! # app-version: 1.3.0
+//!
+//! theme = "dark"
+//! ```
+//!
+//! When a file stamped by an older version is rewritten, the header also lists the
+//! [`Addition`]s registered in [`ConfigOption.additions`] since that version, so users
+//! learn about the new options:
+//!
+//! ```toml
+//! # app-version: 1.3.0
+//! # Options added since version 1.1.0:
+//! #   `server.timeout` (1.2.0): The timeout of requests, in seconds.
+//! #   `cache.dir` (1.3.0)
+//!
+//! theme = "dark"
+//! ```
+//!
+//! The notice is kept by the following writes of the same version, and replaced on the next
+//! upgrade. Only the formats with comments, i.e. TOML, YAML and RON, are stamped.
+//!
+//! [`ConfigOption.version_stamp`]: crate::ConfigOption#structfield.version_stamp
+//! [`ConfigOption.additions`]: crate::ConfigOption#structfield.additions
+//! [version]: crate::ConfigOption#structfield.app_version
+
+use crate::deprecation::compare_versions;
+use crate::error::{ConrigResultExt, Operation};
+use crate::generate::comment_prefix;
+use crate::parser::ConfigFile;
+use crate::{filesystem, ConfigOption, ConrigError, FileFormat};
+
+/// The label of the version stamp, following the comment marker.
+const STAMP: &str = "app-version:";
+
+/// A configuration key added by a version of your application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Addition {
+    /// The dotted path of the key, e.g. `server.timeout`.
+    pub key: &'static str,
+    /// The first version of your application supporting the key.
+    pub added_in: &'static str,
+    /// What the key controls, shown in the upgrade notice, if any.
+    pub description: Option<&'static str>,
+}
+
+impl Addition {
+    /// Create a new `Addition` without description.
+    pub const fn new(key: &'static str, added_in: &'static str) -> Self {
+        Self {
+            key,
+            added_in,
+            description: None,
+        }
+    }
+
+    /// Modify the [`description`] field.
+    ///
+    /// [`description`]: crate::upgrade::Addition#structfield.description
+    pub const fn with_description(mut self, description: &'static str) -> Self {
+        self.description = Some(description);
+        self
+    }
+}
+
+/// Split the content of a configuration file into the version of its stamp, its header,
+/// and the rest of the content.
+///
+/// The header is made of the stamp, the comments following it, and the blank line ending it.
+/// Returns `None` if the file isn't stamped.
+pub(crate) fn split_header(file_format: FileFormat, contents: &str) -> Option<(&str, &str, &str)> {
+    let prefix = comment_prefix(file_format)?;
+    let version = contents
+        .lines()
+        .next()?
+        .strip_prefix(prefix)?
+        .trim_start()
+        .strip_prefix(STAMP)?
+        .trim();
+    let mut end = 0;
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if end > 0 && !trimmed.starts_with(prefix) {
+            if trimmed.is_empty() {
+                end += line.len();
+            }
+            break;
+        }
+        end += line.len();
+    }
+    Some((version, &contents[..end], &contents[end..]))
+}
+
+/// The content of a configuration file, without its version stamp and upgrade notice.
+pub(crate) fn strip_header(file_format: FileFormat, contents: &str) -> &str {
+    split_header(file_format, contents).map_or(contents, |(_, _, rest)| rest)
+}
+
+/// Create the header of a configuration file stamped by `version`, replacing `previous`,
+/// the content of the file being rewritten, if any.
+fn header(
+    file_format: FileFormat,
+    config_option: &ConfigOption,
+    version: &str,
+    previous: Option<&str>,
+) -> Option<String> {
+    let prefix = comment_prefix(file_format)?;
+    let (stamped, previous_header, _) = split_header(file_format, previous?)?;
+    if compare_versions(stamped, version).is_eq() {
+        return Some(previous_header.to_owned());
+    }
+    let mut header = format!("{prefix} {STAMP} {version}\n");
+    let additions: Vec<_> = config_option
+        .additions
+        .iter()
+        .filter(|t| compare_versions(t.added_in, stamped).is_gt())
+        .filter(|t| compare_versions(t.added_in, version).is_le())
+        .collect();
+    if !additions.is_empty() {
+        header.push_str(&format!(
+            "{prefix} Options added since version {stamped}:\n"
+        ));
+    }
+    for addition in additions {
+        header.push_str(&format!(
+            "{prefix}   `{}` ({})",
+            addition.key, addition.added_in
+        ));
+        if let Some(description) = addition.description {
+            header.push_str(&format!(": {description}"));
+        }
+        header.push('\n');
+    }
+    header.push('\n');
+    Some(header)
+}
+
+impl ConfigFile {
+    /// The version of your application which last wrote the configuration file, according to
+    /// its stamp.
+    ///
+    /// Returns `None` if the file doesn't exist or isn't stamped.
+    /// See the [`upgrade`](crate::upgrade) module for more information.
+    pub fn version_stamp(&self) -> Result<Option<String>, ConrigError> {
+        if !filesystem::exists(&self.path) {
+            return Ok(None);
+        }
+        let contents = self
+            .read_contents()
+            .context_path(Operation::Read, &self.path)?;
+        Ok(split_header(self.file_format, &contents).map(|(version, _, _)| version.to_owned()))
+    }
+
+    /// The header to write at the top of the configuration file, if it's [stamped].
+    ///
+    /// [stamped]: crate::ConfigOption#structfield.version_stamp
+    pub(crate) fn version_header(&self) -> Result<Option<String>, ConrigError> {
+        let option = &self.config_option;
+        let Some(version) = option.app_version.filter(|_| option.version_stamp) else {
+            return Ok(None);
+        };
+        let Some(prefix) = comment_prefix(self.file_format) else {
+            return Ok(None);
+        };
+        let previous = if filesystem::exists(&self.path) {
+            Some(
+                self.read_contents()
+                    .context_path(Operation::Write, &self.path)?,
+            )
+        } else {
+            None
+        };
+        Ok(Some(
+            header(self.file_format, option, version, previous.as_deref())
+                .unwrap_or_else(|| format!("{prefix} {STAMP} {version}\n\n")),
+        ))
+    }
+}
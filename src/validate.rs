@@ -0,0 +1,409 @@
+//! Validation of deserialized configurations.
+//!
+//! `serde` only checks the shape of your configuration. Implement [`Validate`] for your
+//! configuration structure to reject the values it can't express, like out-of-range numbers,
+//! then read it with [`read_checked`]. For one-off checks, pass a closure to
+//! [`read_validated_with`] instead.
+//!
+//! Every [`Violation`] found is reported at once by a [`Validation`] error.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use conrig::error::Violation;
+//! use conrig::validate::Validate;
+//! # use serde_derive::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     workers: u32,
+//! }
+//!
+//! impl Validate for Config {
+//!     fn validate(&self) -> Result<(), Vec<Violation>> {
+//!         if self.workers == 0 {
+//!             return Err(vec![Violation::new("workers", "at least one worker is required")]);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//! ```
+//!
+//! For imperative checks, [`collect`] gathers the failures of every [`conrig_assert!`]
+//! it runs:
+//!
+//! ```rust
+//! use conrig::conrig_assert;
+//! use conrig::error::Violation;
+//! use conrig::validate::{collect, Validate};
+//! # use serde_derive::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     workers: u32,
+//!     name: String,
+//! }
+//!
+//! impl Validate for Config {
+//!     fn validate(&self) -> Result<(), Vec<Violation>> {
+//!         collect(|| {
+//!             conrig_assert!(self.workers >= 1, "workers must be >= 1");
+//!             conrig_assert!(!self.name.is_empty(), "a name is required");
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! With the `derive` feature, declare the constraints next to the fields instead, and
+//! `#[derive(Conrig)]` implements [`Validate`] from them:
+//!
+//! - `#[conrig(min = 1, max = 65535)]` bounds a number;
+//! - `#[conrig(regex = "^[a-z]+$")]` requires a string to match a [`Constraint::Regex`];
+//! - `#[conrig(one_of("a", "b"))]` restricts a string to a list of values;
+//! - `#[conrig(requires = "tls_cert")]` requires another field when this one is set.
+//!
+//! Rules spanning nested fields are declared on the structure instead, e.g.
+//! `#[conrig(requires("tls.enabled" => "tls.cert"))]`. To register rules at runtime,
+//! collect them in [`Rules`].
+//!
+//! [`read_checked`]: crate::ConfigPathMetadata::read_checked
+//! [`read_validated_with`]: crate::ConfigPathMetadata::read_validated_with
+//! [`Validation`]: crate::ConrigError::Validation
+
+use crate::error::{ConrigResultExt, Operation, Violation};
+use crate::parser::{ConfigFile, RawConfigSource};
+use crate::pattern::Pattern;
+use crate::value::{to_value, Value};
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+
+thread_local! {
+    /// The violations collected by [`collect`] on this thread, if any.
+    static COLLECTED: RefCell<Option<Vec<Violation>>> = const { RefCell::new(None) };
+}
+
+/// A configuration which can check its own values.
+pub trait Validate {
+    /// Check the configuration, returning every violation found.
+    fn validate(&self) -> Result<(), Vec<Violation>>;
+}
+
+/// Run `f`, returning every violation reported by the [`conrig_assert!`]s it runs.
+///
+/// Unlike [`assert!`], a failed [`conrig_assert!`] doesn't stop `f`, so all the failures are
+/// reported at once.
+pub fn collect(f: impl FnOnce()) -> Result<(), Vec<Violation>> {
+    let previous = COLLECTED.with(|collected| collected.replace(Some(Vec::new())));
+    f();
+    let violations = COLLECTED
+        .with(|collected| collected.replace(previous))
+        .unwrap_or_default();
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+/// Report a violation to the enclosing [`collect`].
+///
+/// Outside of [`collect`], this panics with the violation, like a failed [`assert!`].
+#[doc(hidden)]
+pub fn report(violation: Violation) {
+    COLLECTED.with(|collected| match collected.borrow_mut().as_mut() {
+        Some(violations) => violations.push(violation),
+        None => panic!("{violation}"),
+    })
+}
+
+/// The key path checked by a condition, e.g. `server.port` for `cfg.server.port > 0`.
+///
+/// The leading receiver, like `self` or `cfg`, and trailing method calls are dropped.
+#[doc(hidden)]
+pub fn key_path(condition: &str) -> String {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let condition = condition.replace(" .", ".").replace(". ", ".");
+    let condition = condition.trim_start_matches(|c: char| !is_ident(c));
+    let end = condition
+        .find(|c: char| !(is_ident(c) || c == '.'))
+        .unwrap_or(condition.len());
+    let mut segments: Vec<&str> = condition[..end].split('.').collect();
+    if condition[end..].trim_start().starts_with('(') {
+        segments.pop();
+    }
+    segments.into_iter().skip(1).collect::<Vec<_>>().join(".")
+}
+
+/// A constraint on a field of a configuration.
+///
+/// The constraints are declared with `#[conrig(...)]` attributes by `#[derive(Conrig)]`.
+/// Absent values, e.g. `None`, always satisfy them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constraint {
+    /// The number must be greater than or equal to the bound.
+    Min(f64),
+    /// The number must be less than or equal to the bound.
+    Max(f64),
+    /// The string must match the regular expression.
+    ///
+    /// Classes, groups, alternations, anchors and quantifiers are supported.
+    /// Like the `regex` crate, the pattern may match anywhere in the string,
+    /// so use `^` and `$` to match the whole string.
+    Regex(&'static str),
+    /// The string must be one of the values.
+    OneOf(&'static [&'static str]),
+    /// If the field is set, the field at this dotted path must be set too.
+    ///
+    /// A field is set unless it's absent, `false`, or an empty string, array or table.
+    /// This is checked by [`check_constraints`], as it needs the whole configuration.
+    Requires(&'static str),
+}
+
+impl Constraint {
+    /// Check a value, returning the description of the violation if it's broken.
+    pub fn check(&self, value: &Value) -> Result<(), String> {
+        match (self, value) {
+            (Self::Min(min), Value::Integer(_) | Value::Float(_)) if as_f64(value) < *min => {
+                Err(format!("must be at least {min}"))
+            }
+            (Self::Max(max), Value::Integer(_) | Value::Float(_)) if as_f64(value) > *max => {
+                Err(format!("must be at most {max}"))
+            }
+            (Self::Regex(regex), Value::String(string)) => match Pattern::new(regex) {
+                Ok(pattern) if pattern.is_match(string) => Ok(()),
+                Ok(_) => Err(format!("must match `{regex}`")),
+                Err(err) => Err(format!("invalid pattern `{regex}`: {err}")),
+            },
+            (Self::OneOf(values), Value::String(string)) if !values.contains(&string.as_str()) => {
+                let values: Vec<String> = values.iter().map(|value| format!("`{value}`")).collect();
+                Err(format!("must be one of {}", values.join(", ")))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn as_f64(value: &Value) -> f64 {
+    match value {
+        Value::Integer(integer) => *integer as f64,
+        Value::Float(float) => *float,
+        _ => f64::NAN,
+    }
+}
+
+/// Whether a field is set, i.e. neither absent, `false`, nor empty.
+fn is_set(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null | Value::Bool(false)) => false,
+        Some(Value::String(string)) => !string.is_empty(),
+        Some(Value::Array(array)) => !array.is_empty(),
+        Some(Value::Table(table)) => !table.is_empty(),
+        Some(_) => true,
+    }
+}
+
+/// Check that `required` is set if `field` is.
+fn check_requires(document: &Value, field: &str, required: &str) -> Option<Violation> {
+    (is_set(document.get_path(field)) && !is_set(document.get_path(required)))
+        .then(|| Violation::new(required, format!("is required when `{field}` is set")))
+}
+
+/// A configuration whose fields are constrained, usually implemented by `#[derive(Conrig)]`.
+pub trait Constrained {
+    /// The constraints, along with the dotted path of the field they apply to.
+    const CONSTRAINTS: &'static [(&'static str, Constraint)];
+}
+
+/// Check the [constraints][Constrained::CONSTRAINTS] of a configuration.
+///
+/// This is the [`Validate`] implementation generated by `#[derive(Conrig)]`.
+pub fn check_constraints<T: Serialize + Constrained>(value: &T) -> Result<(), Vec<Violation>> {
+    let document = to_value(value).map_err(|err| vec![Violation::new("", err.to_string())])?;
+    let violations: Vec<Violation> = T::CONSTRAINTS
+        .iter()
+        .filter_map(|(field, constraint)| match constraint {
+            Constraint::Requires(required) => check_requires(&document, field, required),
+            constraint => constraint
+                .check(document.get_path(field)?)
+                .err()
+                .map(|message| Violation::new(*field, message)),
+        })
+        .collect();
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+type Rule<T> = Box<dyn Fn(&T, &Value) -> Vec<Violation> + Send + Sync>;
+
+/// Validation rules registered at runtime.
+///
+/// Every rule is checked, and all their violations are reported at once.
+///
+/// ## Example
+///
+/// ```rust
+/// use conrig::error::Violation;
+/// use conrig::validate::Rules;
+/// # use serde_derive::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Tls {
+///     enabled: bool,
+///     cert: Option<String>,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Config {
+///     tls: Tls,
+///     port: u16,
+/// }
+///
+/// let rules = Rules::new()
+///     .requires("tls.enabled", "tls.cert")
+///     .rule(|config: &Config| match config.tls.enabled && config.port == 80 {
+///         true => Err(vec![Violation::new("port", "TLS can't be served on port 80")]),
+///         false => Ok(()),
+///     });
+///
+/// let config = Config {
+///     tls: Tls { enabled: true, cert: None },
+///     port: 80,
+/// };
+/// assert_eq!(rules.check(&config).unwrap_err().len(), 2);
+/// ```
+pub struct Rules<T> {
+    rules: Vec<Rule<T>>,
+}
+
+impl<T> Default for Rules<T> {
+    fn default() -> Self {
+        Self { rules: Vec::new() }
+    }
+}
+
+impl<T: Serialize> Rules<T> {
+    /// Create an empty set of rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule checking the configuration.
+    pub fn rule(
+        mut self,
+        rule: impl Fn(&T) -> Result<(), Vec<Violation>> + Send + Sync + 'static,
+    ) -> Self {
+        self.rules.push(Box::new(move |value, _| {
+            rule(value).err().unwrap_or_default()
+        }));
+        self
+    }
+
+    /// Add a rule requiring the field at the dotted path `required` if `field` is set.
+    ///
+    /// See [`Constraint::Requires`] for more information.
+    pub fn requires(mut self, field: &'static str, required: &'static str) -> Self {
+        self.rules.push(Box::new(move |_, document| {
+            check_requires(document, field, required).into_iter().collect()
+        }));
+        self
+    }
+
+    /// Check every rule, returning all the violations found.
+    pub fn check(&self, value: &T) -> Result<(), Vec<Violation>> {
+        let document = to_value(value).map_err(|err| vec![Violation::new("", err.to_string())])?;
+        let violations: Vec<Violation> = self
+            .rules
+            .iter()
+            .flat_map(|rule| rule(value, &document))
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+impl<'p, T: DeserializeOwned> ConfigPathMetadata<'p, T> {
+    /// Read a configuration file, then check it with `validate`.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_validated_with(validate)`.
+    pub fn read_validated_with(
+        &self,
+        validate: impl FnOnce(&T) -> Result<(), Vec<Violation>>,
+    ) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_validated_with(validate)
+    }
+}
+
+impl<'p, T: DeserializeOwned + Validate> ConfigPathMetadata<'p, T> {
+    /// Read a configuration file, then check it with [`Validate::validate`].
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.read_checked::<T>()`.
+    pub fn read_checked(&self) -> Result<T, ConrigError> {
+        self.search_config_file()?
+            .fallback_default()?
+            .read_checked()
+    }
+}
+
+impl<'a, 'p, T: DeserializeOwned> RawConfigSource<'a, 'p, T> {
+    /// Read and deserialize the configuration file, then check it with `validate`.
+    ///
+    /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
+    ///
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn read_validated_with(
+        &self,
+        validate: impl FnOnce(&T) -> Result<(), Vec<Violation>>,
+    ) -> Result<T, ConrigError> {
+        self.config_file()?.read_validated_with(validate)
+    }
+}
+
+impl<'a, 'p, T: DeserializeOwned + Validate> RawConfigSource<'a, 'p, T> {
+    /// Read and deserialize the configuration file, then check it with [`Validate::validate`].
+    ///
+    /// If `path` is `None`, a [`NoConfigurationFile`] error will be returned.
+    ///
+    /// [`NoConfigurationFile`]: crate::ConrigError::NoConfigurationFile
+    pub fn read_checked(&self) -> Result<T, ConrigError> {
+        self.config_file()?.read_checked()
+    }
+}
+
+impl ConfigFile {
+    /// Read and deserialize the configuration file, then check it with `validate`.
+    ///
+    /// If any violation is found, a [`Validation`] error will be returned.
+    ///
+    /// [`Validation`]: crate::ConrigError::Validation
+    pub fn read_validated_with<T: DeserializeOwned>(
+        &self,
+        validate: impl FnOnce(&T) -> Result<(), Vec<Violation>>,
+    ) -> Result<T, ConrigError> {
+        let value = self.read()?;
+        validate(&value)
+            .map_err(ConrigError::Validation)
+            .context_path(Operation::Read, &self.path)?;
+        Ok(value)
+    }
+
+    /// Read and deserialize the configuration file, then check it with [`Validate::validate`].
+    ///
+    /// If any violation is found, a [`Validation`] error will be returned.
+    ///
+    /// [`Validation`]: crate::ConrigError::Validation
+    pub fn read_checked<T: DeserializeOwned + Validate>(&self) -> Result<T, ConrigError> {
+        self.read_validated_with(T::validate)
+    }
+}
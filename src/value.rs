@@ -0,0 +1,1028 @@
+//! A format-independent document tree.
+//!
+//! [`Value`] is used by features that need to inspect or transform a
+//! configuration before it is written into, or after it is read from a file.
+//!
+//! **Note**: [ron][ron] distinguishes structs, maps and options, while a `Value` does not.
+//! Ron documents written from a `Value` use the map syntax,
+//! which `ron` cannot deserialize back into a struct.
+//!
+//! [ron]: https://github.com/ron-rs/ron/
+
+use crate::error::UnknownKey;
+use crate::redact;
+use indexmap::IndexMap;
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, SerializeMap as _, SerializeSeq as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::RefCell;
+use std::fmt;
+use thiserror::Error;
+
+/// A table of key-value pairs. Keys keep their insertion order.
+pub type Table = IndexMap<String, Value>;
+
+/// A format-independent configuration value.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Value {
+    /// An absent value, e.g. `None`.
+    #[default]
+    Null,
+    /// A boolean value.
+    Bool(bool),
+    /// A signed integer.
+    Integer(i64),
+    /// A floating point number.
+    Float(f64),
+    /// A string.
+    String(String),
+    /// An array of values.
+    Array(Vec<Value>),
+    /// A table of key-value pairs.
+    Table(Table),
+}
+
+/// Error triggered while converting a value from or into a [`Value`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{0}")]
+pub struct ValueError(String);
+
+impl ser::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Convert a serializable value into a [`Value`].
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, ValueError> {
+    value.serialize(ValueSerializer)
+}
+
+/// Convert a [`Value`] into a deserializable value.
+///
+/// The integers `0` and `1` are accepted as booleans, as some backends can't store booleans,
+/// e.g. the `REG_DWORD` values of the Windows registry.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, ValueError> {
+    T::deserialize(value)
+}
+
+/// Convert a [`Value`] into a deserializable value, locating the value in error.
+///
+/// The error is returned along with the dotted key path of the value failing to deserialize,
+/// e.g. `server.listeners.0.port`, or `""` if the root value itself fails.
+///
+/// If `strict_keys` is set, the keys unknown to `T` are also collected.
+/// A key is unknown if it's not a field of the struct it's deserialized into.
+/// The keys are reported as dotted paths, e.g. `server.prot`,
+/// along with the closest field of the struct, if any.
+pub(crate) fn from_value_tracked<'de, T: Deserialize<'de>>(
+    value: Value,
+    strict_keys: bool,
+) -> (Result<T, (ValueError, String)>, Vec<UnknownKey>) {
+    let unknown = RefCell::new(Vec::new());
+    let failed = RefCell::new(Vec::new());
+    let result = T::deserialize(Tracked {
+        value,
+        path: String::new(),
+        unknown: strict_keys.then_some(&unknown),
+        failed: &failed,
+    });
+    let result = result.map_err(|err| {
+        // the keys are recorded from the innermost one.
+        let mut keys = failed.into_inner();
+        keys.reverse();
+        (err, keys.join("."))
+    });
+    (result, unknown.into_inner())
+}
+
+impl Value {
+    /// Get a value by its dotted key path, e.g. `server.listeners.0.port`.
+    ///
+    /// Numeric segments index into arrays.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        path.split('.').try_fold(self, |value, key| match value {
+            Value::Table(table) => table.get(key),
+            Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+    }
+
+    /// Set a value by its dotted key path, e.g. `server.listeners.0.port`.
+    ///
+    /// Missing tables on the path are created,
+    /// while numeric segments index into existing arrays.
+    pub fn set_path(&mut self, path: &str, value: Value) -> Result<(), ValueError> {
+        let mut current = self;
+        for key in path.split('.') {
+            if *current == Value::Null {
+                *current = Value::Table(Table::new());
+            }
+            current = match current {
+                Value::Table(table) => table.entry(key.to_owned()).or_default(),
+                Value::Array(array) => key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| array.get_mut(index))
+                    .ok_or_else(|| ValueError(format!("`{path}` is out of the array's range")))?,
+                _ => return Err(ValueError(format!("`{path}` is not inside a table"))),
+            };
+        }
+        *current = value;
+        Ok(())
+    }
+
+    /// Get the parts of `self` which differ from `base`.
+    ///
+    /// Tables are compared key by key, recursively.
+    /// Any other value is kept as a whole if it's not equal to its counterpart in `base`.
+    ///
+    /// Returns `None` if `self` equals `base`.
+    pub fn difference(&self, base: &Value) -> Option<Value> {
+        match (self, base) {
+            (Value::Table(this), Value::Table(base)) => {
+                let table: Table = this
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let diff = match base.get(key) {
+                            Some(base) => value.difference(base)?,
+                            None => value.clone(),
+                        };
+                        Some((key.clone(), diff))
+                    })
+                    .collect();
+                if table.is_empty() {
+                    None
+                } else {
+                    Some(Value::Table(table))
+                }
+            }
+            (this, base) if this == base => None,
+            (this, _) => Some(this.clone()),
+        }
+    }
+
+    /// Fill the keys missing in `self` with their counterparts in `base`.
+    ///
+    /// Tables are filled key by key, recursively. Any other value is left untouched.
+    ///
+    /// Returns `true` if any key has been filled.
+    pub fn fill_missing(&mut self, base: &Value) -> bool {
+        let (Value::Table(this), Value::Table(base)) = (self, base) else {
+            return false;
+        };
+        let mut filled = false;
+        for (key, base) in base {
+            match this.get_mut(key) {
+                Some(value) => filled |= value.fill_missing(base),
+                None => {
+                    this.insert(key.clone(), base.clone());
+                    filled = true;
+                }
+            }
+        }
+        filled
+    }
+
+    /// Merge `patch` over `self`.
+    ///
+    /// Tables are merged key by key, recursively.
+    /// Any other value of `patch` replaces its counterpart in `self`.
+    pub fn merge(&mut self, patch: Value) {
+        self.merge_keyed(patch, &[]);
+    }
+
+    /// Merge `patch` over `self`, matching the elements of some arrays of tables by identity.
+    ///
+    /// `keys` pairs the dotted path of an array, e.g. `servers`, with the identity key of its
+    /// elements, e.g. `name`. A `*` segment matches any key, see [`redact::matches`].
+    /// If every element of both arrays is a table holding the identity key, each element of
+    /// `patch` is merged over the element of `self` with the same identity, or appended if
+    /// there's none. So an overlay can modify a single element of a list.
+    ///
+    /// Otherwise, this behaves like [`merge`][Value::merge].
+    ///
+    /// [`redact::matches`]: crate::redact::matches
+    pub fn merge_keyed(&mut self, patch: Value, keys: &[(&str, &str)]) {
+        self.merge_at(patch, keys, &mut String::new());
+    }
+
+    fn merge_at(&mut self, patch: Value, keys: &[(&str, &str)], path: &mut String) {
+        match (self, patch) {
+            (Value::Table(this), Value::Table(patch)) => {
+                for (key, patch) in patch {
+                    match this.get_mut(&key) {
+                        Some(value) => {
+                            let len = push_segment(path, &key);
+                            value.merge_at(patch, keys, path);
+                            path.truncate(len);
+                        }
+                        None => {
+                            this.insert(key, patch);
+                        }
+                    }
+                }
+            }
+            (Value::Array(this), Value::Array(patch)) => {
+                let identity_of = |value: &Value, identity: &str| match value {
+                    Value::Table(table) => table.get(identity).cloned(),
+                    _ => None,
+                };
+                let identity = keys
+                    .iter()
+                    .find(|(pattern, _)| redact::matches(pattern, path))
+                    .map(|(_, identity)| *identity)
+                    .filter(|identity| {
+                        this.iter()
+                            .chain(&patch)
+                            .all(|value| identity_of(value, identity).is_some())
+                    });
+                let Some(identity) = identity else {
+                    *this = patch;
+                    return;
+                };
+                for patch in patch {
+                    let id = identity_of(&patch, identity);
+                    match this.iter().position(|value| identity_of(value, identity) == id) {
+                        Some(index) => {
+                            let len = push_segment(path, &index.to_string());
+                            this[index].merge_at(patch, keys, path);
+                            path.truncate(len);
+                        }
+                        None => this.push(patch),
+                    }
+                }
+            }
+            (this, patch) => *this = patch,
+        }
+    }
+}
+
+/// Append a segment to a dotted key path, returning the previous length of the path.
+fn push_segment(path: &mut String, key: &str) -> usize {
+    let len = path.len();
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(key);
+    len
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_none(),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Integer(v) => serializer.serialize_i64(*v),
+            Value::Float(v) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Array(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for item in v {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Value::Table(v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (key, value) in v {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any configuration value")
+            }
+
+            fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+                Ok(Value::Bool(v))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+                Ok(Value::Integer(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+                i64::try_from(v)
+                    .map(Value::Integer)
+                    .map_err(|_| E::custom(format!("integer `{v}` is out of range")))
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+                Ok(Value::Float(v))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+                Ok(Value::String(v.to_owned()))
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Value, E> {
+                Ok(Value::String(v))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+                Ok(Value::Array(
+                    v.iter().map(|b| Value::Integer(i64::from(*b))).collect(),
+                ))
+            }
+
+            fn visit_none<E>(self) -> Result<Value, E> {
+                Ok(Value::Null)
+            }
+
+            fn visit_unit<E>(self) -> Result<Value, E> {
+                Ok(Value::Null)
+            }
+
+            fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+                Value::deserialize(deserializer)
+            }
+
+            fn visit_newtype_struct<D: Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<Value, D::Error> {
+                Value::deserialize(deserializer)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+                let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(item) = seq.next_element()? {
+                    array.push(item);
+                }
+                Ok(Value::Array(array))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+                let mut table = Table::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some((key, value)) = map.next_entry::<Value, Value>()? {
+                    let key = match key {
+                        Value::String(key) => key,
+                        Value::Bool(key) => key.to_string(),
+                        Value::Integer(key) => key.to_string(),
+                        Value::Float(key) => key.to_string(),
+                        _ => return Err(de::Error::custom("table keys must be scalar values")),
+                    };
+                    table.insert(key, value);
+                }
+                Ok(Value::Table(table))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = ValueError;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeVariant<SerializeArray>;
+    type SerializeMap = SerializeTable;
+    type SerializeStruct = SerializeTable;
+    type SerializeStructVariant = SerializeVariant<SerializeTable>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, ValueError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, ValueError> {
+        i64::try_from(v)
+            .map(Value::Integer)
+            .map_err(|_| ValueError(format!("integer `{v}` is out of range")))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, ValueError> {
+        Ok(Value::Float(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, ValueError> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, ValueError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, ValueError> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, ValueError> {
+        Ok(Value::Array(
+            v.iter().map(|b| Value::Integer(i64::from(*b))).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Value, ValueError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, ValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, ValueError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, ValueError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, ValueError> {
+        Ok(Value::String(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, ValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, ValueError> {
+        let mut table = Table::with_capacity(1);
+        table.insert(variant.to_owned(), to_value(value)?);
+        Ok(Value::Table(table))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, ValueError> {
+        Ok(SerializeArray(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeArray>, ValueError> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeTable, ValueError> {
+        Ok(SerializeTable {
+            table: Table::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeTable, ValueError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeTable>, ValueError> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SerializeArray(Vec<Value>);
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.0.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(Value::Array(self.0))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeTable {
+    table: Table,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeTable {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ValueError> {
+        self.key = Some(match to_value(key)? {
+            Value::String(key) => key,
+            Value::Bool(key) => key.to_string(),
+            Value::Integer(key) => key.to_string(),
+            Value::Float(key) => key.to_string(),
+            _ => return Err(ValueError("table keys must be scalar values".to_owned())),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ValueError("table value serialized before its key".to_owned()))?;
+        self.table.insert(key, to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(Value::Table(self.table))
+    }
+}
+
+impl ser::SerializeStruct for SerializeTable {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        self.table.insert(key.to_owned(), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(Value::Table(self.table))
+    }
+}
+
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> SerializeVariant<S> {
+    fn wrap(variant: &'static str, value: Value) -> Value {
+        let mut table = Table::with_capacity(1);
+        table.insert(variant.to_owned(), value);
+        Value::Table(table)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeArray> {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(Self::wrap(
+            self.variant,
+            ser::SerializeSeq::end(self.inner)?,
+        ))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeTable> {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(Self::wrap(
+            self.variant,
+            ser::SerializeStruct::end(self.inner)?,
+        ))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ValueError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Value {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::Integer(v) => visitor.visit_i64(v),
+            Value::Float(v) => visitor.visit_f64(v),
+            Value::String(v) => visitor.visit_string(v),
+            Value::Array(v) => {
+                let mut seq = SeqDeserializer::new(v.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Table(v) => {
+                let mut map = MapDeserializer::new(v.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self {
+            Value::Integer(v @ (0 | 1)) => visitor.visit_bool(v == 1),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Table(table) if table.len() == 1 => visitor.visit_enum(
+                MapAccessDeserializer::new(MapDeserializer::new(table.into_iter())),
+            ),
+            _ => Err(de::Error::custom(
+                "expected a string or a table with a single key for an enum",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// A deserializer recording the keys unknown to the structs deserialized, if `unknown` is set,
+/// and the keys of the value failing to deserialize.
+struct Tracked<'a> {
+    value: Value,
+    /// The dotted path of the value, only known if `unknown` is set.
+    path: String,
+    unknown: Option<&'a RefCell<Vec<UnknownKey>>>,
+    /// The keys of the value in error, from the innermost one.
+    failed: &'a RefCell<Vec<String>>,
+}
+
+impl<'a> Tracked<'a> {
+    fn child(&self, key: &str, value: Value) -> Self {
+        Self {
+            value,
+            path: match self.unknown {
+                Some(_) if !self.path.is_empty() => format!("{}.{key}", self.path),
+                Some(_) => key.to_owned(),
+                None => String::new(),
+            },
+            unknown: self.unknown,
+            failed: self.failed,
+        }
+    }
+
+    /// Deserialize a child value, recording its key as the error goes up.
+    fn deserialize_child<'de, S: de::DeserializeSeed<'de>>(
+        &self,
+        seed: S,
+        key: &str,
+        value: Value,
+    ) -> Result<S::Value, ValueError> {
+        match seed.deserialize(self.child(key, value)) {
+            Ok(value) => {
+                // the errors recovered by the child are forgotten.
+                self.failed.borrow_mut().clear();
+                Ok(value)
+            }
+            Err(err) => {
+                self.failed.borrow_mut().push(key.to_owned());
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for Tracked<'_> {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.value {
+            Value::Array(array) => {
+                let mut seq = TrackedSeq {
+                    iter: array.into_iter().enumerate(),
+                    parent: Tracked {
+                        value: Value::Null,
+                        ..self
+                    },
+                };
+                let value = visitor.visit_seq(&mut seq)?;
+                match seq.iter.len() {
+                    0 => Ok(value),
+                    rest => Err(de::Error::invalid_length(rest, &"fewer elements in array")),
+                }
+            }
+            Value::Table(table) => visitor.visit_map(TrackedMap {
+                iter: table.into_iter(),
+                value: None,
+                parent: Tracked {
+                    value: Value::Null,
+                    ..self
+                },
+            }),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.value.deserialize_bool(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        if let (Value::Table(table), Some(unknown)) = (&self.value, self.unknown) {
+            let mut unknown = unknown.borrow_mut();
+            for key in table.keys().filter(|key| !fields.contains(&key.as_str())) {
+                // only suggest the fields missing in the table, as the others are already in use.
+                let suggestion = closest(key, fields.iter().filter(|t| !table.contains_key(**t)));
+                unknown.push(UnknownKey {
+                    key: self.child(key, Value::Null).path,
+                    suggestion: suggestion.map(|t| self.child(t, Value::Null).path),
+                });
+            }
+        }
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        self.value.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier
+    }
+}
+
+/// Find the candidate closest to `key`, if it's close enough to be a misspelling.
+fn closest<'c>(key: &str, candidates: impl Iterator<Item = &'c &'c str>) -> Option<&'c str> {
+    let threshold = (key.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (levenshtein(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between two strings, in characters.
+///
+/// Transpositions of adjacent characters count as a single edit,
+/// as they're the most common typos (optimal string alignment distance).
+fn levenshtein(left: &str, right: &str) -> usize {
+    let left: Vec<char> = left.chars().collect();
+    let right: Vec<char> = right.chars().collect();
+    let mut previous: Vec<usize> = vec![0; right.len() + 1];
+    let mut row: Vec<usize> = (0..=right.len()).collect();
+    for i in 1..=left.len() {
+        let before_previous = std::mem::replace(&mut previous, row.clone());
+        row[0] = i;
+        for j in 1..=right.len() {
+            let cost = usize::from(left[i - 1] != right[j - 1]);
+            row[j] = (previous[j] + 1)
+                .min(row[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && left[i - 1] == right[j - 2] && left[i - 2] == right[j - 1] {
+                row[j] = row[j].min(before_previous[j - 2] + 1);
+            }
+        }
+    }
+    row[right.len()]
+}
+
+struct TrackedSeq<'a> {
+    iter: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+    parent: Tracked<'a>,
+}
+
+impl<'de> SeqAccess<'de> for TrackedSeq<'_> {
+    type Error = ValueError;
+
+    fn next_element_seed<S: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, ValueError> {
+        match self.iter.next() {
+            Some((index, value)) => self
+                .parent
+                .deserialize_child(seed, &index.to_string(), value)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct TrackedMap<'a> {
+    iter: indexmap::map::IntoIter<String, Value>,
+    value: Option<(String, Value)>,
+    parent: Tracked<'a>,
+}
+
+impl<'de> MapAccess<'de> for TrackedMap<'_> {
+    type Error = ValueError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ValueError> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                let result = seed.deserialize(key.as_str().into_deserializer());
+                self.value = Some((key, value));
+                result.map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ValueError> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| ValueError("table value deserialized before its key".to_owned()))?;
+        self.parent.deserialize_child(seed, &key, value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
@@ -0,0 +1,562 @@
+//! Watching configuration files for changes.
+//!
+//! A [`Watcher`] polls its files on a background thread, and delivers the path of every
+//! changed file on a bounded channel. Changes are reported once the file has settled down,
+//! and coalesced while the channel is full: a file saved many times in a burst, e.g. by an
+//! editor, is reported once instead of flooding the receiver.
+//! The files written by `conrig` itself are ignored by default.
+//!
+//! A [`Watchdog`] checks a configuration file each time it changes instead,
+//! and reports when it's broken.
+//!
+//! The background thread stops once its [`CancellationToken`] is cancelled,
+//! or when the `Watcher` is dropped, so it never outlives its owner.
+//! The watcher doesn't depend on any async runtime: async applications can receive the changes
+//! from a blocking task, or poll them with [`Watcher::try_recv`].
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! # fn main() -> Result<(), conrig::ConrigError> {
+//! use conrig::watch::WatchOption;
+//! # use conrig::{ConfigPathMetadata, FileFormat, ProjectPath};
+//! # const CONFIG: ConfigPathMetadata<()> = ConfigPathMetadata::new(
+//! #     ProjectPath::new("org", "my-organization", "conrig-test"),
+//! #     &["conrig"],
+//! #     FileFormat::Toml,
+//! #     &[],
+//! #     &[],
+//! #     conrig::ConfigOption::DEFAULT_CONFIG,
+//! # );
+//!
+//! let watcher = CONFIG.watch(WatchOption::DEFAULT)?;
+//! for path in watcher.iter() {
+//!     println!("`{}` has changed", path.display());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{ConrigResultExt, Operation};
+use crate::parser::ConfigFile;
+use crate::restart::{diff_values, ConfigDiff, RestartRequired};
+use crate::validate::Validate;
+use crate::value::to_value;
+use crate::{ConfigPathMetadata, ConrigError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The behaviours of a [`Watcher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchOption {
+    /// The delay between two polls of the files. Default: `500ms`.
+    pub interval: Duration,
+    /// The number of changes buffered until the receiver catches up. Default: `16`.
+    ///
+    /// Further changes are coalesced, so each file is reported at most once more.
+    /// A capacity of `0` is treated as `1`.
+    pub capacity: usize,
+    /// The quiet period a file must stay unchanged for before its change is reported.
+    /// Default: `100ms`.
+    ///
+    /// Editors often write a file several times in a row, and atomic saves create a file
+    /// then rename it: such bursts are reported as a single change once the file settles down.
+    pub debounce: Duration,
+    /// Whether the changes made by `conrig` itself are ignored. Default: `true`.
+    ///
+    /// The [`Fingerprint`] of the content a [`ConfigFile`] writes or [reloads] is remembered,
+    /// and a change resulting in that very content is ignored, so the application doesn't
+    /// reload the configuration it has just saved.
+    ///
+    /// [reloads]: crate::parser::ConfigFile::reload_if_changed
+    pub ignore_own_writes: bool,
+}
+
+impl WatchOption {
+    /// The default options.
+    pub const DEFAULT: Self = Self {
+        interval: Duration::from_millis(500),
+        capacity: 16,
+        debounce: Duration::from_millis(100),
+        ignore_own_writes: true,
+    };
+
+    /// Modify the [`interval`] field.
+    ///
+    /// [`interval`]: crate::watch::WatchOption#structfield.interval
+    pub const fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Modify the [`capacity`] field.
+    ///
+    /// [`capacity`]: crate::watch::WatchOption#structfield.capacity
+    pub const fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Modify the [`debounce`] field.
+    ///
+    /// [`debounce`]: crate::watch::WatchOption#structfield.debounce
+    pub const fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Modify the [`ignore_own_writes`] field.
+    ///
+    /// [`ignore_own_writes`]: crate::watch::WatchOption#structfield.ignore_own_writes
+    pub const fn with_ignore_own_writes(mut self, ignore_own_writes: bool) -> Self {
+        self.ignore_own_writes = ignore_own_writes;
+        self
+    }
+}
+
+impl Default for WatchOption {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A token stopping the [`Watcher`]s it's given to.
+///
+/// Clones of a token share their state: cancelling one cancels all of them.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<(Mutex<bool>, Condvar)>);
+
+impl CancellationToken {
+    /// Create a new, uncancelled token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token, waking up the watchers immediately.
+    pub fn cancel(&self) {
+        let (cancelled, condvar) = &*self.0;
+        *cancelled.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        condvar.notify_all();
+    }
+
+    /// Check if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        *self.0 .0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wait for the token to be cancelled, at most for `timeout`.
+    ///
+    /// Returns `true` if the token has been cancelled.
+    fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (cancelled, condvar) = &*self.0;
+        let guard = cancelled.lock().unwrap_or_else(PoisonError::into_inner);
+        let (guard, _) = condvar
+            .wait_timeout_while(guard, timeout, |cancelled| !*cancelled)
+            .unwrap_or_else(PoisonError::into_inner);
+        *guard
+    }
+}
+
+/// A handle to a background thread watching files for changes.
+///
+/// The thread is stopped and joined when the watcher is dropped.
+#[derive(Debug)]
+pub struct Watcher {
+    receiver: Receiver<PathBuf>,
+    token: CancellationToken,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    /// Start watching some files.
+    ///
+    /// The files don't have to exist: creating or removing a file is a change as well.
+    pub fn new(paths: Vec<PathBuf>, option: WatchOption) -> Self {
+        Self::with_token(paths, option, CancellationToken::new())
+    }
+
+    /// Start watching some files until `token` is cancelled.
+    pub fn with_token(paths: Vec<PathBuf>, option: WatchOption, token: CancellationToken) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(option.capacity.max(1));
+        // stamp the files right away, so no change made after this call is missed.
+        let stamps = paths.iter().map(|path| stamp(path)).collect();
+        let handle = {
+            let token = token.clone();
+            thread::spawn(move || poll(paths, stamps, option, token, sender))
+        };
+        Self {
+            receiver,
+            token,
+            handle: Some(handle),
+        }
+    }
+
+    /// The token stopping this watcher.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Stop watching. The changes already delivered can still be received.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Wait for the next changed file.
+    ///
+    /// Returns `None` once the watcher has been cancelled and every change has been received.
+    pub fn recv(&self) -> Option<PathBuf> {
+        self.receiver.recv().ok()
+    }
+
+    /// Wait for the next changed file, at most for `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<PathBuf> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// Get the next changed file, if any, without blocking.
+    pub fn try_recv(&self) -> Option<PathBuf> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Iterate over the changed files, blocking until the watcher is cancelled.
+    pub fn iter(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.receiver.iter()
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.token.cancel();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// The state of a file, compared between two polls.
+type Stamp = Option<(Option<SystemTime>, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
+}
+
+/// A fingerprint of the content of a file.
+///
+/// Fingerprints are only meant to be compared within the same process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// Compute the fingerprint of some content.
+    pub fn of(content: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+
+    /// Compute the fingerprint of a file, if it can be read.
+    pub fn of_file(path: &Path) -> Option<Self> {
+        fs::read(path).ok().map(|content| Self::of(&content))
+    }
+}
+
+/// The fingerprint of the content `conrig` last wrote into or reloaded from each file.
+static KNOWN: Mutex<Vec<(PathBuf, Fingerprint)>> = Mutex::new(Vec::new());
+
+/// Remember the content `conrig` has just written into or reloaded from a file.
+pub(crate) fn remember(path: &Path, fingerprint: Fingerprint) {
+    let mut known = KNOWN.lock().unwrap_or_else(PoisonError::into_inner);
+    match known.iter_mut().find(|(known, _)| known == path) {
+        Some((_, last)) => *last = fingerprint,
+        None => known.push((path.to_path_buf(), fingerprint)),
+    }
+}
+
+/// The fingerprint of the content `conrig` last wrote into or reloaded from a file.
+pub(crate) fn known(path: &Path) -> Option<Fingerprint> {
+    KNOWN
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|(known, _)| known == path)
+        .map(|(_, fingerprint)| *fingerprint)
+}
+
+/// Check if the content of a file is the one `conrig` knows already.
+fn is_known(path: &Path) -> bool {
+    known(path).is_some_and(|known| Fingerprint::of_file(path) == Some(known))
+}
+
+fn poll(
+    paths: Vec<PathBuf>,
+    mut stamps: Vec<Stamp>,
+    option: WatchOption,
+    token: CancellationToken,
+    sender: SyncSender<PathBuf>,
+) {
+    // the time of the latest change of each file, until the file settles down.
+    let mut changed: Vec<Option<Instant>> = vec![None; paths.len()];
+    // the changes which didn't fit into the channel, each file at most once.
+    let mut backlog: Vec<PathBuf> = Vec::new();
+    while !token.wait_timeout(option.interval) {
+        let now = Instant::now();
+        for ((path, last), changed) in paths.iter().zip(&mut stamps).zip(&mut changed) {
+            let current = stamp(path);
+            if current != *last {
+                *last = current;
+                *changed = Some(now);
+                continue;
+            }
+            if changed.is_some_and(|changed| now.duration_since(changed) >= option.debounce) {
+                *changed = None;
+                if option.ignore_own_writes && is_known(path) {
+                    continue;
+                }
+                if !backlog.contains(path) {
+                    backlog.push(path.clone());
+                }
+            }
+        }
+        while let Some(path) = backlog.first() {
+            match sender.try_send(path.clone()) {
+                Ok(()) => {
+                    backlog.remove(0);
+                }
+                Err(TrySendError::Full(_)) => break,
+                Err(TrySendError::Disconnected(_)) => return,
+            }
+        }
+    }
+}
+
+/// A handle to a background thread checking the health of a configuration file.
+///
+/// Whenever the file changes and settles down, it's checked again, e.g. parsed and
+/// [validated][Validate], and the error is reported to a callback if it's broken.
+/// So a daemon can alert about a file broken externally before its next restart fails.
+///
+/// Only the [`interval`] and [`debounce`] of the [`WatchOption`] are used.
+/// The thread is stopped and joined when the watchdog is dropped.
+///
+/// [`interval`]: crate::watch::WatchOption#structfield.interval
+/// [`debounce`]: crate::watch::WatchOption#structfield.debounce
+#[derive(Debug)]
+pub struct Watchdog {
+    healthy: Arc<AtomicBool>,
+    token: CancellationToken,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Start checking a configuration file with `check`.
+    ///
+    /// The file is checked right away, then each time it changes.
+    /// `on_broken` is called with the error of every failed check.
+    pub fn new<C, B>(file: ConfigFile, option: WatchOption, check: C, on_broken: B) -> Self
+    where
+        C: Fn(&ConfigFile) -> Result<(), ConrigError> + Send + 'static,
+        B: FnMut(&ConrigError) + Send + 'static,
+    {
+        Self::with_token(file, option, check, on_broken, CancellationToken::new())
+    }
+
+    /// Start checking a configuration file with `check`, until `token` is cancelled.
+    ///
+    /// See [`Watchdog::new`].
+    pub fn with_token<C, B>(
+        file: ConfigFile,
+        option: WatchOption,
+        check: C,
+        mut on_broken: B,
+        token: CancellationToken,
+    ) -> Self
+    where
+        C: Fn(&ConfigFile) -> Result<(), ConrigError> + Send + 'static,
+        B: FnMut(&ConrigError) + Send + 'static,
+    {
+        let last = stamp(&file.path);
+        let healthy = Arc::new(AtomicBool::new(true));
+        inspect(&file, &check, &mut on_broken, &healthy);
+        let handle = {
+            let healthy = healthy.clone();
+            let token = token.clone();
+            thread::spawn(move || {
+                guard(file, last, option, token, check, on_broken, healthy);
+            })
+        };
+        Self {
+            healthy,
+            token,
+            handle: Some(handle),
+        }
+    }
+
+    /// Check if the file passed its latest check.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Acquire)
+    }
+
+    /// The token stopping this watchdog.
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Stop checking.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.token.cancel();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Check a file, recording and reporting its health.
+fn inspect(
+    file: &ConfigFile,
+    check: &impl Fn(&ConfigFile) -> Result<(), ConrigError>,
+    on_broken: &mut impl FnMut(&ConrigError),
+    healthy: &AtomicBool,
+) {
+    let result = check(file);
+    healthy.store(result.is_ok(), Ordering::Release);
+    if let Err(error) = result {
+        on_broken(&error);
+    }
+}
+
+fn guard(
+    file: ConfigFile,
+    mut last: Stamp,
+    option: WatchOption,
+    token: CancellationToken,
+    check: impl Fn(&ConfigFile) -> Result<(), ConrigError>,
+    mut on_broken: impl FnMut(&ConrigError),
+    healthy: Arc<AtomicBool>,
+) {
+    let mut changed: Option<Instant> = None;
+    while !token.wait_timeout(option.interval) {
+        let now = Instant::now();
+        let current = stamp(&file.path);
+        if current != last {
+            last = current;
+            changed = Some(now);
+            continue;
+        }
+        if changed.is_some_and(|changed| now.duration_since(changed) >= option.debounce) {
+            changed = None;
+            inspect(&file, &check, &mut on_broken, &healthy);
+        }
+    }
+}
+
+impl<'p, T> ConfigPathMetadata<'p, T> {
+    /// Watch the configuration file for changes.
+    ///
+    /// This is equivalent to `self.search_config_file()?.fallback_default()?.watch(option)`.
+    pub fn watch(&self, option: WatchOption) -> Result<Watcher, ConrigError> {
+        Ok(self.search_config_file()?.fallback_default()?.watch(option))
+    }
+}
+
+impl<'p, T: DeserializeOwned + Validate + 'static> ConfigPathMetadata<'p, T> {
+    /// Check the health of the configuration file in the background.
+    ///
+    /// This is equivalent to
+    /// `self.search_config_file()?.fallback_default()?.watchdog::<T>(option, on_broken)`.
+    pub fn watchdog(
+        &self,
+        option: WatchOption,
+        on_broken: impl FnMut(&ConrigError) + Send + 'static,
+    ) -> Result<Watchdog, ConrigError> {
+        Ok(self
+            .search_config_file()?
+            .fallback_default()?
+            .watchdog::<T>(option, on_broken))
+    }
+}
+
+impl ConfigFile {
+    /// Watch the configuration file for changes.
+    pub fn watch(&self, option: WatchOption) -> Watcher {
+        Watcher::new(vec![self.path.clone()], option)
+    }
+
+    /// Check the health of the configuration file in the background.
+    ///
+    /// The file is checked with [`read_checked::<T>`], see [`Watchdog`] for more information.
+    /// To check a configuration which doesn't implement [`Validate`],
+    /// use [`Watchdog::new`] with [`read::<T>`] instead.
+    ///
+    /// [`read_checked::<T>`]: ConfigFile::read_checked
+    /// [`read::<T>`]: ConfigFile::read
+    pub fn watchdog<T: DeserializeOwned + Validate + 'static>(
+        &self,
+        option: WatchOption,
+        on_broken: impl FnMut(&ConrigError) + Send + 'static,
+    ) -> Watchdog {
+        Watchdog::new(
+            self.clone(),
+            option,
+            |file| file.read_checked::<T>().map(drop),
+            on_broken,
+        )
+    }
+
+    /// Reload the configuration into `value` if the file has been changed by someone else.
+    ///
+    /// The [`Fingerprint`] of the content `conrig` last wrote into or reloaded from the file is
+    /// remembered: if the file still holds that content, `value` is left untouched.
+    /// Otherwise, the file is read again, and its fingerprint is remembered in turn.
+    ///
+    /// Returns `true` if `value` has been reloaded.
+    pub fn reload_if_changed<T: DeserializeOwned>(
+        &self,
+        value: &mut T,
+    ) -> Result<bool, ConrigError> {
+        let contents = self
+            .read_contents()
+            .context_path(Operation::Read, &self.path)?;
+        let fingerprint = Fingerprint::of(contents.as_bytes());
+        if known(&self.path) == Some(fingerprint) {
+            return Ok(false);
+        }
+        *value = self
+            .parse(&contents)
+            .context_path(Operation::Read, &self.path)?;
+        remember(&self.path, fingerprint);
+        Ok(true)
+    }
+
+    /// Reload the configuration into `value` like [`reload_if_changed`], and report the changes.
+    ///
+    /// Returns `None` if the file hasn't been changed by someone else, or the [`ConfigDiff`]
+    /// telling whether the reloaded configuration can be applied without a restart.
+    ///
+    /// [`reload_if_changed`]: ConfigFile::reload_if_changed
+    pub fn reload_diff<T: Serialize + DeserializeOwned + RestartRequired>(
+        &self,
+        value: &mut T,
+    ) -> Result<Option<ConfigDiff>, ConrigError> {
+        let old = to_value(value)?;
+        if !self.reload_if_changed(value)? {
+            return Ok(None);
+        }
+        let new = to_value(value)?;
+        Ok(Some(diff_values(&old, &new, T::RESTART_REQUIRED)))
+    }
+}
@@ -0,0 +1,139 @@
+#![cfg(feature = "clap")]
+
+use clap::Parser;
+use conrig::cli::ConfigArgs;
+use conrig::conrig;
+use conrig::{ConfigOption, ConfigType, FileFormat, ProjectPath};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+#[test]
+fn test_read_with_args() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        server: Server,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath {
+            qualifier: "org",
+            organization: "embers-of-the-fire",
+            application: "conrig-cli",
+        },
+        config_name: &["conrig-cli"],
+        config_option: ConfigOption {
+            allow_dot_prefix: true,
+            config_sys_type: ConfigType::Config,
+            sys_override_local: false,
+            ..ConfigOption::DEFAULT_CONFIG
+        },
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-cli.cfg");
+    std::fs::write(
+        &path,
+        r#"{ "name": "conrig", "server": { "host": "localhost", "port": 80 } }"#,
+    )?;
+
+    let cli = Cli::try_parse_from([
+        "app".as_ref(),
+        "--config-path".as_ref(),
+        path.as_os_str(),
+        "--config-format".as_ref(),
+        "json".as_ref(),
+        "--set".as_ref(),
+        "server.port=8080".as_ref(),
+    ])?;
+    assert_eq!(
+        TEST_APP_CONFIG.read_with_args(&cli.config)?,
+        Config {
+            name: "conrig".to_owned(),
+            server: Server {
+                host: "localhost".to_owned(),
+                port: 8080,
+            },
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_init_with_args() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::cli::InitArgs;
+    use conrig::preset::register_preset;
+    use conrig::ConrigError;
+
+    #[derive(Parser)]
+    struct InitCli {
+        #[command(flatten)]
+        init: InitArgs,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        threads: u32,
+        telemetry: bool,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath {
+            qualifier: "org",
+            organization: "embers-of-the-fire",
+            application: "conrig-cli-init",
+        },
+        config_name: &["conrig-cli-init"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_path_env_var("CONRIG_CLI_INIT_PATH"),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Json,
+    });
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-cli-init.json");
+    let _ = std::fs::remove_file(&path);
+    std::env::set_var("CONRIG_CLI_INIT_PATH", &path);
+    register_preset(
+        "cli-recommended",
+        &Config {
+            threads: 4,
+            telemetry: false,
+        },
+    )?;
+
+    let cli = InitCli::try_parse_from(["app", "--preset", "cli-recommended"])?;
+    let file = TEST_APP_CONFIG.init_with_args(&cli.init)?;
+    assert_eq!(file.path, path);
+    assert_eq!(file.read::<Config>()?.threads, 4);
+
+    let cli = InitCli::try_parse_from(["app"])?;
+    let err = TEST_APP_CONFIG.init_with_args(&cli.init).unwrap_err();
+    assert!(matches!(err.root(), ConrigError::FileSystemError(_)));
+    assert_eq!(file.read::<Config>()?.threads, 4);
+
+    let cli = InitCli::try_parse_from(["app", "--force"])?;
+    TEST_APP_CONFIG.init_with_args(&cli.init)?;
+    assert_eq!(file.read::<Config>()?, Config::default());
+
+    let cli = InitCli::try_parse_from(["app", "--preset", "unknown", "--force"])?;
+    let err = TEST_APP_CONFIG.init_with_args(&cli.init).unwrap_err();
+    assert!(matches!(err.root(), ConrigError::UnknownPreset(_)));
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
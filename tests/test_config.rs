@@ -1,12 +1,14 @@
 use conrig::conrig;
+use conrig::parser::ConfigFile;
 use conrig::path::ConfigType;
+use conrig::testing::Sandbox;
 use conrig::{ConfigOption, FileFormat, ProjectPath};
 use serde_derive::{Deserialize, Serialize};
-use std::env::{current_dir, set_current_dir};
+use std::path::PathBuf;
 
 #[test]
 fn test_config() -> Result<(), Box<dyn std::error::Error>> {
-    set_current_dir(current_dir()?.join("tests"))?;
+    let sandbox = Sandbox::new()?;
 
     #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
     struct Config {
@@ -34,6 +36,7 @@ fn test_config() -> Result<(), Box<dyn std::error::Error>> {
             allow_dot_prefix: true,
             config_sys_type: ConfigType::Config,
             sys_override_local: false,
+            ..ConfigOption::DEFAULT_CONFIG
         },
         extra_files: &[],
         extra_folders: &[],
@@ -58,6 +61,7 @@ fn test_config() -> Result<(), Box<dyn std::error::Error>> {
     println!("{:?}", res);
     let cfg = TEST_APP_CONFIG.search_config_file()?;
     println!("{:?}", cfg);
+    assert_eq!(cfg.path, Some(sandbox.current_dir().join("conrig.toml")));
 
     Ok(())
 }
@@ -90,14 +94,15 @@ fn test_extra_cfg() -> Result<(), Box<dyn std::error::Error>> {
             allow_dot_prefix: true,
             config_sys_type: ConfigType::Config,
             sys_override_local: false,
+            ..ConfigOption::DEFAULT_CONFIG
         },
-        extra_files: &[concat!(env!("CARGO_MANIFEST_DIR"), "/conrig.cfg")],
+        extra_files: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig.cfg")],
         extra_folders: &[],
         default_format: FileFormat::Toml,
     });
 
     std::fs::write(
-        concat!(env!("CARGO_MANIFEST_DIR"), "/conrig.cfg.json"),
+        concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig.cfg.json"),
         r#"{ "name": "conrig", "id": 42 }"#,
     )?;
     let cfg = TEST_APP_CONFIG.search_config_file()?;
@@ -107,3 +112,3416 @@ fn test_extra_cfg() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn test_file_format_names() {
+    use conrig::error::UnknownFormat;
+
+    assert_eq!("toml".parse(), Ok(FileFormat::Toml));
+    assert_eq!("YAML".parse(), Ok(FileFormat::Yaml));
+    assert_eq!("yml".parse(), Ok(FileFormat::Yaml));
+    assert_eq!(
+        "yml5".parse::<FileFormat>(),
+        Err(UnknownFormat("yml5".to_owned()))
+    );
+    assert_eq!(FileFormat::from_extension("json"), Some(FileFormat::Json));
+    assert_eq!(FileFormat::from_extension("conf"), None);
+    for file_format in [FileFormat::Toml, FileFormat::Json, FileFormat::Ron] {
+        assert_eq!(file_format.to_string().parse(), Ok(file_format));
+    }
+    assert_eq!(FileFormat::Plist.to_string(), "plist");
+}
+
+#[test]
+fn test_write_minimal() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(default)]
+    struct Config {
+        name: String,
+        id: u32,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                name: "embers-of-the-fire".to_owned(),
+                id: 0,
+            }
+        }
+    }
+
+    let file = ConfigFile::new(
+        FileFormat::Json,
+        PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-minimal.json"),
+    );
+    file.write_minimal(&Config {
+        id: 42,
+        ..Config::default()
+    })?;
+    assert_eq!(std::fs::read_to_string(&file.path)?, r#"{"id":42}"#);
+    assert_eq!(
+        file.read::<Config>()?,
+        Config {
+            name: "embers-of-the-fire".to_owned(),
+            id: 42,
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_write_generated() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::generate::Generation;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(default)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(default)]
+    struct Config {
+        theme: String,
+        font_size: u8,
+        server: Server,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                theme: "light".to_owned(),
+                font_size: 12,
+                server: Server {
+                    host: "localhost".to_owned(),
+                    port: 80,
+                },
+            }
+        }
+    }
+
+    impl Default for Server {
+        fn default() -> Self {
+            Config::default().server
+        }
+    }
+
+    let config = Config {
+        theme: "dark".to_owned(),
+        server: Server {
+            port: 8080,
+            ..Server::default()
+        },
+        ..Config::default()
+    };
+    let file = ConfigFile::new(
+        FileFormat::Toml,
+        PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-generated.toml"),
+    );
+
+    file.write_generated(&config, Generation::Minimal)?;
+    let contents = std::fs::read_to_string(&file.path)?;
+    assert!(!contents.contains("font_size") && !contents.contains("host"));
+    assert_eq!(file.read::<Config>()?, config);
+
+    file.write_generated(&config, Generation::Commented)?;
+    let contents = std::fs::read_to_string(&file.path)?;
+    assert!(contents.contains("# font_size = 12\n"));
+    assert!(contents.contains("# host = \"localhost\"\n"));
+    assert!(contents.contains("port = 8080\n"));
+    assert_eq!(file.read::<Config>()?, config);
+
+    file.write_generated(&config, Generation::Full)?;
+    let contents = std::fs::read_to_string(&file.path)?;
+    assert!(contents.contains("font_size = 12\n") && !contents.contains('#'));
+    assert_eq!(file.read::<Config>()?, config);
+
+    std::fs::remove_file(&file.path)?;
+    assert_eq!(
+        file.read_or_generate(|| Ok(Config::default()), Generation::Commented)?,
+        Config::default()
+    );
+    let contents = std::fs::read_to_string(&file.path)?;
+    assert!(contents.starts_with("# Other available options"));
+    assert_eq!(file.read::<Config>()?, Config::default());
+
+    std::fs::remove_file(&file.path)?;
+    Ok(())
+}
+
+#[test]
+fn test_write_generated_required() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::generate::Generation;
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        #[serde(default)]
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        #[serde(default)]
+        verbose: bool,
+        server: Server,
+    }
+
+    let file = ConfigFile::new(
+        FileFormat::Toml,
+        PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-generated-required.toml"),
+    );
+    let _ = std::fs::remove_file(&file.path);
+
+    // the required keys are written, even if they equal their default value.
+    assert_eq!(
+        file.read_or_generate(|| Ok(Config::default()), Generation::Minimal)?,
+        Config::default()
+    );
+    assert_eq!(
+        std::fs::read_to_string(&file.path)?,
+        "name = \"\"\n\n[server]\nhost = \"\"\n"
+    );
+    assert_eq!(file.read::<Config>()?, Config::default());
+
+    file.write_generated(&Config::default(), Generation::Commented)?;
+    let contents = std::fs::read_to_string(&file.path)?;
+    assert!(contents.starts_with("name = \"\"\n"));
+    assert!(contents.contains("# verbose = false\n") && !contents.contains("# name"));
+    assert_eq!(file.read::<Config>()?, Config::default());
+
+    std::fs::remove_file(&file.path)?;
+    Ok(())
+}
+
+#[test]
+fn test_keep_comments() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::comments::{comments_path, extract};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        tags: Vec<String>,
+        server: Server,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-comments");
+    std::fs::create_dir_all(&dir)?;
+    let option = ConfigOption::DEFAULT_CONFIG.with_keep_comments(true);
+    let toml =
+        ConfigFile::new(FileFormat::Toml, dir.join("conrig.toml")).with_config_option(option);
+    std::fs::write(
+        &toml.path,
+        "# The name of the application.\n\
+         name = \"conrig\" # not the binary\n\
+         tags = [\n    \"a\", # first\n    \"b\",\n]\n\n\
+         # Where to listen.\n\
+         [server]\n\
+         host = \"localhost\"\n\
+         # Use 0 for a random port.\n\
+         port = 80\n",
+    )?;
+    let comments = toml.comments()?;
+    assert_eq!(
+        comments.get("name").map(String::as_str),
+        Some("The name of the application.\nnot the binary")
+    );
+    assert_eq!(
+        comments.get("server").map(String::as_str),
+        Some("Where to listen.")
+    );
+    assert_eq!(
+        comments.get("server.port").map(String::as_str),
+        Some("Use 0 for a random port.")
+    );
+    assert_eq!(comments.len(), 3);
+
+    // rewrites keep the comments.
+    let mut config = toml.read::<Config>()?;
+    config.server.port = 8080;
+    toml.write(&config)?;
+    assert_eq!(toml.comments()?, comments);
+    assert_eq!(toml.read::<Config>()?, config);
+
+    // converting into JSON moves them into the sidecar, and back into YAML later.
+    let json = ConfigFile::new(FileFormat::Json, dir.join("conrig.json"));
+    toml.convert(&json)?;
+    assert!(comments_path(&json.path).exists());
+    assert_eq!(json.comments()?, comments);
+    assert_eq!(json.read::<Config>()?, config);
+
+    let yaml = ConfigFile::new(FileFormat::Yaml, dir.join("conrig.yaml"));
+    json.convert(&yaml)?;
+    assert_eq!(yaml.comments()?, comments);
+    assert_eq!(yaml.read::<Config>()?, config);
+    assert!(std::fs::read_to_string(&yaml.path)?.contains("  # Use 0 for a random port.\n"));
+
+    assert_eq!(
+        extract(
+            FileFormat::Yaml,
+            "rules:\n# skipped\n- name: a # in a sequence\n  color: '#fff'\n# kept\nlevel: 1\n",
+        ),
+        [("level".to_owned(), "kept".to_owned())].into()
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_migrate_from() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        id: u32,
+    }
+
+    const LEGACY_PROJECT: ProjectPath =
+        ProjectPath::new("org", "embers-of-the-fire", "conrig-legacy");
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath {
+            qualifier: "org",
+            organization: "embers-of-the-fire",
+            application: "conrig-migrated",
+        },
+        config_name: &["conrig"],
+        config_option: ConfigOption {
+            allow_dot_prefix: true,
+            config_sys_type: ConfigType::Config,
+            sys_override_local: false,
+            ..ConfigOption::DEFAULT_CONFIG
+        },
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let _sandbox = Sandbox::new()?;
+    let legacy_dir = LEGACY_PROJECT.sys_config_dir().unwrap();
+    let current_dir = TEST_APP_CONFIG.project_path.sys_config_dir().unwrap();
+    std::fs::create_dir_all(&legacy_dir)?;
+    std::fs::write(
+        legacy_dir.join("conrig.json"),
+        r#"{ "name": "legacy", "id": 7 }"#,
+    )?;
+
+    let migration = TEST_APP_CONFIG.migrate_from(&[LEGACY_PROJECT])?.unwrap();
+    assert_eq!(migration.from, legacy_dir.join("conrig.json"));
+    assert_eq!(migration.to, current_dir.join("conrig.json"));
+    assert_eq!(migration.file_format, FileFormat::Json);
+    assert!(!migration.from.exists());
+    assert_eq!(TEST_APP_CONFIG.migrate_from(&[LEGACY_PROJECT])?, None);
+    Ok(())
+}
+
+#[test]
+fn test_import() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::import::Importer;
+    use conrig::value::Value;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        id: u32,
+    }
+
+    fn import_legacy(value: Value) -> Result<Config, conrig::ConrigError> {
+        Ok(Config {
+            name: match value.get_path("user.name") {
+                Some(Value::String(name)) => name.clone(),
+                _ => "unknown".to_owned(),
+            },
+            id: 1,
+        })
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath {
+            qualifier: "org",
+            organization: "embers-of-the-fire",
+            application: "conrig-import",
+        },
+        config_name: &["conrig-import"],
+        config_option: ConfigOption {
+            allow_dot_prefix: true,
+            config_sys_type: ConfigType::Config,
+            sys_override_local: true,
+            ..ConfigOption::DEFAULT_CONFIG
+        },
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+        importers: &[
+            Importer::direct("missing", "~/.conrig-missing-file", FileFormat::Toml),
+            Importer::new(
+                "legacy",
+                concat!(env!("CARGO_TARGET_TMPDIR"), "/.conrig-legacy-rc"),
+                FileFormat::Json,
+                import_legacy,
+            ),
+        ],
+    });
+
+    let _sandbox = Sandbox::new()?;
+    std::fs::write(
+        concat!(env!("CARGO_TARGET_TMPDIR"), "/.conrig-legacy-rc"),
+        r#"{ "user": { "name": "legacy" } }"#,
+    )?;
+
+    let imported = TEST_APP_CONFIG.import()?.unwrap();
+    assert_eq!(imported.importer, "legacy");
+    assert_eq!(imported.target, TEST_APP_CONFIG.default_sys_config_file()?);
+    assert_eq!(TEST_APP_CONFIG.read()?, imported.value);
+    assert_eq!(TEST_APP_CONFIG.import()?, None);
+    Ok(())
+}
+
+#[test]
+fn test_deprecation() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::deprecation::Deprecation;
+    use conrig::{ConrigError, ConrigWarning};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+    const DEPRECATIONS: &[Deprecation] = &[Deprecation::new("id", "1.2").with_replacement("name")];
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-deprecation.json");
+    std::fs::write(&path, r#"{ "name": "conrig", "id": 42 }"#)?;
+    let option = ConfigOption::DEFAULT_CONFIG
+        .with_deprecations(DEPRECATIONS)
+        .with_warning_handler(|warning| {
+            assert!(matches!(warning, ConrigWarning::DeprecatedKey(_)));
+            WARNINGS.fetch_add(1, Ordering::SeqCst);
+        });
+
+    let file = ConfigFile::new(FileFormat::Json, path)
+        .with_config_option(option.with_app_version("1.2.0-beta"));
+    assert_eq!(file.read::<Config>()?.name, "conrig");
+    assert_eq!(WARNINGS.load(Ordering::SeqCst), 1);
+
+    let file = file.with_config_option(option.with_app_version("1.10"));
+    let error = file.read::<Config>().unwrap_err();
+    assert!(matches!(error.root(), ConrigError::RemovedKey(_)));
+    assert_eq!(
+        error.root().to_string(),
+        "Configuration key `id` has been removed after version 1.2. Use `name` instead."
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_version_stamp() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::upgrade::Addition;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    const ADDITIONS: &[Addition] = &[
+        Addition::new("timeout", "1.2.0").with_description("The timeout of requests."),
+        Addition::new("cache", "1.3"),
+        Addition::new("future", "2.0.0"),
+    ];
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-version-stamp.toml");
+    let _ = std::fs::remove_file(&path);
+    let option = ConfigOption::DEFAULT_CONFIG
+        .with_version_stamp(true)
+        .with_additions(ADDITIONS)
+        .with_keep_comments(true);
+    let config = Config {
+        name: "conrig".to_owned(),
+    };
+
+    // a new file is only stamped.
+    let file = ConfigFile::new(FileFormat::Toml, path.clone())
+        .with_config_option(option.with_app_version("1.1.0"));
+    file.write(&config)?;
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        "# app-version: 1.1.0\n\nname = \"conrig\"\n"
+    );
+    assert_eq!(file.version_stamp()?.as_deref(), Some("1.1.0"));
+    assert_eq!(file.read::<Config>()?, config);
+
+    // rewriting it with a newer version lists the options added since then.
+    let contents = std::fs::read_to_string(&path)?.replace("name", "# mine\nname");
+    std::fs::write(&path, contents)?;
+    let file = file.with_config_option(option.with_app_version("1.3.0"));
+    file.write(&config)?;
+    let expected = "# app-version: 1.3.0\n\
+        # Options added since version 1.1.0:\n\
+        #   `timeout` (1.2.0): The timeout of requests.\n\
+        #   `cache` (1.3)\n\
+        \n\
+        # mine\n\
+        name = \"conrig\"\n";
+    assert_eq!(std::fs::read_to_string(&path)?, expected);
+    assert_eq!(file.version_stamp()?.as_deref(), Some("1.3.0"));
+
+    // the notice is kept until the next upgrade.
+    file.write(&config)?;
+    assert_eq!(std::fs::read_to_string(&path)?, expected);
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_multi_docs() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::ConrigError;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Rule {
+        name: String,
+        priority: u32,
+    }
+
+    let rules = vec![
+        Rule {
+            name: "first".to_owned(),
+            priority: 1,
+        },
+        Rule {
+            name: "second".to_owned(),
+            priority: 2,
+        },
+    ];
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-multi-docs");
+    std::fs::create_dir_all(&dir)?;
+
+    let yaml = ConfigFile::new(FileFormat::Yaml, dir.join("rules.yaml"));
+    yaml.write_all_docs(&rules)?;
+    assert_eq!(
+        std::fs::read_to_string(&yaml.path)?,
+        "name: first\npriority: 1\n---\nname: second\npriority: 2\n"
+    );
+    assert_eq!(yaml.read_all_docs::<Rule>()?, rules);
+    std::fs::write(&yaml.path, "")?;
+    assert_eq!(yaml.read_all_docs::<Rule>()?, []);
+
+    let json = ConfigFile::new(FileFormat::Json, dir.join("rules.jsonl"));
+    json.write_all_docs(&rules)?;
+    assert_eq!(
+        std::fs::read_to_string(&json.path)?,
+        "{\"name\":\"first\",\"priority\":1}\n{\"name\":\"second\",\"priority\":2}\n"
+    );
+    assert_eq!(json.read_all_docs::<Rule>()?, rules);
+
+    // errors are located in the whole file.
+    std::fs::write(
+        &json.path,
+        "{\"name\":\"first\",\"priority\":1}\n{\"name\":}\n",
+    )?;
+    let error = json.read_all_docs::<Rule>().unwrap_err();
+    assert_eq!(error.parse_error().and_then(|t| t.line), Some(2));
+
+    let toml = ConfigFile::new(FileFormat::Toml, dir.join("rules.toml"));
+    assert!(matches!(
+        toml.write_all_docs(&rules).unwrap_err().root(),
+        ConrigError::SingleDocument(FileFormat::Toml)
+    ));
+    assert!(!toml.path.exists());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_atomic_write() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-atomic");
+    let _ = std::fs::remove_dir_all(&dir);
+    let file = ConfigFile::new(FileFormat::Toml, dir.join("conrig.toml"));
+    for name in ["first", "second"] {
+        file.write(&Config {
+            name: name.to_owned(),
+        })?;
+    }
+    assert_eq!(file.read::<Config>()?.name, "second");
+    assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+
+    // concurrent writers never collide on their temporary files.
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..8)
+            .map(|thread| {
+                let file = &file;
+                scope.spawn(move || {
+                    (0..50).try_for_each(|index| {
+                        file.write(&Config {
+                            name: format!("{thread}-{index}"),
+                        })
+                    })
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().unwrap())
+    })?;
+    assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_strict() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::ConrigError;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Server {
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        server: Server,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath {
+            qualifier: "org",
+            organization: "embers-of-the-fire",
+            application: "conrig-strict",
+        },
+        config_name: &["conrig-strict"],
+        config_option: ConfigOption::STRICT.with_sys_override_local(true),
+        extra_files: &[
+            concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-strict-a"),
+            concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-strict-b"),
+        ],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let _ = std::fs::remove_file(dir.join("conrig-strict-b.json"));
+    std::fs::write(
+        dir.join("conrig-strict-a.json"),
+        r#"{ "name": "conrig", "server": { "prot": 80 } }"#,
+    )?;
+    let file = TEST_APP_CONFIG.search_config_file()?.config_file()?;
+    let error = file.read::<Config>().unwrap_err();
+    assert!(matches!(
+        error.root(),
+        ConrigError::UnknownKeys(keys) if keys.len() == 1 && keys[0].key == "server.prot"
+    ));
+    assert_eq!(
+        error.root().to_string(),
+        "Unknown configuration keys: `server.prot` (did you mean `server.port`?)"
+    );
+    assert_eq!(error.path(), Some(file.path.as_path()));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::set_permissions(&file.path, std::fs::Permissions::from_mode(0o666))?;
+        assert!(matches!(
+            file.read::<Config>(),
+            Err(ConrigError::InsecurePermissions(_))
+        ));
+    }
+
+    std::fs::write(dir.join("conrig-strict-b.json"), "{}")?;
+    assert!(matches!(
+        TEST_APP_CONFIG.search_config_file(),
+        Err(ConrigError::AmbiguousConfig(paths)) if paths.len() == 2
+    ));
+
+    std::fs::remove_file(dir.join("conrig-strict-a.json"))?;
+    std::fs::remove_file(dir.join("conrig-strict-b.json"))?;
+    let file = TEST_APP_CONFIG.search_config_file()?.fallback_default()?;
+    assert_eq!(file.path, TEST_APP_CONFIG.default_sys_config_file()?);
+    assert!(matches!(
+        file.write(&Config {
+            name: "conrig".to_owned(),
+            server: Server { port: 80 },
+        }),
+        Err(ConrigError::ReadOnly(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_backup() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        id: u32,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-backup");
+    let _ = std::fs::remove_dir_all(&dir);
+    for atomic_write in [true, false] {
+        let file = ConfigFile::new(FileFormat::Json, dir.join("conrig.json")).with_config_option(
+            ConfigOption::DEFAULT_CONFIG
+                .with_backup(2)
+                .with_atomic_write(atomic_write),
+        );
+        for id in 0..4 {
+            file.write(&Config { id })?;
+        }
+        let read = |name: &str| std::fs::read_to_string(dir.join(name));
+        assert_eq!(read("conrig.json")?, r#"{"id":3}"#);
+        assert_eq!(read("conrig.json.bak")?, r#"{"id":2}"#);
+        assert_eq!(read("conrig.json.bak.1")?, r#"{"id":1}"#);
+        assert_eq!(std::fs::read_dir(&dir)?.count(), 3);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_relaxed() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::provider::Embedded;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        id: u32,
+        enabled: bool,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                name: "conrig".to_owned(),
+                id: 0,
+                enabled: true,
+            }
+        }
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-relaxed");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("conrig.json");
+    let file =
+        ConfigFile::new(FileFormat::Json, path.clone()).with_config_option(ConfigOption::RELAXED);
+
+    // missing keys are repaired
+    std::fs::write(&path, r#"{"id":3}"#)?;
+    let expected = Config {
+        id: 3,
+        ..Config::default()
+    };
+    assert_eq!(file.read_or_default::<Config>()?, expected);
+    assert_eq!(file.read::<Config>()?, expected);
+
+    // invalid values are replaced
+    std::fs::write(&path, r#"{"name":5,"id":3,"enabled":false}"#)?;
+    assert_eq!(
+        file.read_or_default::<Config>()?,
+        Config {
+            id: 3,
+            enabled: false,
+            ..Config::default()
+        }
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        r#"{"name":5,"id":3,"enabled":false}"#
+    );
+
+    // malformed files are replaced
+    std::fs::write(&path, "{{")?;
+    assert_eq!(file.read_or_default::<Config>()?, Config::default());
+
+    // merged documents are repaired, but never written back
+    let contents = r#"{"id":3,"variant":{"prod":{"id":5}}}"#;
+    std::fs::write(&path, contents)?;
+    let option = ConfigOption::RELAXED
+        .with_variant_key("variant")
+        .with_variant("prod");
+    let variant = file.clone().with_config_option(option);
+    assert_eq!(variant.read_or_default::<Config>()?.id, 5);
+    assert_eq!(std::fs::read_to_string(&path)?, contents);
+    let contents = r#"{"extends":"base"}"#;
+    std::fs::write(&path, contents)?;
+    const PRESETS: &[(&str, Embedded)] =
+        &[("base", Embedded::new(FileFormat::Json, r#"{"id":7}"#))];
+    let option = ConfigOption::RELAXED
+        .with_extends_key("extends")
+        .with_presets(PRESETS);
+    let extended = file.clone().with_config_option(option);
+    assert_eq!(extended.read_or_default::<Config>()?.id, 7);
+    assert_eq!(std::fs::read_to_string(&path)?, contents);
+
+    // the default option still fails
+    let file = file.with_config_option(ConfigOption::DEFAULT_CONFIG);
+    assert!(file.read_or_default::<Config>().is_err());
+
+    // missing files and directories are created by `read`
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(default)]
+    struct Defaulted {
+        id: u32,
+    }
+
+    let path = dir.join("missing").join("conrig.toml");
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+    assert!(file.read::<Defaulted>().is_err());
+    assert!(!path.exists());
+    let file = file.with_config_option(ConfigOption::RELAXED);
+    assert_eq!(file.read::<Defaulted>()?, Defaulted::default());
+    assert!(path.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_default_provider() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::provider::Embedded;
+    use conrig::{ConfigPathMetadata, ConrigError};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        id: u32,
+    }
+
+    const EMBEDDED: Embedded = Embedded::new(FileFormat::Toml, "name = \"embedded\"\nid = 1");
+    const BASE: ConfigPathMetadata<Config> = ConfigPathMetadata::new(
+        ProjectPath::new("org", "embers-of-the-fire", "conrig-provider"),
+        &["conrig-provider"],
+        FileFormat::Json,
+        &[],
+        &[],
+        ConfigOption::DEFAULT_CONFIG.with_sys_override_local(true),
+    );
+
+    let _sandbox = Sandbox::new()?;
+    let dir = BASE.project_path.sys_config_dir().unwrap();
+    let read = |config: &ConfigPathMetadata<Config>| {
+        let _ = std::fs::remove_dir_all(&dir);
+        config.read_or_provided()
+    };
+
+    assert!(matches!(
+        read(&BASE).unwrap_err().root(),
+        ConrigError::NoDefaultProvider
+    ));
+    assert!(!dir.exists());
+
+    let embedded = BASE.with_default_provider(&EMBEDDED);
+    assert_eq!(
+        read(&embedded)?,
+        Config {
+            name: "embedded".to_owned(),
+            id: 1,
+        }
+    );
+
+    let closure = BASE.with_default_provider(&|| Config {
+        name: "closure".to_owned(),
+        id: 2,
+    });
+    assert_eq!(read(&closure)?.name, "closure");
+    assert_eq!(BASE.read()?.id, 2);
+    Ok(())
+}
+
+#[test]
+fn test_read_or_else() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        endpoint: String,
+    }
+
+    let file = ConfigFile::new(
+        FileFormat::Json,
+        PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-or-else.json"),
+    );
+    let _ = std::fs::remove_file(&file.path);
+    let build = || Config {
+        endpoint: "localhost".to_owned(),
+    };
+    assert_eq!(file.read_or_else(build)?, build());
+    assert_eq!(
+        file.read_or_else::<Config>(|| unreachable!("the file exists"))?,
+        build()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_error_context() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::error::Operation;
+    use conrig::{ConrigError, ConrigResultExt};
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-context.json");
+    std::fs::write(&path, "{ name")?;
+    let error = ConfigFile::new(FileFormat::Json, path.clone())
+        .read::<String>()
+        .unwrap_err();
+    assert_eq!(error.path(), Some(path.as_path()));
+    assert!(matches!(error.root(), ConrigError::LangError(_)));
+    assert!(error
+        .to_string()
+        .starts_with(&format!("Fail to read `{}`: ", path.display())));
+
+    // the innermost context is kept.
+    let error = Err::<(), _>(error)
+        .context_path(Operation::Import, "/foreign/file")
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ConrigError::Context {
+            operation: Operation::Read,
+            ..
+        }
+    ));
+
+    // file system errors carry the path involved.
+    std::fs::remove_file(&path)?;
+    let error = ConfigFile::new(FileFormat::Json, path.clone())
+        .read::<String>()
+        .unwrap_err();
+    assert!(error.is_missing());
+    assert_eq!(error.path(), Some(path.as_path()));
+    assert!(matches!(
+        error.root(),
+        ConrigError::FileSystemError(conrig::FileSystemError::OpenConfig { path: open, .. })
+            if *open == path
+    ));
+    assert!(error
+        .to_string()
+        .contains(&format!("Cannot open `{}`", path.display())));
+    Ok(())
+}
+
+#[test]
+fn test_validate() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::error::Violation;
+    use conrig::validate::Validate;
+    use conrig::ConrigError;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        workers: u32,
+        ratio: u32,
+    }
+
+    impl Validate for Config {
+        fn validate(&self) -> Result<(), Vec<Violation>> {
+            let mut violations = Vec::new();
+            if self.workers == 0 {
+                violations.push(Violation::new("workers", "at least one worker is required"));
+            }
+            if self.ratio > 100 {
+                violations.push(Violation::new("ratio", "must be a percentage"));
+            }
+            if violations.is_empty() {
+                Ok(())
+            } else {
+                Err(violations)
+            }
+        }
+    }
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-validate.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+
+    file.write(&Config {
+        workers: 0,
+        ratio: 120,
+    })?;
+    let error = file.read_checked::<Config>().unwrap_err();
+    assert!(matches!(
+        error.root(),
+        ConrigError::Validation(violations) if violations.len() == 2
+    ));
+    assert_eq!(
+        error.root().to_string(),
+        "Invalid configuration: `workers`: at least one worker is required; \
+         `ratio`: must be a percentage"
+    );
+
+    file.write(&Config {
+        workers: 4,
+        ratio: 50,
+    })?;
+    assert_eq!(file.read_checked::<Config>()?.workers, 4);
+    assert!(file
+        .read_validated_with(|config: &Config| match config.workers {
+            4 => Err(vec![Violation::new("workers", "unlucky")]),
+            _ => Ok(()),
+        })
+        .is_err());
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_error_report() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::report::ErrorKind;
+    use conrig::value::{to_value, Value};
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-report.json");
+    std::fs::write(&path, "{\n  \"name\": }")?;
+    let error = ConfigFile::new(FileFormat::Json, path.clone())
+        .read::<Value>()
+        .unwrap_err();
+    let report = error.report();
+    assert_eq!(report.kind, ErrorKind::Lang);
+    assert_eq!(report.path.as_deref(), Some(path.as_path()));
+    assert_eq!(report.spans.len(), 1);
+    assert_eq!(report.spans[0].line, 2);
+
+    let value = to_value(&report)?;
+    assert_eq!(
+        value.get_path("kind"),
+        Some(&Value::String("lang".to_owned()))
+    );
+    assert_eq!(
+        value.get_path("operation"),
+        Some(&Value::String("read".to_owned()))
+    );
+    assert_eq!(value.get_path("spans.0.line"), Some(&Value::Integer(2)));
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_parse_error() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::error::ParseError;
+    use conrig::value::Value;
+    use conrig::{ConrigError, LangError};
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-parse-error");
+    std::fs::create_dir_all(&dir)?;
+    let cases = [
+        (FileFormat::Toml, "name = \"conrig\"\nport = \n", (2, 8)),
+        (FileFormat::Json, "{\n  \"name\": }", (2, 11)),
+        (FileFormat::Yaml, "name: conrig\n port: 8080\n", (2, 6)),
+        (
+            FileFormat::Ron,
+            "(\n  name: \"conrig\",\n  port: ,\n)",
+            (3, 9),
+        ),
+    ];
+    for (file_format, contents, (line, column)) in cases {
+        let path = dir.join(format!("conrig.{file_format}"));
+        std::fs::write(&path, contents)?;
+        let error = ConfigFile::new(file_format, path.clone())
+            .read::<Value>()
+            .unwrap_err();
+        assert!(matches!(error.root(), ConrigError::LangError(_)));
+        let parse_error = error.parse_error().unwrap();
+        assert_eq!(parse_error.path.as_deref(), Some(path.as_path()));
+        assert_eq!(parse_error.format, file_format);
+        assert_eq!(
+            (parse_error.line, parse_error.column),
+            (Some(line), Some(column)),
+            "{file_format}: {error}"
+        );
+        assert!(!parse_error.message.contains("line"), "{parse_error}");
+        assert!(parse_error.to_string().starts_with(&format!(
+            "Fail to parse `{}` at line {line}, column {column}: ",
+            path.display()
+        )));
+        assert_eq!(error.report().spans[0].line, line);
+    }
+
+    let file = dir.join("conrig-include.json");
+    let included = dir.join("conrig-included.json");
+    std::fs::write(&file, r#"{ "include": ["conrig-included.json"] }"#)?;
+    std::fs::write(&included, "{\n  \n  \"port\" 8080 }")?;
+    let error = ConfigFile::new(FileFormat::Json, file)
+        .with_config_option(ConfigOption::DEFAULT_CONFIG.with_include_key("include"))
+        .read::<Value>()
+        .unwrap_err();
+    assert_eq!(error.path(), Some(included.as_path()));
+    let ParseError { path, line, .. } = error.parse_error().unwrap();
+    assert_eq!(
+        (path.as_deref(), *line),
+        (Some(included.as_path()), Some(3))
+    );
+
+    let error = FileFormat::Json
+        .read_str::<Value>("{ name")
+        .map_err(ConrigError::from)
+        .unwrap_err();
+    assert!(error.parse_error().is_none());
+    assert!(matches!(
+        error,
+        ConrigError::LangError(LangError::JsonError(_))
+    ));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_error_key() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::ConrigError;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        server: Server,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Server {
+        host: String,
+        listeners: Vec<Listener>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Listener {
+        port: u16,
+    }
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-error-key.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+    std::fs::write(
+        &path,
+        r#"{
+  "server": {
+    "host": "localhost",
+    "listeners": [{ "port": 80 }, { "port": 443 }, { "port": "8080" }]
+  }
+}"#,
+    )?;
+    let error = file.read::<Config>().unwrap_err();
+    let parse_error = error.parse_error().unwrap();
+    assert_eq!(parse_error.key.as_deref(), Some("server.listeners.2.port"));
+    assert_eq!(parse_error.line, Some(4));
+    assert!(
+        error
+            .to_string()
+            .contains("at line 4, column 67 in `server.listeners.2.port`: invalid type"),
+        "{error}"
+    );
+    assert_eq!(error.report().keys, ["server.listeners.2.port"]);
+
+    // documents merged before being deserialized are located too.
+    let strict = file
+        .clone()
+        .with_config_option(ConfigOption::DEFAULT_CONFIG.with_strict_keys(true));
+    let error = strict.read::<Config>().unwrap_err();
+    assert!(matches!(error.root(), ConrigError::ValueError(_)));
+    let parse_error = error.parse_error().unwrap();
+    assert_eq!(parse_error.key.as_deref(), Some("server.listeners.2.port"));
+    assert_eq!(parse_error.line, None);
+
+    // missing fields are reported on their struct.
+    std::fs::write(&path, r#"{ "server": { "listeners": [] } }"#)?;
+    for file in [&file, &strict] {
+        let error = file.read::<Config>().unwrap_err();
+        let key = error.parse_error().and_then(|error| error.key.as_deref());
+        assert_eq!(key, Some("server"), "{error}");
+    }
+    // the root value has no key.
+    std::fs::write(&path, "[]")?;
+    let error = file.read::<Config>().unwrap_err();
+    assert_eq!(error.parse_error().unwrap().key, None);
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mmap_threshold() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::mmap::MmapThreshold;
+
+    // SAFETY: the file is only written atomically, and never while it's read.
+    let option =
+        ConfigOption::DEFAULT_CONFIG.with_mmap_threshold(unsafe { MmapThreshold::new(1024) });
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-mmap.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone()).with_config_option(option);
+
+    let large: Vec<String> = (0..10_000).map(|i| format!("entry-{i}")).collect();
+    file.write(&large)?;
+    assert!(std::fs::metadata(&path)?.len() > 1024);
+    assert_eq!(file.read::<Vec<String>>()?, large);
+    file.write(&["small"])?;
+    assert_eq!(file.read::<Vec<String>>()?, ["small"]);
+
+    // errors are still located in the mapped file.
+    std::fs::write(&path, format!("[\n{}  oops]", "  \"entry\",\n".repeat(100)))?;
+    let error = file.read::<Vec<String>>().unwrap_err();
+    assert_eq!(error.parse_error().unwrap().line, Some(102));
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_try_new() {
+    use conrig::{ConfigPathMetadata, ConrigError};
+
+    const PROJECT: ProjectPath = ProjectPath::new("org", "embers-of-the-fire", "conrig-try-new");
+    const INVALID: Result<ConfigPathMetadata<()>, ConrigError> = ConfigPathMetadata::try_new(
+        PROJECT,
+        &[],
+        FileFormat::Json,
+        &[],
+        &[],
+        ConfigOption::DEFAULT_CONFIG,
+    );
+    assert!(matches!(INVALID, Err(ConrigError::EmptyConfigName)));
+    assert!(ConfigPathMetadata::<()>::try_new(
+        PROJECT,
+        &["conrig"],
+        FileFormat::Json,
+        &[],
+        &[],
+        ConfigOption::DEFAULT_CONFIG,
+    )
+    .is_ok());
+
+    // the macro can't check the names, but the errors are still typed.
+    conrig!(const UNNAMED<()> = {
+        project_path: PROJECT,
+    });
+    assert!(matches!(
+        UNNAMED.default_config_file(),
+        Err(ConrigError::EmptyConfigName)
+    ));
+}
+
+#[test]
+fn test_key_path_access() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::value::Value;
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-key-path.toml");
+    let _ = std::fs::remove_file(&path);
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+
+    file.set_value("server.port", 8080)?;
+    file.set_value("name", "conrig")?;
+    assert_eq!(file.get_value("server.port")?, Some(Value::Integer(8080)));
+    assert_eq!(
+        file.get_value("name")?,
+        Some(Value::String("conrig".to_owned()))
+    );
+    assert_eq!(file.get_value("server.host")?, None);
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        "name = \"conrig\"\n\n[server]\nport = 8080\n"
+    );
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_integer_booleans() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::value::{from_value, Value};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+    struct Config {
+        debug: bool,
+        jobs: u32,
+    }
+
+    let value: Value = FileFormat::Json.read_str(r#"{ "debug": 1, "jobs": 1 }"#)?;
+    assert_eq!(
+        from_value::<Config>(value)?,
+        Config {
+            debug: true,
+            jobs: 1
+        }
+    );
+    let value: Value = FileFormat::Json.read_str(r#"{ "debug": 0, "jobs": 0 }"#)?;
+    assert!(!from_value::<Config>(value)?.debug);
+    // only `0` and `1` are booleans.
+    let value: Value = FileFormat::Json.read_str(r#"{ "debug": 2, "jobs": 0 }"#)?;
+    assert!(from_value::<Config>(value).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_or_default_on_missing() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::ConrigResultExt;
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let missing = ConfigFile::new(FileFormat::Json, dir.join("conrig-missing.json"));
+    let _ = std::fs::remove_file(&missing.path);
+    assert_eq!(
+        missing.read::<Vec<u32>>().or_default_on_missing()?,
+        Vec::<u32>::new()
+    );
+    assert_eq!(missing.read::<u32>().or_else_on_missing(|| 42)?, 42);
+    assert_eq!(missing.read::<u32>().ok_on_missing()?, None);
+
+    // real errors are still propagated.
+    let malformed = ConfigFile::new(FileFormat::Json, dir.join("conrig-malformed.json"));
+    std::fs::write(&malformed.path, "{")?;
+    assert!(malformed
+        .read::<Vec<u32>>()
+        .or_default_on_missing()
+        .is_err());
+
+    std::fs::remove_file(&malformed.path)?;
+    Ok(())
+}
+
+#[test]
+fn test_slow_io() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::error::Operation;
+    use conrig::ConrigWarning;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    static READS: AtomicUsize = AtomicUsize::new(0);
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-slow-io.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+    // no threshold, no warning.
+    file.write(&42)?;
+
+    // every operation is slower than zero.
+    let file = file.with_config_option(
+        ConfigOption::DEFAULT_CONFIG
+            .with_slow_io_threshold(Duration::ZERO)
+            .with_warning_handler(|warning| match warning {
+                ConrigWarning::SlowIo {
+                    operation: Operation::Read,
+                    ..
+                } => {
+                    READS.fetch_add(1, Ordering::SeqCst);
+                }
+                ConrigWarning::SlowIo {
+                    operation: Operation::Write,
+                    ..
+                } => {
+                    WRITES.fetch_add(1, Ordering::SeqCst);
+                }
+                _ => {}
+            }),
+    );
+    assert_eq!(file.read::<u32>()?, 42);
+    file.write(&42)?;
+    assert_eq!(READS.load(Ordering::SeqCst), 1);
+    assert_eq!(WRITES.load(Ordering::SeqCst), 1);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_patch() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::patch::{apply, Partial};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        server: Server,
+    }
+
+    let base = Config {
+        name: "conrig".to_owned(),
+        server: Server {
+            host: "localhost".to_owned(),
+            port: 80,
+        },
+    };
+
+    // an overlay file only sets some of the keys.
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-overlay.toml");
+    std::fs::write(&path, "[server]\nport = 8080\n")?;
+    let mut patch: Partial<Config> = ConfigFile::new(FileFormat::Toml, path.clone()).read()?;
+    assert_eq!(
+        patch.get("server.port"),
+        Some(&conrig::value::Value::Integer(8080))
+    );
+    assert_eq!(patch.get("name"), None);
+
+    // later patches take precedence.
+    let mut flags = Partial::new();
+    flags.set("server.host", "example.com")?;
+    patch.merge(flags);
+
+    let config = apply(base.clone(), patch)?;
+    assert_eq!(config.name, "conrig");
+    assert_eq!(config.server.host, "example.com");
+    assert_eq!(config.server.port, 8080);
+    assert_eq!(Partial::new().apply_to(base.clone())?, base);
+
+    // the result is checked against the configuration type.
+    let mut invalid = Partial::<Config>::new();
+    invalid.set("server.port", "http")?;
+    assert!(apply(base, invalid).is_err());
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_watch() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::watch::{CancellationToken, WatchOption, Watcher};
+    use std::time::Duration;
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-watch.json");
+    let _ = std::fs::remove_file(&path);
+    let option = WatchOption::DEFAULT
+        .with_interval(Duration::from_millis(10))
+        .with_capacity(1)
+        .with_debounce(Duration::ZERO)
+        .with_ignore_own_writes(false);
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+    let watcher = file.watch(option);
+
+    // creating the file is a change.
+    file.write(&1)?;
+    assert_eq!(
+        watcher.recv_timeout(Duration::from_secs(5)),
+        Some(path.clone())
+    );
+
+    // a burst of saves is coalesced while the receiver is busy.
+    for i in 10..100 {
+        file.write(&i)?;
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    let mut changes = 0;
+    while watcher.recv_timeout(Duration::from_millis(100)).is_some() {
+        changes += 1;
+    }
+    assert!((1..=2).contains(&changes));
+    drop(watcher);
+
+    // a burst of saves is reported once the file settles down.
+    let watcher = file.watch(
+        option
+            .with_debounce(Duration::from_millis(50))
+            .with_ignore_own_writes(true),
+    );
+    for i in 100..200 {
+        std::fs::write(&path, i.to_string())?;
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(
+        watcher.recv_timeout(Duration::from_secs(5)),
+        Some(path.clone())
+    );
+    assert_eq!(watcher.recv_timeout(Duration::from_millis(200)), None);
+
+    // our own writes are ignored.
+    file.write(&1)?;
+    assert_eq!(watcher.recv_timeout(Duration::from_millis(200)), None);
+    drop(watcher);
+
+    // a cancelled watcher stops delivering changes.
+    let token = CancellationToken::new();
+    let watcher = Watcher::with_token(vec![path.clone()], option, token.clone());
+    token.cancel();
+    assert!(watcher.token().is_cancelled());
+    assert_eq!(watcher.recv(), None);
+    drop(watcher);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_and_upgrade() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        #[serde(default)]
+        verbose: bool,
+    }
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-upgrade.json");
+    std::fs::write(&path, r#"{ "name": "conrig" }"#)?;
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+
+    // read-only files are never upgraded.
+    let config: Config = file.clone().with_read_only(true).read_and_upgrade()?;
+    assert!(!config.verbose);
+    assert_eq!(file.get_value("verbose")?, None);
+
+    let config: Config = file.read_and_upgrade()?;
+    assert_eq!(config.name, "conrig");
+    assert_eq!(
+        file.get_value("verbose")?,
+        Some(conrig::value::Value::Bool(false))
+    );
+
+    // up-to-date files are left untouched.
+    let modified = std::fs::metadata(&path)?.modified()?;
+    assert_eq!(file.read_and_upgrade::<Config>()?, config);
+    assert_eq!(std::fs::metadata(&path)?.modified()?, modified);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_read_and_upgrade_untouched() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        a: u32,
+        #[serde(default)]
+        b: u32,
+        #[serde(default)]
+        name: Option<String>,
+    }
+
+    // `None` fields are never written back.
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-upgrade-untouched.toml");
+    std::fs::write(&path, "a = 1\nb = 0\n")?;
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+    let modified = std::fs::metadata(&path)?.modified()?;
+    for _ in 0..3 {
+        assert_eq!(file.read_and_upgrade::<Config>()?.name, None);
+    }
+    assert_eq!(std::fs::metadata(&path)?.modified()?, modified);
+
+    // merged documents are never written back.
+    let contents = "a = 1\n\n[variant.prod]\na = 5\n";
+    std::fs::write(&path, contents)?;
+    let option = ConfigOption::DEFAULT_CONFIG
+        .with_variant_key("variant")
+        .with_variant("prod");
+    let config: Config = file.clone().with_config_option(option).read_and_upgrade()?;
+    assert_eq!(config.a, 5);
+    assert_eq!(std::fs::read_to_string(&path)?, contents);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_reload_if_changed() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::watch::Fingerprint;
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-reload.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+
+    // our own saves don't trigger a reload.
+    let mut value = 1;
+    file.write(&value)?;
+    assert!(!file.reload_if_changed(&mut value)?);
+
+    // external edits do, once.
+    std::fs::write(&path, "2")?;
+    assert!(file.reload_if_changed(&mut value)?);
+    assert_eq!(value, 2);
+    assert!(!file.reload_if_changed(&mut value)?);
+
+    assert_eq!(Fingerprint::of_file(&path), Some(Fingerprint::of(b"2")));
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_reload_diff() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::restart::RestartRequired;
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        port: u16,
+        name: String,
+    }
+
+    impl RestartRequired for Config {
+        const RESTART_REQUIRED: &'static [&'static str] = &["port"];
+    }
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-reload-diff.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+    let mut value = Config::default();
+    file.write(&value)?;
+    assert_eq!(file.reload_diff(&mut value)?, None);
+
+    std::fs::write(&path, r#"{ "port": 0, "name": "new" }"#)?;
+    let changes = file.reload_diff(&mut value)?.unwrap();
+    assert_eq!(changes.changed, ["name"]);
+    assert!(changes.can_hot_apply());
+
+    std::fs::write(&path, r#"{ "port": 80, "name": "new" }"#)?;
+    let changes = file.reload_diff(&mut value)?.unwrap();
+    assert_eq!(changes.restart_required, ["port"]);
+    assert_eq!(value.port, 80);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_appliers() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::apply::Appliers;
+    use conrig::restart::RestartRequired;
+    use conrig::ConrigError;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        port: u16,
+        timeout: u32,
+    }
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        log: String,
+        server: Server,
+        cache: u32,
+    }
+
+    impl RestartRequired for Config {
+        const RESTART_REQUIRED: &'static [&'static str] = &["server.port"];
+    }
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let record = |name: &'static str| {
+        let calls = calls.clone();
+        move |_: &Config| {
+            calls.lock().unwrap().push(name);
+            Ok(())
+        }
+    };
+    let appliers = Appliers::new()
+        .register("server", record("server"))
+        .register("log", record("log"))
+        .register("cache", |config: &Config| match config.cache {
+            0 => Ok(()),
+            _ => Err("the cache is disabled".into()),
+        });
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-appliers.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+    let mut value = Config::default();
+    file.write(&value)?;
+    assert_eq!(appliers.reload(&file, &mut value)?, None);
+
+    // the appliers of the changed sections are invoked in order.
+    std::fs::write(
+        &path,
+        r#"{ "log": "debug", "server": { "port": 0, "timeout": 5 }, "cache": 0 }"#,
+    )?;
+    assert!(appliers.reload(&file, &mut value)?.is_some());
+    assert_eq!(*calls.lock().unwrap(), ["server", "log"]);
+
+    // the changes requiring a restart aren't hot-applied.
+    calls.lock().unwrap().clear();
+    std::fs::write(
+        &path,
+        r#"{ "log": "debug", "server": { "port": 80, "timeout": 5 }, "cache": 0 }"#,
+    )?;
+    let changes = appliers.reload(&file, &mut value)?.unwrap();
+    assert!(changes.needs_restart());
+    assert!(calls.lock().unwrap().is_empty());
+
+    std::fs::write(
+        &path,
+        r#"{ "log": "debug", "server": { "port": 80, "timeout": 5 }, "cache": 1 }"#,
+    )?;
+    let err = appliers.reload(&file, &mut value).unwrap_err();
+    assert!(matches!(err, ConrigError::Apply { section, .. } if section == "cache"));
+
+    assert_eq!(
+        appliers.apply_all(&Config::default())?,
+        ["server", "log", "cache"]
+    );
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_appliers_rollback() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::apply::Appliers;
+    use conrig::restart::RestartRequired;
+    use conrig::ConrigError;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        log: String,
+        workers: u32,
+    }
+
+    impl RestartRequired for Config {}
+
+    let log = Arc::new(Mutex::new(String::new()));
+    let appliers = Appliers::new()
+        .register("log", {
+            let log = log.clone();
+            move |config: &Config| {
+                *log.lock().unwrap() = config.log.clone();
+                Ok(())
+            }
+        })
+        .register("workers", |config: &Config| match config.workers {
+            0..=16 => Ok(()),
+            _ => Err("too many workers".into()),
+        })
+        .with_rollback_file(true);
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-rollback.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+    let mut value = Config {
+        log: "info".to_owned(),
+        workers: 4,
+    };
+    file.write(&value)?;
+    let snapshot = value.clone();
+
+    std::fs::write(&path, r#"{ "log": "debug", "workers": 64 }"#)?;
+    let err = appliers.reload(&file, &mut value).unwrap_err();
+    assert!(matches!(err, ConrigError::Apply { section, .. } if section == "workers"));
+    // the configuration, the subsystems and the file are rolled back.
+    assert_eq!(value, snapshot);
+    assert_eq!(*log.lock().unwrap(), "info");
+    assert_eq!(file.read::<Config>()?, snapshot);
+    assert_eq!(appliers.reload(&file, &mut value)?, None);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_config_source() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::source::{ConfigSource, ConfigSourceExt, MemorySource};
+    use conrig::value::Value;
+    use conrig::ConrigError;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        port: u16,
+    }
+
+    /// A key-value store keeping the documents as JSON, like a remote one would.
+    struct Store(Mutex<Option<String>>);
+
+    impl ConfigSource for Store {
+        fn describe(&self) -> String {
+            "store://config".to_owned()
+        }
+
+        fn load_value(&self) -> Result<Option<Value>, ConrigError> {
+            let raw = self.0.lock().unwrap();
+            Ok(raw
+                .as_deref()
+                .map(|raw| FileFormat::Json.read_str(raw))
+                .transpose()?)
+        }
+
+        fn store_value(&self, value: &Value) -> Result<(), ConrigError> {
+            let mut raw = Vec::new();
+            FileFormat::Json.write(value, &mut raw)?;
+            *self.0.lock().unwrap() = Some(String::from_utf8(raw).unwrap());
+            Ok(())
+        }
+    }
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-source.toml");
+    let _ = std::fs::remove_file(&path);
+    let sources: Vec<Box<dyn ConfigSource>> = vec![
+        Box::new(MemorySource::new()),
+        Box::new(Store(Mutex::new(None))),
+        Box::new(ConfigFile::new(FileFormat::Toml, path.clone())),
+    ];
+    for source in &sources {
+        assert!(source.read::<Config>().unwrap_err().is_missing());
+        let config = Config {
+            name: source.describe(),
+            port: 8080,
+        };
+        assert_eq!(source.read_or_new(Config::default())?, Config::default());
+        source.write(&config)?;
+        assert_eq!(source.read::<Config>()?, config);
+    }
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_include() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::ConrigError;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        server: Server,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-include");
+    std::fs::create_dir_all(dir.join("conf"))?;
+    std::fs::write(
+        dir.join("conf/server.json"),
+        r#"{ "server": { "host": "localhost", "port": 80 } }"#,
+    )?;
+    std::fs::write(
+        dir.join("main.toml"),
+        "include = [\"conf/server.json\"]\nname = \"conrig\"\n\n[server]\nport = 8080\n",
+    )?;
+
+    let file = ConfigFile::new(FileFormat::Toml, dir.join("main.toml"))
+        .with_config_option(ConfigOption::DEFAULT_CONFIG.with_include_key("include"));
+    let config: Config = file.read()?;
+    assert_eq!(config.name, "conrig");
+    assert_eq!(config.server.host, "localhost");
+    // the including file takes precedence.
+    assert_eq!(config.server.port, 8080);
+
+    // includes are opt-in.
+    let plain = file
+        .clone()
+        .with_config_option(ConfigOption::DEFAULT_CONFIG);
+    assert!(plain.read::<Config>().is_err());
+
+    // a file can't include itself.
+    std::fs::write(
+        dir.join("conf/server.json"),
+        r#"{ "include": "../main.toml" }"#,
+    )?;
+    let error = file.read::<Config>().unwrap_err();
+    assert!(matches!(error.root(), ConrigError::IncludeCycle(_)));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_fallback_identities() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        theme: String,
+        name: String,
+    }
+
+    const SUITE: ProjectPath = ProjectPath::new("org", "embers-of-the-fire", "conrig-suite");
+    const TOOL: ProjectPath = ProjectPath::new("org", "embers-of-the-fire", "conrig-suite-tool");
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: TOOL,
+        config_name: &["conrig-suite"],
+        config_option: ConfigOption {
+            sys_override_local: true,
+            ..ConfigOption::DEFAULT_CONFIG
+        },
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+        fallback_identities: &[SUITE],
+    });
+
+    let _sandbox = Sandbox::new()?;
+    let suite_dir = SUITE.sys_config_dir().unwrap();
+    let tool_dir = TOOL.sys_config_dir().unwrap();
+    std::fs::create_dir_all(&suite_dir)?;
+    std::fs::write(
+        suite_dir.join("conrig-suite.json"),
+        r#"{ "theme": "dark", "name": "suite" }"#,
+    )?;
+
+    // the shared settings are enough on their own.
+    let config = TEST_APP_CONFIG.read_merged()?;
+    assert_eq!(config.name, "suite");
+
+    // the tool's own settings take precedence.
+    std::fs::create_dir_all(&tool_dir)?;
+    std::fs::write(tool_dir.join("conrig-suite.toml"), "name = \"tool\"\n")?;
+    let config = TEST_APP_CONFIG.read_merged()?;
+    assert_eq!(config.theme, "dark");
+    assert_eq!(config.name, "tool");
+
+    std::fs::remove_dir_all(&suite_dir)?;
+    assert!(TEST_APP_CONFIG.read_merged().is_err());
+    std::fs::remove_dir_all(&tool_dir)?;
+    assert!(matches!(
+        TEST_APP_CONFIG.read_merged().unwrap_err().root(),
+        conrig::ConrigError::NoConfigurationFile(_)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_profile() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        debug: bool,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-profile");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("conrig.toml"), "name = \"conrig\"\ndebug = true\n")?;
+    std::fs::write(dir.join("conrig.production.json"), r#"{ "debug": false }"#)?;
+
+    let option = ConfigOption::DEFAULT_CONFIG.with_profile_env("CONRIG_TEST_PROFILE");
+    let file =
+        ConfigFile::new(FileFormat::Toml, dir.join("conrig.toml")).with_config_option(option);
+
+    // no profile is selected.
+    std::env::remove_var("CONRIG_TEST_PROFILE");
+    assert!(file.read::<Config>()?.debug);
+
+    std::env::set_var("CONRIG_TEST_PROFILE", "production");
+    assert_eq!(option.profile().as_deref(), Some("production"));
+    assert_eq!(
+        file.profile_file().map(|t| t.path),
+        Some(dir.join("conrig.production.json"))
+    );
+    let config: Config = file.read()?;
+    assert_eq!(config.name, "conrig");
+    assert!(!config.debug);
+
+    // a profile without a file changes nothing.
+    std::env::set_var("CONRIG_TEST_PROFILE", "test");
+    assert!(file.read::<Config>()?.debug);
+
+    std::env::remove_var("CONRIG_TEST_PROFILE");
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_parent() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::ConfigPathMetadata;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct SuiteConfig {
+        theme: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct ToolConfig {
+        theme: String,
+        jobs: u32,
+    }
+
+    const OPTION: ConfigOption = ConfigOption::DEFAULT_CONFIG.with_sys_override_local(true);
+    const SUITE: ConfigPathMetadata<SuiteConfig> = ConfigPathMetadata::new(
+        ProjectPath::new("org", "embers-of-the-fire", "conrig-parent-suite"),
+        &["conrig-parent"],
+        FileFormat::Toml,
+        &[],
+        &[],
+        OPTION,
+    );
+    const TOOL: ConfigPathMetadata<ToolConfig> = ConfigPathMetadata::new(
+        ProjectPath::new("org", "embers-of-the-fire", "conrig-parent-tool"),
+        &["conrig-parent"],
+        FileFormat::Toml,
+        &[],
+        &[],
+        OPTION,
+    )
+    .with_parent(&SUITE);
+
+    let _sandbox = Sandbox::new()?;
+    let suite_dir = SUITE.project_path.sys_config_dir().unwrap();
+    let tool_dir = TOOL.project_path.sys_config_dir().unwrap();
+    std::fs::create_dir_all(&suite_dir)?;
+    std::fs::create_dir_all(&tool_dir)?;
+    std::fs::write(
+        suite_dir.join("conrig-parent.toml"),
+        "theme = \"dark\"\njobs = 1\n",
+    )?;
+    std::fs::write(tool_dir.join("conrig-parent.toml"), "jobs = 8\n")?;
+
+    let config = TOOL.read_merged()?;
+    assert_eq!(config.theme, "dark");
+    assert_eq!(config.jobs, 8);
+    assert_eq!(SUITE.read_merged()?.theme, "dark");
+    Ok(())
+}
+
+#[test]
+fn test_redaction() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::redact::{matches, MASK};
+    use conrig::value::{to_value, Value};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Config {
+        name: String,
+        auth: BTreeMap<String, String>,
+        servers: Vec<BTreeMap<String, String>>,
+    }
+
+    assert!(matches("*.password", "database.password"));
+    assert!(!matches("*.password", "password"));
+    assert!(!matches("auth.token", "auth.token.value"));
+
+    const OPTION: ConfigOption =
+        ConfigOption::DEFAULT_CONFIG.with_redactions(&["auth.*", "servers.*.password"]);
+    let mut value = to_value(&Config {
+        name: "conrig".to_owned(),
+        auth: BTreeMap::from([
+            ("github".to_owned(), "ghp_secret".to_owned()),
+            ("gitlab".to_owned(), "glpat_secret".to_owned()),
+        ]),
+        servers: vec![BTreeMap::from([
+            ("host".to_owned(), "localhost".to_owned()),
+            ("password".to_owned(), "hunter2".to_owned()),
+        ])],
+    })?;
+
+    assert_eq!(OPTION.redact(&mut value), 3);
+    let mask = Value::String(MASK.to_owned());
+    assert_eq!(value.get_path("auth.github"), Some(&mask));
+    assert_eq!(value.get_path("auth.gitlab"), Some(&mask));
+    assert_eq!(value.get_path("servers.0.password"), Some(&mask));
+    assert_eq!(
+        value.get_path("servers.0.host"),
+        Some(&Value::String("localhost".to_owned()))
+    );
+    assert_eq!(
+        value.get_path("name"),
+        Some(&Value::String("conrig".to_owned()))
+    );
+    assert_eq!(ConfigOption::DEFAULT_CONFIG.redact(&mut value), 0);
+    Ok(())
+}
+
+#[test]
+fn test_dump_redacted() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::redact::MASK;
+    use conrig::secret::Secrets;
+
+    #[derive(Serialize)]
+    struct Config {
+        user: String,
+        token: String,
+        database: Database,
+    }
+
+    #[derive(Serialize)]
+    struct Database {
+        host: String,
+        password: String,
+    }
+
+    impl Secrets for Config {
+        const SECRETS: &'static [&'static str] = &["token"];
+    }
+
+    let config = Config {
+        user: "alice".to_owned(),
+        token: "ghp_secret".to_owned(),
+        database: Database {
+            host: "localhost".to_owned(),
+            password: "hunter2".to_owned(),
+        },
+    };
+    let file = ConfigFile::new(FileFormat::Json, PathBuf::from("dump-redacted.json"))
+        .with_config_option(ConfigOption::DEFAULT_CONFIG.with_redactions(&["*.password"]));
+    let dump = file.dump_redacted(&config)?;
+    assert!(!dump.contains("ghp_secret"));
+    assert!(!dump.contains("hunter2"));
+    assert!(dump.contains("alice"));
+    assert!(dump.contains("localhost"));
+    assert_eq!(dump.matches(MASK).count(), 2);
+    assert!(!file.path.exists());
+    Ok(())
+}
+
+#[test]
+fn test_watchdog() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::error::Violation;
+    use conrig::validate::Validate;
+    use conrig::watch::WatchOption;
+    use std::time::Duration;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Config {
+        workers: u32,
+    }
+
+    impl Validate for Config {
+        fn validate(&self) -> Result<(), Vec<Violation>> {
+            if self.workers == 0 {
+                return Err(vec![Violation::new(
+                    "workers",
+                    "at least one worker is required",
+                )]);
+            }
+            Ok(())
+        }
+    }
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-watchdog.json");
+    std::fs::write(&path, r#"{ "workers": 4 }"#)?;
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let watchdog = file.watchdog::<Config>(
+        WatchOption::DEFAULT
+            .with_interval(Duration::from_millis(10))
+            .with_debounce(Duration::from_millis(20)),
+        move |error| sender.send(error.root().to_string()).unwrap(),
+    );
+    assert!(watchdog.is_healthy());
+
+    // a syntax error is reported.
+    std::fs::write(&path, r#"{ "workers": "#)?;
+    assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+    assert!(!watchdog.is_healthy());
+
+    // so is a validation error.
+    std::fs::write(&path, r#"{ "workers": 0 }"#)?;
+    let error = receiver.recv_timeout(Duration::from_secs(5))?;
+    assert!(error.contains("workers"));
+
+    // the watchdog recovers once the file is fixed.
+    std::fs::write(&path, r#"{ "workers": 16 }"#)?;
+    let start = std::time::Instant::now();
+    while !watchdog.is_healthy() && start.elapsed() < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(watchdog.is_healthy());
+    drop(watchdog);
+    assert!(receiver.try_recv().is_err());
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_portable() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-portable"),
+        config_name: &["conrig-portable"],
+        config_option: ConfigOption::DEFAULT_CONFIG
+            .with_config_sys_type(ConfigType::Portable)
+            .with_sys_override_local(true),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let exe_dir = std::env::current_exe()?.parent().unwrap().to_path_buf();
+    let path = TEST_APP_CONFIG.default_config_file()?;
+    assert_eq!(path.parent(), Some(exe_dir.as_path()));
+
+    let config = Config {
+        name: "conrig".to_owned(),
+    };
+    TEST_APP_CONFIG.write(&config)?;
+    assert!(path.is_file());
+    assert_eq!(TEST_APP_CONFIG.read()?, config);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_encrypted() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::encrypt::{set_cipher, Cipher, CipherError, Encrypted};
+
+    struct Shifted;
+
+    // the ciphertext holds a `:`, like the common `nonce:ciphertext` layout.
+    impl Cipher for Shifted {
+        fn encrypt(&self, plaintext: &str) -> Result<String, CipherError> {
+            let hex: String = plaintext
+                .bytes()
+                .map(|b| format!("{:02x}", b ^ 0x5a))
+                .collect();
+            Ok(format!("5a:{hex}"))
+        }
+
+        fn decrypt(&self, ciphertext: &str) -> Result<String, CipherError> {
+            let ciphertext = ciphertext.strip_prefix("5a:").ok_or("missing nonce")?;
+            let bytes = (0..ciphertext.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&ciphertext[i..i + 2], 16).map(|b| b ^ 0x5a))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(String::from_utf8(bytes)?)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        user: String,
+        password: Encrypted<String>,
+        pin: Encrypted<u32>,
+    }
+
+    set_cipher(Shifted);
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-encrypted.toml");
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+    let config = Config {
+        user: "conrig".to_owned(),
+        password: Encrypted::new("hunter2".to_owned()),
+        pin: Encrypted::new(1234),
+    };
+    file.write(&config)?;
+    let contents = std::fs::read_to_string(&path)?;
+    assert!(contents.contains("user = \"conrig\""));
+    assert!(contents.contains("password = \"ENC[5a:"));
+    assert!(!contents.contains("hunter2"));
+    assert!(!format!("{config:?}").contains("hunter2"));
+    assert_eq!(file.read::<Config>()?, config);
+
+    // plaintext values typed by hand are accepted.
+    std::fs::write(
+        &path,
+        "user = \"conrig\"\npassword = \"hunter3\"\npin = \"42\"\n",
+    )?;
+    let config = file.read::<Config>()?;
+    assert_eq!(*config.password, "hunter3");
+    assert_eq!(*config.pin, 42);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_rotate_key() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::encrypt::{Cipher, CipherError};
+    use conrig::ConrigError;
+
+    struct Xor(u8, &'static str);
+
+    impl Cipher for Xor {
+        fn encrypt(&self, plaintext: &str) -> Result<String, CipherError> {
+            Ok(plaintext
+                .bytes()
+                .map(|b| format!("{:02x}", b ^ self.0))
+                .collect())
+        }
+
+        fn decrypt(&self, ciphertext: &str) -> Result<String, CipherError> {
+            let bytes = (0..ciphertext.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&ciphertext[i..i + 2], 16).map(|b| b ^ self.0))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(String::from_utf8(bytes)?)
+        }
+
+        fn key_id(&self) -> Option<&str> {
+            Some(self.1)
+        }
+    }
+
+    let (v1, v2, v3) = (Xor(0x11, "v1"), Xor(0x22, "v2"), Xor(0x33, "v3"));
+    let sealed =
+        |cipher: &Xor, text: &str| format!("ENC:{}[{}]", cipher.1, cipher.encrypt(text).unwrap());
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-rotate.toml");
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+    std::fs::write(
+        &path,
+        format!(
+            "user = \"conrig\"\npassword = \"{}\"\n[tokens]\ngithub = \"{}\"\n",
+            sealed(&v1, "hunter2"),
+            sealed(&v1, "ghp_secret"),
+        ),
+    )?;
+    assert_eq!(file.key_ids()?, vec![Some("v1".to_owned())]);
+
+    assert_eq!(file.rotate_key(&v1, &v2)?, 2);
+    assert_eq!(file.key_ids()?, vec![Some("v2".to_owned())]);
+    let password = file.get_value("password")?.unwrap();
+    assert_eq!(
+        password,
+        conrig::value::Value::String(sealed(&v2, "hunter2"))
+    );
+    assert_eq!(
+        file.get_value("user")?,
+        Some(conrig::value::Value::String("conrig".to_owned()))
+    );
+    // the rotation is resumable.
+    assert_eq!(file.rotate_key(&v1, &v2)?, 0);
+
+    // keys holding dots are rotated too.
+    std::fs::write(
+        &path,
+        format!(
+            "{}[hosts.\"example.com\"]\npassword = \"{}\"\n",
+            std::fs::read_to_string(&path)?,
+            sealed(&v1, "hunter3"),
+        ),
+    )?;
+    assert_eq!(file.rotate_key(&v1, &v2)?, 1);
+    assert_eq!(file.key_ids()?, vec![Some("v2".to_owned())]);
+
+    // a mixed-key state is detected, and the file is left untouched.
+    file.set_value("tokens.gitlab", sealed(&v3, "glpat_secret"))?;
+    assert_eq!(file.key_ids()?.len(), 2);
+    let before = std::fs::read_to_string(&path)?;
+    let err = file.rotate_key(&v2, &v1).unwrap_err();
+    assert!(matches!(err.root(), ConrigError::MixedKeys(keys) if keys == &["tokens.gitlab"]));
+    assert_eq!(std::fs::read_to_string(&path)?, before);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_system_wide() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        theme: String,
+        jobs: u32,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-system-wide"),
+        config_name: &["conrig-system-wide"],
+        config_option: ConfigOption::DEFAULT_CONFIG
+            .with_system_wide(true)
+            .with_sys_override_local(true),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    if cfg!(unix) {
+        assert_eq!(
+            TEST_APP_CONFIG.project_path.machine_dir(),
+            Some(PathBuf::from("/etc/conrig-system-wide"))
+        );
+    }
+    let sandbox = Sandbox::new()?;
+    let machine_dir = TEST_APP_CONFIG.project_path.machine_dir().unwrap();
+    assert!(machine_dir.starts_with(sandbox.root()));
+    std::fs::create_dir_all(&machine_dir)?;
+    let machine_file = machine_dir.join("conrig-system-wide.toml");
+    std::fs::write(&machine_file, "theme = \"dark\"\njobs = 1\n")?;
+
+    let file = TEST_APP_CONFIG.search_config_file()?;
+    assert_eq!(file.path.as_deref(), Some(machine_file.as_path()));
+    assert_eq!(TEST_APP_CONFIG.read_merged()?.jobs, 1);
+
+    let user_dir = TEST_APP_CONFIG.project_path.sys_config_dir().unwrap();
+    std::fs::create_dir_all(&user_dir)?;
+    std::fs::write(user_dir.join("conrig-system-wide.toml"), "jobs = 8\n")?;
+    let config = TEST_APP_CONFIG.read_merged()?;
+    assert_eq!(config.theme, "dark");
+    assert_eq!(config.jobs, 8);
+    Ok(())
+}
+
+#[test]
+fn test_state_types() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::ConfigPathMetadata;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct State {
+        last_opened: String,
+    }
+
+    let _sandbox = Sandbox::new()?;
+    let project_path = ProjectPath::new("org", "embers-of-the-fire", "conrig-state");
+    let state = State {
+        last_opened: "conrig.toml".to_owned(),
+    };
+    for (config_type, dir) in [
+        (ConfigType::Data, project_path.sys_data_dir()),
+        (ConfigType::Cache, project_path.sys_cache_dir()),
+        (ConfigType::State, project_path.sys_state_dir()),
+    ] {
+        let Some(dir) = dir else {
+            continue;
+        };
+        let metadata = ConfigPathMetadata::<State>::new(
+            project_path.clone(),
+            &["conrig-state"],
+            FileFormat::Toml,
+            &[],
+            &[],
+            ConfigOption::DEFAULT_CONFIG
+                .with_config_sys_type(config_type)
+                .with_sys_override_local(true),
+        );
+        let path = metadata.default_config_file()?;
+        assert_eq!(path.parent(), Some(dir.as_path()));
+        metadata.write(&state)?;
+        assert_eq!(metadata.read()?, state);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_lazy() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::lazy::Lazy;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Theme {
+        background: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        dataset: Lazy<Vec<u32>>,
+        theme: Lazy<Theme>,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-lazy");
+    std::fs::create_dir_all(dir.join("themes"))?;
+    let path = dir.join("conrig.toml");
+    std::fs::write(
+        &path,
+        "name = \"conrig\"\ndataset = [1, 2, 3]\ntheme = { \"$file\" = \"themes/dark.json\" }\n",
+    )?;
+    std::fs::write(dir.join("themes/dark.json"), r#"{ "background": 0 }"#)?;
+
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+    let mut config: Config = file.read()?;
+    assert!(!config.dataset.is_loaded());
+    assert_eq!(config.dataset.get()?, &[1, 2, 3]);
+    assert!(config.dataset.is_loaded());
+
+    // the external file is resolved from the configuration file, and only read on access.
+    assert_eq!(
+        config.theme.file().map(|t| t.path.clone()),
+        Some(dir.join("themes/dark.json"))
+    );
+    assert!(config.theme.get().is_err());
+    std::fs::write(
+        dir.join("themes/dark.json"),
+        r##"{ "background": "#000000" }"##,
+    )?;
+    assert_eq!(config.theme.get()?.background, "#000000");
+
+    // references are written back as is.
+    config.dataset.get_mut()?.push(4);
+    file.write(&config)?;
+    let contents = std::fs::read_to_string(&path)?;
+    assert!(contents.contains("themes/dark.json"));
+    assert!(!contents.contains("#000000"));
+    assert_eq!(
+        file.read::<Config>()?.dataset.into_inner()?,
+        vec![1, 2, 3, 4]
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_dotfile() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-dotfile"),
+        config_name: &["conrig-dotfile"],
+        config_option: ConfigOption::DEFAULT_CONFIG
+            .with_config_sys_type(ConfigType::Home)
+            .with_sys_override_local(true),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let _sandbox = Sandbox::new()?;
+    let home = TEST_APP_CONFIG.project_path.home_dir().unwrap();
+    std::fs::create_dir_all(&home)?;
+    let path = home.join(".conrig-dotfile.toml");
+    assert_eq!(TEST_APP_CONFIG.default_config_file()?, path);
+
+    let config = Config {
+        name: "conrig".to_owned(),
+    };
+    TEST_APP_CONFIG.write(&config)?;
+    assert!(path.is_file());
+    assert_eq!(TEST_APP_CONFIG.read()?, config);
+    std::fs::remove_file(&path)?;
+
+    // rc files are read in the default format.
+    let rc = home.join(".conrig-dotfilerc");
+    std::fs::write(&rc, "name = \"rc\"\n")?;
+    let file = TEST_APP_CONFIG.search_config_file()?;
+    assert_eq!(file.path.as_deref(), Some(rc.as_path()));
+    assert_eq!(TEST_APP_CONFIG.read()?.name, "rc");
+    std::fs::remove_file(&rc)?;
+    Ok(())
+}
+
+#[test]
+fn test_file_ref() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::lazy::FileRef;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Config {
+        servers: FileRef<Vec<Server>>,
+        users: FileRef<Vec<String>>,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-file-ref");
+    std::fs::create_dir_all(dir.join("sub"))?;
+    let path = dir.join("conrig.toml");
+    std::fs::write(
+        &path,
+        "servers = \"sub/servers.json\"\nusers = \"sub/users\"\n",
+    )?;
+    std::fs::write(dir.join("sub/users.yaml"), "- alice\n- bob\n")?;
+
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+    let config: Config = file.read()?;
+    assert_eq!(config.servers.file().path, dir.join("sub/servers.json"));
+    assert_eq!(config.servers.file().file_format, FileFormat::Json);
+    assert!(config.servers.load().is_err());
+
+    // the format is detected from the extension, or from the existing file.
+    assert_eq!(config.users.file().file_format, FileFormat::Yaml);
+    assert_eq!(config.users.load()?, vec!["alice", "bob"]);
+
+    let servers = vec![Server {
+        host: "localhost".to_owned(),
+    }];
+    config.servers.save(&servers)?;
+    assert_eq!(config.servers.load()?, servers);
+
+    // only the references are written into the configuration file.
+    file.write(&config)?;
+    let contents = std::fs::read_to_string(&path)?;
+    assert!(contents.contains("servers = \"sub/servers.json\""));
+    assert!(!contents.contains("localhost"));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_merge_keys() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::value::Value;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Server {
+        name: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        servers: Vec<Server>,
+        tags: Vec<String>,
+    }
+
+    let base: Value = FileFormat::Toml.read_str(
+        "tags = [\"a\"]\n[[servers]]\nname = \"alpha\"\nport = 80\n[[servers]]\nname = \"beta\"\nport = 81\n",
+    )?;
+    let overlay: Value = FileFormat::Toml.read_str(
+        "tags = [\"b\"]\n[[servers]]\nname = \"beta\"\nport = 8081\n[[servers]]\nname = \"gamma\"\nport = 82\n",
+    )?;
+
+    let mut merged = base.clone();
+    merged.merge_keyed(overlay.clone(), &[("servers", "name")]);
+    let config: Config = conrig::value::from_value(merged)?;
+    let ports: Vec<_> = config
+        .servers
+        .iter()
+        .map(|t| (t.name.as_str(), t.port))
+        .collect();
+    assert_eq!(ports, [("alpha", 80), ("beta", 8081), ("gamma", 82)]);
+    // the other arrays are replaced as a whole.
+    assert_eq!(config.tags, ["b"]);
+
+    let mut merged = base;
+    merged.merge(overlay);
+    let config: Config = conrig::value::from_value(merged)?;
+    assert_eq!(config.servers.len(), 2);
+
+    // the identity keys apply to the included files.
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-merge-keys");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(
+        dir.join("base.toml"),
+        "tags = []\n[[servers]]\nname = \"alpha\"\nport = 80\n",
+    )?;
+    std::fs::write(
+        dir.join("conrig.toml"),
+        "include = \"base.toml\"\n[[servers]]\nname = \"alpha\"\nport = 8080\n",
+    )?;
+    let file = ConfigFile::new(FileFormat::Toml, dir.join("conrig.toml")).with_config_option(
+        ConfigOption::DEFAULT_CONFIG
+            .with_include_key("include")
+            .with_merge_keys(&[("servers", "name")]),
+    );
+    assert_eq!(file.read::<Config>()?.servers[0].port, 8080);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_templates() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::provider::{DefaultProvider, Templates};
+    use conrig::{templates, ConrigError};
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        log_level: String,
+    }
+
+    const TEMPLATES: Templates = templates!(FileFormat::Toml, {
+        "dev" => "log_level = \"debug\"",
+        "prod" => "log_level = \"warn\"",
+    })
+    .with_env("CONRIG_TEST_TEMPLATE");
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-templates"),
+        config_name: &["conrig-templates"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_sys_override_local(true),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+        default_provider: Some(&TEMPLATES),
+    });
+
+    // the first template is the default one.
+    let config: Config = TEMPLATES.provide()?;
+    assert_eq!(config.log_level, "debug");
+
+    // the environment variable selects the template materialized by `read_or_default`.
+    std::env::set_var("CONRIG_TEST_TEMPLATE", "prod");
+    let _sandbox = Sandbox::new()?;
+    let path = TEST_APP_CONFIG.default_config_file()?;
+    assert_eq!(TEST_APP_CONFIG.read_or_default()?.log_level, "warn");
+    assert!(std::fs::read_to_string(&path)?.contains("warn"));
+
+    // an explicit selection takes precedence.
+    let config: Config = TEMPLATES.with_selected("dev").provide()?;
+    assert_eq!(config.log_level, "debug");
+    let err = DefaultProvider::<Config>::provide(&TEMPLATES.with_selected("staging")).unwrap_err();
+    assert!(matches!(err, ConrigError::UnknownTemplate(name) if name == "staging"));
+
+    std::env::remove_var("CONRIG_TEST_TEMPLATE");
+    Ok(())
+}
+
+#[test]
+fn test_drop_ins() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        port: u16,
+        level: String,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-drop-ins");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("conrig.toml.d"))?;
+    std::fs::write(
+        dir.join("conrig.toml"),
+        "name = \"base\"\nport = 80\nlevel = \"info\"\n",
+    )?;
+    std::fs::write(
+        dir.join("conrig.toml.d/20-logging.toml"),
+        "level = \"debug\"\n",
+    )?;
+    std::fs::write(
+        dir.join("conrig.toml.d/10-network.toml"),
+        "port = 8080\nlevel = \"warn\"\n",
+    )?;
+    std::fs::write(dir.join("conrig.toml.d/30-ignored.toml.bak"), "port = 1\n")?;
+
+    let file = ConfigFile::new(FileFormat::Toml, dir.join("conrig.toml"))
+        .with_config_option(ConfigOption::DEFAULT_CONFIG.with_drop_ins(true));
+    assert_eq!(file.drop_in_files().len(), 2);
+    assert_eq!(
+        file.read::<Config>()?,
+        Config {
+            name: "base".to_owned(),
+            port: 8080,
+            level: "debug".to_owned(),
+        }
+    );
+
+    let plain = ConfigFile::new(FileFormat::Toml, dir.join("conrig.toml"));
+    assert!(plain.drop_in_files().is_empty());
+    assert_eq!(plain.read::<Config>()?.port, 80);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_conrig_assert() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::conrig_assert;
+    use conrig::error::Violation;
+    use conrig::validate::{collect, Validate};
+    use conrig::ConrigError;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        workers: u32,
+        server: Server,
+    }
+
+    impl Validate for Config {
+        fn validate(&self) -> Result<(), Vec<Violation>> {
+            collect(|| {
+                conrig_assert!(self.workers >= 1, "workers must be >= 1");
+                conrig_assert!(!self.server.host.is_empty(), "a host is required");
+                conrig_assert!("server.port" => 1024 <= self.server.port, "port {} is privileged", self.server.port);
+            })
+        }
+    }
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-assert.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+
+    file.write(&Config {
+        workers: 0,
+        server: Server {
+            host: String::new(),
+            port: 80,
+        },
+    })?;
+    let error = file.read_checked::<Config>().unwrap_err();
+    assert!(matches!(
+        error.root(),
+        ConrigError::Validation(violations) if violations == &[
+            Violation::new("workers", "workers must be >= 1"),
+            Violation::new("server.host", "a host is required"),
+            Violation::new("server.port", "port 80 is privileged"),
+        ]
+    ));
+
+    file.write(&Config {
+        workers: 2,
+        server: Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+        },
+    })?;
+    assert_eq!(file.read_checked::<Config>()?.workers, 2);
+
+    let workers = 0;
+    assert!(std::panic::catch_unwind(|| conrig_assert!(workers > 0, "no workers")).is_err());
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_constraint_patterns() {
+    use conrig::validate::Constraint;
+    use conrig::value::Value;
+
+    let matches = |regex: &'static str, text: &str| {
+        Constraint::Regex(regex)
+            .check(&Value::String(text.to_owned()))
+            .is_ok()
+    };
+    assert!(matches("b", "abc"));
+    assert!(!matches("^b", "abc"));
+    assert!(matches(r"^\d{3}-\d{4}$", "555-1234"));
+    assert!(!matches(r"^\d{3}-\d{4}$", "555-12345"));
+    assert!(matches(
+        "^(?:http|https)://[^/]+/?$",
+        "https://example.com/"
+    ));
+    assert!(!matches("^(?:http|https)://[^/]+/?$", "ftp://example.com"));
+    assert!(matches(r"^[\w.-]+@[\w-]+\.[a-z]{2,}$", "a.b-c@mail.org"));
+    assert!(matches("^a.*?b$", "a--b--b"));
+    assert!(matches("^(ab)+$", "ababab"));
+    assert!(!matches("^(ab)+$", "aba"));
+    assert!(matches(r"^\S+\s\S+$", "hello world"));
+    assert!(matches("^(a*)*$", "aaaa"));
+    assert!(Constraint::Regex("(a")
+        .check(&Value::String("a".to_owned()))
+        .unwrap_err()
+        .starts_with("invalid pattern"));
+    assert_eq!(Constraint::Regex("^a$").check(&Value::Null), Ok(()));
+}
+
+#[test]
+fn test_file_system() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::filesystem::{with_file_system, FileSystem, MemoryFileSystem};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        port: u16,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-virtual"),
+        config_name: &["conrig"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_drop_ins(true),
+        extra_files: &["/virtual/conrig"],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let _sandbox = Sandbox::new()?;
+    let fs = Arc::new(MemoryFileSystem::new());
+    fs.write(
+        Path::new("/virtual/conrig.json"),
+        br#"{ "name": "virtual", "port": 80 }"#,
+    )?;
+    fs.write(
+        Path::new("/virtual/conrig.json.d/10-port.toml"),
+        b"port = 8080\n",
+    )?;
+
+    let config = with_file_system(fs.clone(), || TEST_APP_CONFIG.read())?;
+    assert_eq!(
+        config,
+        Config {
+            name: "virtual".to_owned(),
+            port: 8080,
+        }
+    );
+    // the virtual files aren't found outside of the virtual file system.
+    assert_ne!(
+        TEST_APP_CONFIG.search_config_file()?.path,
+        Some(PathBuf::from("/virtual/conrig.json"))
+    );
+
+    // files are created in the virtual file system only.
+    let path = PathBuf::from("/virtual/new/conrig.toml");
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+    let config: Config = with_file_system(fs.clone(), || file.read_or_default())?;
+    assert_eq!(config, Config::default());
+    assert!(fs.exists(&path));
+    assert!(!path.exists());
+    assert!(with_file_system(fs.clone(), || file.set_value("port", 1)).is_ok());
+    assert_eq!(fs.read(&path)?, b"name = \"\"\nport = 1\n");
+
+    // files are migrated in the virtual file system only.
+    const LEGACY_PROJECT: ProjectPath =
+        ProjectPath::new("org", "embers-of-the-fire", "conrig-virtual-legacy");
+    let legacy = LEGACY_PROJECT.sys_config_dir().unwrap().join("conrig.toml");
+    fs.write(&legacy, b"name = \"legacy\"\nport = 7\n")?;
+    let migration = with_file_system(fs.clone(), || {
+        TEST_APP_CONFIG.migrate_from(&[LEGACY_PROJECT])
+    })?
+    .unwrap();
+    assert!(!fs.exists(&legacy) && fs.exists(&migration.to));
+    assert!(!migration.to.exists());
+
+    // the file system of the thread is restored after a panic.
+    let panicked = std::panic::catch_unwind(|| with_file_system(fs.clone(), || panic!()));
+    assert!(panicked.is_err());
+    assert_ne!(
+        TEST_APP_CONFIG.search_config_file()?.path,
+        Some(PathBuf::from("/virtual/conrig.json"))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_config_handle() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::handle::ConfigHandle;
+    use std::sync::Arc;
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-handle.json");
+    std::fs::write(&path, "1")?;
+    let handle = ConfigHandle::<u32>::new(ConfigFile::new(FileFormat::Json, path.clone()));
+
+    let first = handle.get()?;
+    assert_eq!(*first, 1);
+    assert!(Arc::ptr_eq(&first, &handle.get()?));
+
+    std::fs::write(&path, "22")?;
+    assert_eq!(*handle.get()?, 22);
+
+    handle.invalidate();
+    assert!(!Arc::ptr_eq(&first, &handle.get()?));
+
+    std::fs::remove_file(&path)?;
+    assert!(handle.get().unwrap_err().is_missing());
+    Ok(())
+}
+
+#[test]
+fn test_static_config() -> Result<(), Box<dyn std::error::Error>> {
+    conrig!(static TEST_APP_CONFIG<u32> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-static"),
+        config_name: &["conrig-static"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-static")],
+        extra_folders: &[],
+        default_format: FileFormat::Json,
+    });
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-static.json");
+    std::fs::write(&path, "7")?;
+    assert_eq!(TEST_APP_CONFIG.read()?, 7);
+    assert_eq!(TEST_APP_CONFIG.config_file()?.path, path);
+
+    // the resolved path is kept even once the file is gone.
+    std::fs::remove_file(&path)?;
+    assert_eq!(TEST_APP_CONFIG.config_file()?.path, path);
+    assert_eq!(TEST_APP_CONFIG.read_or_new(8)?, 8);
+    assert_eq!(std::fs::read_to_string(&path)?.trim(), "8");
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_prefetch() -> Result<(), Box<dyn std::error::Error>> {
+    conrig!(static TEST_APP_CONFIG<u32> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-prefetch"),
+        config_name: &["conrig-prefetch"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-prefetch")],
+        extra_folders: &[],
+        default_format: FileFormat::Json,
+    });
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-prefetch.json");
+    std::fs::write(&path, "7")?;
+    let config = TEST_APP_CONFIG.prefetch();
+    assert_eq!(*config.get()?, 7);
+    assert!(config.is_ready());
+    // the value is kept once read.
+    std::fs::write(&path, "8")?;
+    assert_eq!(*config.get()?, 7);
+
+    // errors aren't kept.
+    std::fs::write(&path, "{")?;
+    let config = TEST_APP_CONFIG.prefetch();
+    assert!(config.get().unwrap_err().parse_error().is_some());
+    std::fs::write(&path, "9")?;
+    assert_eq!(*config.get()?, 9);
+    assert_eq!(TEST_APP_CONFIG.config_file()?.path, path);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_safe_mode() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::filesystem::{with_file_system, FileSystem, MemoryFileSystem};
+    use conrig::safe_mode;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    conrig!(const TEST_APP_CONFIG<u32> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-safe-mode"),
+        config_name: &["conrig-safe-mode"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &["/virtual/conrig-safe-mode"],
+        extra_folders: &[],
+        default_format: FileFormat::Json,
+        default_provider: Some(&|| 3),
+    });
+
+    let fs = Arc::new(MemoryFileSystem::new());
+    let path = Path::new("/virtual/conrig-safe-mode.json");
+    fs.write(path, b"{ broken")?;
+    assert!(with_file_system(fs.clone(), || TEST_APP_CONFIG.read()).is_err());
+
+    let safe = with_file_system(fs.clone(), || TEST_APP_CONFIG.read_safe_mode())?;
+    assert_eq!(safe.value, 3);
+    let aside = Path::new("/virtual/conrig-safe-mode.json.broken");
+    assert_eq!(safe.moved_aside.as_deref(), Some(aside));
+    assert_eq!(safe_mode::aside_path(path), aside);
+    assert!(safe_mode::is_active());
+    assert!(!fs.exists(path));
+    assert_eq!(fs.read(aside)?, b"{ broken");
+    Ok(())
+}
+
+#[test]
+fn test_crash_guard() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::filesystem::{with_file_system, FileSystem, MemoryFileSystem};
+    use conrig::safe_mode::CrashGuard;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    conrig!(const TEST_APP_CONFIG<u32> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-crash-guard"),
+        config_name: &["conrig-crash-guard"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &["/virtual/conrig-crash-guard"],
+        extra_folders: &[],
+        default_format: FileFormat::Json,
+    });
+
+    let fs = Arc::new(MemoryFileSystem::new());
+    let path = Path::new("/virtual/conrig-crash-guard.json");
+    fs.write(path, b"5")?;
+    let guard = CrashGuard::new("/virtual/conrig-crash-guard.starts".into()).with_threshold(2);
+    let read = || with_file_system(fs.clone(), || TEST_APP_CONFIG.read_guarded(&guard));
+
+    assert_eq!(read()?.value, 5);
+    with_file_system(fs.clone(), || guard.mark_clean_exit())?;
+    assert_eq!(read()?.value, 5);
+    assert_eq!(read()?.value, 5);
+    let safe = read()?;
+    assert_eq!(safe.value, 0);
+    assert!(safe.moved_aside.is_some());
+    assert!(!fs.exists(path));
+
+    with_file_system(fs.clone(), || guard.mark_clean_exit())?;
+    let check = with_file_system(fs.clone(), || guard.mark_start())?;
+    assert_eq!(check.unclean_starts, 0);
+    assert!(!check.safe_mode_recommended);
+    Ok(())
+}
+
+#[test]
+fn test_conrig_cell() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::cell::ConrigCell;
+    use std::sync::Arc;
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-cell.json");
+    std::fs::write(&path, "0")?;
+    let cell = Arc::new(ConrigCell::<u32>::open(ConfigFile::new(
+        FileFormat::Json,
+        path.clone(),
+    ))?);
+
+    let before = cell.read();
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let cell = cell.clone();
+            std::thread::spawn(move || cell.update(|value| *value += 1))
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+    assert_eq!(*before, 0);
+    assert_eq!(*cell.read(), 8);
+    assert_eq!(std::fs::read_to_string(&path)?.trim(), "8");
+
+    std::fs::write(&path, "42")?;
+    cell.reload()?;
+    assert_eq!(*cell.read(), 42);
+    #[cfg(feature = "json")]
+    assert_eq!(cell.as_json()?, serde_json::json!(42));
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_policy() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::filesystem::{with_file_system, FileSystem, MemoryFileSystem};
+    use conrig::ConrigError;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        channel: String,
+        volume: u8,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-policy"),
+        config_name: &["conrig-policy"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_policy(true),
+        extra_files: &["/virtual/conrig-policy"],
+        extra_folders: &[],
+        default_format: FileFormat::Json,
+    });
+
+    let fs = Arc::new(MemoryFileSystem::new());
+    fs.write(
+        Path::new("/virtual/conrig-policy.json"),
+        br#"{ "channel": "nightly", "volume": 5 }"#,
+    )?;
+    fs.write(
+        Path::new("/etc/conrig-policy/conrig-policy.policy.json"),
+        br#"{ "channel": "stable" }"#,
+    )?;
+    let config = with_file_system(fs.clone(), || TEST_APP_CONFIG.read_merged())?;
+    assert_eq!(
+        config,
+        Config {
+            channel: "stable".into(),
+            volume: 5,
+        }
+    );
+
+    let file = with_file_system(fs.clone(), || {
+        TEST_APP_CONFIG.search_config_file()?.fallback_default()
+    })?;
+    assert!(file.policy.is_some());
+    let louder = Config {
+        volume: 11,
+        ..config.clone()
+    };
+    with_file_system(fs.clone(), || file.write(&louder))?;
+    let nightly = Config {
+        channel: "nightly".into(),
+        ..config
+    };
+    let err = with_file_system(fs.clone(), || file.write(&nightly)).unwrap_err();
+    assert!(matches!(err.root(), ConrigError::LockedKeys(keys) if keys == &["channel"]));
+    Ok(())
+}
+
+#[test]
+fn test_update() -> Result<(), Box<dyn std::error::Error>> {
+    conrig!(const TEST_APP_CONFIG<u32> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-update"),
+        config_name: &["conrig-update"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-update")],
+        extra_folders: &[],
+        default_format: FileFormat::Json,
+    });
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-update.json");
+    std::fs::write(&path, "0")?;
+    let threads: Vec<_> = (0..8)
+        .map(|_| std::thread::spawn(|| TEST_APP_CONFIG.update(|value| *value += 1)))
+        .collect();
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+    assert_eq!(TEST_APP_CONFIG.read()?, 8);
+    assert_eq!(TEST_APP_CONFIG.update(|value| *value * 2)?, 16);
+
+    std::fs::remove_file(&path)?;
+    std::fs::remove_file(conrig::lock::lock_path(&path))?;
+    Ok(())
+}
+
+#[test]
+fn test_transaction() -> Result<(), Box<dyn std::error::Error>> {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-transaction.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone());
+    file.write(&vec![1, 2])?;
+
+    let mut transaction = file.transaction::<Vec<u32>>()?;
+    transaction.push(3);
+    drop(transaction);
+    assert_eq!(file.read::<Vec<u32>>()?, [1, 2]);
+
+    let mut transaction = file.transaction::<Vec<u32>>()?;
+    transaction.push(3);
+    transaction.rollback();
+    assert_eq!(file.read::<Vec<u32>>()?, [1, 2]);
+
+    let mut transaction = file.transaction::<Vec<u32>>()?;
+    transaction.push(4);
+    transaction.commit()?;
+    assert_eq!(file.read::<Vec<u32>>()?, [1, 2, 4]);
+
+    std::fs::remove_file(&path)?;
+    std::fs::remove_file(conrig::lock::lock_path(&path))?;
+    Ok(())
+}
+
+#[test]
+fn test_checksum() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::checksum::{checksum, checksum_path};
+    use conrig::ConrigError;
+
+    assert_eq!(
+        checksum(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        checksum(b""),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-checksum.json");
+    let file = ConfigFile::new(FileFormat::Json, path.clone())
+        .with_config_option(ConfigOption::DEFAULT_CONFIG.with_checksum(true));
+    file.write(&vec![1, 2])?;
+    assert_eq!(
+        std::fs::read_to_string(checksum_path(&path))?.trim(),
+        checksum(&std::fs::read(&path)?)
+    );
+    assert_eq!(file.read::<Vec<u32>>()?, [1, 2]);
+
+    std::fs::write(&path, "[1, 3]")?;
+    let err = file.read::<Vec<u32>>().unwrap_err();
+    assert!(matches!(err.root(), ConrigError::IntegrityError { .. }));
+
+    std::fs::remove_file(checksum_path(&path))?;
+    assert_eq!(file.read::<Vec<u32>>()?, [1, 3]);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_script() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::script::{set_script_engine, ScriptEngine, ScriptError};
+    use conrig::value::Value;
+
+    /// Doubles the keys listed by the script.
+    struct Doubler;
+
+    impl ScriptEngine for Doubler {
+        fn run(&self, script: &str, mut document: Value) -> Result<Value, ScriptError> {
+            for key in script.split_whitespace() {
+                let Some(Value::Integer(value)) = document.get_path(key) else {
+                    return Err(format!("`{key}` is not an integer").into());
+                };
+                let doubled = Value::Integer(value * 2);
+                document.set_path(key, doubled)?;
+            }
+            Ok(document)
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        workers: i64,
+        threads: i64,
+    }
+
+    set_script_engine(Doubler);
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-script");
+    std::fs::create_dir_all(&dir)?;
+    let file = ConfigFile::new(FileFormat::Toml, dir.join("conrig.toml"))
+        .with_config_option(ConfigOption::DEFAULT_CONFIG.with_script("conrig.script"));
+    std::fs::write(&file.path, "workers = 2\nthreads = 3\n")?;
+
+    // the script is optional.
+    assert_eq!(
+        file.read::<Config>()?,
+        Config {
+            workers: 2,
+            threads: 3,
+        }
+    );
+    std::fs::write(dir.join("conrig.script"), "workers")?;
+    assert_eq!(
+        file.read::<Config>()?,
+        Config {
+            workers: 4,
+            threads: 3,
+        }
+    );
+    assert_eq!(
+        std::fs::read_to_string(&file.path)?,
+        "workers = 2\nthreads = 3\n"
+    );
+
+    std::fs::write(dir.join("conrig.script"), "missing")?;
+    let err = file.read::<Config>().unwrap_err();
+    assert!(matches!(err.root(), conrig::ConrigError::Script { .. }));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_extends() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::provider::Embedded;
+    use conrig::ConrigError;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        theme: String,
+        font_size: u8,
+        telemetry: bool,
+    }
+
+    const PRESETS: &[(&str, Embedded)] = &[
+        (
+            "minimal",
+            Embedded::new(FileFormat::Toml, "theme = \"light\"\ntelemetry = false\n"),
+        ),
+        (
+            "recommended",
+            Embedded::new(FileFormat::Toml, "extends = \"minimal\"\nfont_size = 12\n"),
+        ),
+    ];
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-extends");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(
+        dir.join("base.toml"),
+        "extends = \"recommended\"\ntheme = \"dark\"\n",
+    )?;
+    std::fs::write(
+        dir.join("main.toml"),
+        "extends = \"base.toml\"\nfont_size = 14\n",
+    )?;
+    let file = ConfigFile::new(FileFormat::Toml, dir.join("main.toml")).with_config_option(
+        ConfigOption::DEFAULT_CONFIG
+            .with_extends_key("extends")
+            .with_presets(PRESETS),
+    );
+    assert_eq!(
+        file.read::<Config>()?,
+        Config {
+            theme: "dark".into(),
+            font_size: 14,
+            telemetry: false,
+        }
+    );
+
+    conrig::preset::register_preset(
+        "extends-large",
+        &[("font_size", 20)]
+            .into_iter()
+            .collect::<std::collections::BTreeMap<_, _>>(),
+    )?;
+    std::fs::write(
+        dir.join("base.toml"),
+        "extends = [\"recommended\", \"extends-large\"]\ntheme = \"dark\"\n",
+    )?;
+    std::fs::write(dir.join("main.toml"), "extends = \"base.toml\"\n")?;
+    assert_eq!(file.read::<Config>()?.font_size, 20);
+
+    std::fs::write(dir.join("base.toml"), "extends = \"main.toml\"\n")?;
+    let error = file.read::<Config>().unwrap_err();
+    assert!(matches!(error.root(), ConrigError::IncludeCycle(_)));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_variants() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::ConrigError;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        workers: u32,
+        cache: bool,
+    }
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-variants.toml");
+    std::fs::write(
+        &path,
+        "workers = 4\ncache = true\n\n\
+         [variant.fast]\nworkers = 16\n\n\
+         [variant.safe]\nworkers = 1\ncache = false\n",
+    )?;
+    let option = ConfigOption::DEFAULT_CONFIG
+        .with_strict_keys(true)
+        .with_variant_key("variant")
+        .with_variant_env("CONRIG_TEST_VARIANT");
+    let file = |option| ConfigFile::new(FileFormat::Toml, path.clone()).with_config_option(option);
+
+    // the variants table is dropped, even if no variant is selected.
+    assert_eq!(
+        file(option).read::<Config>()?,
+        Config {
+            workers: 4,
+            cache: true,
+        }
+    );
+
+    std::env::set_var("CONRIG_TEST_VARIANT", "fast");
+    assert_eq!(
+        file(option).read::<Config>()?,
+        Config {
+            workers: 16,
+            cache: true,
+        }
+    );
+    // the explicit selection takes precedence.
+    assert_eq!(
+        file(option.with_variant("safe")).read::<Config>()?,
+        Config {
+            workers: 1,
+            cache: false,
+        }
+    );
+
+    std::env::set_var("CONRIG_TEST_VARIANT", "turbo");
+    let err = file(option).read::<Config>().unwrap_err();
+    assert!(matches!(err.root(), ConrigError::UnknownVariant(name) if name == "turbo"));
+
+    std::env::remove_var("CONRIG_TEST_VARIANT");
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_content_hash() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::checksum::processed_path;
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-content-hash.toml");
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+    let _ = std::fs::remove_file(processed_path(&path));
+    std::fs::write(&path, "name = \"conrig\"\nport = 80\n")?;
+    let hash = file.content_hash()?;
+    assert_eq!(hash.len(), 64);
+
+    // formatting, comments and key order don't change the hash.
+    std::fs::write(&path, "# the port\nport   = 80\nname = 'conrig'\n")?;
+    assert_eq!(file.content_hash()?, hash);
+
+    assert_eq!(file.last_processed_hash("index")?, None);
+    assert_eq!(
+        file.needs_processing("index")?.as_deref(),
+        Some(hash.as_str())
+    );
+    file.set_processed_hash("index", &hash)?;
+    file.set_processed_hash("theme", "0")?;
+    assert_eq!(
+        file.last_processed_hash("index")?.as_deref(),
+        Some(hash.as_str())
+    );
+    assert_eq!(file.needs_processing("index")?, None);
+    assert!(file.needs_processing("theme")?.is_some());
+
+    std::fs::write(&path, "name = \"conrig\"\nport = 81\n")?;
+    let changed = file
+        .needs_processing("index")?
+        .expect("the configuration changed");
+    assert_ne!(changed, hash);
+
+    std::fs::remove_file(processed_path(&path))?;
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
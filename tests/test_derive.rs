@@ -0,0 +1,224 @@
+#![cfg(feature = "derive")]
+
+use conrig::{Conrig, FileFormat};
+use serde_derive::{Deserialize, Serialize};
+
+#[test]
+fn test_derive() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, Conrig)]
+    #[conrig(
+        app = "conrig",
+        org = "embers-of-the-fire",
+        qualifier = "org",
+        name = "conrig-derive",
+        format = "json"
+    )]
+    struct Config {
+        name: String,
+        id: u32,
+    }
+
+    assert_eq!(Config::CONFIG.project_path.application, "conrig");
+    assert_eq!(Config::CONFIG.config_name, ["conrig-derive"]);
+    assert_eq!(Config::CONFIG.default_format, FileFormat::Json);
+
+    let path = Config::CONFIG.default_config_file()?;
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(Config::load()?, Config::default());
+
+    let config = Config {
+        name: "conrig".to_owned(),
+        id: 42,
+    };
+    config.save()?;
+    assert_eq!(Config::load()?, config);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_derive_constraints() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::error::Violation;
+    use conrig::validate::Validate;
+
+    #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, Conrig)]
+    #[conrig(app = "conrig", org = "embers-of-the-fire", qualifier = "org")]
+    struct Config {
+        #[conrig(min = 1, max = 65535)]
+        port: u32,
+        #[conrig(min = 0.5)]
+        ratio: f64,
+        #[conrig(regex = r"^[a-z][a-z0-9-]*(\.[a-z0-9-]+)*$")]
+        host: String,
+        #[serde(rename = "log-level")]
+        #[conrig(one_of("debug", "info", "warn"))]
+        level: Option<String>,
+    }
+
+    let config = Config {
+        port: 8080,
+        ratio: 1.0,
+        host: "example.com".to_owned(),
+        level: None,
+    };
+    assert_eq!(config.validate(), Ok(()));
+
+    let config = Config {
+        port: 0,
+        ratio: 0.25,
+        host: "Example.com".to_owned(),
+        level: Some("trace".to_owned()),
+    };
+    assert_eq!(
+        config.validate(),
+        Err(vec![
+            Violation::new("port", "must be at least 1"),
+            Violation::new("ratio", "must be at least 0.5"),
+            Violation::new("host", r"must match `^[a-z][a-z0-9-]*(\.[a-z0-9-]+)*$`"),
+            Violation::new("log-level", "must be one of `debug`, `info`, `warn`"),
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_derive_requires() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::error::Violation;
+    use conrig::validate::Validate;
+
+    #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+    struct Tls {
+        enabled: bool,
+        cert: Option<String>,
+        key: Option<String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, Conrig)]
+    #[conrig(app = "conrig", org = "embers-of-the-fire", qualifier = "org")]
+    #[conrig(requires("tls.enabled" => "tls.cert"), requires("tls.enabled" => "tls.key"))]
+    struct Config {
+        #[conrig(requires = "proxy_port")]
+        proxy: String,
+        proxy_port: Option<u16>,
+        tls: Tls,
+    }
+
+    assert_eq!(Config::default().validate(), Ok(()));
+
+    let config = Config {
+        proxy: "proxy.local".to_owned(),
+        proxy_port: None,
+        tls: Tls {
+            enabled: true,
+            cert: Some("cert.pem".to_owned()),
+            key: None,
+        },
+    };
+    assert_eq!(
+        config.validate(),
+        Err(vec![
+            Violation::new("proxy_port", "is required when `proxy` is set"),
+            Violation::new("tls.key", "is required when `tls.enabled` is set"),
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_derive_restart_required() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::restart::{diff, RestartRequired};
+
+    #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+        timeout: u32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, Conrig)]
+    #[conrig(app = "conrig", org = "embers-of-the-fire", qualifier = "org")]
+    #[conrig(restart_required("server.port", "server.host"))]
+    struct Config {
+        #[conrig(restart_required)]
+        #[serde(rename = "thread-count")]
+        threads: usize,
+        log_level: String,
+        server: Server,
+    }
+
+    assert_eq!(
+        Config::RESTART_REQUIRED,
+        ["thread-count", "server.port", "server.host"]
+    );
+
+    let old = Config::default();
+    let mut new = old.clone();
+    new.log_level = "debug".to_owned();
+    new.server.timeout = 30;
+    let changes = diff(&old, &new)?;
+    assert_eq!(changes.changed, ["log_level", "server.timeout"]);
+    assert!(changes.can_hot_apply());
+
+    new.threads = 4;
+    new.server.port = 8080;
+    let changes = diff(&old, &new)?;
+    assert_eq!(changes.restart_required, ["thread-count", "server.port"]);
+    assert!(changes.needs_restart());
+    assert!(diff(&old, &old)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_derive_secrets() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::secret::{set_secret_store, MemorySecretStore, Secrets};
+
+    #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+    struct Database {
+        host: String,
+        password: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, Conrig)]
+    #[conrig(
+        app = "conrig",
+        org = "embers-of-the-fire",
+        qualifier = "org",
+        name = "conrig-derive-secrets",
+        format = "json"
+    )]
+    #[conrig(secret("database.password"))]
+    struct Config {
+        user: String,
+        #[conrig(secret)]
+        token: String,
+        database: Database,
+    }
+
+    assert_eq!(Config::SECRETS, ["token", "database.password"]);
+    set_secret_store(MemorySecretStore::new());
+
+    let path = Config::CONFIG.default_config_file()?;
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(Config::load()?, Config::default());
+
+    let config = Config {
+        user: "alice".to_owned(),
+        token: "hunter2".to_owned(),
+        database: Database {
+            host: "localhost".to_owned(),
+            password: "swordfish".to_owned(),
+        },
+    };
+    config.save()?;
+    let contents = std::fs::read_to_string(&path)?;
+    assert!(contents.contains("localhost"));
+    assert!(!contents.contains("hunter2") && !contents.contains("swordfish"));
+    assert_eq!(Config::load()?, config);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "diagnostics")]
+
+use conrig::parser::ConfigFile;
+use conrig::provider::{DefaultProvider, Embedded};
+use conrig::value::Value;
+use conrig::FileFormat;
+use std::path::PathBuf;
+
+#[test]
+fn test_diagnostic() -> Result<(), Box<dyn std::error::Error>> {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-diagnostic.toml");
+    std::fs::write(&path, "name = \"conrig\"\n\tport = \n")?;
+    let error = ConfigFile::new(FileFormat::Toml, path.clone())
+        .read::<Value>()
+        .unwrap_err();
+    let message = error.parse_error().unwrap().message.clone();
+    assert_eq!(
+        error.diagnostic().to_string(),
+        format!(
+            "error: {error}\n  --> {}:2:9\n   |\n 1 | name = \"conrig\"\n 2 | \tport = \n   | \t       ^ {message}\n",
+            path.display()
+        )
+    );
+
+    // a document which isn't read from a file.
+    let source = "[1,\n2,,]";
+    let error =
+        DefaultProvider::<Value>::provide(&Embedded::new(FileFormat::Json, source)).unwrap_err();
+    assert_eq!(error.diagnostic().to_string(), format!("error: {error}\n"));
+    let message = error.parse_error().unwrap().message.clone();
+    assert_eq!(
+        error.diagnostic().with_source(source).to_string(),
+        format!("error: {error}\n   |\n 1 | [1,\n 2 | 2,,]\n   |   ^ {message}\n")
+    );
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
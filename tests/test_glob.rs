@@ -0,0 +1,39 @@
+#![cfg(feature = "glob")]
+
+use conrig::{ConfigOption, ConfigPathMetadata, FileFormat, ProjectPath};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[test]
+fn test_glob_extra_files() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-glob");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("b.json"), r#"{ "name": "b" }"#)?;
+    std::fs::write(dir.join("a.toml"), "name = \"a\"\n")?;
+    std::fs::write(dir.join("c.txt"), "name = \"c\"\n")?;
+
+    let pattern = dir.join("*.*").to_string_lossy().into_owned();
+    let extra_files = [pattern.as_str()];
+    let metadata = ConfigPathMetadata::<Config>::new(
+        ProjectPath::new("org", "embers-of-the-fire", "conrig-glob"),
+        &["conrig-glob"],
+        FileFormat::Toml,
+        &[],
+        &extra_files,
+        ConfigOption::DEFAULT_CONFIG,
+    );
+
+    assert_eq!(metadata.read()?.name, "a");
+    std::fs::remove_file(dir.join("a.toml"))?;
+    let file = metadata.search_config_file()?;
+    assert_eq!(file.file_format, FileFormat::Json);
+    assert_eq!(metadata.read()?.name, "b");
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
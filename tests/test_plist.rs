@@ -0,0 +1,149 @@
+#![cfg(feature = "plist")]
+
+use conrig::parser::ConfigFile;
+use conrig::{detect_file_format, ConrigError, FileFormat, LangError};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+    port: u16,
+    debug: bool,
+}
+
+#[test]
+fn test_plist_xml() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Window {
+        ratio: f64,
+        tags: Vec<String>,
+        title: Option<String>,
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-plist-xml");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+
+    let file = ConfigFile::new(FileFormat::Plist, dir.join("com.foo.conrig.plist"));
+    let window = Window {
+        ratio: 1.5,
+        tags: vec!["a < b".to_owned(), "c & d".to_owned()],
+        title: None,
+    };
+    file.write(&window)?;
+    let contents = std::fs::read_to_string(&file.path)?;
+    assert!(contents.contains("<string>a &lt; b</string>"));
+    assert!(!contents.contains("title"));
+    assert_eq!(file.read::<Window>()?, window);
+
+    std::fs::write(
+        &file.path,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<!-- written by hand -->
+<dict>
+    <key>name</key>
+    <string>con&#x72;ig</string>
+    <key>port</key>
+    <integer>8080</integer>
+    <key>debug</key>
+    <true/>
+</dict>
+</plist>
+"#,
+    )?;
+    assert_eq!(
+        detect_file_format(dir.join("com.foo.conrig"), FileFormat::Toml),
+        Some((file.path.clone(), FileFormat::Plist))
+    );
+    assert_eq!(
+        file.read::<Config>()?,
+        Config {
+            name: "conrig".to_owned(),
+            port: 8080,
+            debug: true,
+        }
+    );
+
+    std::fs::write(
+        &file.path,
+        "<plist>\n<dict>\n  <string>x</string>\n</dict>\n</plist>",
+    )?;
+    let error = file.read::<Config>().unwrap_err();
+    let ConrigError::LangError(LangError::PlistError(error)) = error.root() else {
+        panic!("unexpected error: {error}");
+    };
+    assert_eq!((error.line, error.column), (3, 11));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_plist_binary() -> Result<(), Box<dyn std::error::Error>> {
+    // { "name": "conrig", "port": 8080, "debug": true }
+    let objects: [&[u8]; 7] = [
+        &[0xD3, 1, 2, 3, 4, 5, 6],
+        b"\x54name",
+        b"\x54port",
+        b"\x55debug",
+        b"\x56conrig",
+        &[0x11, 0x1F, 0x90],
+        &[0x09],
+    ];
+    let mut bytes = b"bplist00".to_vec();
+    let mut offsets = Vec::new();
+    for object in objects {
+        offsets.push(bytes.len() as u8);
+        bytes.extend_from_slice(object);
+    }
+    let table = bytes.len() as u64;
+    bytes.extend_from_slice(&offsets);
+    bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+    bytes.extend_from_slice(&(objects.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(&0u64.to_be_bytes());
+    bytes.extend_from_slice(&table.to_be_bytes());
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-binary.plist");
+    std::fs::write(&path, &bytes)?;
+    let file = ConfigFile::new(FileFormat::Plist, path.clone());
+    assert_eq!(
+        file.read::<Config>()?,
+        Config {
+            name: "conrig".to_owned(),
+            port: 8080,
+            debug: true,
+        }
+    );
+
+    // mapped files are parsed in place.
+    #[cfg(feature = "mmap")]
+    {
+        // SAFETY: the file isn't modified while it's read.
+        let threshold = unsafe { conrig::mmap::MmapThreshold::new(1) };
+        let mapped = file.clone().with_config_option(
+            conrig::ConfigOption::DEFAULT_CONFIG.with_mmap_threshold(threshold),
+        );
+        assert_eq!(mapped.read::<Config>()?, file.read::<Config>()?);
+        assert_eq!(
+            mapped.get_value("port")?,
+            Some(conrig::value::Value::Integer(8080))
+        );
+        std::fs::write(&path, &bytes[..bytes.len() - 40])?;
+        assert!(matches!(
+            mapped.read::<Config>().unwrap_err().root(),
+            ConrigError::LangError(LangError::PlistError(_))
+        ));
+    }
+
+    std::fs::write(&path, &bytes[..bytes.len() - 40])?;
+    assert!(matches!(
+        file.read::<Config>().unwrap_err().root(),
+        ConrigError::LangError(LangError::PlistError(_))
+    ));
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
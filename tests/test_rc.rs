@@ -0,0 +1,76 @@
+use conrig::conrig;
+use conrig::testing::Sandbox;
+use conrig::{ConfigOption, FileFormat, ProjectPath};
+use serde_derive::{Deserialize, Serialize};
+
+#[test]
+fn test_rc_chain() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        registry: String,
+        color: bool,
+        jobs: u32,
+        name: String,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-rc-chain"),
+        config_name: &["conrig-rc-chain"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let sandbox = Sandbox::new()?;
+    let sys_dir = TEST_APP_CONFIG.project_path.sys_config_dir().unwrap();
+    let home_rc = TEST_APP_CONFIG
+        .project_path
+        .home_dir()
+        .unwrap()
+        .join(".conrig-rc-chainrc");
+    let root = sandbox.root();
+    let nested = sandbox.current_dir();
+    std::fs::create_dir_all(&sys_dir)?;
+    std::fs::create_dir_all(home_rc.parent().unwrap())?;
+    std::fs::write(
+        sys_dir.join("conrig-rc-chain.toml"),
+        "registry = \"https://sys\"\ncolor = false\njobs = 1\nname = \"sys\"\n",
+    )?;
+    std::fs::write(&home_rc, "color = true\njobs = 2\nname = \"home\"\n")?;
+    std::fs::write(
+        root.join(".conrig-rc-chain.toml"),
+        "jobs = 4\nname = \"root\"\n",
+    )?;
+    std::fs::write(
+        nested.join("conrig-rc-chain.json"),
+        r#"{ "name": "project" }"#,
+    )?;
+
+    let files: Vec<_> = TEST_APP_CONFIG
+        .rc_files()?
+        .into_iter()
+        .map(|t| t.path)
+        .collect();
+    assert_eq!(
+        &files[files.len() - 4..],
+        [
+            sys_dir.join("conrig-rc-chain.toml"),
+            home_rc.clone(),
+            root.join(".conrig-rc-chain.toml"),
+            nested.join("conrig-rc-chain.json"),
+        ]
+    );
+
+    let config = TEST_APP_CONFIG.read_rc_chain()?;
+    assert_eq!(
+        config,
+        Config {
+            registry: "https://sys".to_owned(),
+            color: true,
+            jobs: 4,
+            name: "project".to_owned(),
+        }
+    );
+    Ok(())
+}
@@ -0,0 +1,63 @@
+#![cfg(all(windows, feature = "windows-registry"))]
+
+use conrig::backend::ConfigBackend;
+use conrig::registry::RegistryKey;
+use conrig::value::Value;
+use serde_derive::{Deserialize, Serialize};
+
+#[test]
+fn test_registry() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        debug: bool,
+        ratio: f64,
+        tags: Vec<String>,
+        servers: Vec<Server>,
+        proxy: Option<String>,
+    }
+
+    let key = RegistryKey::new("Software\\conrig-test\\registry");
+    let backend = ConfigBackend::from(key.clone());
+    key.delete()?;
+
+    let config = Config {
+        name: "conrig".to_owned(),
+        debug: true,
+        ratio: 0.5,
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        servers: vec![Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+        }],
+        proxy: None,
+    };
+    backend.write(&config)?;
+    assert!(key.exists());
+    assert_eq!(backend.read::<Config>()?, config);
+    assert_eq!(
+        key.read_value()?.get_path("servers.0.port"),
+        Some(&Value::Integer(8080))
+    );
+    // booleans are read back as integers.
+    assert_eq!(
+        key.read_value()?.get_path("debug"),
+        Some(&Value::Integer(1))
+    );
+
+    let missing = RegistryKey::new("Software\\conrig-test\\missing");
+    assert!(missing.read::<Config>().unwrap_err().is_missing());
+    assert_eq!(missing.read_or_default::<Config>()?, Config::default());
+    assert!(missing.exists());
+
+    key.delete()?;
+    missing.delete()?;
+    assert!(!key.exists());
+    Ok(())
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "http")]
+
+use conrig::parser::ConfigFile;
+use conrig::remote::{FetchStatus, RemoteSource};
+use conrig::{ConfigOption, ConrigError, ConrigWarning, FileFormat};
+use serde_derive::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::Mutex;
+use std::thread;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+}
+
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn record(warning: &ConrigWarning) {
+    WARNINGS.lock().unwrap().push(warning.to_string());
+}
+
+/// Serve the responses in order, one per connection, and return the request heads.
+fn serve(responses: Vec<&'static str>) -> (String, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/conrig.toml", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                request.push_str(&line);
+            }
+            requests.push(request);
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+        requests
+    });
+    (url, handle)
+}
+
+#[test]
+fn test_remote_source() {
+    let dir = std::env::temp_dir().join(format!("conrig-remote-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let cache = ConfigFile::new(FileFormat::Toml, dir.join("conrig.toml"))
+        .with_config_option(ConfigOption::DEFAULT_CONFIG.with_warning_handler(record));
+
+    let (url, server) = serve(vec![
+        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nTransfer-Encoding: chunked\r\n\r\n\
+         5\r\nname \r\nb\r\n= \"remote\"\n\r\n0\r\n\r\n",
+        "HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n",
+        "HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\nnot toml!",
+    ]);
+    let source = RemoteSource::new([url.clone()], cache.clone());
+
+    assert_eq!(
+        source.fetch().unwrap(),
+        FetchStatus::Downloaded(url.clone())
+    );
+    let config: Config = cache.read().unwrap();
+    assert_eq!(config.name, "remote");
+
+    assert_eq!(
+        source.fetch().unwrap(),
+        FetchStatus::NotModified(url.clone())
+    );
+    // an invalid response never replaces the cached copy.
+    assert_eq!(source.fetch().unwrap(), FetchStatus::Cached);
+    assert_eq!(source.read::<Config>().unwrap(), config);
+    assert_eq!(WARNINGS.lock().unwrap().len(), 2);
+
+    let requests = server.join().unwrap();
+    assert!(!requests[0].contains("If-None-Match"));
+    assert!(requests[1].contains("If-None-Match: \"v1\""));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    let err = source.fetch().unwrap_err();
+    assert!(matches!(err, ConrigError::Remote { .. }), "{err}");
+
+    let https = RemoteSource::new(["https://example.com/conrig.toml"], cache);
+    assert!(https.fetch().unwrap_err().to_string().contains("TLS"));
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "schemars")]
+
+use conrig::error::Violation;
+use conrig::parser::ConfigFile;
+use conrig::schema::JsonSchema;
+use conrig::{ConrigError, FileFormat};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[test]
+fn test_read_validated() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+    struct Listener {
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+    struct Config {
+        name: String,
+        level: Option<u8>,
+        listeners: Vec<Listener>,
+    }
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-schema.toml");
+    let file = ConfigFile::new(FileFormat::Toml, path.clone());
+
+    std::fs::write(
+        &path,
+        "level = 3\n[[listeners]]\nport = 80\n[[listeners]]\nport = 70000\n",
+    )?;
+    let error = file.read_validated::<Config>().unwrap_err();
+    let ConrigError::SchemaViolation(violations) = error.root() else {
+        panic!("unexpected error: {error}");
+    };
+    assert_eq!(
+        violations,
+        &[
+            Violation::new("name", "missing required key"),
+            Violation::new(
+                "listeners.1.port",
+                "70000 is greater than the maximum 65535"
+            ),
+        ]
+    );
+
+    std::fs::write(&path, "name = \"conrig\"\nlisteners = []\n")?;
+    assert_eq!(
+        file.read_validated::<Config>()?,
+        Config {
+            name: "conrig".to_owned(),
+            level: None,
+            listeners: vec![],
+        }
+    );
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[test]
+fn test_keys() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::schema::KeyInfo;
+    use conrig::value::Value;
+    use conrig::{conrig, ConfigOption, ProjectPath};
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Info,
+        Debug,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Server {
+        /// The listening port.
+        port: u16,
+        tags: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Config {
+        level: Level,
+        name: Option<String>,
+        server: Server,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-keys"),
+        config_name: &["conrig-keys"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let keys = TEST_APP_CONFIG.keys()?;
+    let paths: Vec<&str> = keys.iter().map(|key| key.path.as_str()).collect();
+    assert_eq!(paths, ["level", "name", "server.port", "server.tags"]);
+    assert_eq!(
+        keys[0].allowed,
+        [Value::String("info".into()), Value::String("debug".into())]
+    );
+    assert_eq!(keys[1].types, ["string", "null"]);
+    assert_eq!(
+        keys[2],
+        KeyInfo {
+            path: "server.port".into(),
+            types: vec!["integer".into()],
+            allowed: vec![],
+            description: Some("The listening port.".into()),
+        }
+    );
+    assert_eq!(keys[3].types, ["array"]);
+    Ok(())
+}
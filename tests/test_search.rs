@@ -0,0 +1,646 @@
+use conrig::conrig;
+use conrig::{ConfigOption, FileFormat, ProjectPath};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[test]
+fn test_search_ancestors() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::testing::Sandbox;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-ancestors"),
+        config_name: &["conrig-ancestors"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_search_ancestors(true),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let sandbox = Sandbox::new()?;
+    let (root, nested) = (sandbox.root(), sandbox.current_dir());
+    std::fs::write(root.join(".conrig-ancestors.toml"), "name = \"root\"\n")?;
+
+    // found from any subdirectory.
+    assert_eq!(TEST_APP_CONFIG.read()?.name, "root");
+
+    // the nearest file takes precedence.
+    std::fs::write(nested.join("conrig-ancestors.toml"), "name = \"project\"\n")?;
+    assert_eq!(TEST_APP_CONFIG.read()?.name, "project");
+
+    // the search stops at the project's root.
+    std::fs::remove_file(nested.join("conrig-ancestors.toml"))?;
+    std::fs::create_dir_all(nested.join(".git"))?;
+    let config = TEST_APP_CONFIG
+        .with_config_option(TEST_APP_CONFIG.config_option.with_ancestor_marker(".git"));
+    assert!(config.search_config_file()?.path.is_none());
+
+    // ancestors are only searched if enabled.
+    let config = TEST_APP_CONFIG.with_config_option(ConfigOption::DEFAULT_CONFIG);
+    assert!(config.search_config_file()?.path.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_path_env_var() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-path-env"),
+        config_name: &["conrig-path-env"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_path_env_var("CONRIG_TEST_CONFIG"),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-path-env.json");
+    std::env::set_var("CONRIG_TEST_CONFIG", &path);
+    let file = TEST_APP_CONFIG.search_config_file()?;
+    assert_eq!(file.path.as_deref(), Some(path.as_path()));
+    assert_eq!(file.file_format, FileFormat::Json);
+
+    let config = Config {
+        name: "conrig".to_owned(),
+    };
+    TEST_APP_CONFIG.write(&config)?;
+    assert!(std::fs::read_to_string(&path)?.contains("\"name\""));
+    assert_eq!(TEST_APP_CONFIG.read()?, config);
+
+    // an empty variable is ignored.
+    std::env::set_var("CONRIG_TEST_CONFIG", "");
+    assert_ne!(
+        TEST_APP_CONFIG.search_config_file()?.path.as_deref(),
+        Some(path.as_path())
+    );
+    std::env::remove_var("CONRIG_TEST_CONFIG");
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_search_all_config_files() -> Result<(), Box<dyn std::error::Error>> {
+    conrig!(const TEST_APP_CONFIG<()> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-search-all"),
+        config_name: &["conrig-search-all"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &[
+            concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-search-all/a"),
+            concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-search-all/b"),
+        ],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-search-all");
+    std::fs::create_dir_all(&dir)?;
+    assert!(TEST_APP_CONFIG.search_all_config_files()?.is_empty());
+
+    std::fs::write(dir.join("a.toml"), "")?;
+    std::fs::write(dir.join("a.json"), "{}")?;
+    std::fs::write(dir.join("b.yaml"), "")?;
+    assert_eq!(
+        TEST_APP_CONFIG.search_all_config_files()?,
+        [
+            (dir.join("a.toml"), FileFormat::Toml),
+            (dir.join("a.json"), FileFormat::Json),
+            (dir.join("b.yaml"), FileFormat::Yaml),
+        ]
+    );
+    // the first one is the one in use.
+    assert_eq!(
+        TEST_APP_CONFIG.search_config_file()?.path,
+        Some(dir.join("a.toml"))
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_error_on_ambiguity() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::ConrigError;
+
+    conrig!(const TEST_APP_CONFIG<()> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-ambiguity"),
+        config_name: &["conrig-ambiguity"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_error_on_ambiguity(true),
+        extra_files: &[],
+        extra_folders: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-ambiguity")],
+        default_format: FileFormat::Toml,
+    });
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-ambiguity");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("conrig-ambiguity.toml"), "")?;
+    assert_eq!(
+        TEST_APP_CONFIG.search_config_file()?.path,
+        Some(dir.join("conrig-ambiguity.toml"))
+    );
+
+    std::fs::write(dir.join(".conrig-ambiguity.yaml"), "")?;
+    let err = TEST_APP_CONFIG.search_config_file().unwrap_err();
+    assert!(matches!(
+        &err,
+        ConrigError::AmbiguousConfig(paths) if *paths == [
+            dir.join("conrig-ambiguity.toml"),
+            dir.join(".conrig-ambiguity.yaml"),
+        ]
+    ));
+    assert!(err.to_string().contains(".conrig-ambiguity.yaml"));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_doctor() -> Result<(), Box<dyn std::error::Error>> {
+    conrig!(const TEST_APP_CONFIG<()> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-doctor"),
+        config_name: &["conrig-doctor"],
+        config_option: ConfigOption::DEFAULT_CONFIG
+            .with_path_env_var("CONRIG_DOCTOR_PATH")
+            .with_profile_env("CONRIG_DOCTOR_PROFILE"),
+        extra_files: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-doctor/conrig-doctor")],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-doctor");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("conrig-doctor.toml"), "")?;
+    std::fs::write(dir.join("conrig-doctor.json"), "{}")?;
+
+    let report = TEST_APP_CONFIG.doctor();
+    assert_eq!(report.selected, Some(dir.join("conrig-doctor.toml")));
+    assert_eq!(report.candidates.len(), 2);
+    assert!(report.problems.is_empty());
+    let var = |name: &str| {
+        report
+            .environment
+            .iter()
+            .find(|var| var.name == name)
+            .cloned()
+            .unwrap()
+    };
+    assert!(!var("CONRIG_DOCTOR_PATH").affected);
+    assert!(!var("CONRIG_DOCTOR_PROFILE").affected);
+
+    std::fs::write(dir.join("conrig-doctor.production.toml"), "")?;
+    std::env::set_var("CONRIG_DOCTOR_PROFILE", "production");
+    let report = TEST_APP_CONFIG.doctor();
+    std::env::remove_var("CONRIG_DOCTOR_PROFILE");
+    let profile = report
+        .environment
+        .iter()
+        .find(|var| var.name == "CONRIG_DOCTOR_PROFILE")
+        .unwrap();
+    assert_eq!(profile.value.as_deref(), Some("production"));
+    assert!(profile.affected);
+    assert!(report
+        .to_string()
+        .contains("CONRIG_DOCTOR_PROFILE=production (profile, used)"));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_search_order() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::{SearchLocation, SearchOrder};
+
+    conrig!(const TEST_APP_CONFIG<()> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-search-order"),
+        config_name: &["conrig-search-order"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_search_order(SearchOrder(&[
+            SearchLocation::ExtraFolders,
+            SearchLocation::ExtraFiles,
+        ])),
+        extra_files: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-search-order/file/a")],
+        extra_folders: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-search-order/folder")],
+        default_format: FileFormat::Toml,
+    });
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-search-order");
+    std::fs::create_dir_all(dir.join("file"))?;
+    std::fs::create_dir_all(dir.join("folder"))?;
+    std::fs::write(dir.join("file/a.toml"), "")?;
+    std::fs::write(dir.join("folder/conrig-search-order.json"), "{}")?;
+
+    // the extra folders come first, and no other location is searched.
+    assert_eq!(
+        TEST_APP_CONFIG.search_all_config_files()?,
+        [
+            (
+                dir.join("folder/conrig-search-order.json"),
+                FileFormat::Json
+            ),
+            (dir.join("file/a.toml"), FileFormat::Toml),
+        ]
+    );
+    assert_eq!(
+        TEST_APP_CONFIG.search_config_file()?.path,
+        Some(dir.join("folder/conrig-search-order.json"))
+    );
+    assert_eq!(
+        ConfigOption::DEFAULT_CONFIG
+            .with_sys_override_local(true)
+            .effective_search_order(),
+        SearchOrder::SYSTEM_FIRST
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_custom_extensions() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-extensions"),
+        config_name: &["conrig-extensions"],
+        config_option: ConfigOption::DEFAULT_CONFIG
+            .with_extensions(&[("conf", FileFormat::Toml), ("", FileFormat::Json)]),
+        extra_files: &[
+            concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-extensions/app"),
+            concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-extensions/bare"),
+        ],
+        extra_folders: &[],
+        default_format: FileFormat::Yaml,
+    });
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-extensions");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("app.conf"), "name = \"conf\"\n")?;
+    std::fs::write(dir.join("bare"), "{\"name\": \"bare\"}")?;
+
+    assert_eq!(
+        TEST_APP_CONFIG.search_all_config_files()?,
+        [
+            (dir.join("app.conf"), FileFormat::Toml),
+            (dir.join("bare"), FileFormat::Json),
+        ]
+    );
+    let config: Config = TEST_APP_CONFIG.search_config_file()?.read()?;
+    assert_eq!(config.name, "conf");
+    assert_eq!(
+        TEST_APP_CONFIG.config_option.format_of(&dir.join("bare")),
+        Some(FileFormat::Json)
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_detect_file_format() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::detect_file_format;
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-detect");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+
+    // names without an extension are detected.
+    assert_eq!(
+        detect_file_format(dir.join("conrig"), FileFormat::Toml),
+        None
+    );
+    std::fs::write(dir.join("conrig.yml"), "name: conrig\n")?;
+    assert_eq!(
+        detect_file_format(dir.join("conrig"), FileFormat::Toml),
+        Some((dir.join("conrig.yml"), FileFormat::Yaml))
+    );
+
+    // the extensions are appended to dotted names, never replacing their last part.
+    std::fs::write(dir.join("com.foo.json"), "{}")?;
+    assert_eq!(
+        detect_file_format(dir.join("com.foo"), FileFormat::Toml),
+        Some((dir.join("com.foo.json"), FileFormat::Json))
+    );
+    std::fs::write(dir.join("app.yaml.yml"), "name: app\n")?;
+    assert_eq!(detect_file_format(dir.join("app"), FileFormat::Toml), None);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_platform_paths() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::platform::PlatformPaths;
+    use conrig::ConfigType;
+
+    struct Custom;
+
+    impl PlatformPaths for Custom {
+        fn sys_dir(&self, project_path: &ProjectPath, _: ConfigType) -> Option<PathBuf> {
+            Some(PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(project_path.application))
+        }
+    }
+
+    conrig!(const TEST_APP_CONFIG<()> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-platform"),
+        config_name: &["conrig-platform"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+    let metadata = TEST_APP_CONFIG.with_platform_paths(&Custom);
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-platform");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("conrig-platform.toml"), "")?;
+
+    assert_eq!(metadata.sys_dir(ConfigType::Config), Some(dir.clone()));
+    assert_eq!(
+        metadata.search_config_file()?.path,
+        Some(dir.join("conrig-platform.toml"))
+    );
+    assert_ne!(
+        TEST_APP_CONFIG.search_config_file()?.path,
+        Some(dir.join("conrig-platform.toml"))
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_app_data() {
+    use conrig::{AppData, ConfigType};
+
+    conrig!(const TEST_APP_CONFIG<()> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-app-data"),
+        config_name: &["conrig-app-data"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_app_data(AppData::LocalLow),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let sys_dir = TEST_APP_CONFIG.sys_dir(ConfigType::Config);
+    if cfg!(windows) {
+        let sys_dir = sys_dir.unwrap();
+        assert!(sys_dir.ends_with("LocalLow/embers-of-the-fire/conrig-app-data/config"));
+    } else {
+        // the option only applies on Windows.
+        assert_eq!(
+            sys_dir,
+            TEST_APP_CONFIG.project_path.sys_dir(ConfigType::Config)
+        );
+    }
+    assert_eq!(
+        TEST_APP_CONFIG.sys_dir(ConfigType::Cache),
+        TEST_APP_CONFIG.project_path.sys_dir(ConfigType::Cache)
+    );
+}
+
+#[test]
+fn test_auto_config_type() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::platform::PlatformPaths;
+    use conrig::ConfigType;
+
+    // a platform where the preference and config directories differ, like macOS.
+    struct Split;
+
+    impl PlatformPaths for Split {
+        fn sys_dir(&self, _: &ProjectPath, config_type: ConfigType) -> Option<PathBuf> {
+            let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-auto");
+            Some(dir.join(format!("{config_type:?}")))
+        }
+    }
+
+    conrig!(const TEST_APP_CONFIG<()> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-auto"),
+        config_name: &["conrig-auto"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_config_sys_type(ConfigType::Auto),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+    let metadata = TEST_APP_CONFIG.with_platform_paths(&Split);
+    let [conventional, other] = ConfigType::auto_candidates();
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-auto");
+    let other_dir = dir.join(format!("{other:?}"));
+    std::fs::create_dir_all(&other_dir)?;
+    assert_eq!(metadata.sys_type(), conventional);
+    assert_eq!(
+        metadata.default_sys_config_file()?,
+        dir.join(format!("{conventional:?}/conrig-auto.toml"))
+    );
+
+    std::fs::write(other_dir.join("conrig-auto.toml"), "")?;
+    assert_eq!(metadata.sys_type(), other);
+    assert_eq!(
+        metadata.search_config_file()?.path,
+        Some(other_dir.join("conrig-auto.toml"))
+    );
+    assert_eq!(
+        metadata.default_sys_config_file()?,
+        other_dir.join("conrig-auto.toml")
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_missing_sys_dir() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::platform::PlatformPaths;
+    use conrig::{ConfigType, ConrigWarning, SearchLocation};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+    // like a container without `HOME`.
+    struct Homeless;
+
+    impl PlatformPaths for Homeless {
+        fn sys_dir(&self, _: &ProjectPath, _: ConfigType) -> Option<PathBuf> {
+            None
+        }
+    }
+
+    conrig!(const TEST_APP_CONFIG<()> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-homeless"),
+        config_name: &["conrig-homeless"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_warning_handler(|warning| {
+            if let ConrigWarning::SkippedLocation {
+                location: SearchLocation::System,
+                ..
+            } = warning
+            {
+                SKIPPED.fetch_add(1, Ordering::SeqCst);
+            }
+        }),
+        extra_files: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-homeless")],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+    let metadata = TEST_APP_CONFIG.with_platform_paths(&Homeless);
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-homeless.toml");
+    std::fs::write(&path, "")?;
+    // the other locations are still searched.
+    assert_eq!(metadata.search_config_file()?.path, Some(path.clone()));
+    assert_eq!(SKIPPED.load(Ordering::SeqCst), 1);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_local_dir() -> Result<(), Box<dyn std::error::Error>> {
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-local-dir"),
+        config_name: &["conrig-local-dir"],
+        config_option: ConfigOption::DEFAULT_CONFIG.with_search_ancestors(true),
+        extra_files: &[],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-local-dir");
+    let nested = dir.join("a/b");
+    std::fs::create_dir_all(&nested)?;
+    std::fs::write(dir.join("conrig-local-dir.toml"), "name = \"anchored\"\n")?;
+
+    // the local location is anchored to `nested` instead of the current directory.
+    let metadata = TEST_APP_CONFIG.with_local_dir(&nested);
+    assert_eq!(
+        metadata.search_config_file()?.path,
+        Some(dir.join("conrig-local-dir.toml"))
+    );
+    assert_eq!(metadata.search_config_file()?.read()?.name, "anchored");
+    assert_eq!(
+        metadata.default_local_config_file()?,
+        nested.join("conrig-local-dir.toml")
+    );
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_sandbox() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::testing::Sandbox;
+
+    let project_path = ProjectPath::new("org", "embers-of-the-fire", "conrig-sandbox");
+    let real = project_path.sys_config_dir();
+
+    let sandbox = Sandbox::new()?;
+    let root = sandbox.root().to_path_buf();
+    assert!(project_path.sys_config_dir().unwrap().starts_with(&root));
+    assert!(project_path.home_dir().unwrap().starts_with(&root));
+    {
+        // nested sandboxes restore the outer one.
+        let inner = Sandbox::new()?;
+        assert!(project_path
+            .sys_config_dir()
+            .unwrap()
+            .starts_with(inner.root()));
+    }
+    assert!(project_path.sys_config_dir().unwrap().starts_with(&root));
+
+    drop(sandbox);
+    assert!(!root.exists());
+    assert_eq!(project_path.sys_config_dir(), real);
+    Ok(())
+}
+
+#[test]
+fn test_dot_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::testing::Sandbox;
+
+    conrig!(const TEST_APP_CONFIG<()> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-dot-prefix"),
+        config_name: &["conrig-a", "conrig-b"],
+        config_option: ConfigOption::DEFAULT_CONFIG,
+        extra_files: &[],
+        extra_folders: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-dot-prefix")],
+        default_format: FileFormat::Toml,
+    });
+
+    let _sandbox = Sandbox::new()?;
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-dot-prefix");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("conrig-b.json"), "{}")?;
+    std::fs::write(dir.join(".conrig-a.toml"), "")?;
+    std::fs::write(dir.join("conrig-a.yaml"), "")?;
+    assert_eq!(
+        TEST_APP_CONFIG.search_all_config_files()?,
+        [
+            (dir.join("conrig-a.yaml"), FileFormat::Yaml),
+            (dir.join(".conrig-a.toml"), FileFormat::Toml),
+            (dir.join("conrig-b.json"), FileFormat::Json),
+        ]
+    );
+
+    let no_dot = TEST_APP_CONFIG
+        .with_config_option(ConfigOption::DEFAULT_CONFIG.with_allow_dot_prefix(false));
+    assert_eq!(no_dot.search_all_config_files()?.len(), 2);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_searched_paths() -> Result<(), Box<dyn std::error::Error>> {
+    use conrig::{ConrigError, SearchLocation, SearchMiss, SearchOrder};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+    }
+
+    conrig!(const TEST_APP_CONFIG<Config> = {
+        project_path: ProjectPath::new("org", "embers-of-the-fire", "conrig-searched"),
+        config_name: &["conrig-searched"],
+        config_option: ConfigOption::DEFAULT_CONFIG
+            .with_search_order(SearchOrder(&[SearchLocation::ExtraFiles])),
+        extra_files: &[concat!(env!("CARGO_TARGET_TMPDIR"), "/conrig-searched/app")],
+        extra_folders: &[],
+        default_format: FileFormat::Toml,
+    });
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("conrig-searched");
+    std::fs::create_dir_all(dir.join("app.toml"))?;
+
+    let err = TEST_APP_CONFIG.search_config_file()?.read().unwrap_err();
+    let ConrigError::NoConfigurationFile(searched) = &err else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(
+        searched.first().map(|t| &t.path),
+        Some(&dir.join("app.toml"))
+    );
+    assert_eq!(searched[0].reason, SearchMiss::NotAFile);
+    assert!(searched[1..]
+        .iter()
+        .all(|t| t.reason == SearchMiss::NotFound));
+    assert_eq!(searched.last().map(|t| &t.path), Some(&dir.join("app")));
+    assert!(err
+        .to_string()
+        .contains(&format!("`{}` (not a file)", dir.join("app.toml").display())));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}